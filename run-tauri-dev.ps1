@@ -30,6 +30,53 @@ function Prepend-Env([string]$name, [string]$val) {
 $ProjectRoot = Split-Path -Parent $MyInvocation.MyCommand.Path
 Set-Location $ProjectRoot
 
+# --- 'places': regenerate src-tauri\resources\geonames\places.tsv from the GeoNames dumps ---
+if ($args.Count -gt 0 -and $args[0] -ieq 'places') {
+  $GeoNamesUrl = 'https://download.geonames.org/export/dump'
+  $tmp = Join-Path ([IO.Path]::GetTempPath()) 'fmlm-geonames'
+  New-Item -ItemType Directory -Force -Path $tmp | Out-Null
+
+  Write-Host "[i] Downloading GeoNames dumps to $tmp"
+  Invoke-WebRequest -Uri "$GeoNamesUrl/cities15000.zip" -OutFile (Join-Path $tmp 'cities15000.zip')
+  Expand-Archive -Path (Join-Path $tmp 'cities15000.zip') -DestinationPath $tmp -Force
+  foreach ($name in @('admin1CodesASCII.txt', 'countryInfo.txt')) {
+    Invoke-WebRequest -Uri "$GeoNamesUrl/$name" -OutFile (Join-Path $tmp $name)
+  }
+
+  $countries = @{}
+  foreach ($line in Get-Content (Join-Path $tmp 'countryInfo.txt') -Encoding UTF8) {
+    if ($line.StartsWith('#') -or [string]::IsNullOrWhiteSpace($line)) { continue }
+    $f = $line -split "`t"
+    $countries[$f[0]] = $f[4]
+  }
+  $regions = @{}
+  foreach ($line in Get-Content (Join-Path $tmp 'admin1CodesASCII.txt') -Encoding UTF8) {
+    $f = $line -split "`t"
+    if ($f.Count -ge 2) { $regions[$f[0]] = $f[1] }
+  }
+
+  $out = [Collections.Generic.List[string]]::new()
+  $out.Add('# Places for offline reverse geocoding, one per line, tab-separated:')
+  $out.Add('# latitude, longitude, ISO country code, country, region (may be empty), city')
+  $out.Add('#')
+  $out.Add('# Generated from the GeoNames cities15000 dump (https://www.geonames.org, CC BY 4.0)')
+  $out.Add('# with `run-tauri-dev.ps1 places`.')
+  foreach ($line in Get-Content (Join-Path $tmp 'cities15000.txt') -Encoding UTF8) {
+    $f = $line -split "`t"
+    # Skip sections of other places and historical, abandoned or destroyed ones
+    if (@('PPLX', 'PPLH', 'PPLQ', 'PPLW') -contains $f[7]) { continue }
+    if (-not $countries.ContainsKey($f[8])) { continue }
+    $region = $regions["$($f[8]).$($f[10])"]
+    $out.Add((@($f[4], $f[5], $f[8], $countries[$f[8]], $region, $f[1]) -join "`t"))
+  }
+
+  $places = Join-Path $ProjectRoot 'src-tauri\resources\geonames\places.tsv'
+  [IO.File]::WriteAllLines($places, $out, [Text.UTF8Encoding]::new($false))
+  Remove-Item -Recurse -Force $tmp
+  Write-Host "[i] Wrote $($out.Count) lines to $places"
+  exit 0
+}
+
 # --- 1) Import MSVC x64 toolchain into this PS process ---
 # Prefer VsDevCmd if available (more robust), else fallback to vcvarsall
 $vswhere = "C:\Program Files (x86)\Microsoft Visual Studio\Installer\vswhere.exe"
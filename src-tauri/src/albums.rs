@@ -0,0 +1,1139 @@
+//! Albums and manual collections
+//!
+//! Albums group `media_metadata` rows independently of where the files live:
+//! membership is a reference to the row, never a copy of the file, and the
+//! same item can belong to any number of albums. Items keep a manual order
+//! (`album_items.position`), an album can pick a cover item (falling back to
+//! its first item), and albums can be organized in nested album folders.
+//!
+//! Smart albums have no stored members. They keep a serialized
+//! [`SmartAlbumDefinition`] (search filters plus sort order) and their
+//! contents are evaluated live in SQL through [`search::query_media`].
+//!
+//! Deleting a media row removes it from every album; deleting an album folder
+//! deletes its subfolders and the albums inside them, but never any media.
+
+use crate::database::{DatabaseError, DbPool, MediaMetadata};
+use crate::search::{self, MediaPage, MediaQuery, SearchFilters, SortDirection, SortKey};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, Row, SqliteConnection};
+use std::collections::HashSet;
+use tauri::State;
+use thiserror::Error;
+use tracing::{info, warn};
+
+/// Error types for album operations
+#[derive(Debug, Error)]
+pub enum AlbumError {
+    #[error("Database error: {0}")]
+    Database(#[from] DatabaseError),
+    
+    #[error("Invalid name: {0}")]
+    InvalidName(String),
+    
+    #[error("Album not found: {0}")]
+    AlbumNotFound(i64),
+    
+    #[error("Smart album not found: {0}")]
+    SmartAlbumNotFound(i64),
+    
+    #[error("Invalid smart album definition: {0}")]
+    InvalidDefinition(String),
+    
+    #[error("Query error: {0}")]
+    Query(String),
+    
+    #[error("Album folder not found: {0}")]
+    FolderNotFound(i64),
+    
+    #[error("Media {media_id} is not in album {album_id}")]
+    NotInAlbum { album_id: i64, media_id: i64 },
+    
+    #[error("Cannot move album folder {0} into itself or one of its subfolders")]
+    FolderCycle(i64),
+}
+
+impl From<sqlx::Error> for AlbumError {
+    fn from(err: sqlx::Error) -> Self {
+        AlbumError::Database(err.into())
+    }
+}
+
+pub type AlbumResult<T> = Result<T, AlbumError>;
+
+/// An album with its item count and effective cover
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Album {
+    pub id: i64,
+    pub name: String,
+    /// Album folder containing the album (`None` at the top level)
+    pub folder_id: Option<i64>,
+    /// Chosen cover item, or the first item when none was chosen
+    pub cover_media_id: Option<i64>,
+    /// Path of the cover item's file, for thumbnail lookup
+    pub cover_file_path: Option<String>,
+    pub item_count: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A folder of albums
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct AlbumFolder {
+    pub id: i64,
+    pub name: String,
+    /// Parent folder (`None` at the top level)
+    pub parent_id: Option<i64>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Saved query of a smart album
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SmartAlbumDefinition {
+    /// Filters the album's contents must match
+    pub filters: SearchFilters,
+    pub sort: SortKey,
+    pub direction: SortDirection,
+}
+
+/// A smart album with its live item count
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmartAlbum {
+    pub id: i64,
+    pub name: String,
+    /// Album folder containing the album (`None` at the top level)
+    pub folder_id: Option<i64>,
+    pub definition: SmartAlbumDefinition,
+    /// Number of media rows currently matching the definition
+    pub item_count: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Stored form of a smart album, with the definition still serialized
+#[derive(Debug, FromRow)]
+struct SmartAlbumRow {
+    id: i64,
+    name: String,
+    folder_id: Option<i64>,
+    definition: String,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+/// Trims a display name and rejects empty ones
+fn clean_name(name: &str) -> AlbumResult<String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(AlbumError::InvalidName("name is empty".to_string()));
+    }
+    Ok(name.to_string())
+}
+
+async fn ensure_album_exists(conn: &mut SqliteConnection, album_id: i64) -> AlbumResult<()> {
+    sqlx::query("SELECT 1 FROM albums WHERE id = ?")
+        .bind(album_id)
+        .fetch_optional(&mut *conn)
+        .await?
+        .map(|_| ())
+        .ok_or(AlbumError::AlbumNotFound(album_id))
+}
+
+async fn ensure_folder_exists(conn: &mut SqliteConnection, folder_id: Option<i64>) -> AlbumResult<()> {
+    let Some(folder_id) = folder_id else {
+        return Ok(());
+    };
+    sqlx::query("SELECT 1 FROM album_folders WHERE id = ?")
+        .bind(folder_id)
+        .fetch_optional(&mut *conn)
+        .await?
+        .map(|_| ())
+        .ok_or(AlbumError::FolderNotFound(folder_id))
+}
+
+async fn touch_album(conn: &mut SqliteConnection, album_id: i64) -> AlbumResult<()> {
+    sqlx::query("UPDATE albums SET updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+        .bind(album_id)
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+// ============================================================================
+// Album Folders
+// ============================================================================
+
+/// Creates an album folder, optionally inside another one
+///
+/// # Returns
+///
+/// Returns the ID of the new folder
+pub async fn create_album_folder(pool: &DbPool, name: &str, parent_id: Option<i64>) -> AlbumResult<i64> {
+    let name = clean_name(name)?;
+    let mut conn = pool.acquire().await?;
+    ensure_folder_exists(&mut conn, parent_id).await?;
+    
+    let id = sqlx::query("INSERT INTO album_folders (name, parent_id) VALUES (?, ?)")
+        .bind(name)
+        .bind(parent_id)
+        .execute(&mut *conn)
+        .await?
+        .last_insert_rowid();
+    Ok(id)
+}
+
+/// Lists all album folders, sorted by name
+pub async fn list_album_folders(pool: &DbPool) -> AlbumResult<Vec<AlbumFolder>> {
+    let folders = sqlx::query_as::<_, AlbumFolder>(
+        "SELECT id, name, parent_id, created_at FROM album_folders ORDER BY name COLLATE NOCASE, id",
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(folders)
+}
+
+/// Renames an album folder
+pub async fn rename_album_folder(pool: &DbPool, folder_id: i64, name: &str) -> AlbumResult<()> {
+    let name = clean_name(name)?;
+    let updated = sqlx::query("UPDATE album_folders SET name = ? WHERE id = ?")
+        .bind(name)
+        .bind(folder_id)
+        .execute(pool)
+        .await?
+        .rows_affected();
+    
+    if updated == 0 {
+        return Err(AlbumError::FolderNotFound(folder_id));
+    }
+    Ok(())
+}
+
+/// Moves an album folder below another folder (or to the top level)
+///
+/// # Errors
+///
+/// Returns an error if the target is the folder itself or one of its
+/// subfolders.
+pub async fn move_album_folder(pool: &DbPool, folder_id: i64, parent_id: Option<i64>) -> AlbumResult<()> {
+    let mut tx = pool.begin().await?;
+    ensure_folder_exists(&mut tx, Some(folder_id)).await?;
+    ensure_folder_exists(&mut tx, parent_id).await?;
+    
+    if let Some(parent_id) = parent_id {
+        // Walk up from the target; meeting the moved folder means a cycle
+        let is_descendant: bool = sqlx::query(
+            r#"
+            WITH RECURSIVE ancestors(id) AS (
+                SELECT ?
+                UNION
+                SELECT f.parent_id FROM album_folders f
+                JOIN ancestors a ON f.id = a.id
+                WHERE f.parent_id IS NOT NULL
+            )
+            SELECT EXISTS (SELECT 1 FROM ancestors WHERE id = ?)
+            "#,
+        )
+        .bind(parent_id)
+        .bind(folder_id)
+        .fetch_one(&mut *tx)
+        .await?
+        .get(0);
+        
+        if is_descendant {
+            return Err(AlbumError::FolderCycle(folder_id));
+        }
+    }
+    
+    sqlx::query("UPDATE album_folders SET parent_id = ? WHERE id = ?")
+        .bind(parent_id)
+        .bind(folder_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Deletes an album folder with its subfolders and the albums inside them
+///
+/// The media referenced by those albums is not touched.
+pub async fn delete_album_folder(pool: &DbPool, folder_id: i64) -> AlbumResult<()> {
+    let deleted = sqlx::query("DELETE FROM album_folders WHERE id = ?")
+        .bind(folder_id)
+        .execute(pool)
+        .await?
+        .rows_affected();
+    
+    if deleted == 0 {
+        return Err(AlbumError::FolderNotFound(folder_id));
+    }
+    info!("Deleted album folder {}", folder_id);
+    Ok(())
+}
+
+// ============================================================================
+// Albums
+// ============================================================================
+
+/// Creates an empty album, optionally inside an album folder
+///
+/// # Returns
+///
+/// Returns the ID of the new album
+pub async fn create_album(pool: &DbPool, name: &str, folder_id: Option<i64>) -> AlbumResult<i64> {
+    let name = clean_name(name)?;
+    let mut conn = pool.acquire().await?;
+    ensure_folder_exists(&mut conn, folder_id).await?;
+    
+    let id = sqlx::query("INSERT INTO albums (name, folder_id) VALUES (?, ?)")
+        .bind(name)
+        .bind(folder_id)
+        .execute(&mut *conn)
+        .await?
+        .last_insert_rowid();
+    Ok(id)
+}
+
+/// Lists all albums with their item counts and covers, sorted by name
+pub async fn list_albums(pool: &DbPool) -> AlbumResult<Vec<Album>> {
+    let albums = sqlx::query_as::<_, Album>(
+        r#"
+        WITH covers AS (
+            SELECT a.id AS album_id, COALESCE(a.cover_media_id, (
+                SELECT ai.media_id FROM album_items ai
+                JOIN media_metadata m ON m.id = ai.media_id
+                WHERE ai.album_id = a.id AND m.trashed_at IS NULL
+                ORDER BY ai.position LIMIT 1
+            )) AS media_id
+            FROM albums a
+        )
+        SELECT a.id, a.name, a.folder_id, c.media_id AS cover_media_id, m.file_path AS cover_file_path,
+               (SELECT COUNT(*) FROM album_items ai JOIN media_metadata m ON m.id = ai.media_id
+                WHERE ai.album_id = a.id AND m.trashed_at IS NULL) AS item_count,
+               a.created_at, a.updated_at
+        FROM albums a
+        JOIN covers c ON c.album_id = a.id
+        LEFT JOIN media_metadata m ON m.id = c.media_id
+        ORDER BY a.name COLLATE NOCASE, a.id
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(albums)
+}
+
+/// Lists the IDs of the albums containing a media item
+pub async fn albums_for_media(pool: &DbPool, media_id: i64) -> AlbumResult<Vec<i64>> {
+    let rows = sqlx::query("SELECT album_id FROM album_items WHERE media_id = ? ORDER BY album_id")
+        .bind(media_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.into_iter().map(|row| row.get(0)).collect())
+}
+
+/// Renames an album
+pub async fn rename_album(pool: &DbPool, album_id: i64, name: &str) -> AlbumResult<()> {
+    let name = clean_name(name)?;
+    let updated = sqlx::query("UPDATE albums SET name = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+        .bind(name)
+        .bind(album_id)
+        .execute(pool)
+        .await?
+        .rows_affected();
+    
+    if updated == 0 {
+        return Err(AlbumError::AlbumNotFound(album_id));
+    }
+    Ok(())
+}
+
+/// Moves an album into an album folder (or to the top level)
+pub async fn move_album(pool: &DbPool, album_id: i64, folder_id: Option<i64>) -> AlbumResult<()> {
+    let mut conn = pool.acquire().await?;
+    ensure_folder_exists(&mut conn, folder_id).await?;
+    
+    let updated = sqlx::query("UPDATE albums SET folder_id = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+        .bind(folder_id)
+        .bind(album_id)
+        .execute(&mut *conn)
+        .await?
+        .rows_affected();
+    
+    if updated == 0 {
+        return Err(AlbumError::AlbumNotFound(album_id));
+    }
+    Ok(())
+}
+
+/// Deletes an album; the media it referenced is not touched
+pub async fn delete_album(pool: &DbPool, album_id: i64) -> AlbumResult<()> {
+    let deleted = sqlx::query("DELETE FROM albums WHERE id = ?")
+        .bind(album_id)
+        .execute(pool)
+        .await?
+        .rows_affected();
+    
+    if deleted == 0 {
+        return Err(AlbumError::AlbumNotFound(album_id));
+    }
+    info!("Deleted album {}", album_id);
+    Ok(())
+}
+
+/// Returns the media rows of an album in album order
+pub async fn get_album_media(pool: &DbPool, album_id: i64) -> AlbumResult<Vec<MediaMetadata>> {
+    let media = sqlx::query_as::<_, MediaMetadata>(
+        r#"
+        SELECT m.* FROM album_items ai
+        JOIN media_metadata m ON m.id = ai.media_id
+        WHERE ai.album_id = ? AND m.trashed_at IS NULL
+        ORDER BY ai.position
+        "#,
+    )
+    .bind(album_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(media)
+}
+
+/// Appends media items to the end of an album
+///
+/// Items already in the album keep their position.
+///
+/// # Returns
+///
+/// Returns the number of items added
+pub async fn add_to_album(pool: &DbPool, album_id: i64, media_ids: &[i64]) -> AlbumResult<u64> {
+    let mut tx = pool.begin().await?;
+    ensure_album_exists(&mut tx, album_id).await?;
+    
+    let mut added = 0;
+    for media_id in media_ids {
+        added += sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO album_items (album_id, media_id, position)
+            SELECT ?1, ?2, COALESCE(MAX(position) + 1, 0) FROM album_items WHERE album_id = ?1
+            "#,
+        )
+        .bind(album_id)
+        .bind(media_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    }
+    
+    touch_album(&mut tx, album_id).await?;
+    tx.commit().await?;
+    Ok(added)
+}
+
+/// Removes media items from an album
+///
+/// If the album's chosen cover is removed, the album falls back to its first
+/// item as cover.
+///
+/// # Returns
+///
+/// Returns the number of items removed
+pub async fn remove_from_album(pool: &DbPool, album_id: i64, media_ids: &[i64]) -> AlbumResult<u64> {
+    let mut tx = pool.begin().await?;
+    ensure_album_exists(&mut tx, album_id).await?;
+    
+    let mut removed = 0;
+    for media_id in media_ids {
+        removed += sqlx::query("DELETE FROM album_items WHERE album_id = ? AND media_id = ?")
+            .bind(album_id)
+            .bind(media_id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+    }
+    
+    sqlx::query(
+        r#"
+        UPDATE albums SET cover_media_id = NULL
+        WHERE id = ?1 AND cover_media_id NOT IN (SELECT media_id FROM album_items WHERE album_id = ?1)
+        "#,
+    )
+    .bind(album_id)
+    .execute(&mut *tx)
+    .await?;
+    
+    touch_album(&mut tx, album_id).await?;
+    tx.commit().await?;
+    Ok(removed)
+}
+
+/// Reorders the items of an album
+///
+/// # Arguments
+///
+/// * `pool` - Database connection pool
+/// * `album_id` - ID of the album
+/// * `media_ids` - Items in their new order; items left out keep their
+///   relative order after the listed ones
+///
+/// # Errors
+///
+/// Returns an error if a listed item is not in the album.
+pub async fn reorder_album(pool: &DbPool, album_id: i64, media_ids: &[i64]) -> AlbumResult<()> {
+    let mut tx = pool.begin().await?;
+    ensure_album_exists(&mut tx, album_id).await?;
+    
+    let current: Vec<i64> = sqlx::query("SELECT media_id FROM album_items WHERE album_id = ? ORDER BY position")
+        .bind(album_id)
+        .fetch_all(&mut *tx)
+        .await?
+        .into_iter()
+        .map(|row| row.get(0))
+        .collect();
+    
+    let members: HashSet<i64> = current.iter().copied().collect();
+    if let Some(&media_id) = media_ids.iter().find(|id| !members.contains(id)) {
+        return Err(AlbumError::NotInAlbum { album_id, media_id });
+    }
+    
+    let mut seen = HashSet::new();
+    let listed = media_ids.iter().copied().filter(|id| seen.insert(*id)).collect::<Vec<_>>();
+    let rest = current.into_iter().filter(|id| !seen.contains(id));
+    
+    for (position, media_id) in listed.into_iter().chain(rest).enumerate() {
+        sqlx::query("UPDATE album_items SET position = ? WHERE album_id = ? AND media_id = ?")
+            .bind(position as i64)
+            .bind(album_id)
+            .bind(media_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    
+    touch_album(&mut tx, album_id).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Chooses the cover item of an album, or clears the choice with `None`
+///
+/// # Errors
+///
+/// Returns an error if the item is not in the album.
+pub async fn set_album_cover(pool: &DbPool, album_id: i64, media_id: Option<i64>) -> AlbumResult<()> {
+    let mut tx = pool.begin().await?;
+    ensure_album_exists(&mut tx, album_id).await?;
+    
+    if let Some(media_id) = media_id {
+        sqlx::query("SELECT 1 FROM album_items WHERE album_id = ? AND media_id = ?")
+            .bind(album_id)
+            .bind(media_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or(AlbumError::NotInAlbum { album_id, media_id })?;
+    }
+    
+    sqlx::query("UPDATE albums SET cover_media_id = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+        .bind(media_id)
+        .bind(album_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+// ============================================================================
+// Smart Albums
+// ============================================================================
+
+fn serialize_definition(definition: &SmartAlbumDefinition) -> AlbumResult<String> {
+    serde_json::to_string(definition).map_err(|e| AlbumError::InvalidDefinition(e.to_string()))
+}
+
+async fn load_smart_album(pool: &DbPool, smart_album_id: i64) -> AlbumResult<SmartAlbumRow> {
+    sqlx::query_as::<_, SmartAlbumRow>(
+        "SELECT id, name, folder_id, definition, created_at, updated_at FROM smart_albums WHERE id = ?",
+    )
+    .bind(smart_album_id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or(AlbumError::SmartAlbumNotFound(smart_album_id))
+}
+
+fn parse_definition(row: &SmartAlbumRow) -> AlbumResult<SmartAlbumDefinition> {
+    serde_json::from_str(&row.definition)
+        .map_err(|e| AlbumError::InvalidDefinition(format!("smart album {}: {}", row.id, e)))
+}
+
+/// Saves a query as a smart album
+///
+/// # Returns
+///
+/// Returns the ID of the new smart album
+pub async fn create_smart_album(
+    pool: &DbPool,
+    name: &str,
+    folder_id: Option<i64>,
+    definition: &SmartAlbumDefinition,
+) -> AlbumResult<i64> {
+    let name = clean_name(name)?;
+    let definition = serialize_definition(definition)?;
+    let mut conn = pool.acquire().await?;
+    ensure_folder_exists(&mut conn, folder_id).await?;
+    
+    let id = sqlx::query("INSERT INTO smart_albums (name, folder_id, definition) VALUES (?, ?, ?)")
+        .bind(name)
+        .bind(folder_id)
+        .bind(definition)
+        .execute(&mut *conn)
+        .await?
+        .last_insert_rowid();
+    Ok(id)
+}
+
+/// Replaces the name and saved query of a smart album
+pub async fn update_smart_album(
+    pool: &DbPool,
+    smart_album_id: i64,
+    name: &str,
+    definition: &SmartAlbumDefinition,
+) -> AlbumResult<()> {
+    let name = clean_name(name)?;
+    let definition = serialize_definition(definition)?;
+    let updated = sqlx::query(
+        "UPDATE smart_albums SET name = ?, definition = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+    )
+    .bind(name)
+    .bind(definition)
+    .bind(smart_album_id)
+    .execute(pool)
+    .await?
+    .rows_affected();
+    
+    if updated == 0 {
+        return Err(AlbumError::SmartAlbumNotFound(smart_album_id));
+    }
+    Ok(())
+}
+
+/// Moves a smart album into an album folder (or to the top level)
+pub async fn move_smart_album(pool: &DbPool, smart_album_id: i64, folder_id: Option<i64>) -> AlbumResult<()> {
+    let mut conn = pool.acquire().await?;
+    ensure_folder_exists(&mut conn, folder_id).await?;
+    
+    let updated =
+        sqlx::query("UPDATE smart_albums SET folder_id = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+            .bind(folder_id)
+            .bind(smart_album_id)
+            .execute(&mut *conn)
+            .await?
+            .rows_affected();
+    
+    if updated == 0 {
+        return Err(AlbumError::SmartAlbumNotFound(smart_album_id));
+    }
+    Ok(())
+}
+
+/// Deletes a smart album
+pub async fn delete_smart_album(pool: &DbPool, smart_album_id: i64) -> AlbumResult<()> {
+    let deleted = sqlx::query("DELETE FROM smart_albums WHERE id = ?")
+        .bind(smart_album_id)
+        .execute(pool)
+        .await?
+        .rows_affected();
+    
+    if deleted == 0 {
+        return Err(AlbumError::SmartAlbumNotFound(smart_album_id));
+    }
+    Ok(())
+}
+
+/// Lists all smart albums with their current item counts, sorted by name
+///
+/// Albums whose stored definition cannot be read (corrupt, or written by an
+/// incompatible version) are logged and left out instead of failing the list.
+pub async fn list_smart_albums(pool: &DbPool) -> AlbumResult<Vec<SmartAlbum>> {
+    let rows = sqlx::query_as::<_, SmartAlbumRow>(
+        r#"
+        SELECT id, name, folder_id, definition, created_at, updated_at
+        FROM smart_albums
+        ORDER BY name COLLATE NOCASE, id
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+    
+    let mut albums = Vec::with_capacity(rows.len());
+    for row in rows {
+        let definition = match parse_definition(&row) {
+            Ok(definition) => definition,
+            Err(e) => {
+                warn!("Skipping smart album '{}': {}", row.name, e);
+                continue;
+            }
+        };
+        let item_count = search::count_media(pool, &definition.filters).await?;
+        albums.push(SmartAlbum {
+            id: row.id,
+            name: row.name,
+            folder_id: row.folder_id,
+            definition,
+            item_count,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        });
+    }
+    Ok(albums)
+}
+
+/// Evaluates a smart album and returns one page of its contents
+///
+/// # Arguments
+///
+/// * `pool` - Database connection pool
+/// * `smart_album_id` - ID of the smart album
+/// * `cursor` - `next_cursor` of the previous page; `None` for the first page
+/// * `limit` - Page size (defaults to 500, capped at 1000)
+pub async fn get_smart_album_media(
+    pool: &DbPool,
+    smart_album_id: i64,
+    cursor: Option<String>,
+    limit: Option<i64>,
+) -> AlbumResult<MediaPage> {
+    let row = load_smart_album(pool, smart_album_id).await?;
+    let definition = parse_definition(&row)?;
+    let query = MediaQuery {
+        filters: definition.filters,
+        sort: definition.sort,
+        direction: definition.direction,
+        cursor,
+        limit,
+    };
+    search::query_media(pool, &query).await.map_err(AlbumError::Query)
+}
+
+// ============================================================================
+// Commands
+// ============================================================================
+
+/// Tauri command to list all albums
+#[tauri::command]
+pub async fn get_albums(pool: State<'_, DbPool>) -> Result<Vec<Album>, String> {
+    list_albums(&pool).await.map_err(|e| e.to_string())
+}
+
+/// Tauri command to list all album folders
+#[tauri::command]
+pub async fn get_album_folders(pool: State<'_, DbPool>) -> Result<Vec<AlbumFolder>, String> {
+    list_album_folders(&pool).await.map_err(|e| e.to_string())
+}
+
+/// Tauri command to get the media of an album in album order
+#[tauri::command]
+pub async fn get_album_items(pool: State<'_, DbPool>, album_id: i64) -> Result<Vec<MediaMetadata>, String> {
+    get_album_media(&pool, album_id).await.map_err(|e| e.to_string())
+}
+
+/// Tauri command to get the IDs of the albums containing a media item
+#[tauri::command]
+pub async fn get_albums_for_media(pool: State<'_, DbPool>, media_id: i64) -> Result<Vec<i64>, String> {
+    albums_for_media(&pool, media_id).await.map_err(|e| e.to_string())
+}
+
+/// Tauri command to create an album
+///
+/// # Arguments
+///
+/// * `pool` - Database connection pool (injected by Tauri)
+/// * `name` - Display name of the album
+/// * `folder_id` - Album folder to create it in (`None` for the top level)
+///
+/// # Returns
+///
+/// Returns the ID of the new album
+#[tauri::command]
+pub async fn create_album_command(
+    pool: State<'_, DbPool>,
+    name: String,
+    folder_id: Option<i64>,
+) -> Result<i64, String> {
+    create_album(&pool, &name, folder_id).await.map_err(|e| e.to_string())
+}
+
+/// Tauri command to rename an album
+#[tauri::command]
+pub async fn rename_album_command(pool: State<'_, DbPool>, album_id: i64, name: String) -> Result<(), String> {
+    rename_album(&pool, album_id, &name).await.map_err(|e| e.to_string())
+}
+
+/// Tauri command to move an album into an album folder
+#[tauri::command]
+pub async fn move_album_command(
+    pool: State<'_, DbPool>,
+    album_id: i64,
+    folder_id: Option<i64>,
+) -> Result<(), String> {
+    move_album(&pool, album_id, folder_id).await.map_err(|e| e.to_string())
+}
+
+/// Tauri command to delete an album (its media is kept)
+#[tauri::command]
+pub async fn delete_album_command(pool: State<'_, DbPool>, album_id: i64) -> Result<(), String> {
+    delete_album(&pool, album_id).await.map_err(|e| e.to_string())
+}
+
+/// Tauri command to append media items to an album
+///
+/// # Returns
+///
+/// Returns the number of items that were not already in the album
+#[tauri::command]
+pub async fn add_to_album_command(
+    pool: State<'_, DbPool>,
+    album_id: i64,
+    media_ids: Vec<i64>,
+) -> Result<u64, String> {
+    add_to_album(&pool, album_id, &media_ids).await.map_err(|e| e.to_string())
+}
+
+/// Tauri command to remove media items from an album
+///
+/// # Returns
+///
+/// Returns the number of items removed
+#[tauri::command]
+pub async fn remove_from_album_command(
+    pool: State<'_, DbPool>,
+    album_id: i64,
+    media_ids: Vec<i64>,
+) -> Result<u64, String> {
+    remove_from_album(&pool, album_id, &media_ids).await.map_err(|e| e.to_string())
+}
+
+/// Tauri command to reorder the items of an album
+#[tauri::command]
+pub async fn reorder_album_command(
+    pool: State<'_, DbPool>,
+    album_id: i64,
+    media_ids: Vec<i64>,
+) -> Result<(), String> {
+    reorder_album(&pool, album_id, &media_ids).await.map_err(|e| e.to_string())
+}
+
+/// Tauri command to choose (or clear) the cover item of an album
+#[tauri::command]
+pub async fn set_album_cover_command(
+    pool: State<'_, DbPool>,
+    album_id: i64,
+    media_id: Option<i64>,
+) -> Result<(), String> {
+    set_album_cover(&pool, album_id, media_id).await.map_err(|e| e.to_string())
+}
+
+/// Tauri command to create an album folder
+///
+/// # Returns
+///
+/// Returns the ID of the new folder
+#[tauri::command]
+pub async fn create_album_folder_command(
+    pool: State<'_, DbPool>,
+    name: String,
+    parent_id: Option<i64>,
+) -> Result<i64, String> {
+    create_album_folder(&pool, &name, parent_id).await.map_err(|e| e.to_string())
+}
+
+/// Tauri command to rename an album folder
+#[tauri::command]
+pub async fn rename_album_folder_command(
+    pool: State<'_, DbPool>,
+    folder_id: i64,
+    name: String,
+) -> Result<(), String> {
+    rename_album_folder(&pool, folder_id, &name).await.map_err(|e| e.to_string())
+}
+
+/// Tauri command to move an album folder below another one
+#[tauri::command]
+pub async fn move_album_folder_command(
+    pool: State<'_, DbPool>,
+    folder_id: i64,
+    parent_id: Option<i64>,
+) -> Result<(), String> {
+    move_album_folder(&pool, folder_id, parent_id).await.map_err(|e| e.to_string())
+}
+
+/// Tauri command to delete an album folder with its subfolders and albums
+#[tauri::command]
+pub async fn delete_album_folder_command(pool: State<'_, DbPool>, folder_id: i64) -> Result<(), String> {
+    delete_album_folder(&pool, folder_id).await.map_err(|e| e.to_string())
+}
+
+/// Tauri command to list all smart albums with their current item counts
+#[tauri::command]
+pub async fn get_smart_albums(pool: State<'_, DbPool>) -> Result<Vec<SmartAlbum>, String> {
+    list_smart_albums(&pool).await.map_err(|e| e.to_string())
+}
+
+/// Tauri command to fetch one page of a smart album's live contents
+///
+/// # Arguments
+///
+/// * `pool` - Database connection pool (injected by Tauri)
+/// * `smart_album_id` - ID of the smart album
+/// * `cursor` - `next_cursor` of the previous page; omitted for the first page
+/// * `limit` - Page size (defaults to 500, capped at 1000)
+///
+/// # Returns
+///
+/// Returns the page, the cursor of the next page and the total match count
+#[tauri::command]
+pub async fn get_smart_album_page(
+    pool: State<'_, DbPool>,
+    smart_album_id: i64,
+    cursor: Option<String>,
+    limit: Option<i64>,
+) -> Result<MediaPage, String> {
+    get_smart_album_media(&pool, smart_album_id, cursor, limit)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Tauri command to save a query as a smart album
+///
+/// # Returns
+///
+/// Returns the ID of the new smart album
+#[tauri::command]
+pub async fn create_smart_album_command(
+    pool: State<'_, DbPool>,
+    name: String,
+    folder_id: Option<i64>,
+    definition: SmartAlbumDefinition,
+) -> Result<i64, String> {
+    create_smart_album(&pool, &name, folder_id, &definition)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Tauri command to change the name and saved query of a smart album
+#[tauri::command]
+pub async fn update_smart_album_command(
+    pool: State<'_, DbPool>,
+    smart_album_id: i64,
+    name: String,
+    definition: SmartAlbumDefinition,
+) -> Result<(), String> {
+    update_smart_album(&pool, smart_album_id, &name, &definition)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Tauri command to move a smart album into an album folder
+#[tauri::command]
+pub async fn move_smart_album_command(
+    pool: State<'_, DbPool>,
+    smart_album_id: i64,
+    folder_id: Option<i64>,
+) -> Result<(), String> {
+    move_smart_album(&pool, smart_album_id, folder_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Tauri command to delete a smart album
+#[tauri::command]
+pub async fn delete_smart_album_command(pool: State<'_, DbPool>, smart_album_id: i64) -> Result<(), String> {
+    delete_smart_album(&pool, smart_album_id).await.map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{self, InsertMediaParams};
+    
+    /// Pool with `count` image rows (IDs 1..=count)
+    async fn seeded_pool(count: usize) -> DbPool {
+        let pool = database::open_test_pool().await;
+        let folder_id = database::upsert_scanned_folder(&pool, "/lib", "lib", count as i64).await.unwrap();
+        for i in 1..=count {
+            let params = InsertMediaParams {
+                folder_id,
+                file_path: format!("/lib/{}.jpg", i),
+                file_name: format!("{}.jpg", i),
+                file_type: "jpg".to_string(),
+                file_size: 100,
+                media_type: Some("image".to_string()),
+                ..Default::default()
+            };
+            database::insert_media_metadata(&pool, params).await.unwrap();
+        }
+        pool
+    }
+    
+    async fn item_ids(pool: &DbPool, album_id: i64) -> Vec<i64> {
+        get_album_media(pool, album_id).await.unwrap().into_iter().map(|m| m.id).collect()
+    }
+    
+    async fn album(pool: &DbPool, album_id: i64) -> Album {
+        list_albums(pool).await.unwrap().into_iter().find(|a| a.id == album_id).unwrap()
+    }
+    
+    #[tokio::test]
+    async fn test_album_membership_order_and_cover() {
+        let pool = seeded_pool(4).await;
+        let trip = create_album(&pool, " Trip ", None).await.unwrap();
+        let best = create_album(&pool, "Best of", None).await.unwrap();
+        
+        assert_eq!(add_to_album(&pool, trip, &[3, 1, 2]).await.unwrap(), 3);
+        assert_eq!(add_to_album(&pool, trip, &[1, 4]).await.unwrap(), 1);
+        add_to_album(&pool, best, &[1]).await.unwrap();
+        assert_eq!(item_ids(&pool, trip).await, [3, 1, 2, 4]);
+        assert_eq!(albums_for_media(&pool, 1).await.unwrap(), [trip, best]);
+        
+        // Listed items first, the rest keep their relative order
+        reorder_album(&pool, trip, &[4, 2]).await.unwrap();
+        assert_eq!(item_ids(&pool, trip).await, [4, 2, 3, 1]);
+        assert!(matches!(
+            reorder_album(&pool, trip, &[99]).await,
+            Err(AlbumError::NotInAlbum { media_id: 99, .. })
+        ));
+        
+        let listed = album(&pool, trip).await;
+        assert_eq!((listed.name.as_str(), listed.item_count), ("Trip", 4));
+        assert_eq!(listed.cover_media_id, Some(4));
+        assert_eq!(listed.cover_file_path.as_deref(), Some("/lib/4.jpg"));
+        
+        set_album_cover(&pool, trip, Some(3)).await.unwrap();
+        assert_eq!(album(&pool, trip).await.cover_media_id, Some(3));
+        assert!(set_album_cover(&pool, best, Some(3)).await.is_err());
+        
+        // Removing the cover falls back to the first item
+        assert_eq!(remove_from_album(&pool, trip, &[3, 4]).await.unwrap(), 2);
+        assert_eq!(album(&pool, trip).await.cover_media_id, Some(2));
+        
+        // Deleting a media row drops it from every album
+        database::delete_media_metadata(&pool, "/lib/1.jpg").await.unwrap();
+        assert_eq!(item_ids(&pool, trip).await, [2]);
+        assert_eq!(album(&pool, best).await.item_count, 0);
+        
+        delete_album(&pool, trip).await.unwrap();
+        assert!(matches!(rename_album(&pool, trip, "x").await, Err(AlbumError::AlbumNotFound(_))));
+        assert_eq!(database::get_all_media(&pool).await.unwrap().len(), 3);
+    }
+    
+    #[tokio::test]
+    async fn test_nested_album_folders() {
+        let pool = seeded_pool(1).await;
+        let travel = create_album_folder(&pool, "Travel", None).await.unwrap();
+        let europe = create_album_folder(&pool, "Europe", Some(travel)).await.unwrap();
+        let lisbon = create_album(&pool, "Lisbon", Some(europe)).await.unwrap();
+        let other = create_album(&pool, "Other", None).await.unwrap();
+        add_to_album(&pool, lisbon, &[1]).await.unwrap();
+        
+        assert!(matches!(
+            move_album_folder(&pool, travel, Some(europe)).await,
+            Err(AlbumError::FolderCycle(_))
+        ));
+        assert!(move_album_folder(&pool, travel, Some(travel)).await.is_err());
+        assert!(create_album(&pool, "x", Some(999)).await.is_err());
+        
+        move_album(&pool, other, Some(travel)).await.unwrap();
+        assert_eq!(album(&pool, other).await.folder_id, Some(travel));
+        
+        move_album_folder(&pool, europe, None).await.unwrap();
+        delete_album_folder(&pool, travel).await.unwrap();
+        let names: Vec<String> = list_albums(&pool).await.unwrap().into_iter().map(|a| a.name).collect();
+        assert_eq!(names, ["Lisbon"]);
+        
+        delete_album_folder(&pool, europe).await.unwrap();
+        assert!(list_albums(&pool).await.unwrap().is_empty());
+        assert!(list_album_folders(&pool).await.unwrap().is_empty());
+        assert_eq!(database::get_all_media(&pool).await.unwrap().len(), 1);
+    }
+    
+    #[tokio::test]
+    async fn test_smart_albums_are_evaluated_live() {
+        use chrono::TimeZone;
+        
+        let pool = database::open_test_pool().await;
+        let folder_id = database::upsert_scanned_folder(&pool, "/lib", "lib", 4).await.unwrap();
+        let rows = [
+            ("long_4k.mp4", "video", 3840, 600.0, 2024),
+            ("short_4k.mp4", "video", 3840, 60.0, 2024),
+            ("old_4k.mp4", "video", 3840, 600.0, 2023),
+            ("photo.heic", "image", 4032, 0.0, 2024),
+        ];
+        for (name, media_type, width, duration, year) in rows {
+            let params = InsertMediaParams {
+                folder_id,
+                file_path: format!("/lib/{}", name),
+                file_name: name.to_string(),
+                file_type: name.rsplit('.').next().unwrap().to_string(),
+                file_size: 100,
+                media_type: Some(media_type.to_string()),
+                width: Some(width),
+                height: Some(2160),
+                duration: Some(duration),
+                created_date: Some(Utc.with_ymd_and_hms(year, 6, 1, 12, 0, 0).unwrap()),
+                ..Default::default()
+            };
+            database::insert_media_metadata(&pool, params).await.unwrap();
+        }
+        
+        let long_4k = SmartAlbumDefinition {
+            filters: SearchFilters {
+                media_types: vec!["video".to_string()],
+                min_width: Some(3840),
+                min_duration: Some(300.0),
+                date_from: Some(Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap()),
+                date_to: Some(Utc.with_ymd_and_hms(2024, 12, 31, 23, 59, 59).unwrap()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let untagged_heic: SmartAlbumDefinition =
+            serde_json::from_str(r#"{"filters": {"file_types": ["heic"], "has_tags": false}}"#).unwrap();
+        
+        let long_id = create_smart_album(&pool, "4K over 5 minutes", None, &long_4k).await.unwrap();
+        let heic_id = create_smart_album(&pool, "Untagged HEIC", None, &untagged_heic).await.unwrap();
+        
+        let page = get_smart_album_media(&pool, long_id, None, None).await.unwrap();
+        let names: Vec<&str> = page.items.iter().map(|m| m.file_name.as_str()).collect();
+        assert_eq!(names, ["long_4k.mp4"]);
+        
+        let listed = list_smart_albums(&pool).await.unwrap();
+        assert_eq!(listed[0].definition, long_4k);
+        assert_eq!(listed[1].item_count, 1);
+        
+        // Contents follow the library without touching the album
+        let heic = get_smart_album_media(&pool, heic_id, None, None).await.unwrap().items[0].id;
+        crate::tags::add_tags(&pool, &[heic], &["Family".to_string()]).await.unwrap();
+        assert_eq!(get_smart_album_media(&pool, heic_id, None, None).await.unwrap().total_count, 0);
+        
+        let tagged = SmartAlbumDefinition {
+            filters: SearchFilters {
+                tags: Some(crate::tags::TagExpr::parse("Family").unwrap()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        update_smart_album(&pool, heic_id, "Family", &tagged).await.unwrap();
+        assert_eq!(get_smart_album_media(&pool, heic_id, None, None).await.unwrap().total_count, 1);
+        
+        delete_smart_album(&pool, heic_id).await.unwrap();
+        assert!(matches!(
+            get_smart_album_media(&pool, heic_id, None, None).await,
+            Err(AlbumError::SmartAlbumNotFound(_))
+        ));
+        
+        // An unreadable definition hides that album only
+        let broken_id = create_smart_album(&pool, "Broken", None, &tagged).await.unwrap();
+        sqlx::query("UPDATE smart_albums SET definition = '{\"filters\": 42}' WHERE id = ?")
+            .bind(broken_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let names: Vec<String> = list_smart_albums(&pool).await.unwrap().into_iter().map(|a| a.name).collect();
+        assert_eq!(names, ["4K over 5 minutes"]);
+    }
+}
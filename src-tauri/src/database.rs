@@ -9,6 +9,7 @@ use std::str::FromStr;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use tauri::Manager;
+use crate::migrations;
 
 /// Application database connection pool
 pub type DbPool = SqlitePool;
@@ -22,6 +23,9 @@ pub enum DatabaseError {
     #[error("Migration error: {0}")]
     Migration(String),
     
+    #[error("Database schema version {found} is newer than this app supports ({supported}); please update the app")]
+    NewerSchema { found: i64, supported: i64 },
+    
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 }
@@ -67,10 +71,44 @@ pub struct MediaMetadata {
     pub audio_channels: Option<i64>,
     pub format: Option<String>,
     pub metadata_json: Option<String>,
+    // Media type and audio tags
+    pub media_type: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub title: Option<String>,
+    pub track_number: Option<i64>,
+    // Camera settings from EXIF (see `exif`)
+    pub camera_make: Option<String>,
+    pub camera_model: Option<String>,
+    pub lens_model: Option<String>,
+    pub focal_length: Option<f64>,
+    pub aperture: Option<f64>,
+    pub exposure_time: Option<f64>,
+    pub iso: Option<i64>,
+    pub flash: Option<bool>,
+    pub date_taken: Option<String>,
+    pub orientation: Option<i64>,
+    // GPS position (see `location`)
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub altitude: Option<f64>,
+    // Place names resolved from the GPS position (see `geocode`)
+    pub country_code: Option<String>,
+    pub country: Option<String>,
+    pub region: Option<String>,
+    pub city: Option<String>,
+    // User judgments (see `ratings`)
+    pub rating: i64,
+    pub favorite: bool,
+    pub color_label: Option<String>,
+    pub pick_flag: Option<String>,
 }
 
 /// Parameters for inserting media metadata
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// `Default` leaves every optional field empty, so callers and test fixtures
+/// only spell out what they know.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct InsertMediaParams {
     pub folder_id: i64,
     pub file_path: String,
@@ -94,6 +132,54 @@ pub struct InsertMediaParams {
     pub audio_channels: Option<i64>,
     pub format: Option<String>,
     pub metadata_json: Option<String>,
+    // Media type and audio tags
+    pub media_type: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub title: Option<String>,
+    pub track_number: Option<i64>,
+    // Camera settings from EXIF
+    pub camera_make: Option<String>,
+    pub camera_model: Option<String>,
+    pub lens_model: Option<String>,
+    pub focal_length: Option<f64>,
+    pub aperture: Option<f64>,
+    pub exposure_time: Option<f64>,
+    pub iso: Option<i64>,
+    pub flash: Option<bool>,
+    pub date_taken: Option<String>,
+    pub orientation: Option<i64>,
+    // GPS position (see `location`)
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub altitude: Option<f64>,
+}
+
+/// Minimal view of an indexed media row used to detect changes on disk
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromRow)]
+pub struct IndexedFile {
+    pub id: i64,
+    pub file_path: String,
+    pub file_size: i64,
+    pub modified_date: DateTime<Utc>,
+    /// Quick content hash, if computed (used to recognize moved files)
+    pub quick_hash: Option<String>,
+    /// Full content hash, if computed
+    pub content_hash: Option<String>,
+}
+
+/// New location of an indexed file that was moved or renamed on disk
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MediaMove {
+    /// ID of the media row to update
+    pub id: i64,
+    /// Scanned folder the file now belongs to
+    pub folder_id: i64,
+    pub file_path: String,
+    pub file_name: String,
+    pub file_type: String,
+    /// Quick hash computed while confirming the move, kept if the row had none
+    pub quick_hash: Option<String>,
 }
 
 /// Represents user preferences
@@ -144,148 +230,30 @@ pub async fn initialize_database(app_handle: &tauri::AppHandle) -> DatabaseResul
         .connect_with(connect_options)
         .await?;
     
-    // Run migrations
-    run_migrations(&pool).await?;
+    // Bring the schema up to date
+    migrations::run_migrations(&pool).await?;
+    println!("Database migrations completed successfully");
     
     Ok(pool)
 }
 
-/// Runs database migrations to create or update schema
-async fn run_migrations(pool: &DbPool) -> DatabaseResult<()> {
-    // Create scanned_folders table
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS scanned_folders (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            path TEXT NOT NULL UNIQUE,
-            name TEXT NOT NULL,
-            last_scanned DATETIME NOT NULL,
-            file_count INTEGER NOT NULL DEFAULT 0,
-            created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
-        )
-        "#,
-    )
-    .execute(pool)
-    .await?;
-    
-    // Create media_metadata table
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS media_metadata (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            folder_id INTEGER NOT NULL,
-            file_path TEXT NOT NULL UNIQUE,
-            file_name TEXT NOT NULL,
-            file_type TEXT NOT NULL,
-            file_size INTEGER NOT NULL,
-            width INTEGER,
-            height INTEGER,
-            duration REAL,
-            created_date DATETIME,
-            modified_date DATETIME NOT NULL,
-            thumbnail_path TEXT,
-            indexed_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
-            FOREIGN KEY (folder_id) REFERENCES scanned_folders(id) ON DELETE CASCADE
-        )
-        "#,
-    )
-    .execute(pool)
-    .await?;
-    
-    // Add new columns for extended metadata (safe to run on existing databases)
-    // Video codec information
-    let _ = sqlx::query("ALTER TABLE media_metadata ADD COLUMN video_codec TEXT")
-        .execute(pool)
-        .await;
-    
-    let _ = sqlx::query("ALTER TABLE media_metadata ADD COLUMN video_codec_long TEXT")
-        .execute(pool)
-        .await;
-    
-    // Audio codec information
-    let _ = sqlx::query("ALTER TABLE media_metadata ADD COLUMN audio_codec TEXT")
-        .execute(pool)
-        .await;
-    
-    let _ = sqlx::query("ALTER TABLE media_metadata ADD COLUMN audio_codec_long TEXT")
-        .execute(pool)
-        .await;
-    
-    // Bitrate information
-    let _ = sqlx::query("ALTER TABLE media_metadata ADD COLUMN bitrate INTEGER")
-        .execute(pool)
-        .await;
-    
-    // Frame rate
-    let _ = sqlx::query("ALTER TABLE media_metadata ADD COLUMN frame_rate REAL")
-        .execute(pool)
-        .await;
-    
-    // Sample rate for audio
-    let _ = sqlx::query("ALTER TABLE media_metadata ADD COLUMN sample_rate INTEGER")
-        .execute(pool)
-        .await;
-    
-    // Number of audio channels
-    let _ = sqlx::query("ALTER TABLE media_metadata ADD COLUMN audio_channels INTEGER")
-        .execute(pool)
-        .await;
-    
-    // General format information
-    let _ = sqlx::query("ALTER TABLE media_metadata ADD COLUMN format TEXT")
-        .execute(pool)
-        .await;
-    
-    // Additional metadata as JSON
-    let _ = sqlx::query("ALTER TABLE media_metadata ADD COLUMN metadata_json TEXT")
-        .execute(pool)
-        .await;
-    
-    // Create indexes for better query performance
-    sqlx::query(
-        r#"
-        CREATE INDEX IF NOT EXISTS idx_media_folder_id 
-        ON media_metadata(folder_id)
-        "#,
-    )
-    .execute(pool)
-    .await?;
-    
-    sqlx::query(
-        r#"
-        CREATE INDEX IF NOT EXISTS idx_media_file_type 
-        ON media_metadata(file_type)
-        "#,
-    )
-    .execute(pool)
-    .await?;
-    
-    // Index for fast cache lookups by file path
-    sqlx::query(
-        r#"
-        CREATE INDEX IF NOT EXISTS idx_media_file_path 
-        ON media_metadata(file_path)
-        "#,
-    )
-    .execute(pool)
-    .await?;
-    
-    // Create user_preferences table
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS user_preferences (
-            key TEXT PRIMARY KEY,
-            value TEXT NOT NULL,
-            updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
-        )
-        "#,
-    )
-    .execute(pool)
-    .await?;
-    
-    println!("Database migrations completed successfully");
-    
-    Ok(())
+/// Opens a private in-memory database with no schema for tests
+#[cfg(test)]
+pub(crate) async fn open_empty_test_pool() -> DbPool {
+    // Every connection to `:memory:` gets its own database, so keep just one
+    SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect_with(SqliteConnectOptions::from_str("sqlite::memory:").unwrap().foreign_keys(true))
+        .await
+        .unwrap()
+}
+
+/// Opens a private in-memory database with the current schema for tests
+#[cfg(test)]
+pub(crate) async fn open_test_pool() -> DbPool {
+    let pool = open_empty_test_pool().await;
+    migrations::run_migrations(&pool).await.unwrap();
+    pool
 }
 
 // ============================================================================
@@ -322,6 +290,33 @@ pub async fn upsert_scanned_folder(
     Ok(result.get(0))
 }
 
+/// Returns the ID of a scanned folder, registering it if needed
+///
+/// Unlike [`upsert_scanned_folder`] this leaves the stats of a known folder
+/// alone; a new folder starts with no files and `last_scanned` equal to
+/// `created_at` until [`update_folder_stats`] records a finished scan.
+pub async fn register_scanned_folder(pool: &DbPool, path: &str, name: &str) -> DatabaseResult<i64> {
+    let now = Utc::now();
+    
+    // DO UPDATE (not DO NOTHING) so RETURNING also yields existing rows
+    let result = sqlx::query(
+        r#"
+        INSERT INTO scanned_folders (path, name, last_scanned, file_count, created_at)
+        VALUES (?, ?, ?, 0, ?)
+        ON CONFLICT(path) DO UPDATE SET path = excluded.path
+        RETURNING id
+        "#,
+    )
+    .bind(path)
+    .bind(name)
+    .bind(now)
+    .bind(now)
+    .fetch_one(pool)
+    .await?;
+    
+    Ok(result.get(0))
+}
+
 /// Retrieves all scanned folders
 pub async fn get_all_scanned_folders(pool: &DbPool) -> DatabaseResult<Vec<ScannedFolder>> {
     let folders = sqlx::query_as::<_, ScannedFolder>(
@@ -337,6 +332,22 @@ pub async fn get_all_scanned_folders(pool: &DbPool) -> DatabaseResult<Vec<Scanne
     Ok(folders)
 }
 
+/// Retrieves a single scanned folder by ID
+pub async fn get_scanned_folder(pool: &DbPool, folder_id: i64) -> DatabaseResult<Option<ScannedFolder>> {
+    let folder = sqlx::query_as::<_, ScannedFolder>(
+        r#"
+        SELECT id, path, name, last_scanned, file_count, created_at
+        FROM scanned_folders
+        WHERE id = ?
+        "#,
+    )
+    .bind(folder_id)
+    .fetch_optional(pool)
+    .await?;
+    
+    Ok(folder)
+}
+
 /// Deletes a scanned folder and its associated media metadata
 pub async fn delete_scanned_folder(pool: &DbPool, folder_id: i64) -> DatabaseResult<()> {
     sqlx::query("DELETE FROM scanned_folders WHERE id = ?")
@@ -361,6 +372,17 @@ pub async fn insert_media_metadata(
     pool: &DbPool,
     params: InsertMediaParams,
 ) -> DatabaseResult<i64> {
+    upsert_media_metadata(pool, &params).await
+}
+
+/// Inserts or updates a media row on any SQLite executor
+///
+/// Shared by [`insert_media_metadata`] and the transactional folder updates so
+/// both paths write exactly the same columns.
+async fn upsert_media_metadata<'e, E>(executor: E, params: &InsertMediaParams) -> DatabaseResult<i64>
+where
+    E: sqlx::SqliteExecutor<'e>,
+{
     let now = Utc::now();
     
     let result = sqlx::query(
@@ -370,10 +392,15 @@ pub async fn insert_media_metadata(
             width, height, duration, created_date, modified_date,
             thumbnail_path, indexed_at,
             video_codec, video_codec_long, audio_codec, audio_codec_long,
-            bitrate, frame_rate, sample_rate, audio_channels, format, metadata_json
+            bitrate, frame_rate, sample_rate, audio_channels, format, metadata_json,
+            media_type, artist, album, title, track_number,
+            camera_make, camera_model, lens_model, focal_length, aperture,
+            exposure_time, iso, flash, date_taken, orientation,
+            latitude, longitude, altitude
         )
-        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
         ON CONFLICT(file_path) DO UPDATE SET
+            created_date = excluded.created_date,
             modified_date = excluded.modified_date,
             file_size = excluded.file_size,
             width = excluded.width,
@@ -389,7 +416,50 @@ pub async fn insert_media_metadata(
             sample_rate = excluded.sample_rate,
             audio_channels = excluded.audio_channels,
             format = excluded.format,
-            metadata_json = excluded.metadata_json
+            metadata_json = excluded.metadata_json,
+            media_type = COALESCE(excluded.media_type, media_type),
+            artist = excluded.artist,
+            album = excluded.album,
+            title = excluded.title,
+            track_number = excluded.track_number,
+            camera_make = excluded.camera_make,
+            camera_model = excluded.camera_model,
+            lens_model = excluded.lens_model,
+            focal_length = excluded.focal_length,
+            aperture = excluded.aperture,
+            exposure_time = excluded.exposure_time,
+            iso = excluded.iso,
+            flash = excluded.flash,
+            date_taken = excluded.date_taken,
+            orientation = excluded.orientation,
+            latitude = excluded.latitude,
+            longitude = excluded.longitude,
+            altitude = excluded.altitude,
+            quick_hash = CASE
+                WHEN media_metadata.file_size = excluded.file_size
+                     AND media_metadata.modified_date = excluded.modified_date
+                THEN media_metadata.quick_hash
+            END,
+            content_hash = CASE
+                WHEN media_metadata.file_size = excluded.file_size
+                     AND media_metadata.modified_date = excluded.modified_date
+                THEN media_metadata.content_hash
+            END,
+            dhash = CASE
+                WHEN media_metadata.file_size = excluded.file_size
+                     AND media_metadata.modified_date = excluded.modified_date
+                THEN media_metadata.dhash
+            END,
+            phash = CASE
+                WHEN media_metadata.file_size = excluded.file_size
+                     AND media_metadata.modified_date = excluded.modified_date
+                THEN media_metadata.phash
+            END,
+            video_fingerprint = CASE
+                WHEN media_metadata.file_size = excluded.file_size
+                     AND media_metadata.modified_date = excluded.modified_date
+                THEN media_metadata.video_fingerprint
+            END
         RETURNING id
         "#,
     )
@@ -415,13 +485,31 @@ pub async fn insert_media_metadata(
     .bind(params.audio_channels)
     .bind(params.format.as_deref())
     .bind(params.metadata_json.as_deref())
-    .fetch_one(pool)
+    .bind(params.media_type.as_deref())
+    .bind(params.artist.as_deref())
+    .bind(params.album.as_deref())
+    .bind(params.title.as_deref())
+    .bind(params.track_number)
+    .bind(params.camera_make.as_deref())
+    .bind(params.camera_model.as_deref())
+    .bind(params.lens_model.as_deref())
+    .bind(params.focal_length)
+    .bind(params.aperture)
+    .bind(params.exposure_time)
+    .bind(params.iso)
+    .bind(params.flash)
+    .bind(params.date_taken.as_deref())
+    .bind(params.orientation)
+    .bind(params.latitude)
+    .bind(params.longitude)
+    .bind(params.altitude)
+    .fetch_one(executor)
     .await?;
     
     Ok(result.get(0))
 }
 
-/// Retrieves media metadata for a specific folder
+/// Retrieves media metadata for a specific folder (trashed rows excluded)
 pub async fn get_media_by_folder(pool: &DbPool, folder_id: i64) -> DatabaseResult<Vec<MediaMetadata>> {
     let media = sqlx::query_as::<_, MediaMetadata>(
         r#"
@@ -429,9 +517,15 @@ pub async fn get_media_by_folder(pool: &DbPool, folder_id: i64) -> DatabaseResul
                width, height, duration, created_date, modified_date,
                thumbnail_path, indexed_at,
                video_codec, video_codec_long, audio_codec, audio_codec_long,
-               bitrate, frame_rate, sample_rate, audio_channels, format, metadata_json
+               bitrate, frame_rate, sample_rate, audio_channels, format, metadata_json,
+               media_type, artist, album, title, track_number,
+               camera_make, camera_model, lens_model, focal_length, aperture,
+               exposure_time, iso, flash, date_taken, orientation,
+               latitude, longitude, altitude,
+               country_code, country, region, city,
+               rating, favorite, color_label, pick_flag
         FROM media_metadata
-        WHERE folder_id = ?
+        WHERE folder_id = ? AND trashed_at IS NULL
         ORDER BY file_name ASC
         "#,
     )
@@ -442,7 +536,7 @@ pub async fn get_media_by_folder(pool: &DbPool, folder_id: i64) -> DatabaseResul
     Ok(media)
 }
 
-/// Retrieves all media metadata
+/// Retrieves all media metadata (trashed rows excluded)
 pub async fn get_all_media(pool: &DbPool) -> DatabaseResult<Vec<MediaMetadata>> {
     let media = sqlx::query_as::<_, MediaMetadata>(
         r#"
@@ -450,8 +544,15 @@ pub async fn get_all_media(pool: &DbPool) -> DatabaseResult<Vec<MediaMetadata>>
                width, height, duration, created_date, modified_date,
                thumbnail_path, indexed_at,
                video_codec, video_codec_long, audio_codec, audio_codec_long,
-               bitrate, frame_rate, sample_rate, audio_channels, format, metadata_json
+               bitrate, frame_rate, sample_rate, audio_channels, format, metadata_json,
+               media_type, artist, album, title, track_number,
+               camera_make, camera_model, lens_model, focal_length, aperture,
+               exposure_time, iso, flash, date_taken, orientation,
+               latitude, longitude, altitude,
+               country_code, country, region, city,
+               rating, favorite, color_label, pick_flag
         FROM media_metadata
+        WHERE trashed_at IS NULL
         ORDER BY indexed_at DESC
         "#,
     )
@@ -471,6 +572,147 @@ pub async fn delete_media_metadata(pool: &DbPool, file_path: &str) -> DatabaseRe
     Ok(())
 }
 
+/// Retrieves the indexed rows for a path and any rows stored below it
+///
+/// Used when a file or a whole directory disappears from disk. Descendants are
+/// matched with an exact prefix comparison rather than `LIKE`, which is
+/// case-insensitive in SQLite and treats `_` and `%` as wildcards.
+pub async fn get_indexed_files_at_path(pool: &DbPool, path: &str) -> DatabaseResult<Vec<IndexedFile>> {
+    let separator = std::path::MAIN_SEPARATOR;
+    let prefix = format!("{}{}", path.trim_end_matches(separator), separator);
+    
+    let files = sqlx::query_as::<_, IndexedFile>(
+        r#"
+        SELECT id, file_path, file_size, modified_date, quick_hash, content_hash
+        FROM media_metadata
+        WHERE (file_path = ? OR substr(file_path, 1, length(?)) = ?) AND trashed_at IS NULL
+        "#,
+    )
+    .bind(path)
+    .bind(&prefix)
+    .bind(&prefix)
+    .fetch_all(pool)
+    .await?;
+    
+    Ok(files)
+}
+
+/// Retrieves the path, size and modification date of every media row in a folder
+///
+/// Used by the incremental rescan to compare the index against the files
+/// currently on disk without loading the full metadata rows. Trashed rows are
+/// left out: their files live in the trash, not in the folder.
+pub async fn get_indexed_files(pool: &DbPool, folder_id: i64) -> DatabaseResult<Vec<IndexedFile>> {
+    let files = sqlx::query_as::<_, IndexedFile>(
+        r#"
+        SELECT id, file_path, file_size, modified_date, quick_hash, content_hash
+        FROM media_metadata
+        WHERE folder_id = ? AND trashed_at IS NULL
+        "#,
+    )
+    .bind(folder_id)
+    .fetch_all(pool)
+    .await?;
+    
+    Ok(files)
+}
+
+/// Points a media row at the new location of its file
+///
+/// Everything else on the row (metadata, tags, ratings, album membership,
+/// hashes) stays as it is, since the file's content did not change.
+async fn move_media_row<'e, E>(executor: E, media_move: &MediaMove) -> DatabaseResult<()>
+where
+    E: sqlx::SqliteExecutor<'e>,
+{
+    sqlx::query(
+        r#"
+        UPDATE media_metadata
+        SET folder_id = ?, file_path = ?, file_name = ?, file_type = ?,
+            quick_hash = COALESCE(quick_hash, ?), thumbnail_path = NULL
+        WHERE id = ?
+        "#,
+    )
+    .bind(media_move.folder_id)
+    .bind(&media_move.file_path)
+    .bind(&media_move.file_name)
+    .bind(&media_move.file_type)
+    .bind(&media_move.quick_hash)
+    .bind(media_move.id)
+    .execute(executor)
+    .await?;
+    
+    Ok(())
+}
+
+/// Applies a batch of moves in a single transaction
+pub async fn move_media_batch(pool: &DbPool, moves: &[MediaMove]) -> DatabaseResult<()> {
+    let mut tx = pool.begin().await?;
+    
+    for media_move in moves {
+        move_media_row(&mut *tx, media_move).await?;
+    }
+    
+    tx.commit().await?;
+    
+    Ok(())
+}
+
+/// Upserts a batch of media rows in a single transaction
+///
+/// Used by the scan-and-index pipeline, which splits large folders into
+/// batches so each one is committed atomically without holding a single
+/// write transaction for the whole walk.
+///
+/// # Arguments
+///
+/// * `pool` - Database connection pool
+/// * `rows` - Rows to insert or update
+pub async fn upsert_media_batch(pool: &DbPool, rows: &[InsertMediaParams]) -> DatabaseResult<()> {
+    let mut tx = pool.begin().await?;
+    
+    for params in rows {
+        upsert_media_metadata(&mut *tx, params).await?;
+    }
+    
+    tx.commit().await?;
+    
+    Ok(())
+}
+
+/// Deletes a batch of media rows by ID in a single transaction
+///
+/// # Arguments
+///
+/// * `pool` - Database connection pool
+/// * `ids` - IDs of the media rows to delete
+pub async fn delete_media_batch(pool: &DbPool, ids: &[i64]) -> DatabaseResult<()> {
+    let mut tx = pool.begin().await?;
+    
+    for id in ids {
+        sqlx::query("DELETE FROM media_metadata WHERE id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    
+    tx.commit().await?;
+    
+    Ok(())
+}
+
+/// Refreshes a folder's `file_count` and sets `last_scanned` to now
+pub async fn update_folder_stats(pool: &DbPool, folder_id: i64, file_count: i64) -> DatabaseResult<()> {
+    sqlx::query("UPDATE scanned_folders SET last_scanned = ?, file_count = ? WHERE id = ?")
+        .bind(Utc::now())
+        .bind(file_count)
+        .bind(folder_id)
+        .execute(pool)
+        .await?;
+    
+    Ok(())
+}
+
 /// Retrieves media metadata by file path for caching purposes
 ///
 /// This function is used to check if metadata already exists for a file
@@ -491,7 +733,13 @@ pub async fn get_media_metadata_by_path(pool: &DbPool, file_path: &str) -> Datab
                width, height, duration, created_date, modified_date,
                thumbnail_path, indexed_at,
                video_codec, video_codec_long, audio_codec, audio_codec_long,
-               bitrate, frame_rate, sample_rate, audio_channels, format, metadata_json
+               bitrate, frame_rate, sample_rate, audio_channels, format, metadata_json,
+               media_type, artist, album, title, track_number,
+               camera_make, camera_model, lens_model, focal_length, aperture,
+               exposure_time, iso, flash, date_taken, orientation,
+               latitude, longitude, altitude,
+               country_code, country, region, city,
+               rating, favorite, color_label, pick_flag
         FROM media_metadata
         WHERE file_path = ?
         "#,
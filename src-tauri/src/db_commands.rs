@@ -5,7 +5,10 @@
 use crate::database::{
     self, DbPool, InsertMediaParams, MediaMetadata, ScannedFolder, UserPreference,
 };
-use tauri::State;
+use crate::watcher::LibraryWatcher;
+use std::path::Path;
+use tauri::{AppHandle, Manager, State};
+use tracing::warn;
 
 /// Error type for command operations
 #[derive(Debug, serde::Serialize)]
@@ -30,8 +33,12 @@ type CommandResult<T> = Result<T, CommandError>;
 
 /// Adds or updates a scanned folder
 ///
+/// The folder is also registered with the library watcher so later changes on
+/// disk are picked up automatically.
+///
 /// # Arguments
 ///
+/// * `app` - Application handle (injected by Tauri)
 /// * `pool` - Database connection pool (injected by Tauri)
 /// * `path` - Full path to the folder
 /// * `name` - Display name of the folder
@@ -42,12 +49,20 @@ type CommandResult<T> = Result<T, CommandError>;
 /// Returns the folder ID
 #[tauri::command]
 pub async fn add_scanned_folder(
+    app: AppHandle,
     pool: State<'_, DbPool>,
     path: String,
     name: String,
     file_count: i64,
 ) -> CommandResult<i64> {
     let folder_id = database::upsert_scanned_folder(&pool, &path, &name, file_count).await?;
+
+    if let Some(watcher) = app.try_state::<LibraryWatcher>() {
+        if let Err(e) = watcher.watch_folder(folder_id, Path::new(&path)) {
+            warn!("Cannot watch folder {}: {}", path, e);
+        }
+    }
+
     Ok(folder_id)
 }
 
@@ -68,16 +83,28 @@ pub async fn get_scanned_folders(pool: State<'_, DbPool>) -> CommandResult<Vec<S
 
 /// Deletes a scanned folder and its associated media
 ///
+/// The folder is unregistered from the library watcher as well.
+///
 /// # Arguments
 ///
+/// * `app` - Application handle (injected by Tauri)
 /// * `pool` - Database connection pool (injected by Tauri)
 /// * `folder_id` - ID of the folder to delete
 #[tauri::command]
 pub async fn delete_scanned_folder(
+    app: AppHandle,
     pool: State<'_, DbPool>,
     folder_id: i64,
 ) -> CommandResult<()> {
+    let folder = database::get_scanned_folder(&pool, folder_id).await?;
     database::delete_scanned_folder(&pool, folder_id).await?;
+
+    if let (Some(folder), Some(watcher)) = (folder, app.try_state::<LibraryWatcher>()) {
+        if let Err(e) = watcher.unwatch_folder(Path::new(&folder.path)) {
+            warn!("Cannot unwatch folder {}: {}", folder.path, e);
+        }
+    }
+
     Ok(())
 }
 
@@ -0,0 +1,550 @@
+//! Duplicate resolution with keep/delete policies
+//!
+//! Applies one decision to many duplicate groups at once: a list of keep
+//! rules picks the copy to keep in every group (later rules break ties of
+//! earlier ones), and an action deals with the other copies:
+//!
+//! * `trash` moves them to the trash (see [`trash`]), so they can be restored.
+//! * `hardlink` replaces them with hard links to the kept file.
+//! * `symlink` replaces them with symbolic links to the kept file.
+//!
+//! Links keep every path (and its row, tags and albums) valid while storing
+//! the data once, so they are only made between exact duplicates with equal
+//! content hashes. A dry run reports the decisions without touching anything.
+
+use crate::database::{DbPool, MediaMetadata};
+use crate::duplicates;
+use crate::trash::{self, TrashLocation};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, QueryBuilder, Sqlite};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use tauri::{AppHandle, State};
+use tracing::info;
+
+/// Media IDs bound per candidate query, well below SQLite's variable limit
+const LOAD_CHUNK_SIZE: usize = 500;
+
+/// Rule deciding which copy of a group is kept
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "rule", rename_all = "snake_case")]
+pub enum KeepRule {
+    /// Largest file, then highest resolution
+    Largest,
+    /// Earliest capture date (modification date when unknown)
+    Oldest,
+    /// Copies inside the given folder
+    PreferredRoot { path: String },
+    /// Most filled-in metadata, tags, album memberships and judgments
+    MostMetadata,
+}
+
+/// What happens to the copies that are not kept
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResolveAction {
+    Trash,
+    Hardlink,
+    Symlink,
+}
+
+/// A resolution request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResolvePolicy {
+    /// Keep rules in order of priority
+    pub rules: Vec<KeepRule>,
+    pub action: ResolveAction,
+    /// Groups of media IDs to resolve; every exact-duplicate group when absent
+    #[serde(default)]
+    pub groups: Option<Vec<Vec<i64>>>,
+    /// Only report what would happen
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// A file of a resolved group
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResolvedFile {
+    pub media_id: i64,
+    pub file_path: String,
+    pub file_size: i64,
+    /// Why the action failed for this file
+    pub error: Option<String>,
+}
+
+/// Decision taken for one group
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupResolution {
+    pub keep: ResolvedFile,
+    pub losers: Vec<ResolvedFile>,
+}
+
+/// Outcome (or, for a dry run, plan) of a resolution
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResolveReport {
+    pub dry_run: bool,
+    pub action: ResolveAction,
+    pub groups: Vec<GroupResolution>,
+    /// Bytes freed (or to be freed) by the successful actions
+    pub reclaimable_bytes: i64,
+    /// Number of files the action failed for
+    pub failed: usize,
+}
+
+/// A group member with what the keep rules look at
+#[derive(FromRow)]
+struct Candidate {
+    #[sqlx(flatten)]
+    media: MediaMetadata,
+    content_hash: Option<String>,
+    tag_count: i64,
+    album_count: i64,
+}
+
+impl Candidate {
+    fn pixels(&self) -> i64 {
+        self.media.width.unwrap_or(0) * self.media.height.unwrap_or(0)
+    }
+    
+    fn date(&self) -> DateTime<Utc> {
+        self.media.created_date.unwrap_or(self.media.modified_date)
+    }
+    
+    /// Number of filled-in metadata fields and user annotations
+    fn metadata_score(&self) -> i64 {
+        let m = &self.media;
+        let fields = [
+            m.width.is_some(),
+            m.height.is_some(),
+            m.duration.is_some(),
+            m.created_date.is_some(),
+            m.video_codec.is_some(),
+            m.audio_codec.is_some(),
+            m.bitrate.is_some(),
+            m.frame_rate.is_some(),
+            m.format.is_some(),
+            m.metadata_json.is_some(),
+            m.artist.is_some(),
+            m.album.is_some(),
+            m.title.is_some(),
+            m.track_number.is_some(),
+            m.rating > 0,
+            m.favorite,
+            m.color_label.is_some(),
+            m.pick_flag.is_some(),
+        ];
+        fields.iter().filter(|&&filled| filled).count() as i64 + self.tag_count + self.album_count
+    }
+}
+
+impl KeepRule {
+    /// Orders two candidates, the one to keep first
+    fn compare(&self, a: &Candidate, b: &Candidate) -> Ordering {
+        match self {
+            KeepRule::Largest => b
+                .media
+                .file_size
+                .cmp(&a.media.file_size)
+                .then_with(|| b.pixels().cmp(&a.pixels())),
+            KeepRule::Oldest => a.date().cmp(&b.date()),
+            KeepRule::PreferredRoot { path } => {
+                let root = Path::new(path);
+                let inside = |c: &Candidate| Path::new(&c.media.file_path).starts_with(root);
+                inside(b).cmp(&inside(a))
+            }
+            KeepRule::MostMetadata => b.metadata_score().cmp(&a.metadata_score()),
+        }
+    }
+}
+
+/// Sorts a group so the copy to keep comes first
+///
+/// Ties left by the rules go to the shortest path, then the oldest row.
+fn rank(candidates: &mut [Candidate], rules: &[KeepRule]) {
+    candidates.sort_by(|a, b| {
+        rules
+            .iter()
+            .fold(Ordering::Equal, |order, rule| order.then_with(|| rule.compare(a, b)))
+            .then_with(|| a.media.file_path.len().cmp(&b.media.file_path.len()))
+            .then_with(|| a.media.id.cmp(&b.media.id))
+    });
+}
+
+/// Loads the non-trashed rows of the given IDs
+async fn load_candidates(pool: &DbPool, media_ids: &[i64]) -> Result<HashMap<i64, Candidate>, String> {
+    let mut candidates = HashMap::with_capacity(media_ids.len());
+    
+    for chunk in media_ids.chunks(LOAD_CHUNK_SIZE) {
+        let mut builder = QueryBuilder::<Sqlite>::new(
+            r#"
+            SELECT m.*,
+                   (SELECT COUNT(*) FROM media_tags WHERE media_id = m.id) AS tag_count,
+                   (SELECT COUNT(*) FROM album_items WHERE media_id = m.id) AS album_count
+            FROM media_metadata m
+            WHERE m.trashed_at IS NULL AND m.id IN (
+            "#,
+        );
+        let mut separated = builder.separated(", ");
+        for id in chunk {
+            separated.push_bind(*id);
+        }
+        builder.push(")");
+        
+        let rows = builder
+            .build_query_as::<Candidate>()
+            .fetch_all(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+        candidates.extend(rows.into_iter().map(|c| (c.media.id, c)));
+    }
+    
+    Ok(candidates)
+}
+
+/// Replaces `path` with a hard or symbolic link to `target`
+///
+/// The link is created next to `path` and renamed over it, so `path` is never
+/// missing and stays untouched if linking fails (e.g. hard links across
+/// filesystems).
+fn replace_with_link(target: &Path, path: &Path, action: ResolveAction) -> io::Result<()> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Path has no file name"))?;
+    let temp = path.with_file_name(format!(".{}.fmlm-link", file_name.to_string_lossy()));
+    let _ = fs::remove_file(&temp);
+    
+    match action {
+        ResolveAction::Hardlink => fs::hard_link(target, &temp)?,
+        #[cfg(unix)]
+        ResolveAction::Symlink => std::os::unix::fs::symlink(target, &temp)?,
+        #[cfg(windows)]
+        ResolveAction::Symlink => std::os::windows::fs::symlink_file(target, &temp)?,
+        _ => return Err(io::Error::new(io::ErrorKind::Unsupported, "Unsupported link type")),
+    }
+    
+    if let Err(e) = fs::rename(&temp, path) {
+        let _ = fs::remove_file(&temp);
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// Picks the copy to keep in every group and applies the action to the others
+///
+/// # Arguments
+///
+/// * `pool` - Database connection pool
+/// * `policy` - Keep rules, action, groups and dry-run flag
+/// * `trash_location` - App trash or system trash, for the trash action
+/// * `app_trash` - The app-managed trash folder
+///
+/// # Returns
+///
+/// Returns the decision and outcome for every group with at least two copies
+pub async fn resolve_duplicates(
+    pool: &DbPool,
+    policy: &ResolvePolicy,
+    trash_location: TrashLocation,
+    app_trash: &Path,
+) -> Result<ResolveReport, String> {
+    let groups: Vec<Vec<i64>> = match &policy.groups {
+        Some(groups) => groups.clone(),
+        None => duplicates::find_duplicates(pool)
+            .await
+            .map_err(|e| e.to_string())?
+            .groups
+            .into_iter()
+            .map(|group| group.media.into_iter().map(|media| media.id).collect())
+            .collect(),
+    };
+    
+    let all_ids: Vec<i64> = groups.iter().flatten().copied().collect();
+    let mut candidates = load_candidates(pool, &all_ids).await?;
+    
+    let mut report = ResolveReport {
+        dry_run: policy.dry_run,
+        action: policy.action,
+        groups: Vec::new(),
+        reclaimable_bytes: 0,
+        failed: 0,
+    };
+    let mut to_trash: Vec<(usize, usize)> = Vec::new();
+    
+    for ids in groups {
+        let mut group: Vec<Candidate> = ids.iter().filter_map(|id| candidates.remove(id)).collect();
+        if group.len() < 2 {
+            continue;
+        }
+        rank(&mut group, &policy.rules);
+        
+        let keep = &group[0];
+        let keep_identity = duplicates::file_identity(&keep.media.file_path);
+        let mut losers = Vec::new();
+        
+        for loser in &group[1..] {
+            // Copies already linked to the kept file free nothing
+            if keep_identity.is_some() && duplicates::file_identity(&loser.media.file_path) == keep_identity {
+                continue;
+            }
+            
+            let mut error = None;
+            if policy.action != ResolveAction::Trash
+                && (loser.content_hash.is_none() || loser.content_hash != keep.content_hash)
+            {
+                error = Some("Not an exact duplicate of the kept file (content hashes differ or are missing)".to_string());
+            } else if !policy.dry_run && policy.action != ResolveAction::Trash {
+                let target = Path::new(&keep.media.file_path);
+                error = replace_with_link(target, Path::new(&loser.media.file_path), policy.action)
+                    .err()
+                    .map(|e| e.to_string());
+            }
+            
+            if !policy.dry_run && policy.action == ResolveAction::Trash {
+                to_trash.push((report.groups.len(), losers.len()));
+            }
+            losers.push(ResolvedFile {
+                media_id: loser.media.id,
+                file_path: loser.media.file_path.clone(),
+                file_size: loser.media.file_size,
+                error,
+            });
+        }
+        
+        report.groups.push(GroupResolution {
+            keep: ResolvedFile {
+                media_id: keep.media.id,
+                file_path: keep.media.file_path.clone(),
+                file_size: keep.media.file_size,
+                error: None,
+            },
+            losers,
+        });
+    }
+    
+    if !to_trash.is_empty() {
+        let ids: Vec<i64> = to_trash
+            .iter()
+            .map(|&(group, loser)| report.groups[group].losers[loser].media_id)
+            .collect();
+        let outcome = trash::trash_media(pool, &ids, trash_location, app_trash).await;
+        let errors: HashMap<i64, String> = outcome
+            .failed
+            .into_iter()
+            .map(|failure| (failure.media_id, failure.error))
+            .collect();
+        for (group, loser) in to_trash {
+            let loser = &mut report.groups[group].losers[loser];
+            loser.error = errors.get(&loser.media_id).cloned();
+        }
+    }
+    
+    for loser in report.groups.iter().flat_map(|group| &group.losers) {
+        match loser.error {
+            None => report.reclaimable_bytes += loser.file_size,
+            Some(_) => report.failed += 1,
+        }
+    }
+    
+    info!(
+        "{} {} duplicate groups: {} bytes reclaimable, {} failures",
+        if policy.dry_run { "Planned" } else { "Resolved" },
+        report.groups.len(),
+        report.reclaimable_bytes,
+        report.failed
+    );
+    Ok(report)
+}
+
+/// Tauri command to resolve duplicate groups with a keep policy
+///
+/// # Arguments
+///
+/// * `app` - Application handle (injected by Tauri)
+/// * `pool` - Database connection pool (injected by Tauri)
+/// * `policy` - Keep rules, action, groups and dry-run flag
+///
+/// # Returns
+///
+/// Returns the decision and outcome for every group
+#[tauri::command]
+pub async fn resolve_duplicates_command(
+    app: AppHandle,
+    pool: State<'_, DbPool>,
+    policy: ResolvePolicy,
+) -> Result<ResolveReport, String> {
+    let settings = trash::load_trash_settings(&pool).await.map_err(|e| e.to_string())?;
+    let app_trash = trash::app_trash_dir(&app).map_err(|e| e.to_string())?;
+    resolve_duplicates(&pool, &policy, settings.location, &app_trash).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{self, InsertMediaParams};
+    use std::sync::atomic::AtomicBool;
+    
+    /// Writes a file and indexes it, returning its row ID
+    async fn add_file(pool: &DbPool, folder_id: i64, path: &Path, content: &[u8]) -> i64 {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+        let params = InsertMediaParams {
+            folder_id,
+            file_path: path.to_string_lossy().to_string(),
+            file_name: path.file_name().unwrap().to_string_lossy().to_string(),
+            file_type: "jpg".to_string(),
+            file_size: content.len() as i64,
+            media_type: Some("image".to_string()),
+            ..Default::default()
+        };
+        database::insert_media_metadata(pool, params).await.unwrap()
+    }
+    
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_resolve_duplicates_with_hardlinks() {
+        let dir = std::env::temp_dir().join(format!("fmlm_dedupe_{}", std::process::id()));
+        let pool = database::open_test_pool().await;
+        let folder_id = database::upsert_scanned_folder(&pool, "/library", "library", 0).await.unwrap();
+        
+        let paths = ["imports/a/IMG_1.jpg", "originals/IMG_1.jpg", "imports/b/IMG_1 (1).jpg"];
+        for name in paths {
+            add_file(&pool, folder_id, &dir.join(name), b"same bytes").await;
+        }
+        duplicates::hash_library(&pool, &AtomicBool::new(false), |_| {}).await.unwrap();
+        
+        let mut policy = ResolvePolicy {
+            rules: vec![
+                KeepRule::PreferredRoot {
+                    path: dir.join("originals").to_string_lossy().to_string(),
+                },
+                KeepRule::Oldest,
+            ],
+            action: ResolveAction::Hardlink,
+            groups: None,
+            dry_run: true,
+        };
+        let app_trash = dir.join("trash");
+        
+        let plan = resolve_duplicates(&pool, &policy, TrashLocation::App, &app_trash).await.unwrap();
+        assert_eq!(plan.groups.len(), 1);
+        assert!(plan.groups[0].keep.file_path.ends_with("originals/IMG_1.jpg"));
+        assert_eq!((plan.reclaimable_bytes, plan.failed), (20, 0));
+        assert_eq!(duplicates::find_duplicates(&pool).await.unwrap().groups.len(), 1);
+        
+        policy.dry_run = false;
+        let report = resolve_duplicates(&pool, &policy, TrashLocation::App, &app_trash).await.unwrap();
+        assert_eq!((report.reclaimable_bytes, report.failed), (20, 0));
+        let identity = |name: &str| duplicates::file_identity(&dir.join(name).to_string_lossy());
+        assert_eq!(identity(paths[0]), identity(paths[1]));
+        assert_eq!(fs::read(dir.join(paths[2])).unwrap(), b"same bytes");
+        
+        // Linked copies are no longer reported as duplicates
+        assert!(duplicates::find_duplicates(&pool).await.unwrap().groups.is_empty());
+        
+        fs::remove_dir_all(&dir).unwrap();
+    }
+    
+    fn policy_for(action: ResolveAction, groups: Option<Vec<Vec<i64>>>) -> ResolvePolicy {
+        ResolvePolicy {
+            rules: vec![KeepRule::Oldest],
+            action,
+            groups,
+            dry_run: false,
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_resolve_duplicates_reports_trash_failures() {
+        let dir = std::env::temp_dir().join(format!("fmlm_dedupe_trash_{}", std::process::id()));
+        let pool = database::open_test_pool().await;
+        let folder_id = database::upsert_scanned_folder(&pool, "/library", "library", 0).await.unwrap();
+        
+        let keep = add_file(&pool, folder_id, &dir.join("a.jpg"), b"same bytes").await;
+        let trashed = add_file(&pool, folder_id, &dir.join("bb.jpg"), b"same bytes").await;
+        let vanished = add_file(&pool, folder_id, &dir.join("ccc.jpg"), b"same bytes").await;
+        duplicates::hash_library(&pool, &AtomicBool::new(false), |_| {}).await.unwrap();
+        // Deleted behind the app's back, so it cannot be moved to the trash
+        fs::remove_file(dir.join("ccc.jpg")).unwrap();
+        
+        let policy = policy_for(ResolveAction::Trash, None);
+        let report = resolve_duplicates(&pool, &policy, TrashLocation::App, &dir.join("trash")).await.unwrap();
+        assert_eq!(report.groups[0].keep.media_id, keep);
+        let errors: Vec<(i64, bool)> = report.groups[0]
+            .losers
+            .iter()
+            .map(|loser| (loser.media_id, loser.error.is_some()))
+            .collect();
+        assert_eq!(errors, [(trashed, false), (vanished, true)]);
+        assert_eq!((report.reclaimable_bytes, report.failed), (10, 1));
+        
+        let trashed_ids: Vec<i64> = trash::list_trash(&pool)
+            .await
+            .unwrap()
+            .iter()
+            .map(|item| item.media.id)
+            .collect();
+        assert_eq!(trashed_ids, [trashed]);
+        assert!(dir.join("a.jpg").exists());
+        
+        fs::remove_dir_all(&dir).unwrap();
+    }
+    
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_resolve_duplicates_with_symlinks() {
+        let dir = std::env::temp_dir().join(format!("fmlm_dedupe_symlink_{}", std::process::id()));
+        let pool = database::open_test_pool().await;
+        let folder_id = database::upsert_scanned_folder(&pool, "/library", "library", 0).await.unwrap();
+        
+        add_file(&pool, folder_id, &dir.join("a.jpg"), b"same bytes").await;
+        add_file(&pool, folder_id, &dir.join("copy/a.jpg"), b"same bytes").await;
+        duplicates::hash_library(&pool, &AtomicBool::new(false), |_| {}).await.unwrap();
+        
+        let policy = policy_for(ResolveAction::Symlink, None);
+        let report = resolve_duplicates(&pool, &policy, TrashLocation::App, &dir.join("trash")).await.unwrap();
+        assert_eq!((report.reclaimable_bytes, report.failed), (10, 0));
+        
+        let link = dir.join("copy/a.jpg");
+        assert!(fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+        assert_eq!(fs::read_link(&link).unwrap(), dir.join("a.jpg"));
+        assert_eq!(fs::read(&link).unwrap(), b"same bytes");
+        
+        fs::remove_dir_all(&dir).unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_links_require_equal_content_hashes() {
+        let dir = std::env::temp_dir().join(format!("fmlm_dedupe_mismatch_{}", std::process::id()));
+        let pool = database::open_test_pool().await;
+        let folder_id = database::upsert_scanned_folder(&pool, "/library", "library", 0).await.unwrap();
+        
+        let keep = add_file(&pool, folder_id, &dir.join("a.jpg"), b"same bytes").await;
+        let different = add_file(&pool, folder_id, &dir.join("bb.jpg"), b"diff bytes").await;
+        let unhashed = add_file(&pool, folder_id, &dir.join("ccc.jpg"), b"same bytes").await;
+        for (id, hash) in [(keep, "1111"), (different, "2222")] {
+            sqlx::query("UPDATE media_metadata SET content_hash = ? WHERE id = ?")
+                .bind(hash)
+                .bind(id)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+        
+        let groups = Some(vec![vec![keep, different, unhashed]]);
+        let policy = policy_for(ResolveAction::Hardlink, groups);
+        let report = resolve_duplicates(&pool, &policy, TrashLocation::App, &dir.join("trash")).await.unwrap();
+        assert_eq!((report.reclaimable_bytes, report.failed), (0, 2));
+        assert!(report.groups[0].losers.iter().all(|loser| loser.error.is_some()));
+        
+        // Neither copy was replaced
+        assert_eq!(fs::read(dir.join("bb.jpg")).unwrap(), b"diff bytes");
+        let identity = |name: &str| duplicates::file_identity(&dir.join(name).to_string_lossy());
+        assert_ne!(identity("a.jpg"), identity("ccc.jpg"));
+        
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,624 @@
+//! Content hashing job and exact-duplicate detection
+//!
+//! Hashing every file in a large library is expensive, so hashes are computed
+//! in stages and only where they can tell files apart:
+//!
+//! 1. Files that share their size with another file get a quick hash
+//!    (size plus the first and last 64 KiB, see [`hashing::quick_hash`]).
+//! 2. Files that share size and quick hash get a full content hash
+//!    ([`hashing::content_hash`]), which confirms exact duplicates.
+//! 3. Images get perceptual hashes (see [`similar`](crate::similar)) unless
+//!    a thumbnail generation already computed them.
+//! 4. Videos get frame fingerprints for near-duplicate detection.
+//!
+//! The stages run in a background job that reports `hash-progress` events and
+//! can be cancelled; hashes already written are kept, so the next run resumes
+//! where it stopped. Hashes are cleared by the upsert when a file changes.
+
+use crate::database::{DatabaseResult, DbPool, MediaMetadata};
+use crate::hashing;
+use crate::perceptual::{self, ImageHashes};
+use crate::similar;
+use crate::thumbnail::{self, ThumbnailError};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, Row};
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, State};
+use tracing::{error, info, warn};
+
+/// Event emitted periodically while the hashing job runs
+pub const HASH_PROGRESS_EVENT: &str = "hash-progress";
+
+/// Event emitted once when the hashing job completes, fails or is cancelled
+pub const HASH_FINISHED_EVENT: &str = "hash-finished";
+
+/// Number of files hashed between two database writes
+const HASH_BATCH_SIZE: usize = 64;
+
+/// Minimum time between two progress events
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Stage of the hashing job
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashPhase {
+    /// Quick hashes of files sharing their size
+    #[default]
+    Quick,
+    /// Full content hashes of files sharing their quick hash
+    Full,
+    /// Perceptual hashes of images
+    Perceptual,
+    /// Frame fingerprints of videos
+    Video,
+}
+
+impl HashPhase {
+    fn columns(self) -> &'static [&'static str] {
+        match self {
+            HashPhase::Quick => &["quick_hash"],
+            HashPhase::Full => &["content_hash"],
+            HashPhase::Perceptual => &["dhash", "phash"],
+            HashPhase::Video => &["video_fingerprint"],
+        }
+    }
+    
+    /// Rows that still need a hash in this phase
+    fn candidates_query(self) -> &'static str {
+        match self {
+            HashPhase::Quick => {
+                r#"
+                SELECT id, file_path FROM media_metadata
+                WHERE quick_hash IS NULL AND trashed_at IS NULL AND file_size IN (
+                    SELECT file_size FROM media_metadata
+                    WHERE trashed_at IS NULL
+                    GROUP BY file_size HAVING COUNT(*) > 1
+                )
+                "#
+            }
+            HashPhase::Full => {
+                r#"
+                SELECT id, file_path FROM media_metadata
+                WHERE content_hash IS NULL AND trashed_at IS NULL AND quick_hash IN (
+                    SELECT quick_hash FROM media_metadata
+                    WHERE trashed_at IS NULL AND quick_hash IS NOT NULL
+                    GROUP BY quick_hash HAVING COUNT(*) > 1
+                )
+                "#
+            }
+            HashPhase::Perceptual => {
+                r#"
+                SELECT id, file_path FROM media_metadata
+                WHERE phash IS NULL AND trashed_at IS NULL AND media_type = 'image'
+                "#
+            }
+            HashPhase::Video => {
+                r#"
+                SELECT id, file_path FROM media_metadata
+                WHERE video_fingerprint IS NULL AND trashed_at IS NULL AND media_type = 'video'
+                "#
+            }
+        }
+    }
+    
+    /// Computes the values of [`columns`](Self::columns) for a file
+    ///
+    /// Images and videos that can't be decoded get empty perceptual hashes so
+    /// they are skipped by later runs; only read errors are reported.
+    fn hash(self, path: &Path) -> Result<Vec<String>, String> {
+        let hashes = match self {
+            HashPhase::Quick => vec![hashing::quick_hash(path).map_err(|e| e.to_string())?],
+            HashPhase::Full => vec![hashing::content_hash(path).map_err(|e| e.to_string())?],
+            HashPhase::Perceptual => match thumbnail::load_image(&path.to_string_lossy()) {
+                Ok(img) => {
+                    let hashes = ImageHashes::compute(&img);
+                    vec![perceptual::to_hex(hashes.dhash), perceptual::to_hex(hashes.phash)]
+                }
+                Err(ThumbnailError::Io(e)) => return Err(e.to_string()),
+                Err(_) => vec![String::new(), String::new()],
+            },
+            HashPhase::Video => match similar::video_fingerprint(&path.to_string_lossy()) {
+                Ok(fingerprint) => vec![similar::fingerprint_to_string(&fingerprint)],
+                Err(ThumbnailError::Io(e)) => return Err(e.to_string()),
+                Err(_) => vec![String::new()],
+            },
+        };
+        Ok(hashes)
+    }
+}
+
+/// Progress counters of the hashing job
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HashProgress {
+    pub phase: HashPhase,
+    /// Files to hash in the current phase
+    pub total: u64,
+    /// Files hashed so far in the current phase
+    pub hashed: u64,
+    /// Files that could not be read (across phases)
+    pub error_count: u64,
+}
+
+/// Lifecycle state of the hashing job
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "state", content = "message", rename_all = "lowercase")]
+pub enum HashJobStatus {
+    Running,
+    Completed,
+    Cancelled,
+    Failed(String),
+}
+
+/// Snapshot of the hashing job sent to the frontend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HashJobInfo {
+    pub status: HashJobStatus,
+    pub progress: HashProgress,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+struct HashJob {
+    info: Mutex<HashJobInfo>,
+    cancelled: AtomicBool,
+}
+
+/// Keeps track of the hashing job; only one runs at a time.
+///
+/// Managed as Tauri state. The last job stays available for status queries
+/// after it finishes.
+#[derive(Default)]
+pub struct HashJobManager {
+    current: Mutex<Option<Arc<HashJob>>>,
+}
+
+/// Media rows sharing the same content
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateGroup {
+    pub content_hash: String,
+    /// Size of each copy in bytes
+    pub file_size: i64,
+    /// The copies, sorted by path
+    pub media: Vec<MediaMetadata>,
+    /// Bytes freed by keeping a single copy
+    pub reclaimable_bytes: i64,
+}
+
+/// All exact-duplicate groups of the library
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DuplicateReport {
+    /// Groups, largest reclaimable size first
+    pub groups: Vec<DuplicateGroup>,
+    pub total_reclaimable_bytes: i64,
+}
+
+#[derive(FromRow)]
+struct HashedMedia {
+    #[sqlx(flatten)]
+    media: MediaMetadata,
+    content_hash: String,
+}
+
+/// Computes the hashes of one phase, writing them batch by batch
+async fn run_phase<F>(
+    pool: &DbPool,
+    phase: HashPhase,
+    cancelled: &AtomicBool,
+    progress: &mut HashProgress,
+    on_progress: &mut F,
+) -> DatabaseResult<()>
+where
+    F: FnMut(&HashProgress),
+{
+    let candidates: Vec<(i64, String)> = sqlx::query(phase.candidates_query())
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|row| (row.get(0), row.get(1)))
+        .collect();
+    
+    progress.phase = phase;
+    progress.total = candidates.len() as u64;
+    progress.hashed = 0;
+    on_progress(progress);
+    
+    let assignments: Vec<String> = phase.columns().iter().map(|column| format!("{} = ?", column)).collect();
+    let update = format!("UPDATE media_metadata SET {} WHERE id = ?", assignments.join(", "));
+    for chunk in candidates.chunks(HASH_BATCH_SIZE) {
+        if cancelled.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+        
+        let files = chunk.to_vec();
+        let hashes = tokio::task::spawn_blocking(move || {
+            files
+                .into_iter()
+                .map(|(id, path)| {
+                    let hash = phase.hash(Path::new(&path)).map_err(|e| format!("{}: {}", path, e));
+                    (id, hash)
+                })
+                .collect::<Vec<_>>()
+        })
+        .await;
+        // A panicking decoder loses the whole chunk; count it as unreadable
+        let hashes = match hashes {
+            Ok(hashes) => hashes,
+            Err(e) => {
+                error!("Hashing task failed: {}", e);
+                progress.error_count += chunk.len() as u64;
+                on_progress(progress);
+                continue;
+            }
+        };
+        
+        let mut tx = pool.begin().await?;
+        for (id, hash) in hashes {
+            match hash {
+                Ok(values) => {
+                    let mut query = sqlx::query(&update);
+                    for value in values {
+                        query = query.bind(value);
+                    }
+                    query.bind(id).execute(&mut *tx).await?;
+                }
+                Err(e) => {
+                    warn!("Cannot hash {}", e);
+                    progress.error_count += 1;
+                }
+            }
+        }
+        tx.commit().await?;
+        
+        progress.hashed += chunk.len() as u64;
+        on_progress(progress);
+    }
+    
+    Ok(())
+}
+
+/// Computes the missing hashes needed for duplicate and similarity detection
+///
+/// # Arguments
+///
+/// * `pool` - Database connection pool
+/// * `cancelled` - Checked between batches; set it to stop early
+/// * `on_progress` - Called after every batch
+///
+/// # Returns
+///
+/// Returns the final progress counters
+pub async fn hash_library<F>(
+    pool: &DbPool,
+    cancelled: &AtomicBool,
+    mut on_progress: F,
+) -> DatabaseResult<HashProgress>
+where
+    F: FnMut(&HashProgress),
+{
+    let mut progress = HashProgress::default();
+    for phase in [HashPhase::Quick, HashPhase::Full, HashPhase::Perceptual, HashPhase::Video] {
+        run_phase(pool, phase, cancelled, &mut progress, &mut on_progress).await?;
+    }
+    Ok(progress)
+}
+
+/// Computes full content hashes right away for the given rows
+///
+/// # Returns
+///
+/// Returns the number of rows hashed
+pub async fn hash_media_now(pool: &DbPool, media_ids: &[i64]) -> DatabaseResult<u64> {
+    let mut hashed = 0;
+    for &media_id in media_ids {
+        let Some(row) = sqlx::query("SELECT file_path FROM media_metadata WHERE id = ?")
+            .bind(media_id)
+            .fetch_optional(pool)
+            .await?
+        else {
+            continue;
+        };
+        let path: String = row.get(0);
+        let hashes = tokio::task::spawn_blocking(move || {
+            let path = Path::new(&path);
+            Ok::<_, std::io::Error>((hashing::quick_hash(path)?, hashing::content_hash(path)?))
+        })
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+        
+        match hashes {
+            Ok((quick, full)) => {
+                sqlx::query("UPDATE media_metadata SET quick_hash = ?, content_hash = ? WHERE id = ?")
+                    .bind(quick)
+                    .bind(full)
+                    .bind(media_id)
+                    .execute(pool)
+                    .await?;
+                hashed += 1;
+            }
+            Err(e) => warn!("Cannot hash media {}: {}", media_id, e),
+        }
+    }
+    Ok(hashed)
+}
+
+/// Identifies the data a path points to (device and inode on Unix)
+///
+/// Hard links and symlinks to the same file share an identity, so copies
+/// already linked together don't count as reclaimable. Returns `None` where
+/// this can't be determined.
+pub(crate) fn file_identity(path: &str) -> Option<(u64, u64)> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        std::fs::metadata(path).ok().map(|metadata| (metadata.dev(), metadata.ino()))
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        None
+    }
+}
+
+/// Groups media rows with identical content hashes
+///
+/// Only rows hashed so far are considered; run [`hash_library`] first.
+/// Copies that are already hard links or symlinks to each other only count
+/// once, and groups made only of linked copies are left out.
+pub async fn find_duplicates(pool: &DbPool) -> DatabaseResult<DuplicateReport> {
+    let rows = sqlx::query_as::<_, HashedMedia>(
+        r#"
+        SELECT * FROM media_metadata
+        WHERE trashed_at IS NULL AND content_hash IN (
+            SELECT content_hash FROM media_metadata
+            WHERE trashed_at IS NULL AND content_hash IS NOT NULL
+            GROUP BY content_hash HAVING COUNT(*) > 1
+        )
+        ORDER BY content_hash, file_path
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+    
+    let mut groups: Vec<DuplicateGroup> = Vec::new();
+    for row in rows {
+        match groups.last_mut() {
+            Some(group) if group.content_hash == row.content_hash => group.media.push(row.media),
+            _ => groups.push(DuplicateGroup {
+                content_hash: row.content_hash,
+                file_size: row.media.file_size,
+                media: vec![row.media],
+                reclaimable_bytes: 0,
+            }),
+        }
+    }
+    
+    groups.retain_mut(|group| {
+        let mut identities = HashSet::new();
+        let distinct = group
+            .media
+            .iter()
+            .filter(|media| file_identity(&media.file_path).is_none_or(|identity| identities.insert(identity)))
+            .count();
+        group.reclaimable_bytes = group.file_size * (distinct as i64 - 1);
+        distinct > 1
+    });
+    groups.sort_by_key(|group| std::cmp::Reverse(group.reclaimable_bytes));
+    
+    Ok(DuplicateReport {
+        total_reclaimable_bytes: groups.iter().map(|group| group.reclaimable_bytes).sum(),
+        groups,
+    })
+}
+
+/// Updates the job snapshot and emits a progress event
+fn publish(app_handle: &AppHandle, job: &HashJob, progress: &HashProgress) {
+    let snapshot = match job.info.lock() {
+        Ok(mut info) => {
+            info.progress = progress.clone();
+            info.clone()
+        }
+        Err(_) => return,
+    };
+    if let Err(e) = app_handle.emit(HASH_PROGRESS_EVENT, &snapshot) {
+        error!("Failed to emit hash progress: {}", e);
+    }
+}
+
+/// Starts hashing the library in the background
+///
+/// # Arguments
+///
+/// * `app` - Application handle (injected by Tauri)
+/// * `manager` - Hash job registry (injected by Tauri)
+/// * `pool` - Database connection pool (injected by Tauri)
+///
+/// # Errors
+///
+/// Returns an error if a hashing job is already running.
+#[tauri::command]
+pub async fn start_hashing(
+    app: AppHandle,
+    manager: State<'_, HashJobManager>,
+    pool: State<'_, DbPool>,
+) -> Result<HashJobInfo, String> {
+    let info = HashJobInfo {
+        status: HashJobStatus::Running,
+        progress: HashProgress::default(),
+        started_at: Utc::now(),
+        finished_at: None,
+    };
+    let job = Arc::new(HashJob {
+        info: Mutex::new(info.clone()),
+        cancelled: AtomicBool::new(false),
+    });
+    
+    {
+        let mut current = manager
+            .current
+            .lock()
+            .map_err(|_| "Hash job registry is poisoned".to_string())?;
+        let running = current
+            .as_ref()
+            .and_then(|job| job.info.lock().ok().map(|info| info.status == HashJobStatus::Running))
+            .unwrap_or(false);
+        if running {
+            return Err("A hashing job is already running".to_string());
+        }
+        *current = Some(job.clone());
+    }
+    
+    let pool = pool.inner().clone();
+    tauri::async_runtime::spawn(async move {
+        let mut last_emit = Instant::now();
+        let result = hash_library(&pool, &job.cancelled, |progress| {
+            if last_emit.elapsed() >= PROGRESS_INTERVAL {
+                last_emit = Instant::now();
+                publish(&app, &job, progress);
+            }
+        })
+        .await;
+        
+        let status = match result {
+            Ok(progress) => {
+                publish(&app, &job, &progress);
+                if job.cancelled.load(Ordering::Relaxed) {
+                    HashJobStatus::Cancelled
+                } else {
+                    HashJobStatus::Completed
+                }
+            }
+            Err(e) => HashJobStatus::Failed(e.to_string()),
+        };
+        
+        let snapshot = match job.info.lock() {
+            Ok(mut info) => {
+                info.status = status;
+                info.finished_at = Some(Utc::now());
+                info.clone()
+            }
+            Err(_) => return,
+        };
+        info!("Hashing job finished: {:?}", snapshot.status);
+        if let Err(e) = app.emit(HASH_FINISHED_EVENT, &snapshot) {
+            error!("Failed to emit hash finished event: {}", e);
+        }
+    });
+    
+    Ok(info)
+}
+
+/// Tauri command to get the state of the current (or last) hashing job
+#[tauri::command]
+pub fn get_hashing_status(manager: State<'_, HashJobManager>) -> Result<Option<HashJobInfo>, String> {
+    let current = manager
+        .current
+        .lock()
+        .map_err(|_| "Hash job registry is poisoned".to_string())?;
+    Ok(current.as_ref().and_then(|job| job.info.lock().ok().map(|info| info.clone())))
+}
+
+/// Tauri command to stop the running hashing job after its current batch
+#[tauri::command]
+pub fn cancel_hashing(manager: State<'_, HashJobManager>) -> Result<(), String> {
+    let current = manager
+        .current
+        .lock()
+        .map_err(|_| "Hash job registry is poisoned".to_string())?;
+    if let Some(job) = current.as_ref() {
+        job.cancelled.store(true, Ordering::Relaxed);
+    }
+    Ok(())
+}
+
+/// Tauri command to compute full content hashes for specific media right away
+///
+/// # Returns
+///
+/// Returns the number of rows hashed
+#[tauri::command]
+pub async fn hash_media(pool: State<'_, DbPool>, media_ids: Vec<i64>) -> Result<u64, String> {
+    hash_media_now(&pool, &media_ids).await.map_err(|e| e.to_string())
+}
+
+/// Tauri command to list exact-duplicate groups with their reclaimable bytes
+#[tauri::command]
+pub async fn get_duplicates(pool: State<'_, DbPool>) -> Result<DuplicateReport, String> {
+    find_duplicates(&pool).await.map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{self, InsertMediaParams};
+    use std::fs;
+    
+    #[tokio::test]
+    async fn test_hash_library_finds_exact_duplicates() {
+        let dir = std::env::temp_dir().join(format!("fmlm_duplicates_{}", std::process::id()));
+        let pool = database::open_test_pool().await;
+        let folder_id = database::upsert_scanned_folder(&pool, "/backups", "backups", 0).await.unwrap();
+        
+        // The same phone backup copied into three folders, plus a same-size
+        // file with different content and a file of unique size
+        let files = [
+            ("2023/backup/img.jpg", &b"phone photo"[..]),
+            ("2024/backup/img.jpg", b"phone photo"),
+            ("old/copy of img.jpg", b"phone photo"),
+            ("other/same_size.jpg", b"other photo"),
+            ("other/unique.jpg", b"a photo of unique length"),
+        ];
+        for (name, content) in files {
+            let path = dir.join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, content).unwrap();
+            let params = InsertMediaParams {
+                folder_id,
+                file_path: path.to_string_lossy().to_string(),
+                file_name: path.file_name().unwrap().to_string_lossy().to_string(),
+                file_type: "jpg".to_string(),
+                file_size: content.len() as i64,
+                media_type: Some("image".to_string()),
+                ..Default::default()
+            };
+            database::insert_media_metadata(&pool, params).await.unwrap();
+        }
+        
+        let mut phases = Vec::new();
+        let progress = hash_library(&pool, &AtomicBool::new(false), |progress| {
+            phases.push((progress.phase, progress.total, progress.hashed));
+        })
+        .await
+        .unwrap();
+        assert_eq!(progress.error_count, 0);
+        // Four files share a size, three of those share the quick hash
+        assert!(phases.contains(&(HashPhase::Quick, 4, 4)));
+        assert!(phases.contains(&(HashPhase::Full, 3, 3)));
+        // The fake images can't be decoded and are marked as such
+        assert!(phases.contains(&(HashPhase::Perceptual, 5, 5)));
+        
+        let report = find_duplicates(&pool).await.unwrap();
+        assert_eq!(report.groups.len(), 1);
+        let group = &report.groups[0];
+        assert_eq!(group.media.len(), 3);
+        assert_eq!(group.reclaimable_bytes, 2 * 11);
+        assert_eq!(report.total_reclaimable_bytes, 22);
+        
+        // Nothing left to do on a second run
+        let progress = hash_library(&pool, &AtomicBool::new(false), |_| {}).await.unwrap();
+        assert_eq!((progress.phase, progress.total), (HashPhase::Video, 0));
+        
+        // On-demand hashing covers files outside any collision
+        let unique = database::get_media_metadata_by_path(&pool, &dir.join("other/unique.jpg").to_string_lossy())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(hash_media_now(&pool, &[unique.id]).await.unwrap(), 1);
+        
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
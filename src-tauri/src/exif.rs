@@ -0,0 +1,384 @@
+//! EXIF metadata of photos
+//!
+//! Reads the EXIF block of JPEG, TIFF (and TIFF-based RAW), HEIF/HEIC, PNG
+//! and WebP files with the `kamadak-exif` crate. Two views are returned:
+//!
+//! - every readable tag of the primary image as display text, keyed by its
+//!   EXIF name (`Make`, `FNumber`, `DateTimeOriginal`, ...), for the info panel
+//!   and full-text search
+//! - the camera settings worth filtering and sorting on as typed values
+//!   ([`CameraInfo`]), stored in their own database columns, and the GPS
+//!   position ([`GeoLocation`])
+
+use crate::location::GeoLocation;
+use ::exif::{Exif, Field, In, Reader, Tag, Value};
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use thiserror::Error;
+
+/// Binary values longer than this (maker notes, embedded previews) are not
+/// worth showing as text
+const MAX_UNDEFINED_LEN: usize = 32;
+
+/// Format of the local capture time in [`CameraInfo::date_taken`]
+const LOCAL_DATE_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.f";
+
+/// Error types for EXIF reading
+#[derive(Debug, Error)]
+pub enum ExifError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    
+    #[error("Invalid EXIF data: {0}")]
+    Parse(#[from] ::exif::Error),
+}
+
+pub type ExifResult<T> = Result<T, ExifError>;
+
+/// Camera and exposure settings of a photo
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CameraInfo {
+    /// Camera manufacturer (e.g. "Canon")
+    pub make: Option<String>,
+    
+    /// Camera model (e.g. "Canon EOS R5")
+    pub model: Option<String>,
+    
+    /// Lens model (e.g. "RF24-105mm F4 L IS USM")
+    pub lens: Option<String>,
+    
+    /// Focal length in millimeters
+    pub focal_length: Option<f64>,
+    
+    /// Aperture as an f-number (e.g. 2.8)
+    pub aperture: Option<f64>,
+    
+    /// Exposure time in seconds
+    pub exposure_time: Option<f64>,
+    
+    /// ISO sensitivity
+    pub iso: Option<i64>,
+    
+    /// Whether the flash fired
+    pub flash: Option<bool>,
+    
+    /// Capture time as recorded by the camera (`2024-05-01T14:03:22`), with
+    /// the UTC offset appended when the camera stored one
+    pub date_taken: Option<String>,
+    
+    /// EXIF orientation (1-8, 1 being upright)
+    pub orientation: Option<i64>,
+}
+
+impl CameraInfo {
+    /// Whether no setting was found
+    pub fn is_empty(&self) -> bool {
+        *self == CameraInfo::default()
+    }
+    
+    /// Capture time as an instant
+    ///
+    /// Times without an offset are taken to be in the computer's time zone,
+    /// which is usually the one the camera clock was set to.
+    pub fn captured_at(&self) -> Option<DateTime<Utc>> {
+        let date_taken = self.date_taken.as_deref()?;
+        if let Ok(date) = DateTime::parse_from_rfc3339(date_taken) {
+            return Some(date.with_timezone(&Utc));
+        }
+        let naive = NaiveDateTime::parse_from_str(date_taken, LOCAL_DATE_FORMAT).ok()?;
+        Local
+            .from_local_datetime(&naive)
+            .earliest()
+            .map(|date| date.with_timezone(&Utc))
+    }
+}
+
+/// EXIF metadata of one file
+#[derive(Debug, Clone, Default)]
+pub struct ExifData {
+    /// Typed camera settings
+    pub camera: CameraInfo,
+    
+    /// Where the photo was taken
+    pub location: Option<GeoLocation>,
+    
+    /// Every readable tag as display text
+    pub tags: HashMap<String, String>,
+}
+
+/// Reads the EXIF metadata of a file
+///
+/// # Errors
+///
+/// Returns an error if the file can't be read, its container is not
+/// supported or it holds no (valid) EXIF block.
+pub fn read_exif(path: &Path) -> ExifResult<ExifData> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let exif = Reader::new().read_from_container(&mut reader)?;
+    Ok(parse_exif(&exif))
+}
+
+/// Extracts the typed settings and display tags from parsed EXIF data
+pub fn parse_exif(exif: &Exif) -> ExifData {
+    let field = |tag: Tag| exif.get_field(tag, In::PRIMARY);
+    let text = |tag: Tag| field(tag).and_then(ascii);
+    let number = |tag: Tag| field(tag).and_then(rational);
+    let integer = |tag: Tag| field(tag).and_then(|field| field.value.get_uint(0)).map(i64::from);
+    
+    let camera = CameraInfo {
+        make: text(Tag::Make),
+        model: text(Tag::Model),
+        lens: text(Tag::LensModel),
+        focal_length: number(Tag::FocalLength),
+        aperture: number(Tag::FNumber),
+        exposure_time: number(Tag::ExposureTime),
+        iso: integer(Tag::PhotographicSensitivity).filter(|&iso| iso > 0),
+        flash: integer(Tag::Flash).map(|flash| flash & 1 == 1),
+        date_taken: date_taken(exif),
+        orientation: integer(Tag::Orientation).filter(|orientation| (1..=8).contains(orientation)),
+    };
+    
+    let mut tags = HashMap::new();
+    for field in exif.fields().filter(|field| field.ifd_num == In::PRIMARY) {
+        // Skip tags the library doesn't know and undisplayable blobs
+        if field.tag.description().is_none() {
+            continue;
+        }
+        let value = match &field.value {
+            Value::Ascii(_) => ascii(field),
+            Value::Undefined(bytes, _) if bytes.len() > MAX_UNDEFINED_LEN => None,
+            _ => Some(field.display_value().with_unit(exif).to_string()),
+        };
+        if let Some(value) = value.filter(|value| !value.is_empty()) {
+            tags.entry(field.tag.to_string()).or_insert(value);
+        }
+    }
+    
+    ExifData {
+        camera,
+        location: gps_location(exif),
+        tags,
+    }
+}
+
+/// Text of an ASCII field, without padding
+fn ascii(field: &Field) -> Option<String> {
+    match &field.value {
+        Value::Ascii(values) => {
+            let text = values
+                .iter()
+                .map(|value| String::from_utf8_lossy(value).trim().to_string())
+                .filter(|value| !value.is_empty())
+                .collect::<Vec<_>>()
+                .join(", ");
+            (!text.is_empty()).then_some(text)
+        }
+        _ => None,
+    }
+}
+
+/// First value of a (signed) rational field
+fn rational(field: &Field) -> Option<f64> {
+    let value = match &field.value {
+        Value::Rational(values) => values.first()?.to_f64(),
+        Value::SRational(values) => values.first()?.to_f64(),
+        _ => return None,
+    };
+    value.is_finite().then_some(value)
+}
+
+/// Position from the GPS tags, in signed decimal degrees
+fn gps_location(exif: &Exif) -> Option<GeoLocation> {
+    let field = |tag: Tag| exif.get_field(tag, In::PRIMARY);
+    let reference = |tag: Tag| field(tag).and_then(ascii).unwrap_or_default().to_uppercase();
+    let degrees = |tag: Tag| match &field(tag)?.value {
+        Value::Rational(parts) if !parts.is_empty() => {
+            let part = |index: usize| parts.get(index).map_or(0.0, |part| part.to_f64());
+            let degrees = part(0) + part(1) / 60.0 + part(2) / 3600.0;
+            degrees.is_finite().then_some(degrees)
+        }
+        _ => None,
+    };
+    
+    let mut latitude = degrees(Tag::GPSLatitude)?;
+    if reference(Tag::GPSLatitudeRef) == "S" {
+        latitude = -latitude;
+    }
+    let mut longitude = degrees(Tag::GPSLongitude)?;
+    if reference(Tag::GPSLongitudeRef) == "W" {
+        longitude = -longitude;
+    }
+    // Altitude reference 1 means below sea level
+    let altitude = field(Tag::GPSAltitude).and_then(rational).map(|altitude| {
+        match field(Tag::GPSAltitudeRef).and_then(|field| field.value.get_uint(0)) {
+            Some(1) => -altitude,
+            _ => altitude,
+        }
+    });
+    GeoLocation::new(latitude, longitude, altitude)
+}
+
+/// Capture time from `DateTimeOriginal` (or `DateTime`) with sub-seconds and offset
+fn date_taken(exif: &Exif) -> Option<String> {
+    let field = |tag: Tag| exif.get_field(tag, In::PRIMARY);
+    let (date_tag, subsec_tag, offset_tag) = if field(Tag::DateTimeOriginal).is_some() {
+        (Tag::DateTimeOriginal, Tag::SubSecTimeOriginal, Tag::OffsetTimeOriginal)
+    } else {
+        (Tag::DateTime, Tag::SubSecTime, Tag::OffsetTime)
+    };
+    
+    let mut date = match &field(date_tag)?.value {
+        Value::Ascii(values) => ::exif::DateTime::from_ascii(values.first()?).ok()?,
+        _ => return None,
+    };
+    if let Some(Value::Ascii(values)) = field(subsec_tag).map(|field| &field.value) {
+        let _ = date.parse_subsec(values.first()?);
+    }
+    if let Some(Value::Ascii(values)) = field(offset_tag).map(|field| &field.value) {
+        let _ = date.parse_offset(values.first()?);
+    }
+    
+    let naive = NaiveDate::from_ymd_opt(i32::from(date.year), u32::from(date.month), u32::from(date.day))?
+        .and_hms_nano_opt(
+            u32::from(date.hour),
+            u32::from(date.minute),
+            u32::from(date.second),
+            date.nanosecond.unwrap_or(0),
+        )?;
+    match date.offset.and_then(|minutes| FixedOffset::east_opt(i32::from(minutes) * 60)) {
+        Some(offset) => {
+            let date = offset.from_local_datetime(&naive).single()?;
+            Some(date.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, false))
+        }
+        None => Some(naive.format(LOCAL_DATE_FORMAT).to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::exif::experimental::Writer;
+    use ::exif::Rational;
+    use image::{DynamicImage, RgbImage};
+    use std::io::Cursor;
+    
+    /// Encodes the fields as a TIFF-structured EXIF block
+    fn exif_block(fields: &[Field]) -> Vec<u8> {
+        let mut writer = Writer::new();
+        for field in fields {
+            writer.push_field(field);
+        }
+        let mut block = Cursor::new(Vec::new());
+        writer.write(&mut block, false).unwrap();
+        block.into_inner()
+    }
+    
+    /// A small JPEG with the EXIF block in an APP1 segment after the SOI marker
+    fn jpeg_with_exif(block: &[u8]) -> Vec<u8> {
+        let mut jpeg = Vec::new();
+        DynamicImage::ImageRgb8(RgbImage::new(8, 8))
+            .write_to(&mut Cursor::new(&mut jpeg), image::ImageFormat::Jpeg)
+            .unwrap();
+        
+        let mut segment = b"Exif\0\0".to_vec();
+        segment.extend_from_slice(block);
+        let mut file = jpeg[..2].to_vec();
+        file.extend_from_slice(&[0xFF, 0xE1]);
+        file.extend_from_slice(&(segment.len() as u16 + 2).to_be_bytes());
+        file.extend_from_slice(&segment);
+        file.extend_from_slice(&jpeg[2..]);
+        file
+    }
+    
+    fn field(tag: Tag, value: Value) -> Field {
+        Field {
+            tag,
+            ifd_num: In::PRIMARY,
+            value,
+        }
+    }
+    
+    fn ascii_value(text: &str) -> Value {
+        Value::Ascii(vec![text.as_bytes().to_vec()])
+    }
+    
+    #[test]
+    fn test_read_exif_from_jpeg() {
+        let fields = [
+            field(Tag::Make, ascii_value("Canon")),
+            field(Tag::Model, ascii_value("Canon EOS R5 ")),
+            field(Tag::Orientation, Value::Short(vec![6])),
+            field(Tag::LensModel, ascii_value("RF24-105mm F4 L IS USM")),
+            field(Tag::FocalLength, Value::Rational(vec![Rational { num: 50, denom: 1 }])),
+            field(Tag::FNumber, Value::Rational(vec![Rational { num: 28, denom: 10 }])),
+            field(Tag::ExposureTime, Value::Rational(vec![Rational { num: 1, denom: 250 }])),
+            field(Tag::PhotographicSensitivity, Value::Short(vec![400])),
+            field(Tag::Flash, Value::Short(vec![0x19])),
+            field(Tag::DateTimeOriginal, ascii_value("2024:05:01 14:03:22")),
+            field(Tag::SubSecTimeOriginal, ascii_value("5")),
+            field(Tag::OffsetTimeOriginal, ascii_value("+02:00")),
+            field(Tag::GPSLatitudeRef, ascii_value("S")),
+            field(Tag::GPSLatitude, Value::Rational(vec![
+                Rational { num: 33, denom: 1 },
+                Rational { num: 51, denom: 1 },
+                Rational { num: 2448, denom: 100 },
+            ])),
+            field(Tag::GPSLongitudeRef, ascii_value("E")),
+            field(Tag::GPSLongitude, Value::Rational(vec![
+                Rational { num: 151, denom: 1 },
+                Rational { num: 12, denom: 1 },
+                Rational { num: 5508, denom: 100 },
+            ])),
+            field(Tag::GPSAltitude, Value::Rational(vec![Rational { num: 10, denom: 1 }])),
+            field(Tag::GPSAltitudeRef, Value::Byte(vec![1])),
+        ];
+        let path = std::env::temp_dir().join(format!("fmlm_exif_{}.jpg", std::process::id()));
+        std::fs::write(&path, jpeg_with_exif(&exif_block(&fields))).unwrap();
+        
+        let data = read_exif(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        
+        assert_eq!(
+            data.camera,
+            CameraInfo {
+                make: Some("Canon".to_string()),
+                model: Some("Canon EOS R5".to_string()),
+                lens: Some("RF24-105mm F4 L IS USM".to_string()),
+                focal_length: Some(50.0),
+                aperture: Some(2.8),
+                exposure_time: Some(0.004),
+                iso: Some(400),
+                flash: Some(true),
+                date_taken: Some("2024-05-01T14:03:22.500+02:00".to_string()),
+                orientation: Some(6),
+            }
+        );
+        assert_eq!(
+            data.camera.captured_at(),
+            Some(Utc.with_ymd_and_hms(2024, 5, 1, 12, 3, 22).unwrap() + chrono::Duration::milliseconds(500))
+        );
+        let location = data.location.unwrap();
+        assert!((location.latitude + 33.8568).abs() < 1e-9);
+        assert!((location.longitude - 151.2153).abs() < 1e-9);
+        assert_eq!(location.altitude, Some(-10.0));
+        assert_eq!(data.tags["Model"], "Canon EOS R5");
+        assert_eq!(data.tags["ExposureTime"], "1/250 s");
+        assert_eq!(data.tags["FNumber"], "f/2.8");
+    }
+    
+    #[test]
+    fn test_date_without_offset_stays_local() {
+        let fields = [field(Tag::DateTime, ascii_value("2019:12:31 23:59:59"))];
+        let mut reader = Cursor::new(exif_block(&fields));
+        let exif = Reader::new().read_from_container(&mut reader).unwrap();
+        
+        let camera = parse_exif(&exif).camera;
+        assert_eq!(camera.date_taken.as_deref(), Some("2019-12-31T23:59:59"));
+        assert!(camera.captured_at().is_some());
+        assert!(!camera.is_empty());
+    }
+}
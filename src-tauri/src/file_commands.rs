@@ -4,6 +4,7 @@
 //! that can be used by the frontend, since Tauri doesn't allow direct
 //! file:// protocol access for security reasons.
 
+use crate::media_detect;
 use std::path::Path;
 
 /// Converts a file path to an asset URL that can be used by the frontend.
@@ -41,7 +42,10 @@ pub fn get_asset_url(file_path: String) -> Result<String, String> {
     Ok(file_path)
 }
 
-/// Gets the MIME type for a file based on its extension.
+/// Gets the MIME type for a file.
+///
+/// The type is detected from the file's content, falling back to its extension
+/// when the content is not recognized or cannot be read.
 ///
 /// # Arguments
 ///
@@ -52,36 +56,8 @@ pub fn get_asset_url(file_path: String) -> Result<String, String> {
 /// Returns the MIME type string, or "application/octet-stream" if unknown.
 #[tauri::command]
 pub fn get_mime_type(file_path: String) -> String {
-    let path = Path::new(&file_path);
-    let extension = path
-        .extension()
-        .and_then(|e| e.to_str())
-        .unwrap_or("")
-        .to_lowercase();
-    
-    match extension.as_str() {
-        // Images
-        "jpg" | "jpeg" => "image/jpeg",
-        "png" => "image/png",
-        "gif" => "image/gif",
-        "webp" => "image/webp",
-        "bmp" => "image/bmp",
-        "svg" => "image/svg+xml",
-        "heic" | "heif" => "image/heic",
-        "tiff" | "tif" => "image/tiff",
-        
-        // Videos
-        "mp4" => "video/mp4",
-        "mov" => "video/quicktime",
-        "avi" => "video/x-msvideo",
-        "mkv" => "video/x-matroska",
-        "webm" => "video/webm",
-        "flv" => "video/x-flv",
-        "wmv" => "video/x-ms-wmv",
-        "m4v" => "video/x-m4v",
-        "mpg" | "mpeg" => "video/mpeg",
-        
-        _ => "application/octet-stream",
-    }
-    .to_string()
+    media_detect::detect_file(Path::new(&file_path))
+        .map(|detection| detection.format.mime_type)
+        .unwrap_or("application/octet-stream")
+        .to_string()
 }
@@ -0,0 +1,626 @@
+//! Offline reverse geocoding
+//!
+//! Turns the GPS coordinates of media (see [`location`](crate::location)) into
+//! country, region and city names without sending them anywhere. Places come
+//! from `geonames/places.tsv` in the app resources, a compact table preprocessed
+//! from the GeoNames dumps (`run-tauri-dev.ps1 places` regenerates it). Without
+//! the file, geocoding commands fail with an error and the rest of the app works.
+//!
+//! The places are loaded once into a k-d tree over points on the unit sphere,
+//! so the nearest one is found in logarithmic time and straight-line distances
+//! order places like great-circle distances do.
+//!
+//! Resolved names are stored in the `country_code`, `country`, `region` and
+//! `city` columns (an empty `country_code` marks points with no place nearby),
+//! indexed for full-text search, and attached as `Places/<Country>/<Region>/<City>`
+//! tags. Changing a row's coordinates clears its place and removes the tag
+//! attached by geocoding (`place_tag_id`) so both are resolved again.
+
+use crate::database::{DatabaseError, DbPool};
+use crate::tags::{self, TagError, TAG_SEPARATOR};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tauri::State;
+use thiserror::Error;
+use tracing::info;
+
+/// Folder of the place dataset inside the app resources
+pub const DATASET_DIR: &str = "geonames";
+
+const PLACES_FILE: &str = "places.tsv";
+
+/// Root of the automatically generated place tags
+pub const PLACE_TAG_ROOT: &str = "Places";
+
+/// Points at most this far from a city are placed in it
+pub const CITY_RADIUS_KM: f64 = 30.0;
+
+/// Points farther from any city still get its region and country up to this distance
+pub const REGION_RADIUS_KM: f64 = 150.0;
+
+const EARTH_RADIUS_KM: f64 = 6371.0;
+
+/// Error types for geocoding operations
+#[derive(Debug, Error)]
+pub enum GeocodeError {
+    #[error("Database error: {0}")]
+    Database(#[from] DatabaseError),
+    
+    #[error("Tag error: {0}")]
+    Tags(#[from] TagError),
+    
+    #[error("Place dataset {0} is missing or unreadable: {1}")]
+    Dataset(PathBuf, io::Error),
+    
+    #[error("Invalid place dataset: {0}")]
+    InvalidDataset(String),
+}
+
+impl From<sqlx::Error> for GeocodeError {
+    fn from(err: sqlx::Error) -> Self {
+        GeocodeError::Database(err.into())
+    }
+}
+
+pub type GeocodeResult<T> = Result<T, GeocodeError>;
+
+/// Names of the place a point lies in
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Place {
+    /// ISO 3166 code (e.g. `PT`)
+    pub country_code: String,
+    pub country: String,
+    /// First-level division (state, province, district...)
+    pub region: Option<String>,
+    /// Nearest city, when close enough
+    pub city: Option<String>,
+}
+
+impl Place {
+    /// Tag path such as `Places/Portugal/Lisbon/Lisbon`
+    pub fn tag_path(&self) -> String {
+        // Tag levels cannot contain the separator or double quotes
+        let level = |name: &str| name.replace(TAG_SEPARATOR, "-").replace('"', "");
+        let mut levels = vec![PLACE_TAG_ROOT.to_string(), level(&self.country)];
+        levels.extend(self.region.as_deref().map(level));
+        levels.extend(self.city.as_deref().map(level));
+        levels.join(&TAG_SEPARATOR.to_string())
+    }
+}
+
+/// A city of the dataset
+struct IndexedPlace {
+    /// Position on the unit sphere
+    point: [f64; 3],
+    name: String,
+    country: usize,
+    region: Option<usize>,
+}
+
+/// Converts degrees to a point on the unit sphere
+fn to_point(latitude: f64, longitude: f64) -> [f64; 3] {
+    let (latitude, longitude) = (latitude.to_radians(), longitude.to_radians());
+    [
+        latitude.cos() * longitude.cos(),
+        latitude.cos() * longitude.sin(),
+        latitude.sin(),
+    ]
+}
+
+/// Squared straight-line distance between two points
+fn distance_squared(a: &[f64; 3], b: &[f64; 3]) -> f64 {
+    a.iter().zip(b).map(|(a, b)| (a - b) * (a - b)).sum()
+}
+
+/// Great-circle distance in kilometers for a straight-line distance on the unit sphere
+fn chord_to_km(chord: f64) -> f64 {
+    2.0 * (chord / 2.0).min(1.0).asin() * EARTH_RADIUS_KM
+}
+
+/// Cities of the dataset in a k-d tree
+///
+/// The tree is implicit: every sub-slice holds its splitting place at the
+/// middle, the places before it on the lower side of the split axis and the
+/// places after it on the upper side.
+pub struct PlaceIndex {
+    places: Vec<IndexedPlace>,
+    /// `(code, name)` of every country
+    countries: Vec<(String, String)>,
+    regions: Vec<String>,
+}
+
+impl PlaceIndex {
+    /// Loads `places.tsv` from a folder
+    pub fn load(dir: &Path) -> GeocodeResult<Self> {
+        let path = dir.join(PLACES_FILE);
+        let places = fs::read_to_string(&path).map_err(|e| GeocodeError::Dataset(path.clone(), e))?;
+        let index = PlaceIndex::parse(&places)?;
+        info!("Loaded {} places from {}", index.len(), path.display());
+        Ok(index)
+    }
+    
+    /// Builds the index from the contents of `places.tsv`
+    ///
+    /// Lines starting with `#` are comments. Every other line holds a city as
+    /// tab-separated latitude, longitude, ISO country code, country name,
+    /// region name (may be empty) and city name.
+    pub fn parse(places: &str) -> GeocodeResult<Self> {
+        let mut index = PlaceIndex {
+            places: Vec::new(),
+            countries: Vec::new(),
+            regions: Vec::new(),
+        };
+        let mut country_ids = HashMap::new();
+        let mut region_ids = HashMap::new();
+        
+        for (number, line) in places.lines().enumerate() {
+            if line.starts_with('#') || line.trim().is_empty() {
+                continue;
+            }
+            let invalid = || GeocodeError::InvalidDataset(format!("line {}", number + 1));
+            let fields: Vec<&str> = line.split('\t').collect();
+            let [latitude, longitude, code, country, region, city] = fields[..] else {
+                return Err(invalid());
+            };
+            let latitude: f64 = latitude.parse().map_err(|_| invalid())?;
+            let longitude: f64 = longitude.parse().map_err(|_| invalid())?;
+            
+            let country = *country_ids.entry(code.to_string()).or_insert_with(|| {
+                index.countries.push((code.to_string(), country.to_string()));
+                index.countries.len() - 1
+            });
+            let region = (!region.is_empty()).then(|| {
+                *region_ids.entry((country, region.to_string())).or_insert_with(|| {
+                    index.regions.push(region.to_string());
+                    index.regions.len() - 1
+                })
+            });
+            
+            index.places.push(IndexedPlace {
+                point: to_point(latitude, longitude),
+                name: city.to_string(),
+                country,
+                region,
+            });
+        }
+        
+        build_tree(&mut index.places, 0);
+        Ok(index)
+    }
+    
+    /// Number of places in the index
+    pub fn len(&self) -> usize {
+        self.places.len()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.places.is_empty()
+    }
+    
+    /// Finds the place a point lies in
+    ///
+    /// Returns the nearest city with its region and country within
+    /// [`CITY_RADIUS_KM`], only its region and country within
+    /// [`REGION_RADIUS_KM`], and `None` farther away (e.g. at sea).
+    pub fn reverse_geocode(&self, latitude: f64, longitude: f64) -> Option<Place> {
+        let mut best = None;
+        self.nearest(0, self.places.len(), 0, &to_point(latitude, longitude), &mut best);
+        let (position, distance_squared) = best?;
+        
+        let distance = chord_to_km(distance_squared.sqrt());
+        if distance > REGION_RADIUS_KM {
+            return None;
+        }
+        
+        let place = &self.places[position];
+        let (country_code, country) = self.countries[place.country].clone();
+        Some(Place {
+            country_code,
+            country,
+            region: place.region.map(|region| self.regions[region].clone()),
+            city: (distance <= CITY_RADIUS_KM).then(|| place.name.clone()),
+        })
+    }
+    
+    /// Searches the sub-tree `places[start..end]` for a place closer than `best`
+    fn nearest(&self, start: usize, end: usize, depth: usize, target: &[f64; 3], best: &mut Option<(usize, f64)>) {
+        if start >= end {
+            return;
+        }
+        
+        let middle = start + (end - start) / 2;
+        let split = &self.places[middle].point;
+        let distance = distance_squared(split, target);
+        if best.is_none_or(|(_, closest)| distance < closest) {
+            *best = Some((middle, distance));
+        }
+        
+        let axis = depth % 3;
+        let offset = target[axis] - split[axis];
+        let (near, far) = if offset < 0.0 {
+            ((start, middle), (middle + 1, end))
+        } else {
+            ((middle + 1, end), (start, middle))
+        };
+        self.nearest(near.0, near.1, depth + 1, target, best);
+        // The other side can only be closer if the split plane is
+        if best.is_none_or(|(_, closest)| offset * offset < closest) {
+            self.nearest(far.0, far.1, depth + 1, target, best);
+        }
+    }
+}
+
+/// Arranges places into an implicit k-d tree, splitting on x, y and z in turn
+fn build_tree(places: &mut [IndexedPlace], depth: usize) {
+    if places.len() < 2 {
+        return;
+    }
+    
+    let axis = depth % 3;
+    let middle = places.len() / 2;
+    places.select_nth_unstable_by(middle, |a, b| {
+        a.point[axis].partial_cmp(&b.point[axis]).unwrap_or(Ordering::Equal)
+    });
+    let (lower, upper) = places.split_at_mut(middle);
+    build_tree(lower, depth + 1);
+    build_tree(&mut upper[1..], depth + 1);
+}
+
+/// The place index, loaded on first use
+///
+/// Managed as Tauri state.
+pub struct Geocoder {
+    dataset_dir: PathBuf,
+    index: Mutex<Option<Arc<PlaceIndex>>>,
+}
+
+impl Geocoder {
+    pub fn new(dataset_dir: PathBuf) -> Self {
+        Geocoder {
+            dataset_dir,
+            index: Mutex::new(None),
+        }
+    }
+    
+    /// Returns the place index, loading the dataset the first time
+    pub async fn index(&self) -> GeocodeResult<Arc<PlaceIndex>> {
+        if let Some(index) = self.index.lock().ok().and_then(|index| index.clone()) {
+            return Ok(index);
+        }
+        
+        let dir = self.dataset_dir.clone();
+        let index = tokio::task::spawn_blocking(move || PlaceIndex::load(&dir))
+            .await
+            .map_err(|e| GeocodeError::InvalidDataset(e.to_string()))??;
+        let index = Arc::new(index);
+        if let Ok(mut current) = self.index.lock() {
+            *current = Some(index.clone());
+        }
+        Ok(index)
+    }
+}
+
+/// Outcome of one geocoding batch
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GeocodeBatch {
+    /// Rows placed in a country
+    pub geocoded: usize,
+    /// Rows with no place nearby
+    pub unresolved: usize,
+    /// Located rows still waiting for a place
+    pub remaining: i64,
+}
+
+/// Resolves the place of the next located rows without one
+///
+/// Every resolved row is also tagged with its place (see [`Place::tag_path`]),
+/// and the tag is recorded in `place_tag_id` so it is removed with the place
+/// when the row's coordinates change.
+///
+/// # Arguments
+///
+/// * `pool` - Database connection pool
+/// * `index` - The place index
+/// * `limit` - Maximum number of rows to resolve
+pub async fn geocode_pending(pool: &DbPool, index: &PlaceIndex, limit: i64) -> GeocodeResult<GeocodeBatch> {
+    let pending: Vec<(i64, f64, f64)> = sqlx::query_as(
+        r#"
+        SELECT id, latitude, longitude FROM media_metadata
+        WHERE country_code IS NULL AND latitude IS NOT NULL AND longitude IS NOT NULL
+              AND trashed_at IS NULL
+        ORDER BY id
+        LIMIT ?
+        "#,
+    )
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    
+    let mut batch = GeocodeBatch::default();
+    let mut tagged: HashMap<String, Vec<i64>> = HashMap::new();
+    let mut tx = pool.begin().await?;
+    for (id, latitude, longitude) in pending {
+        let place = index.reverse_geocode(latitude, longitude);
+        sqlx::query("UPDATE media_metadata SET country_code = ?, country = ?, region = ?, city = ? WHERE id = ?")
+            .bind(place.as_ref().map_or("", |place| place.country_code.as_str()))
+            .bind(place.as_ref().map(|place| place.country.as_str()))
+            .bind(place.as_ref().and_then(|place| place.region.as_deref()))
+            .bind(place.as_ref().and_then(|place| place.city.as_deref()))
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        
+        match place {
+            Some(place) => {
+                tagged.entry(place.tag_path()).or_default().push(id);
+                batch.geocoded += 1;
+            }
+            None => batch.unresolved += 1,
+        }
+    }
+    tx.commit().await?;
+    
+    for (path, media_ids) in tagged {
+        let path = tags::normalize_tag_path(&path)?;
+        
+        // Rows that already carry the tag got it by hand, so it is not ours to remove
+        let mut untagged = Vec::with_capacity(media_ids.len());
+        for id in media_ids {
+            let tagged: bool = sqlx::query_scalar(
+                "SELECT EXISTS (SELECT 1 FROM media_tags JOIN tags ON tags.id = media_tags.tag_id \
+                 WHERE media_tags.media_id = ? AND tags.path = ?)",
+            )
+            .bind(id)
+            .bind(&path)
+            .fetch_one(pool)
+            .await?;
+            if !tagged {
+                untagged.push(id);
+            }
+        }
+        tags::add_tags(pool, &untagged, std::slice::from_ref(&path)).await?;
+        
+        let mut tx = pool.begin().await?;
+        for id in &untagged {
+            sqlx::query("UPDATE media_metadata SET place_tag_id = (SELECT id FROM tags WHERE path = ?) WHERE id = ?")
+                .bind(&path)
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+    }
+    
+    batch.remaining = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM media_metadata \
+         WHERE country_code IS NULL AND latitude IS NOT NULL AND longitude IS NOT NULL AND trashed_at IS NULL",
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(batch)
+}
+
+/// Tauri command to resolve the places of the next batch of located media
+///
+/// Call it repeatedly until `remaining` is 0.
+///
+/// # Arguments
+///
+/// * `pool` - Database connection pool (injected by Tauri)
+/// * `geocoder` - Place index (injected by Tauri)
+/// * `limit` - Rows per batch (defaults to 500)
+#[tauri::command]
+pub async fn geocode_pending_command(
+    pool: State<'_, DbPool>,
+    geocoder: State<'_, Geocoder>,
+    limit: Option<i64>,
+) -> Result<GeocodeBatch, String> {
+    let index = geocoder.index().await.map_err(|e| e.to_string())?;
+    geocode_pending(&pool, &index, limit.unwrap_or(500).max(1))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Tauri command to find the place of a point, e.g. under the map cursor
+///
+/// # Returns
+///
+/// Returns `None` when no city is within [`REGION_RADIUS_KM`]
+#[tauri::command]
+pub async fn reverse_geocode(
+    geocoder: State<'_, Geocoder>,
+    latitude: f64,
+    longitude: f64,
+) -> Result<Option<Place>, String> {
+    let index = geocoder.index().await.map_err(|e| e.to_string())?;
+    Ok(index.reverse_geocode(latitude, longitude))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{self, InsertMediaParams};
+    use crate::search::{self, SearchFilters};
+    
+    const PLACES: &str = "\
+# latitude\tlongitude\tcode\tcountry\tregion\tcity
+38.71667\t-9.13333\tPT\tPortugal\tLisbon\tLisbon
+38.80097\t-9.37826\tPT\tPortugal\tLisbon\tSintra
+41.14961\t-8.61099\tPT\tPortugal\tPorto\tPorto
+48.85341\t2.3488\tFR\tFrance\tÎle-de-France\tParis
+";
+
+    fn test_index() -> PlaceIndex {
+        PlaceIndex::parse(PLACES).unwrap()
+    }
+    
+    #[test]
+    fn test_reverse_geocode() {
+        let index = test_index();
+        assert_eq!(index.len(), 4);
+        
+        let belem = index.reverse_geocode(38.6916, -9.2160).unwrap();
+        assert_eq!(belem.country, "Portugal");
+        assert_eq!(belem.region.as_deref(), Some("Lisbon"));
+        assert_eq!(belem.city.as_deref(), Some("Lisbon"));
+        assert_eq!(belem.tag_path(), "Places/Portugal/Lisbon/Lisbon");
+        
+        let pena_palace = index.reverse_geocode(38.7876, -9.3906).unwrap();
+        assert_eq!(pena_palace.city.as_deref(), Some("Sintra"));
+        
+        let eiffel = index.reverse_geocode(48.8584, 2.2945).unwrap();
+        assert_eq!(eiffel.city.as_deref(), Some("Paris"));
+        assert_eq!(eiffel.region.as_deref(), Some("Île-de-France"));
+        
+        // Far from every city: region and country only, then nothing
+        let douro = index.reverse_geocode(41.1621, -7.7886).unwrap();
+        assert_eq!((douro.country_code.as_str(), douro.region.as_deref(), douro.city), ("PT", Some("Porto"), None));
+        assert_eq!(index.reverse_geocode(40.0, -20.0), None);
+    }
+    
+    #[test]
+    fn test_bundled_dataset_loads() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("resources").join(DATASET_DIR);
+        let index = PlaceIndex::load(&dir).unwrap();
+        // Holds for the seed list as well as for the full cities15000 output
+        let tokyo = index.reverse_geocode(35.6586, 139.7454).unwrap();
+        assert_eq!(tokyo.country, "Japan");
+        assert!(tokyo.city.is_some());
+        let belem = index.reverse_geocode(38.6916, -9.2160).unwrap();
+        assert_eq!((belem.country_code.as_str(), belem.region.as_deref()), ("PT", Some("Lisbon")));
+        assert_eq!(index.reverse_geocode(30.0, -40.0), None);
+    }
+    
+    #[test]
+    fn test_parse_rejects_malformed_lines() {
+        assert!(matches!(
+            PlaceIndex::parse("38.7\t-9.1\tPT\tPortugal\tLisbon"),
+            Err(GeocodeError::InvalidDataset(_))
+        ));
+        assert!(matches!(
+            PlaceIndex::parse("north\t-9.1\tPT\tPortugal\tLisbon\tLisbon"),
+            Err(GeocodeError::InvalidDataset(_))
+        ));
+        assert!(matches!(
+            PlaceIndex::load(Path::new("/nonexistent")),
+            Err(GeocodeError::Dataset(..))
+        ));
+    }
+    
+    #[test]
+    fn test_nearest_matches_brute_force() {
+        let index = test_index();
+        for latitude in (-80..=80).step_by(10) {
+            for longitude in (-180..180).step_by(15) {
+                let target = to_point(f64::from(latitude), f64::from(longitude));
+                let mut best = None;
+                index.nearest(0, index.len(), 0, &target, &mut best);
+                let expected = index
+                    .places
+                    .iter()
+                    .map(|place| distance_squared(&place.point, &target))
+                    .fold(f64::INFINITY, f64::min);
+                assert_eq!(best.map(|(_, distance)| distance), Some(expected));
+            }
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_geocode_pending_stores_places_and_tags() {
+        let pool = database::open_test_pool().await;
+        let folder_id = database::upsert_scanned_folder(&pool, "/lib", "lib", 0).await.unwrap();
+        let photo = |name: &str, latitude: f64, longitude: f64| InsertMediaParams {
+            folder_id,
+            file_path: format!("/lib/{}", name),
+            file_name: name.to_string(),
+            file_type: "jpg".to_string(),
+            media_type: Some("image".to_string()),
+            latitude: Some(latitude),
+            longitude: Some(longitude),
+            ..Default::default()
+        };
+        let rows = [
+            photo("belem.jpg", 38.6916, -9.2160),
+            photo("ribeira.jpg", 41.1406, -8.6132),
+            photo("atlantic.jpg", 40.0, -20.0),
+        ];
+        database::upsert_media_batch(&pool, &rows).await.unwrap();
+        
+        let batch = geocode_pending(&pool, &test_index(), 10).await.unwrap();
+        assert_eq!((batch.geocoded, batch.unresolved, batch.remaining), (2, 1, 0));
+        
+        let found = |filters: SearchFilters| {
+            let pool = pool.clone();
+            async move {
+                let mut names: Vec<String> = search::find_media(&pool, &filters, 10)
+                    .await
+                    .unwrap()
+                    .into_iter()
+                    .map(|media| media.file_name)
+                    .collect();
+                names.sort();
+                names
+            }
+        };
+        let text = |text: &str| SearchFilters {
+            text: Some(text.to_string()),
+            ..Default::default()
+        };
+        assert_eq!(found(text("porto")).await, ["ribeira.jpg"]);
+        assert_eq!(found(text("lisbon")).await, ["belem.jpg"]);
+        assert_eq!(
+            found(SearchFilters {
+                tags: Some(tags::TagExpr::parse("Places/Portugal/Lisbon").unwrap()),
+                ..Default::default()
+            })
+            .await,
+            ["belem.jpg"]
+        );
+        
+        // Moving a photo clears its place until it is resolved again
+        sqlx::query("UPDATE media_metadata SET latitude = 48.8584, longitude = 2.2945 WHERE file_name = 'belem.jpg'")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let country = |country: &str| SearchFilters {
+            country: Some(country.to_string()),
+            ..Default::default()
+        };
+        assert_eq!(found(country("portugal")).await, ["ribeira.jpg"]);
+        let place_tag = |path: &str| SearchFilters {
+            tags: Some(tags::TagExpr::parse(path).unwrap()),
+            ..Default::default()
+        };
+        assert!(found(place_tag("Places/Portugal/Lisbon")).await.is_empty());
+        let batch = geocode_pending(&pool, &test_index(), 10).await.unwrap();
+        assert_eq!((batch.geocoded, batch.remaining), (1, 0));
+        assert_eq!(found(country("france")).await, ["belem.jpg"]);
+        assert_eq!(found(text("paris")).await, ["belem.jpg"]);
+        assert_eq!(found(place_tag("Places/France")).await, ["belem.jpg"]);
+        assert!(found(place_tag("Places/Portugal/Lisbon")).await.is_empty());
+        
+        // A place tag added by hand stays when the photo moves again
+        let belem: i64 = sqlx::query_scalar("SELECT id FROM media_metadata WHERE file_name = 'belem.jpg'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        tags::add_tags(&pool, &[belem], &["Places/Portugal/Lisbon/Lisbon".to_string()]).await.unwrap();
+        for (latitude, longitude) in [(38.6916, -9.2160), (48.8584, 2.2945)] {
+            sqlx::query("UPDATE media_metadata SET latitude = ?, longitude = ? WHERE id = ?")
+                .bind(latitude)
+                .bind(longitude)
+                .bind(belem)
+                .execute(&pool)
+                .await
+                .unwrap();
+            geocode_pending(&pool, &test_index(), 10).await.unwrap();
+        }
+        assert_eq!(found(place_tag("Places/Portugal/Lisbon")).await, ["belem.jpg"]);
+        assert_eq!(found(place_tag("Places/France")).await, ["belem.jpg"]);
+    }
+}
@@ -0,0 +1,102 @@
+//! Content hashing for media files
+//!
+//! The quick hash identifies a file's content without reading all of it: it
+//! covers the file size plus the first and last [`QUICK_HASH_SAMPLE`] bytes.
+//! It is cheap enough to compute on demand (e.g. when a file is tagged) and
+//! reliable enough to recognize a moved file among files of the same size.
+//!
+//! The content hash is a SHA-256 of the whole file. It is only computed for
+//! files whose quick hash collides with another file, to confirm exact
+//! duplicates.
+
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Number of bytes sampled from each end of the file
+pub const QUICK_HASH_SAMPLE: u64 = 64 * 1024;
+
+/// Computes the quick content hash of a file
+///
+/// # Returns
+///
+/// Returns the hex-encoded SHA-256 of the file size, the first
+/// [`QUICK_HASH_SAMPLE`] bytes and the last [`QUICK_HASH_SAMPLE`] bytes (the
+/// whole file when it is smaller than two samples).
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be opened or read.
+pub fn quick_hash(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    
+    let mut hasher = Sha256::new();
+    hasher.update(len.to_le_bytes());
+    
+    let mut buffer = vec![0u8; QUICK_HASH_SAMPLE as usize];
+    if len <= 2 * QUICK_HASH_SAMPLE {
+        io::copy(&mut file, &mut hasher)?;
+    } else {
+        file.read_exact(&mut buffer)?;
+        hasher.update(&buffer);
+        file.seek(SeekFrom::End(-(QUICK_HASH_SAMPLE as i64)))?;
+        file.read_exact(&mut buffer)?;
+        hasher.update(&buffer);
+    }
+    
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Computes the full content hash of a file
+///
+/// # Returns
+///
+/// Returns the hex-encoded SHA-256 of the file's bytes.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be opened or read.
+pub fn content_hash(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_quick_hash_depends_on_content_not_name() {
+        let dir = std::env::temp_dir().join(format!("fmlm_hashing_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        
+        let large: Vec<u8> = (0..3 * QUICK_HASH_SAMPLE).map(|i| (i % 251) as u8).collect();
+        let mut middle_changed = large.clone();
+        middle_changed[QUICK_HASH_SAMPLE as usize + 10] ^= 0xFF;
+        let mut tail_changed = large.clone();
+        *tail_changed.last_mut().unwrap() ^= 0xFF;
+        
+        let write = |name: &str, data: &[u8]| {
+            let path = dir.join(name);
+            std::fs::write(&path, data).unwrap();
+            quick_hash(&path).unwrap()
+        };
+        
+        assert_eq!(write("a.jpg", &large), write("b.jpg", &large));
+        // Bytes outside the samples are not covered
+        assert_eq!(write("a.jpg", &large), write("middle.jpg", &middle_changed));
+        assert_ne!(write("a.jpg", &large), write("tail.jpg", &tail_changed));
+        assert_ne!(write("small.jpg", b"abc"), write("small2.jpg", b"abd"));
+        
+        // The content hash covers every byte
+        std::fs::write(dir.join("a.jpg"), &large).unwrap();
+        std::fs::write(dir.join("middle.jpg"), &middle_changed).unwrap();
+        assert_ne!(content_hash(&dir.join("a.jpg")).unwrap(), content_hash(&dir.join("middle.jpg")).unwrap());
+        
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
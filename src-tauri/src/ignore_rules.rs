@@ -0,0 +1,357 @@
+//! Ignore rules for directory scanning
+//!
+//! Decides which paths a scan skips. Three sources are combined:
+//!
+//! * `.fmlmignore` files, using gitignore syntax, honored in every directory
+//!   of the walk. Rules in deeper files take precedence and `!pattern` lines
+//!   re-include paths excluded further up.
+//! * A global list of exclude globs stored in `user_preferences`, with the
+//!   same syntax, matched relative to the scanned folder.
+//! * Hidden-file handling: dotfiles and dot-directories are skipped unless
+//!   explicitly included.
+//!
+//! Excluded directories are pruned, so nothing below them is read.
+
+use crate::database::{self, DbPool};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Arc;
+use tauri::State;
+use tracing::{debug, warn};
+
+/// Name of the per-directory ignore file
+pub const IGNORE_FILE_NAME: &str = ".fmlmignore";
+
+/// Preference key holding the global exclude globs (JSON array of strings)
+pub const EXCLUDE_PATTERNS_KEY: &str = "scan_exclude_patterns";
+
+/// Preference key holding whether hidden files are scanned ("true"/"false")
+pub const INCLUDE_HIDDEN_KEY: &str = "scan_include_hidden";
+
+/// Exclude globs used until the user saves their own list.
+///
+/// Covers version control and package folders, Synology and desktop thumbnail
+/// caches, and Lightroom preview catalogs, all of which contain preview
+/// images that would otherwise show up in the grid.
+pub const DEFAULT_EXCLUDE_PATTERNS: &[&str] = &[
+    ".git/",
+    "node_modules/",
+    "@eaDir/",
+    ".thumbnails/",
+    "*.lrdata/",
+];
+
+/// User-configurable ignore settings applied to every scan
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IgnoreSettings {
+    /// Gitignore-style globs excluded from every scan
+    pub exclude_patterns: Vec<String>,
+    /// Whether files and directories starting with a dot are scanned
+    pub include_hidden: bool,
+}
+
+impl Default for IgnoreSettings {
+    fn default() -> Self {
+        Self {
+            exclude_patterns: DEFAULT_EXCLUDE_PATTERNS.iter().map(|p| p.to_string()).collect(),
+            include_hidden: false,
+        }
+    }
+}
+
+/// Rules from the `.fmlmignore` files of a directory and its ancestors.
+///
+/// Each layer holds the matcher of one directory and links to the layer of
+/// the closest ancestor that has an ignore file, so subdirectories visited
+/// on different threads can share their parents' rules.
+#[derive(Debug)]
+pub struct IgnoreLayer {
+    matcher: Gitignore,
+    parent: IgnoreChain,
+}
+
+/// Innermost `.fmlmignore` layer in effect for a directory, if any
+pub type IgnoreChain = Option<Arc<IgnoreLayer>>;
+
+/// Compiled ignore rules for one scan.
+#[derive(Debug)]
+pub struct IgnoreRules {
+    global: Gitignore,
+    include_hidden: bool,
+}
+
+impl IgnoreRules {
+    /// Compiles the settings for a scan rooted at `root`.
+    ///
+    /// Invalid globs are logged and skipped so a single typo doesn't disable
+    /// the whole exclude list.
+    ///
+    /// # Arguments
+    ///
+    /// * `root` - The directory being scanned; global globs are relative to it
+    /// * `settings` - The exclude globs and hidden-file option
+    pub fn new(root: &Path, settings: &IgnoreSettings) -> Self {
+        let mut builder = GitignoreBuilder::new(root);
+        for pattern in &settings.exclude_patterns {
+            if let Err(e) = builder.add_line(None, pattern) {
+                warn!("Ignoring invalid exclude pattern {:?}: {}", pattern, e);
+            }
+        }
+        
+        let global = builder.build().unwrap_or_else(|e| {
+            warn!("Cannot compile exclude patterns: {}", e);
+            Gitignore::empty()
+        });
+        
+        Self {
+            global,
+            include_hidden: settings.include_hidden,
+        }
+    }
+    
+    /// Returns the rules in effect inside `dir`.
+    ///
+    /// Reads `dir/.fmlmignore` if it exists and stacks it on top of `parent`;
+    /// otherwise the parent chain is returned unchanged.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - The directory about to be listed
+    /// * `parent` - The rules in effect in the parent directory
+    pub fn enter_directory(&self, dir: &Path, parent: &IgnoreChain) -> IgnoreChain {
+        let ignore_file = dir.join(IGNORE_FILE_NAME);
+        if !ignore_file.is_file() {
+            return parent.clone();
+        }
+        
+        let (matcher, error) = Gitignore::new(&ignore_file);
+        if let Some(e) = error {
+            warn!("Problem reading {}: {}", ignore_file.display(), e);
+        }
+        debug!("Loaded {} rules from {}", matcher.len(), ignore_file.display());
+        
+        Some(Arc::new(IgnoreLayer {
+            matcher,
+            parent: parent.clone(),
+        }))
+    }
+    
+    /// Checks whether an entry of a directory should be skipped.
+    ///
+    /// The closest `.fmlmignore` with a matching rule decides; if none
+    /// matches, the global globs do. Hidden entries are skipped first unless
+    /// hidden files are included.
+    ///
+    /// # Arguments
+    ///
+    /// * `chain` - The rules in effect in the entry's parent directory
+    /// * `path` - Path of the entry
+    /// * `is_dir` - Whether the entry is a directory
+    pub fn is_ignored(&self, chain: &IgnoreChain, path: &Path, is_dir: bool) -> bool {
+        if !self.include_hidden && is_hidden(path) {
+            return true;
+        }
+        
+        let mut layer = chain.as_deref();
+        while let Some(current) = layer {
+            match current.matcher.matched(path, is_dir) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+                Match::None => layer = current.parent.as_deref(),
+            }
+        }
+        
+        self.global.matched(path, is_dir).is_ignore()
+    }
+    
+    /// Checks a path below the scan root, including all of its ancestors.
+    ///
+    /// Used for single paths reported outside of a walk (e.g. by the folder
+    /// watcher), where the parent directories were not visited first.
+    ///
+    /// # Arguments
+    ///
+    /// * `root` - The scan root the rules were compiled for
+    /// * `path` - A path at or below `root`
+    /// * `is_dir` - Whether `path` is a directory
+    pub fn is_path_ignored(&self, root: &Path, path: &Path, is_dir: bool) -> bool {
+        let Ok(relative) = path.strip_prefix(root) else {
+            return false;
+        };
+        
+        let mut chain = self.enter_directory(root, &None);
+        let mut current = root.to_path_buf();
+        let mut components = relative.components().peekable();
+        
+        while let Some(component) = components.next() {
+            current.push(component);
+            let is_last = components.peek().is_none();
+            let current_is_dir = if is_last { is_dir } else { true };
+            
+            if self.is_ignored(&chain, &current, current_is_dir) {
+                return true;
+            }
+            if !is_last {
+                chain = self.enter_directory(&current, &chain);
+            }
+        }
+        
+        false
+    }
+}
+
+/// Whether a path's file name starts with a dot
+fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.starts_with('.'))
+}
+
+/// Checks that every exclude glob compiles.
+///
+/// # Errors
+///
+/// Returns a message naming the first invalid pattern.
+pub fn validate_patterns(patterns: &[String]) -> Result<(), String> {
+    let mut builder = GitignoreBuilder::new("");
+    for pattern in patterns {
+        builder
+            .add_line(None, pattern)
+            .map_err(|e| format!("Invalid exclude pattern {:?}: {}", pattern, e))?;
+    }
+    Ok(())
+}
+
+/// Loads the ignore settings from user preferences.
+///
+/// Missing or unreadable preferences fall back to [`IgnoreSettings::default`].
+///
+/// # Arguments
+///
+/// * `pool` - Database connection pool
+pub async fn load_ignore_settings(pool: &DbPool) -> database::DatabaseResult<IgnoreSettings> {
+    let mut settings = IgnoreSettings::default();
+    
+    if let Some(value) = database::get_preference(pool, EXCLUDE_PATTERNS_KEY).await? {
+        match serde_json::from_str::<Vec<String>>(&value) {
+            Ok(patterns) => settings.exclude_patterns = patterns,
+            Err(e) => warn!("Invalid {} preference, using defaults: {}", EXCLUDE_PATTERNS_KEY, e),
+        }
+    }
+    
+    if let Some(value) = database::get_preference(pool, INCLUDE_HIDDEN_KEY).await? {
+        settings.include_hidden = value == "true";
+    }
+    
+    Ok(settings)
+}
+
+/// Loads the ignore settings, falling back to the defaults on database errors.
+///
+/// Scans should still run when preferences are unavailable, so callers that
+/// only need the settings to walk a folder use this variant.
+pub async fn load_ignore_settings_or_default(pool: &DbPool) -> IgnoreSettings {
+    load_ignore_settings(pool).await.unwrap_or_else(|e| {
+        warn!("Cannot load ignore settings, using defaults: {}", e);
+        IgnoreSettings::default()
+    })
+}
+
+/// Tauri command to get the ignore settings applied to scans
+///
+/// # Arguments
+///
+/// * `pool` - Database connection pool (injected by Tauri)
+#[tauri::command]
+pub async fn get_ignore_settings(pool: State<'_, DbPool>) -> Result<IgnoreSettings, String> {
+    load_ignore_settings(&pool).await.map_err(|e| e.to_string())
+}
+
+/// Tauri command to save the ignore settings applied to scans
+///
+/// # Arguments
+///
+/// * `pool` - Database connection pool (injected by Tauri)
+/// * `settings` - The exclude globs and hidden-file option to store
+///
+/// # Errors
+///
+/// Returns an error if a pattern is not a valid glob or the preferences
+/// cannot be written.
+#[tauri::command]
+pub async fn set_ignore_settings(
+    pool: State<'_, DbPool>,
+    settings: IgnoreSettings,
+) -> Result<(), String> {
+    validate_patterns(&settings.exclude_patterns)?;
+    
+    let patterns = serde_json::to_string(&settings.exclude_patterns).map_err(|e| e.to_string())?;
+    database::set_preference(&pool, EXCLUDE_PATTERNS_KEY, &patterns)
+        .await
+        .map_err(|e| e.to_string())?;
+    database::set_preference(&pool, INCLUDE_HIDDEN_KEY, &settings.include_hidden.to_string())
+        .await
+        .map_err(|e| e.to_string())?;
+    
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    
+    fn settings(patterns: &[&str], include_hidden: bool) -> IgnoreSettings {
+        IgnoreSettings {
+            exclude_patterns: patterns.iter().map(|p| p.to_string()).collect(),
+            include_hidden,
+        }
+    }
+    
+    #[test]
+    fn test_global_patterns_and_hidden_files() {
+        let root = PathBuf::from("/library");
+        let rules = IgnoreRules::new(&root, &IgnoreSettings::default());
+        
+        assert!(rules.is_ignored(&None, &root.join("@eaDir"), true));
+        assert!(rules.is_ignored(&None, &root.join("trips/Previews.lrdata"), true));
+        assert!(rules.is_ignored(&None, &root.join(".cache"), true));
+        assert!(rules.is_ignored(&None, &root.join(".hidden.jpg"), false));
+        assert!(!rules.is_ignored(&None, &root.join("photo.jpg"), false));
+        
+        let rules = IgnoreRules::new(&root, &settings(&["*.tmp"], true));
+        assert!(!rules.is_ignored(&None, &root.join(".hidden.jpg"), false));
+        assert!(rules.is_ignored(&None, &root.join("a/b.tmp"), false));
+    }
+    
+    #[test]
+    fn test_ignore_file_overrides_and_whitelists() {
+        let root = std::env::temp_dir().join(format!("fmlm_ignore_rules_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("sub")).unwrap();
+        std::fs::write(root.join(IGNORE_FILE_NAME), "*.png\nexports/\n").unwrap();
+        std::fs::write(root.join("sub").join(IGNORE_FILE_NAME), "!keep.png\n").unwrap();
+        
+        let rules = IgnoreRules::new(&root, &settings(&[], false));
+        let top = rules.enter_directory(&root, &None);
+        let sub = rules.enter_directory(&root.join("sub"), &top);
+        
+        assert!(rules.is_ignored(&top, &root.join("a.png"), false));
+        assert!(rules.is_ignored(&top, &root.join("exports"), true));
+        assert!(rules.is_ignored(&sub, &root.join("sub/other.png"), false));
+        assert!(!rules.is_ignored(&sub, &root.join("sub/keep.png"), false));
+        
+        assert!(rules.is_path_ignored(&root, &root.join("exports/x.jpg"), false));
+        assert!(!rules.is_path_ignored(&root, &root.join("sub/keep.png"), false));
+        
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+    
+    #[test]
+    fn test_validate_patterns() {
+        assert!(validate_patterns(&["*.jpg".to_string(), "cache/".to_string()]).is_ok());
+        assert!(validate_patterns(&["photos/{a,b".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,205 @@
+//! Scan-and-index pipeline
+//!
+//! Walks a folder and writes every discovered media file straight into
+//! `media_metadata` from the backend, instead of the frontend calling
+//! `add_media_metadata` once per file. Rows are written in batches of
+//! [`INDEX_BATCH_SIZE`], each in its own transaction, so a crash leaves only
+//! whole batches behind and the next run picks up where it stopped (files
+//! already indexed with the same size and modification time are skipped).
+
+use crate::database::{self, DbPool};
+use crate::ignore_rules;
+use crate::rescan::{self, FolderSync};
+use crate::scanner::{self, MediaFile, ScanOptions};
+use crate::watcher::LibraryWatcher;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::Instant;
+use tauri::{AppHandle, Manager, State};
+use tracing::{info, warn};
+
+/// Number of rows written per transaction
+pub const INDEX_BATCH_SIZE: usize = 500;
+
+/// Outcome of a scan-and-index run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexSummary {
+    /// ID of the folder in `scanned_folders`
+    pub folder_id: i64,
+    /// All media files found, newest first
+    pub files: Vec<MediaFile>,
+    /// Files that were not in the index before
+    pub added: usize,
+    /// Files whose size or modification time changed
+    pub updated: usize,
+    /// Indexed files found at a new path
+    pub moved: usize,
+    /// Indexed files that no longer exist on disk
+    pub removed: usize,
+    /// Files that were already up to date
+    pub unchanged: usize,
+    /// Number of transactions committed
+    pub batches: usize,
+    /// Wall-clock time of the whole run in milliseconds
+    pub duration_ms: u64,
+}
+
+/// Scans a folder and indexes its media files in batched transactions.
+///
+/// The folder is registered in `scanned_folders` (or updated if it already
+/// is) and its rows are synced with [`rescan::sync_folder_index`]: new and
+/// changed files are upserted, rows of moved files are updated in place, and
+/// rows of files that disappeared are deleted. The folder's `file_count` and
+/// `last_scanned` are only refreshed once every batch has been committed, so
+/// an interrupted run does not look like a finished one.
+///
+/// # Arguments
+///
+/// * `pool` - Database connection pool
+/// * `path` - The directory to index
+/// * `options` - Recursion, threading and ignore options for the walk
+///
+/// # Errors
+///
+/// Returns an error if the path is not a directory or a database write fails.
+/// Batches committed before the failure are kept.
+pub async fn index_folder(pool: &DbPool, path: &str, options: ScanOptions) -> Result<IndexSummary, String> {
+    let started = Instant::now();
+    info!("Scan and index of {}", path);
+    
+    // Walking the tree is blocking IO, keep it off the async runtime
+    let walk_path = path.to_string();
+    let files = tokio::task::spawn_blocking(move || {
+        scanner::scan_directory_with_options(Path::new(&walk_path), &options)
+    })
+    .await
+    .map_err(|e| format!("Scan task failed: {}", e))??;
+    
+    let folder_name = Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string());
+    
+    let folder_id = database::register_scanned_folder(pool, path, &folder_name)
+        .await
+        .map_err(|e| e.to_string())?;
+    let FolderSync { diff, batches } =
+        rescan::sync_folder_index(pool, folder_id, files.clone(), INDEX_BATCH_SIZE).await?;
+    
+    let summary = IndexSummary {
+        folder_id,
+        files,
+        added: diff.added.len(),
+        updated: diff.modified.len(),
+        moved: diff.moved.len(),
+        removed: diff.removed.len(),
+        unchanged: diff.unchanged,
+        batches,
+        duration_ms: started.elapsed().as_millis() as u64,
+    };
+    
+    info!(
+        "Indexed folder {} in {}ms: {} added, {} updated, {} moved, {} removed, {} unchanged ({} batches)",
+        folder_id,
+        summary.duration_ms,
+        summary.added,
+        summary.updated,
+        summary.moved,
+        summary.removed,
+        summary.unchanged,
+        summary.batches
+    );
+    
+    Ok(summary)
+}
+
+/// Tauri command to scan a folder and persist its media files
+///
+/// Replaces `scan_directory` followed by per-file `add_media_metadata` calls.
+/// The folder is also registered with the library watcher.
+///
+/// # Arguments
+///
+/// * `app` - Application handle (injected by Tauri)
+/// * `pool` - Database connection pool (injected by Tauri)
+/// * `path` - The directory path to index
+/// * `recursive` - Whether to scan subdirectories recursively
+/// * `threads` - Optional worker thread count for the walk
+///
+/// # Returns
+///
+/// Returns the folder ID, the discovered files and the write counts
+#[tauri::command]
+pub async fn scan_and_index(
+    app: AppHandle,
+    pool: State<'_, DbPool>,
+    path: String,
+    recursive: bool,
+    threads: Option<usize>,
+) -> Result<IndexSummary, String> {
+    let options = ScanOptions::new(recursive)
+        .with_threads(threads)
+        .with_ignore(ignore_rules::load_ignore_settings_or_default(&pool).await);
+    let summary = index_folder(&pool, &path, options).await?;
+    
+    if let Some(watcher) = app.try_state::<LibraryWatcher>() {
+        if let Err(e) = watcher.watch_folder(summary.folder_id, Path::new(&path)) {
+            warn!("Cannot watch folder {}: {}", path, e);
+        }
+    }
+    
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[tokio::test]
+    async fn test_folder_stats_written_after_last_batch() {
+        let dir = std::env::temp_dir().join(format!("fmlm_indexer_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let file_count = INDEX_BATCH_SIZE + 20;
+        for index in 0..file_count {
+            std::fs::write(dir.join(format!("photo{:04}.jpg", index)), index.to_string()).unwrap();
+        }
+        let path = dir.to_string_lossy().into_owned();
+        
+        let pool = database::open_test_pool().await;
+        let stats = || async {
+            sqlx::query_scalar::<_, i64>("SELECT file_count FROM scanned_folders")
+                .fetch_one(&pool)
+                .await
+                .unwrap()
+        };
+        let rows = || async {
+            sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM media_metadata")
+                .fetch_one(&pool)
+                .await
+                .unwrap()
+        };
+        
+        // Make the second batch fail: the first one stays, the stats are not written
+        sqlx::query(&format!(
+            "CREATE TRIGGER fail_second_batch BEFORE INSERT ON media_metadata \
+             WHEN (SELECT COUNT(*) FROM media_metadata) >= {} BEGIN SELECT RAISE(ABORT, 'disk full'); END",
+            INDEX_BATCH_SIZE
+        ))
+        .execute(&pool)
+        .await
+        .unwrap();
+        assert!(index_folder(&pool, &path, ScanOptions::default()).await.is_err());
+        assert_eq!(rows().await, INDEX_BATCH_SIZE as i64);
+        assert_eq!(stats().await, 0);
+        
+        // The next run only writes what is missing, then the stats
+        sqlx::query("DROP TRIGGER fail_second_batch").execute(&pool).await.unwrap();
+        let summary = index_folder(&pool, &path, ScanOptions::default()).await.unwrap();
+        assert_eq!((summary.added, summary.unchanged, summary.batches), (20, INDEX_BATCH_SIZE, 1));
+        assert_eq!(rows().await, file_count as i64);
+        assert_eq!(stats().await, file_count as i64);
+        
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
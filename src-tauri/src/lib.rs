@@ -7,12 +7,33 @@ use tauri::Manager;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 pub mod scanner;
+pub mod media_detect;
+pub mod ignore_rules;
 pub mod database;
+pub mod migrations;
 pub mod db_commands;
 pub mod thumbnail;
 pub mod thumbnail_commands;
 pub mod file_commands;
 pub mod media_info;
+pub mod raw;
+pub mod exif;
+pub mod location;
+pub mod geocode;
+pub mod rescan;
+pub mod indexer;
+pub mod search;
+pub mod hashing;
+pub mod tags;
+pub mod albums;
+pub mod ratings;
+pub mod trash;
+pub mod duplicates;
+pub mod perceptual;
+pub mod similar;
+pub mod dedupe;
+pub mod scan_jobs;
+pub mod watcher;
 
 // Re-export common types for convenience
 pub use scanner::{MediaFile, MediaType, scan_directory};
@@ -56,13 +77,48 @@ pub fn run() {
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
+        .manage(scan_jobs::ScanJobManager::default())
+        .manage(duplicates::HashJobManager::default())
         .setup(|app| {
+            // The place dataset is bundled with the app and loaded on first use
+            let dataset_dir = app
+                .path()
+                .resource_dir()
+                .map(|dir| dir.join(geocode::DATASET_DIR))
+                .unwrap_or_default();
+            app.manage(geocode::Geocoder::new(dataset_dir));
+            
             // Initialize database on app startup
             let app_handle = app.handle().clone();
             tauri::async_runtime::block_on(async move {
                 match database::initialize_database(&app_handle).await {
                     Ok(pool) => {
                         println!("Database initialized successfully");
+                        
+                        // Keep the library in sync with changes made outside the app.
+                        // A watcher failure is not fatal; rescans still work without it.
+                        match watcher::start(app_handle.clone(), pool.clone()).await {
+                            Ok(library_watcher) => {
+                                app_handle.manage(library_watcher);
+                            }
+                            Err(e) => {
+                                eprintln!("Failed to start file watcher: {}", e);
+                            }
+                        }
+                        
+                        // Drop trashed items past the retention period in the background
+                        match trash::app_trash_dir(&app_handle) {
+                            Ok(app_trash) => {
+                                let purge_pool = pool.clone();
+                                tauri::async_runtime::spawn(async move {
+                                    if let Err(e) = trash::purge_expired(&purge_pool, &app_trash).await {
+                                        eprintln!("Failed to purge the trash: {}", e);
+                                    }
+                                });
+                            }
+                            Err(e) => eprintln!("Failed to locate the trash folder: {}", e),
+                        }
+                        
                         app_handle.manage(pool);
                         Ok(())
                     }
@@ -76,6 +132,65 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             greet,
             scanner::scan_directory,
+            rescan::rescan_folder,
+            indexer::scan_and_index,
+            search::search_media,
+            search::get_media_page,
+            tags::get_tags,
+            tags::get_tags_for_media,
+            tags::add_tags_to_media,
+            tags::remove_tags_from_media,
+            tags::rename_tag_command,
+            tags::merge_tags_command,
+            tags::delete_tag_command,
+            albums::get_albums,
+            albums::get_album_folders,
+            albums::get_album_items,
+            albums::get_albums_for_media,
+            albums::create_album_command,
+            albums::rename_album_command,
+            albums::move_album_command,
+            albums::delete_album_command,
+            albums::add_to_album_command,
+            albums::remove_from_album_command,
+            albums::reorder_album_command,
+            albums::set_album_cover_command,
+            albums::create_album_folder_command,
+            albums::rename_album_folder_command,
+            albums::move_album_folder_command,
+            albums::delete_album_folder_command,
+            albums::get_smart_albums,
+            albums::get_smart_album_page,
+            albums::create_smart_album_command,
+            albums::update_smart_album_command,
+            albums::move_smart_album_command,
+            albums::delete_smart_album_command,
+            ratings::set_media_rating,
+            ratings::set_media_favorite,
+            ratings::set_media_color_label,
+            ratings::set_media_pick_flag,
+            trash::move_to_trash,
+            trash::restore_from_trash,
+            trash::get_trash,
+            trash::empty_trash,
+            trash::get_trash_settings,
+            trash::set_trash_settings,
+            duplicates::start_hashing,
+            duplicates::get_hashing_status,
+            duplicates::cancel_hashing,
+            duplicates::hash_media,
+            duplicates::get_duplicates,
+            similar::get_similar_images,
+            similar::get_similar_videos,
+            dedupe::resolve_duplicates_command,
+            // Background scan job commands
+            scan_jobs::start_scan,
+            scan_jobs::get_scan_status,
+            scan_jobs::cancel_scan,
+            scan_jobs::take_scan_results,
+            // Scan ignore rule commands
+            ignore_rules::get_ignore_settings,
+            ignore_rules::set_ignore_settings,
             // Database commands
             db_commands::add_scanned_folder,
             db_commands::get_scanned_folders,
@@ -100,6 +215,10 @@ pub fn run() {
             file_commands::get_mime_type,
             // Media info commands
             media_info::get_media_info,
+            media_info::extract_pending_metadata_command,
+            location::get_location_clusters,
+            geocode::geocode_pending_command,
+            geocode::reverse_geocode,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,393 @@
+//! GPS locations and map queries
+//!
+//! Coordinates come from the EXIF GPS tags of photos (see [`exif`](crate::exif))
+//! and from the container tags of videos: QuickTime's
+//! `com.apple.quicktime.location.ISO6709` and the MP4 `location` tag, both
+//! ISO 6709 strings such as `+48.8577+002.2950+035.000/`.
+//!
+//! They are stored in the `latitude`, `longitude` and `altitude` columns; the
+//! `media_locations` R*Tree table, kept in sync by triggers, indexes them for
+//! bounding-box queries. The map asks for clusters at its zoom level, which
+//! are computed here on a Web Mercator pixel grid so the frontend only
+//! receives one marker per cluster.
+
+use crate::database::{DatabaseResult, DbPool};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{QueryBuilder, Row, Sqlite};
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use tauri::State;
+
+/// Container tags holding an ISO 6709 location, in order of preference
+const LOCATION_TAGS: &[&str] = &["com.apple.quicktime.location.ISO6709", "location", "location-eng"];
+
+/// Size of a map tile in pixels at zoom level 0
+const TILE_SIZE: f64 = 256.0;
+
+/// Side of a clustering cell in screen pixels
+const CLUSTER_CELL_SIZE: f64 = 64.0;
+
+/// Highest zoom level accepted for clustering
+pub const MAX_ZOOM: u8 = 22;
+
+/// Web Mercator stops at this latitude
+const MAX_MERCATOR_LATITUDE: f64 = 85.051_128_78;
+
+/// A point on Earth
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GeoLocation {
+    /// Degrees north of the equator (negative for south)
+    pub latitude: f64,
+    /// Degrees east of Greenwich (negative for west)
+    pub longitude: f64,
+    /// Meters above sea level
+    pub altitude: Option<f64>,
+}
+
+impl GeoLocation {
+    /// Builds a location, rejecting out-of-range coordinates
+    ///
+    /// `0, 0` is rejected too: devices without a fix write it, and no photo
+    /// library has a point of interest in the Gulf of Guinea at exactly that spot.
+    pub fn new(latitude: f64, longitude: f64, altitude: Option<f64>) -> Option<Self> {
+        let valid = (-90.0..=90.0).contains(&latitude)
+            && (-180.0..=180.0).contains(&longitude)
+            && (latitude, longitude) != (0.0, 0.0);
+        valid.then_some(GeoLocation {
+            latitude,
+            longitude,
+            altitude: altitude.filter(|altitude| altitude.is_finite()),
+        })
+    }
+}
+
+/// Parses an ISO 6709 point such as `+40.7128-074.0060+010.5/`
+///
+/// Latitude and longitude may be decimal degrees (`±DD.D`, `±DDD.D`), degrees
+/// and minutes (`±DDMM.M`, `±DDDMM.M`) or degrees, minutes and seconds
+/// (`±DDMMSS.S`, `±DDDMMSS.S`). The altitude and the trailing `/` (or a
+/// `CRS` suffix) are optional.
+pub fn parse_iso6709(text: &str) -> Option<GeoLocation> {
+    let text = text.trim();
+    let text = text.split("CRS").next()?.trim_end_matches('/');
+    
+    // Split into signed components: "+40.7128", "-074.0060", "+010.5"
+    let mut parts = Vec::new();
+    let mut start = 0;
+    for (index, c) in text.char_indices().skip(1) {
+        if c == '+' || c == '-' {
+            parts.push(&text[start..index]);
+            start = index;
+        }
+    }
+    parts.push(&text[start..]);
+    
+    let latitude = parse_iso6709_angle(parts.first()?, 2)?;
+    let longitude = parse_iso6709_angle(parts.get(1)?, 3)?;
+    let altitude = parts.get(2).and_then(|altitude| altitude.parse().ok());
+    GeoLocation::new(latitude, longitude, altitude)
+}
+
+/// Parses one signed ISO 6709 angle whose degrees have `degree_digits` digits
+fn parse_iso6709_angle(part: &str, degree_digits: usize) -> Option<f64> {
+    let sign = match part.as_bytes().first()? {
+        b'+' => 1.0,
+        b'-' => -1.0,
+        _ => return None,
+    };
+    let number = &part[1..];
+    let integer_digits = number.find('.').unwrap_or(number.len());
+    if !number.bytes().all(|b| b.is_ascii_digit() || b == b'.') || number.is_empty() {
+        return None;
+    }
+    
+    let value: f64 = number.parse().ok()?;
+    let degrees = match integer_digits.checked_sub(degree_digits)? {
+        0 => value,
+        2 => (value / 100.0).trunc() + (value % 100.0) / 60.0,
+        4 => (value / 10_000.0).trunc() + ((value % 10_000.0) / 100.0).trunc() / 60.0 + (value % 100.0) / 3600.0,
+        _ => return None,
+    };
+    Some(sign * degrees)
+}
+
+/// Finds the location in the container tags of a video
+pub fn location_from_tags(tags: &HashMap<String, String>) -> Option<GeoLocation> {
+    LOCATION_TAGS
+        .iter()
+        .filter_map(|key| tags.get(*key))
+        .find_map(|value| parse_iso6709(value))
+}
+
+/// An area of the map
+///
+/// `west` is greater than `east` when the box crosses the antimeridian.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BoundingBox {
+    pub south: f64,
+    pub west: f64,
+    pub north: f64,
+    pub east: f64,
+}
+
+impl BoundingBox {
+    /// The whole world
+    pub const WORLD: BoundingBox = BoundingBox {
+        south: -90.0,
+        west: -180.0,
+        north: 90.0,
+        east: 180.0,
+    };
+}
+
+/// Appends a condition restricting `media_metadata` rows to a bounding box
+///
+/// The R*Tree narrows the candidates down; the exact comparison on the columns
+/// then drops points the R*Tree's 32-bit coordinates let through.
+pub(crate) fn push_bounds_condition(builder: &mut QueryBuilder<'_, Sqlite>, bounds: &BoundingBox) {
+    // Across the antimeridian the box is east of `west` or west of `east`
+    let longitude_join = if bounds.west > bounds.east { " OR " } else { " AND " };
+    
+    builder
+        .push(" AND media_metadata.id IN (SELECT id FROM media_locations WHERE max_lat >= ")
+        .push_bind(bounds.south)
+        .push(" AND min_lat <= ")
+        .push_bind(bounds.north)
+        .push(" AND (max_lon >= ")
+        .push_bind(bounds.west)
+        .push(longitude_join)
+        .push("min_lon <= ")
+        .push_bind(bounds.east)
+        .push("))");
+    
+    builder
+        .push(" AND media_metadata.latitude BETWEEN ")
+        .push_bind(bounds.south)
+        .push(" AND ")
+        .push_bind(bounds.north)
+        .push(" AND (media_metadata.longitude >= ")
+        .push_bind(bounds.west)
+        .push(longitude_join)
+        .push("media_metadata.longitude <= ")
+        .push_bind(bounds.east)
+        .push(")");
+}
+
+/// A group of nearby media shown as one map marker
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LocationCluster {
+    /// Average position of the members
+    pub latitude: f64,
+    pub longitude: f64,
+    /// Number of media in the cluster
+    pub count: i64,
+    /// Most recently captured member, e.g. for the marker thumbnail
+    pub media_id: i64,
+    /// Extent of the members, to zoom in on the cluster
+    pub bounds: BoundingBox,
+}
+
+/// Projects a point onto the Web Mercator plane, in pixels at `zoom`
+fn project(latitude: f64, longitude: f64, zoom: u8) -> (f64, f64) {
+    let world = TILE_SIZE * f64::from(1u32 << zoom);
+    let latitude = latitude.clamp(-MAX_MERCATOR_LATITUDE, MAX_MERCATOR_LATITUDE).to_radians();
+    let x = (longitude + 180.0) / 360.0 * world;
+    let y = (1.0 - (latitude.tan() + 1.0 / latitude.cos()).ln() / PI) / 2.0 * world;
+    (x, y)
+}
+
+/// Groups points falling into the same grid cell at `zoom`
+///
+/// Points are `(media_id, latitude, longitude, capture date)`. Clusters are
+/// returned largest first.
+pub fn cluster_points(points: &[(i64, f64, f64, DateTime<Utc>)], zoom: u8) -> Vec<LocationCluster> {
+    struct Cell {
+        latitude_sum: f64,
+        longitude_sum: f64,
+        count: i64,
+        newest: (DateTime<Utc>, i64),
+        bounds: BoundingBox,
+    }
+    
+    let zoom = zoom.min(MAX_ZOOM);
+    let mut cells: HashMap<(i64, i64), Cell> = HashMap::new();
+    for &(media_id, latitude, longitude, date) in points {
+        let (x, y) = project(latitude, longitude, zoom);
+        let key = ((x / CLUSTER_CELL_SIZE).floor() as i64, (y / CLUSTER_CELL_SIZE).floor() as i64);
+        let cell = cells.entry(key).or_insert(Cell {
+            latitude_sum: 0.0,
+            longitude_sum: 0.0,
+            count: 0,
+            newest: (date, media_id),
+            bounds: BoundingBox {
+                south: latitude,
+                west: longitude,
+                north: latitude,
+                east: longitude,
+            },
+        });
+        cell.latitude_sum += latitude;
+        cell.longitude_sum += longitude;
+        cell.count += 1;
+        cell.newest = cell.newest.max((date, media_id));
+        cell.bounds.south = cell.bounds.south.min(latitude);
+        cell.bounds.west = cell.bounds.west.min(longitude);
+        cell.bounds.north = cell.bounds.north.max(latitude);
+        cell.bounds.east = cell.bounds.east.max(longitude);
+    }
+    
+    let mut clusters: Vec<LocationCluster> = cells
+        .into_values()
+        .map(|cell| LocationCluster {
+            latitude: cell.latitude_sum / cell.count as f64,
+            longitude: cell.longitude_sum / cell.count as f64,
+            count: cell.count,
+            media_id: cell.newest.1,
+            bounds: cell.bounds,
+        })
+        .collect();
+    clusters.sort_by(|a, b| b.count.cmp(&a.count).then(a.media_id.cmp(&b.media_id)));
+    clusters
+}
+
+/// Clusters the located media inside a bounding box
+///
+/// # Arguments
+///
+/// * `pool` - Database connection pool
+/// * `bounds` - Visible area of the map
+/// * `zoom` - Map zoom level (0 shows the whole world in one 256 px tile)
+pub async fn find_location_clusters(
+    pool: &DbPool,
+    bounds: &BoundingBox,
+    zoom: u8,
+) -> DatabaseResult<Vec<LocationCluster>> {
+    let mut builder = QueryBuilder::<Sqlite>::new(
+        "SELECT id, latitude, longitude, COALESCE(created_date, modified_date) FROM media_metadata \
+         WHERE trashed_at IS NULL",
+    );
+    push_bounds_condition(&mut builder, bounds);
+    
+    let points: Vec<(i64, f64, f64, DateTime<Utc>)> = builder
+        .build()
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|row| (row.get(0), row.get(1), row.get(2), row.get(3)))
+        .collect();
+    Ok(cluster_points(&points, zoom))
+}
+
+/// Tauri command to cluster the located media visible on the map
+///
+/// # Arguments
+///
+/// * `pool` - Database connection pool (injected by Tauri)
+/// * `bounds` - Visible area (the whole world when omitted)
+/// * `zoom` - Map zoom level
+///
+/// # Returns
+///
+/// Returns one cluster per occupied 64 px cell, largest first
+#[tauri::command]
+pub async fn get_location_clusters(
+    pool: State<'_, DbPool>,
+    bounds: Option<BoundingBox>,
+    zoom: u8,
+) -> Result<Vec<LocationCluster>, String> {
+    find_location_clusters(&pool, &bounds.unwrap_or(BoundingBox::WORLD), zoom)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{self, InsertMediaParams};
+    use crate::search::{self, SearchFilters};
+    
+    #[test]
+    fn test_parse_iso6709() {
+        let apple = parse_iso6709("+48.8577+002.2950+035.000/").unwrap();
+        assert_eq!((apple.latitude, apple.longitude, apple.altitude), (48.8577, 2.295, Some(35.0)));
+        
+        let mp4 = parse_iso6709("-33.8688+151.2093/").unwrap();
+        assert_eq!((mp4.latitude, mp4.longitude, mp4.altitude), (-33.8688, 151.2093, None));
+        
+        // Degrees and minutes, and degrees, minutes and seconds
+        let minutes = parse_iso6709("+4051.30-07400.36/").unwrap();
+        assert!((minutes.latitude - 40.855).abs() < 1e-9 && (minutes.longitude + 74.006).abs() < 1e-9);
+        let seconds = parse_iso6709("+404230-0740036CRSWGS_84/").unwrap();
+        assert!((seconds.latitude - 40.708_333).abs() < 1e-6 && (seconds.longitude + 74.01).abs() < 1e-9);
+        
+        assert_eq!(parse_iso6709("+00.0000+000.0000/"), None);
+        assert_eq!(parse_iso6709("+95.0+010.0/"), None);
+        assert_eq!(parse_iso6709("garbage"), None);
+    }
+    
+    #[tokio::test]
+    async fn test_bounds_filter_and_clusters() {
+        let pool = database::open_test_pool().await;
+        let folder_id = database::upsert_scanned_folder(&pool, "/lib", "lib", 0).await.unwrap();
+        let photo = |name: &str, location: Option<(f64, f64)>| InsertMediaParams {
+            folder_id,
+            file_path: format!("/lib/{}", name),
+            file_name: name.to_string(),
+            file_type: "jpg".to_string(),
+            media_type: Some("image".to_string()),
+            latitude: location.map(|(latitude, _)| latitude),
+            longitude: location.map(|(_, longitude)| longitude),
+            ..Default::default()
+        };
+        let rows = [
+            photo("eiffel.jpg", Some((48.8584, 2.2945))),
+            photo("louvre.jpg", Some((48.8606, 2.3376))),
+            photo("sydney.jpg", Some((-33.8568, 151.2153))),
+            photo("fiji.jpg", Some((-17.7134, 178.065))),
+            photo("samoa.jpg", Some((-13.759, -172.1046))),
+            photo("no_gps.jpg", None),
+        ];
+        database::upsert_media_batch(&pool, &rows).await.unwrap();
+        
+        let in_bounds = |south, west, north, east| {
+            let pool = pool.clone();
+            async move {
+                let filters = SearchFilters {
+                    bounds: Some(BoundingBox { south, west, north, east }),
+                    ..Default::default()
+                };
+                let mut names: Vec<String> = search::find_media(&pool, &filters, 10)
+                    .await
+                    .unwrap()
+                    .into_iter()
+                    .map(|media| media.file_name)
+                    .collect();
+                names.sort();
+                names
+            }
+        };
+        assert_eq!(in_bounds(48.0, 2.0, 49.0, 3.0).await, ["eiffel.jpg", "louvre.jpg"]);
+        // A box crossing the antimeridian
+        assert_eq!(in_bounds(-20.0, 170.0, -10.0, -170.0).await, ["fiji.jpg", "samoa.jpg"]);
+        
+        // Paris is one cluster at country scale and two at street scale
+        let clusters = find_location_clusters(&pool, &BoundingBox::WORLD, 5).await.unwrap();
+        assert_eq!(clusters.iter().map(|c| c.count).collect::<Vec<_>>(), [2, 1, 1, 1]);
+        assert!((clusters[0].latitude - 48.8595).abs() < 1e-9);
+        let paris = BoundingBox {
+            south: 48.8,
+            west: 2.2,
+            north: 48.9,
+            east: 2.4,
+        };
+        assert_eq!(find_location_clusters(&pool, &paris, 16).await.unwrap().len(), 2);
+        
+        // Moving a row out of the box updates the index
+        sqlx::query("UPDATE media_metadata SET latitude = NULL, longitude = NULL WHERE file_name = 'louvre.jpg'")
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert_eq!(in_bounds(48.0, 2.0, 49.0, 3.0).await, ["eiffel.jpg"]);
+    }
+}
@@ -0,0 +1,440 @@
+//! Media format detection
+//!
+//! Single source of truth for which files the app treats as media. Formats
+//! are identified from the file's leading bytes (magic numbers) and fall back
+//! to the extension only when the content is not recognized, so recovered
+//! files without a proper extension are still picked up and misnamed files
+//! (e.g. HEIC photos saved as `.jpg`) are reported with their real format.
+//!
+//! Used by the scanner, media info extraction and file commands alike.
+
+use crate::scanner::MediaType;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// Number of leading bytes read to identify a file
+pub const HEADER_LEN: u64 = 64;
+
+/// Extensions that say nothing about a file's content.
+///
+/// Files with these extensions (or none at all) are sniffed too, which is how
+/// data recovery tools such as PhotoRec often leave them.
+const UNTRUSTED_EXTENSIONS: &[&str] = &["", "bin", "dat", "tmp"];
+
+/// Container family of a format.
+///
+/// Formats sharing a container are interchangeable as far as extension
+/// checks are concerned; an `.m4v` file with an `isom` brand is not misnamed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Container {
+    /// Format without siblings
+    Own,
+    /// ISO base media file format (MP4, MOV, M4V)
+    IsoBmff,
+    /// Matroska (MKV, WebM)
+    Matroska,
+    /// TIFF and the camera RAW formats built on it (CR2, NEF, ARW, DNG, ...)
+    Tiff,
+    /// Ogg (Vorbis, Opus)
+    Ogg,
+}
+
+/// A supported media format
+#[derive(Debug, PartialEq, Eq)]
+pub struct MediaFormat {
+    /// Canonical extension (e.g. "jpg")
+    pub name: &'static str,
+    /// MIME type served to the frontend
+    pub mime_type: &'static str,
+    /// Whether the format is an image, video or audio (RAW formats are images)
+    pub media_type: MediaType,
+    /// All extensions used for the format, in lowercase
+    pub extensions: &'static [&'static str],
+    /// Container family used for extension mismatch checks
+    pub container: Container,
+    /// Whether the format is a camera RAW format (see [`crate::raw`])
+    pub is_raw: bool,
+}
+
+const fn format(
+    name: &'static str,
+    mime_type: &'static str,
+    media_type: MediaType,
+    extensions: &'static [&'static str],
+    container: Container,
+) -> MediaFormat {
+    MediaFormat {
+        name,
+        mime_type,
+        media_type,
+        extensions,
+        container,
+        is_raw: false,
+    }
+}
+
+const fn raw_format(
+    name: &'static str,
+    mime_type: &'static str,
+    extensions: &'static [&'static str],
+    container: Container,
+) -> MediaFormat {
+    MediaFormat {
+        is_raw: true,
+        ..format(name, mime_type, MediaType::Image, extensions, container)
+    }
+}
+
+const JPEG: MediaFormat = format("jpg", "image/jpeg", MediaType::Image, &["jpg", "jpeg"], Container::Own);
+const PNG: MediaFormat = format("png", "image/png", MediaType::Image, &["png"], Container::Own);
+const GIF: MediaFormat = format("gif", "image/gif", MediaType::Image, &["gif"], Container::Own);
+const BMP: MediaFormat = format("bmp", "image/bmp", MediaType::Image, &["bmp"], Container::Own);
+const WEBP: MediaFormat = format("webp", "image/webp", MediaType::Image, &["webp"], Container::Own);
+const HEIC: MediaFormat = format("heic", "image/heic", MediaType::Image, &["heic", "heif"], Container::Own);
+const TIFF: MediaFormat = format("tiff", "image/tiff", MediaType::Image, &["tiff", "tif"], Container::Tiff);
+const SVG: MediaFormat = format("svg", "image/svg+xml", MediaType::Image, &["svg"], Container::Own);
+const CR2: MediaFormat = raw_format("cr2", "image/x-canon-cr2", &["cr2"], Container::Tiff);
+const CR3: MediaFormat = raw_format("cr3", "image/x-canon-cr3", &["cr3"], Container::Own);
+const NEF: MediaFormat = raw_format("nef", "image/x-nikon-nef", &["nef"], Container::Tiff);
+const ARW: MediaFormat = raw_format("arw", "image/x-sony-arw", &["arw"], Container::Tiff);
+const RAF: MediaFormat = raw_format("raf", "image/x-fuji-raf", &["raf"], Container::Own);
+const ORF: MediaFormat = raw_format("orf", "image/x-olympus-orf", &["orf"], Container::Tiff);
+const RW2: MediaFormat = raw_format("rw2", "image/x-panasonic-rw2", &["rw2"], Container::Tiff);
+const DNG: MediaFormat = raw_format("dng", "image/x-adobe-dng", &["dng"], Container::Tiff);
+const MP4: MediaFormat = format("mp4", "video/mp4", MediaType::Video, &["mp4"], Container::IsoBmff);
+const MOV: MediaFormat = format("mov", "video/quicktime", MediaType::Video, &["mov"], Container::IsoBmff);
+const M4V: MediaFormat = format("m4v", "video/x-m4v", MediaType::Video, &["m4v"], Container::IsoBmff);
+const AVI: MediaFormat = format("avi", "video/x-msvideo", MediaType::Video, &["avi"], Container::Own);
+const MKV: MediaFormat = format("mkv", "video/x-matroska", MediaType::Video, &["mkv"], Container::Matroska);
+const WEBM: MediaFormat = format("webm", "video/webm", MediaType::Video, &["webm"], Container::Matroska);
+const FLV: MediaFormat = format("flv", "video/x-flv", MediaType::Video, &["flv"], Container::Own);
+const WMV: MediaFormat = format("wmv", "video/x-ms-wmv", MediaType::Video, &["wmv"], Container::Own);
+const MPEG: MediaFormat = format("mpg", "video/mpeg", MediaType::Video, &["mpg", "mpeg"], Container::Own);
+const MP3: MediaFormat = format("mp3", "audio/mpeg", MediaType::Audio, &["mp3"], Container::Own);
+const FLAC: MediaFormat = format("flac", "audio/flac", MediaType::Audio, &["flac"], Container::Own);
+const WAV: MediaFormat = format("wav", "audio/wav", MediaType::Audio, &["wav", "wave"], Container::Own);
+const M4A: MediaFormat = format("m4a", "audio/mp4", MediaType::Audio, &["m4a", "m4b"], Container::IsoBmff);
+const OGG: MediaFormat = format("ogg", "audio/ogg", MediaType::Audio, &["ogg", "oga"], Container::Ogg);
+const OPUS: MediaFormat = format("opus", "audio/opus", MediaType::Audio, &["opus"], Container::Ogg);
+
+/// Every supported format
+pub const FORMATS: &[&MediaFormat] = &[
+    &JPEG, &PNG, &GIF, &BMP, &WEBP, &HEIC, &TIFF, &SVG,
+    &CR2, &CR3, &NEF, &ARW, &RAF, &ORF, &RW2, &DNG,
+    &MP4, &MOV, &M4V, &AVI, &MKV, &WEBM, &FLV, &WMV, &MPEG,
+    &MP3, &FLAC, &WAV, &M4A, &OGG, &OPUS,
+];
+
+/// Result of detecting a file's format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Detection {
+    /// The detected format
+    pub format: &'static MediaFormat,
+    /// Whether the extension does not match the content
+    pub extension_mismatch: bool,
+}
+
+/// Looks up a format by extension.
+///
+/// # Arguments
+///
+/// * `extension` - The file extension (without the dot), in any case
+///
+/// # Returns
+///
+/// Returns the format using that extension, or `None` if it is not a media extension.
+pub fn format_for_extension(extension: &str) -> Option<&'static MediaFormat> {
+    FORMATS
+        .iter()
+        .copied()
+        .find(|format| format.extensions.iter().any(|ext| ext.eq_ignore_ascii_case(extension)))
+}
+
+/// Identifies a format from a file's leading bytes.
+///
+/// # Arguments
+///
+/// * `header` - The first bytes of the file (see [`HEADER_LEN`])
+///
+/// # Returns
+///
+/// Returns the format whose signature matches, or `None` if the content is
+/// not recognized.
+pub fn sniff(header: &[u8]) -> Option<&'static MediaFormat> {
+    if header.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        return Some(&JPEG);
+    }
+    if header.starts_with(b"\x89PNG\r\n\x1a\n") {
+        return Some(&PNG);
+    }
+    if header.starts_with(b"GIF87a") || header.starts_with(b"GIF89a") {
+        return Some(&GIF);
+    }
+    if header.starts_with(b"II*\0") && header.get(8..10) == Some(b"CR") {
+        return Some(&CR2);
+    }
+    // NEF, ARW and DNG files are plain TIFF files; the extension tells them apart
+    if header.starts_with(b"II*\0") || header.starts_with(b"MM\0*") {
+        return Some(&TIFF);
+    }
+    if header.starts_with(b"IIRO") || header.starts_with(b"IIRS") || header.starts_with(b"MMOR") {
+        return Some(&ORF);
+    }
+    if header.starts_with(b"IIU\0") {
+        return Some(&RW2);
+    }
+    if header.starts_with(b"FUJIFILMCCD-RAW") {
+        return Some(&RAF);
+    }
+    if header.starts_with(b"RIFF") && header.len() >= 12 {
+        return match &header[8..12] {
+            b"WEBP" => Some(&WEBP),
+            b"AVI " => Some(&AVI),
+            b"WAVE" => Some(&WAV),
+            _ => None,
+        };
+    }
+    if header.starts_with(&[0x1A, 0x45, 0xDF, 0xA3]) {
+        // The EBML header names the document type within its first bytes
+        let is_webm = header.windows(4).any(|window| window == b"webm");
+        return Some(if is_webm { &WEBM } else { &MKV });
+    }
+    if header.starts_with(b"fLaC") {
+        return Some(&FLAC);
+    }
+    if header.starts_with(b"OggS") {
+        // The first page carries the codec identification header
+        let is_opus = header.windows(8).any(|window| window == b"OpusHead");
+        return Some(if is_opus { &OPUS } else { &OGG });
+    }
+    if header.starts_with(b"ID3") || is_mpeg_audio_frame(header) {
+        return Some(&MP3);
+    }
+    if header.starts_with(b"FLV\x01") {
+        return Some(&FLV);
+    }
+    if header.starts_with(&[0x30, 0x26, 0xB2, 0x75, 0x8E, 0x66, 0xCF, 0x11]) {
+        return Some(&WMV);
+    }
+    if header.starts_with(&[0x00, 0x00, 0x01, 0xBA]) || header.starts_with(&[0x00, 0x00, 0x01, 0xB3]) {
+        return Some(&MPEG);
+    }
+    if header.len() >= 12 {
+        if let Some(format) = sniff_iso_bmff(&header[4..8], &header[8..12]) {
+            return Some(format);
+        }
+    }
+    if header.starts_with(b"BM") && header.len() >= 14 && header[6..10] == [0, 0, 0, 0] {
+        return Some(&BMP);
+    }
+    if trim_text_start(header).starts_with(b"<svg") {
+        return Some(&SVG);
+    }
+    
+    None
+}
+
+/// Identifies ISO base media files from their first box.
+///
+/// Modern files start with an `ftyp` box whose major brand tells HEIF images,
+/// QuickTime movies and MP4 variants apart. Old QuickTime files start
+/// directly with a movie or media data box.
+fn sniff_iso_bmff(box_type: &[u8], brand: &[u8]) -> Option<&'static MediaFormat> {
+    match box_type {
+        b"ftyp" => match brand {
+            b"heic" | b"heix" | b"hevc" | b"hevx" | b"heim" | b"heis" | b"hevm" | b"hevs"
+            | b"mif1" | b"msf1" => Some(&HEIC),
+            b"qt  " => Some(&MOV),
+            b"crx " => Some(&CR3),
+            b"M4V " | b"M4VH" | b"M4VP" => Some(&M4V),
+            b"M4A " | b"M4B " | b"M4P " => Some(&M4A),
+            _ => Some(&MP4),
+        },
+        b"moov" | b"mdat" | b"wide" | b"pnot" => Some(&MOV),
+        _ => None,
+    }
+}
+
+/// Checks for an MPEG audio frame header (layer I, II or III).
+///
+/// MP3 files without an ID3 tag start directly with a frame: 11 sync bits
+/// followed by a non-reserved layer.
+fn is_mpeg_audio_frame(header: &[u8]) -> bool {
+    header.len() >= 2 && header[0] == 0xFF && header[1] & 0xE0 == 0xE0 && header[1] & 0x06 != 0
+}
+
+/// Skips a UTF-8 byte order mark and leading whitespace
+fn trim_text_start(header: &[u8]) -> &[u8] {
+    let header = header.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(header);
+    let start = header
+        .iter()
+        .position(|byte| !byte.is_ascii_whitespace())
+        .unwrap_or(header.len());
+    &header[start..]
+}
+
+/// Combines the extension and the sniffed content into a detection.
+///
+/// The content wins when it is recognized. If it belongs to the same
+/// container family as the extension, the extension's format is kept (an
+/// `.m4v` file stays M4V). When the content is not recognized, the extension
+/// is trusted.
+///
+/// # Arguments
+///
+/// * `extension` - The file extension (without the dot), in any case
+/// * `header` - The first bytes of the file, if they could be read
+///
+/// # Returns
+///
+/// Returns `None` if neither the content nor the extension is a supported format.
+pub fn detect(extension: &str, header: Option<&[u8]>) -> Option<Detection> {
+    let by_extension = format_for_extension(extension);
+    
+    match (header.and_then(sniff), by_extension) {
+        (Some(sniffed), Some(expected)) if sniffed.container != Container::Own
+            && sniffed.container == expected.container =>
+        {
+            Some(Detection {
+                format: expected,
+                extension_mismatch: false,
+            })
+        }
+        (Some(sniffed), expected) => Some(Detection {
+            format: sniffed,
+            extension_mismatch: expected != Some(sniffed),
+        }),
+        (None, expected) => expected.map(|format| Detection {
+            format,
+            extension_mismatch: false,
+        }),
+    }
+}
+
+/// Detects the format of a file on disk.
+///
+/// Only files with a media extension or an uninformative one (none, `.bin`,
+/// ...) are opened; other files are rejected without any IO.
+///
+/// # Arguments
+///
+/// * `path` - Path to the file
+///
+/// # Returns
+///
+/// Returns `None` if the file is not a supported media file.
+pub fn detect_file(path: &Path) -> Option<Detection> {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_lowercase)
+        .unwrap_or_default();
+    
+    let trusted_non_media = format_for_extension(&extension).is_none()
+        && !UNTRUSTED_EXTENSIONS.contains(&extension.as_str());
+    if trusted_non_media {
+        return None;
+    }
+    
+    let header = read_header(path).ok();
+    detect(&extension, header.as_deref())
+}
+
+/// Reads up to [`HEADER_LEN`] leading bytes of a file.
+pub fn read_header(path: &Path) -> std::io::Result<Vec<u8>> {
+    let mut header = Vec::with_capacity(HEADER_LEN as usize);
+    File::open(path)?.take(HEADER_LEN).read_to_end(&mut header)?;
+    Ok(header)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn ftyp(brand: &[u8; 4]) -> Vec<u8> {
+        let mut header = vec![0, 0, 0, 24];
+        header.extend_from_slice(b"ftyp");
+        header.extend_from_slice(brand);
+        header.extend_from_slice(&[0; 12]);
+        header
+    }
+    
+    #[test]
+    fn test_sniff_signatures() {
+        assert_eq!(sniff(&[0xFF, 0xD8, 0xFF, 0xE1, 0, 0]).map(|f| f.name), Some("jpg"));
+        assert_eq!(sniff(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR").map(|f| f.name), Some("png"));
+        assert_eq!(sniff(b"RIFF\0\0\0\0WEBPVP8 ").map(|f| f.name), Some("webp"));
+        assert_eq!(sniff(b"RIFF\0\0\0\0AVI LIST").map(|f| f.name), Some("avi"));
+        assert_eq!(sniff(&ftyp(b"heic")).map(|f| f.name), Some("heic"));
+        assert_eq!(sniff(&ftyp(b"qt  ")).map(|f| f.name), Some("mov"));
+        assert_eq!(sniff(&ftyp(b"isom")).map(|f| f.name), Some("mp4"));
+        assert_eq!(sniff(b"\x1a\x45\xdf\xa3\x9f\x42\x82\x84webm").map(|f| f.name), Some("webm"));
+        assert_eq!(sniff(b"\x1a\x45\xdf\xa3\x9f\x42\x82\x88matroska").map(|f| f.name), Some("mkv"));
+        assert_eq!(sniff(b"  <svg xmlns=\"http://www.w3.org/2000/svg\">").map(|f| f.name), Some("svg"));
+        assert_eq!(sniff(b"hello world"), None);
+    }
+    
+    #[test]
+    fn test_detect_flags_mismatches() {
+        let heic = ftyp(b"heic");
+        let detection = detect("jpg", Some(&heic)).unwrap();
+        assert_eq!(detection.format.name, "heic");
+        assert!(detection.extension_mismatch);
+        
+        let jpeg = [0xFF, 0xD8, 0xFF, 0xE0];
+        let detection = detect("bin", Some(&jpeg)).unwrap();
+        assert_eq!(detection.format.media_type, MediaType::Image);
+        assert!(detection.extension_mismatch);
+        
+        let detection = detect("JPEG", Some(&jpeg)).unwrap();
+        assert!(!detection.extension_mismatch);
+    }
+    
+    #[test]
+    fn test_detect_raw_formats() {
+        let tiff = b"II*\0\x08\0\0\0\0\0";
+        let detection = detect("nef", Some(tiff)).unwrap();
+        assert_eq!(detection.format.name, "nef");
+        assert!(detection.format.is_raw);
+        assert!(!detection.extension_mismatch);
+        
+        assert_eq!(sniff(b"II*\0\x10\0\0\0CR\x02\0").map(|f| f.name), Some("cr2"));
+        assert_eq!(sniff(b"FUJIFILMCCD-RAW 0201").map(|f| f.name), Some("raf"));
+        assert_eq!(sniff(&ftyp(b"crx ")).map(|f| f.name), Some("cr3"));
+        assert_eq!(detect("bin", Some(tiff)).map(|d| d.format.name), Some("tiff"));
+    }
+    
+    #[test]
+    fn test_sniff_audio() {
+        assert_eq!(sniff(b"ID3\x04\0\0\0\0\0\0").map(|f| f.name), Some("mp3"));
+        assert_eq!(sniff(&[0xFF, 0xFB, 0x90, 0x64]).map(|f| f.name), Some("mp3"));
+        assert_eq!(sniff(b"fLaC\0\0\0\x22").map(|f| f.name), Some("flac"));
+        assert_eq!(sniff(b"RIFF\0\0\0\0WAVEfmt ").map(|f| f.name), Some("wav"));
+        assert_eq!(sniff(&ftyp(b"M4A ")).map(|f| f.name), Some("m4a"));
+        
+        let mut opus = b"OggS".to_vec();
+        opus.extend_from_slice(&[0; 24]);
+        opus.extend_from_slice(b"OpusHead");
+        assert_eq!(sniff(&opus).map(|f| f.name), Some("opus"));
+        assert_eq!(sniff(b"OggS\0\x02\0\0\0\0\x01vorbis").map(|f| f.name), Some("ogg"));
+        
+        let detection = detect("m4a", Some(&ftyp(b"mp42"))).unwrap();
+        assert_eq!(detection.format.media_type, MediaType::Audio);
+        assert!(!detection.extension_mismatch);
+    }
+    
+    #[test]
+    fn test_detect_same_container_and_fallback() {
+        let detection = detect("m4v", Some(&ftyp(b"isom"))).unwrap();
+        assert_eq!(detection.format.name, "m4v");
+        assert!(!detection.extension_mismatch);
+        
+        // Unrecognized or unreadable content trusts the extension
+        let detection = detect("mov", None).unwrap();
+        assert_eq!(detection.format.name, "mov");
+        assert!(!detection.extension_mismatch);
+        assert_eq!(detect("bin", Some(b"not media")), None);
+        assert_eq!(detect("txt", None), None);
+    }
+}
@@ -15,8 +15,14 @@ use std::ffi::CString;
 use std::collections::HashMap;
 use thiserror::Error;
 use tracing::{debug, error, instrument};
-use image::GenericImageView;
+use image::{GenericImageView, ImageReader};
+use std::path::Path;
 use crate::database::{self, DbPool, InsertMediaParams};
+use crate::exif::{self, CameraInfo};
+use crate::location::{self, GeoLocation};
+use crate::media_detect;
+use crate::raw;
+use crate::scanner::MediaType;
 use chrono::{DateTime, Utc};
 use tauri::State;
 
@@ -59,6 +65,14 @@ pub struct MediaInfo {
     /// General file information
     pub general: GeneralInfo,
     
+    /// Camera settings from EXIF (photos only)
+    #[serde(default)]
+    pub camera: Option<CameraInfo>,
+    
+    /// Where the photo or video was taken
+    #[serde(default)]
+    pub location: Option<GeoLocation>,
+    
     /// Additional metadata (EXIF, tags, etc.)
     pub metadata: HashMap<String, String>,
 }
@@ -137,8 +151,15 @@ pub struct GeneralInfo {
 pub fn extract_media_info(file_path: &str) -> MediaInfoResult<MediaInfo> {
     debug!("Starting media info extraction");
     
+    let detection = media_detect::detect_file(Path::new(file_path));
+    
+    // Camera RAW files can't be decoded, read their tags instead
+    if let Some(detection) = detection.filter(|detection| detection.format.is_raw) {
+        return extract_raw_info(file_path, detection.format.name);
+    }
+    
     // First check if it's an image - handle separately for better EXIF support
-    if is_image_file(file_path) {
+    if detection.is_some_and(|detection| detection.format.media_type == MediaType::Image) {
         return extract_image_info(file_path);
     }
     
@@ -146,21 +167,16 @@ pub fn extract_media_info(file_path: &str) -> MediaInfoResult<MediaInfo> {
     extract_video_info(file_path)
 }
 
-/// Check if file is an image based on extension
-fn is_image_file(file_path: &str) -> bool {
-    let extensions = ["jpg", "jpeg", "png", "gif", "bmp", "webp", "heic", "heif", "tiff", "tif"];
-    file_path
-        .rsplit('.')
-        .next()
-        .map(|ext| extensions.contains(&ext.to_lowercase().as_str()))
-        .unwrap_or(false)
-}
-
 /// Extract information from image files
 fn extract_image_info(file_path: &str) -> MediaInfoResult<MediaInfo> {
     debug!("Extracting image info");
     
-    let img = image::open(file_path)?;
+    // Decode by content so misnamed and extensionless images still open
+    let img = ImageReader::open(file_path)
+        .map_err(|e| MediaInfoError::FileOpen(e.to_string()))?
+        .with_guessed_format()
+        .map_err(|e| MediaInfoError::FileOpen(e.to_string()))?
+        .decode()?;
     let (width, height) = img.dimensions();
     
     // Get file size
@@ -168,10 +184,10 @@ fn extract_image_info(file_path: &str) -> MediaInfoResult<MediaInfo> {
         .map(|m| m.len() as i64)
         .unwrap_or(0);
     
-    // Determine format from extension
-    let format = file_path
-        .rsplit('.')
-        .next()
+    // Determine format from content, falling back to the extension
+    let format = media_detect::detect_file(Path::new(file_path))
+        .map(|detection| detection.format.name)
+        .or_else(|| file_path.rsplit('.').next())
         .unwrap_or("unknown")
         .to_uppercase();
     
@@ -181,6 +197,11 @@ fn extract_image_info(file_path: &str) -> MediaInfoResult<MediaInfo> {
         "N/A".to_string()
     };
     
+    // Many images (screenshots, exports) carry no EXIF at all
+    let exif = exif::read_exif(Path::new(file_path))
+        .inspect_err(|e| debug!("No EXIF data: {}", e))
+        .unwrap_or_default();
+    
     Ok(MediaInfo {
         video: Some(VideoInfo {
             codec: "image".to_string(),
@@ -200,7 +221,73 @@ fn extract_image_info(file_path: &str) -> MediaInfoResult<MediaInfo> {
             bitrate: None,
             size: file_size,
         },
-        metadata: HashMap::new(),
+        camera: Some(exif.camera).filter(|camera| !camera.is_empty()),
+        location: exif.location,
+        metadata: exif.tags,
+    })
+}
+
+/// Extract information from camera RAW files
+///
+/// Reports the sensor dimensions and the camera make and model. Both are
+/// also added to the metadata under their EXIF names (`Make`, `Model`).
+/// TIFF-based RAW files additionally get their full EXIF data.
+fn extract_raw_info(file_path: &str, format_name: &str) -> MediaInfoResult<MediaInfo> {
+    debug!("Extracting RAW info");
+    
+    let raw_info = raw::read_raw_info(Path::new(file_path))
+        .map_err(|e| MediaInfoError::MetadataExtraction(e.to_string()))?;
+    
+    let file_size = std::fs::metadata(file_path)
+        .map(|m| m.len() as i64)
+        .unwrap_or(0);
+    
+    let width = raw_info.width.unwrap_or(0);
+    let height = raw_info.height.unwrap_or(0);
+    let aspect_ratio = if height > 0 {
+        format!("{}:{}", width, height)
+    } else {
+        "N/A".to_string()
+    };
+    
+    let format = format_name.to_uppercase();
+    let exif = exif::read_exif(Path::new(file_path))
+        .inspect_err(|e| debug!("No EXIF data: {}", e))
+        .unwrap_or_default();
+    let mut metadata = exif.tags;
+    let mut camera = exif.camera;
+    let location = exif.location;
+    if let Some(make) = raw_info.make {
+        camera.make.get_or_insert_with(|| make.clone());
+        metadata.insert("Make".to_string(), make);
+    }
+    if let Some(model) = raw_info.model {
+        camera.model.get_or_insert_with(|| model.clone());
+        metadata.insert("Model".to_string(), model);
+    }
+    
+    Ok(MediaInfo {
+        video: Some(VideoInfo {
+            codec: "raw".to_string(),
+            codec_long: format!("{} Camera RAW", format),
+            width: width as i32,
+            height: height as i32,
+            fps: 0.0,
+            bitrate: None,
+            pix_fmt: "raw".to_string(),
+            aspect_ratio,
+        }),
+        audio: None,
+        general: GeneralInfo {
+            format: format.clone(),
+            format_long: format!("{} Camera RAW File", format),
+            duration: None,
+            bitrate: None,
+            size: file_size,
+        },
+        camera: Some(camera).filter(|camera| !camera.is_empty()),
+        location,
+        metadata,
     })
 }
 
@@ -281,6 +368,7 @@ fn extract_video_info(file_path: &str) -> MediaInfoResult<MediaInfo> {
     
     // Extract metadata
     let metadata = extract_metadata(&input_context);
+    let location = location::location_from_tags(&metadata);
     
     Ok(MediaInfo {
         video: video_info,
@@ -292,6 +380,8 @@ fn extract_video_info(file_path: &str) -> MediaInfoResult<MediaInfo> {
             bitrate,
             size: file_size,
         },
+        camera: None,
+        location,
         metadata,
     })
 }
@@ -311,7 +401,11 @@ fn extract_video_stream_info(input_context: &mut AVFormatContextInput) -> Option
                 if codecpar.is_null() {
                     return false;
                 }
-                (*codecpar).codec_type == ffi::AVMEDIA_TYPE_VIDEO
+                // Cover art in audio files is exposed as a single-frame video stream
+                let is_attached_pic = (*stream.as_ptr()).disposition
+                    & ffi::AV_DISPOSITION_ATTACHED_PIC as i32
+                    != 0;
+                (*codecpar).codec_type == ffi::AVMEDIA_TYPE_VIDEO && !is_attached_pic
             }
         });
     
@@ -489,39 +583,92 @@ fn extract_audio_stream_info(input_context: &mut AVFormatContextInput) -> Option
 }
 
 /// Extract metadata from the media file
+///
+/// Reads the container tags and the tags of the first audio stream (Ogg and
+/// Opus files store their Vorbis comments on the stream). Container tags win
+/// on conflicts. Common audio tags are also exposed under lowercase canonical
+/// keys (`artist`, `album`, `title`, `track`).
 fn extract_metadata(input_context: &AVFormatContextInput) -> HashMap<String, String> {
     let mut metadata = HashMap::new();
     
     unsafe {
         let format_context = input_context.as_ptr();
-        let mut tag: *mut ffi::AVDictionaryEntry = std::ptr::null_mut();
+        read_dictionary((*format_context).metadata, &mut metadata);
         
-        loop {
-            tag = ffi::av_dict_get(
-                (*format_context).metadata,
-                std::ptr::null(),
-                tag,
-                ffi::AV_DICT_IGNORE_SUFFIX as i32,
-            );
-            
-            if tag.is_null() {
+        for stream in input_context.streams().into_iter() {
+            let stream_ptr = stream.as_ptr();
+            let codecpar = (*stream_ptr).codecpar;
+            if !codecpar.is_null() && (*codecpar).codec_type == ffi::AVMEDIA_TYPE_AUDIO {
+                let mut stream_tags = HashMap::new();
+                read_dictionary((*stream_ptr).metadata, &mut stream_tags);
+                for (key, value) in stream_tags {
+                    metadata.entry(key).or_insert(value);
+                }
                 break;
             }
-            
-            let key = std::ffi::CStr::from_ptr((*tag).key)
-                .to_string_lossy()
-                .to_string();
-            let value = std::ffi::CStr::from_ptr((*tag).value)
-                .to_string_lossy()
-                .to_string();
-            
-            metadata.insert(key, value);
         }
     }
     
+    add_canonical_audio_tags(&mut metadata);
     metadata
 }
 
+/// Copies every entry of an FFmpeg dictionary into `metadata`
+///
+/// # Safety
+///
+/// `dictionary` must be null or point to a valid `AVDictionary`.
+unsafe fn read_dictionary(dictionary: *mut ffi::AVDictionary, metadata: &mut HashMap<String, String>) {
+    if dictionary.is_null() {
+        return;
+    }
+    
+    let mut tag: *mut ffi::AVDictionaryEntry = std::ptr::null_mut();
+    loop {
+        tag = ffi::av_dict_get(
+            dictionary,
+            std::ptr::null(),
+            tag,
+            ffi::AV_DICT_IGNORE_SUFFIX as i32,
+        );
+        
+        if tag.is_null() {
+            break;
+        }
+        
+        let key = std::ffi::CStr::from_ptr((*tag).key)
+            .to_string_lossy()
+            .to_string();
+        let value = std::ffi::CStr::from_ptr((*tag).value)
+            .to_string_lossy()
+            .to_string();
+        
+        metadata.insert(key, value);
+    }
+}
+
+/// Adds lowercase `artist`, `album`, `title` and `track` keys when the file
+/// only has them under a different case (e.g. `ARTIST` in Vorbis comments)
+fn add_canonical_audio_tags(metadata: &mut HashMap<String, String>) {
+    for key in ["artist", "album", "title", "track"] {
+        if metadata.contains_key(key) {
+            continue;
+        }
+        let value = metadata
+            .iter()
+            .find(|(existing, _)| existing.eq_ignore_ascii_case(key))
+            .map(|(_, value)| value.clone());
+        if let Some(value) = value {
+            metadata.insert(key.to_string(), value);
+        }
+    }
+}
+
+/// Parses a track tag such as `"3"` or `"3/12"` into the track number
+fn parse_track_number(track: &str) -> Option<i64> {
+    track.split('/').next()?.trim().parse().ok()
+}
+
 /// Calculate greatest common divisor for aspect ratio
 fn gcd(mut a: i32, mut b: i32) -> i32 {
     while b != 0 {
@@ -548,16 +695,7 @@ async fn get_media_info_with_cache(pool: &DbPool, file_path: &str) -> MediaInfoR
     let file_metadata = std::fs::metadata(file_path)
         .map_err(|e| MediaInfoError::FileOpen(format!("Cannot access file: {}", e)))?;
     
-    let file_modified = file_metadata
-        .modified()
-        .ok()
-        .and_then(|time| {
-            time.duration_since(std::time::UNIX_EPOCH)
-                .ok()
-                .map(|d| DateTime::from_timestamp(d.as_secs() as i64, 0))
-        })
-        .flatten()
-        .unwrap_or_else(Utc::now);
+    let file_modified = modified_date(&file_metadata);
     
     // Check if we have cached metadata in the database
     if let Ok(Some(cached_metadata)) = database::get_media_metadata_by_path(pool, file_path).await {
@@ -592,12 +730,118 @@ async fn get_media_info_with_cache(pool: &DbPool, file_path: &str) -> MediaInfoR
     Ok(media_info)
 }
 
+/// Modification time of a file, truncated to seconds like the scanner's
+fn modified_date(file_metadata: &std::fs::Metadata) -> DateTime<Utc> {
+    file_metadata
+        .modified()
+        .ok()
+        .and_then(|time| {
+            time.duration_since(std::time::UNIX_EPOCH)
+                .ok()
+                .map(|d| DateTime::from_timestamp(d.as_secs() as i64, 0))
+        })
+        .flatten()
+        .unwrap_or_else(Utc::now)
+}
+
+/// Result of one [`extract_pending_metadata`] batch
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MetadataBatch {
+    /// Files whose metadata was extracted
+    pub extracted: u64,
+    /// Files that could not be read or decoded
+    pub failed: u64,
+    /// Files still waiting for extraction
+    pub remaining: i64,
+}
+
+/// Extracts the metadata of indexed files that were never opened
+///
+/// Media info is otherwise only extracted when a file is shown in the info
+/// panel; this fills the codec, EXIF and GPS columns of the rest of the
+/// library one batch at a time. Files that fail, including those whose decoder
+/// panics, get an empty `format` so the next batches skip them until they
+/// change on disk.
+///
+/// # Arguments
+///
+/// * `pool` - Database connection pool
+/// * `limit` - Maximum number of files to process
+pub async fn extract_pending_metadata(pool: &DbPool, limit: i64) -> Result<MetadataBatch, database::DatabaseError> {
+    let pending: Vec<(i64, i64, String)> = sqlx::query_as(
+        r#"
+        SELECT id, folder_id, file_path FROM media_metadata
+        WHERE format IS NULL AND trashed_at IS NULL
+        ORDER BY id
+        LIMIT ?
+        "#,
+    )
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    
+    let mut batch = MetadataBatch::default();
+    for (id, folder_id, file_path) in pending {
+        // One task per file, so a decoder panic only fails that file
+        let path = file_path.clone();
+        let info = tokio::task::spawn_blocking(move || {
+            let file_metadata = std::fs::metadata(&path).map_err(|e| MediaInfoError::FileOpen(e.to_string()))?;
+            let info = extract_media_info(&path)?;
+            Ok((info, modified_date(&file_metadata)))
+        })
+        .await
+        .unwrap_or_else(|e| Err(MediaInfoError::MetadataExtraction(e.to_string())));
+        
+        match info {
+            Ok((info, file_modified)) => {
+                let params = media_info_to_params(folder_id, &file_path, &info, file_modified);
+                database::insert_media_metadata(pool, params).await?;
+                batch.extracted += 1;
+            }
+            Err(e) => {
+                debug!("Cannot extract metadata of {}: {}", file_path, e);
+                sqlx::query("UPDATE media_metadata SET format = '' WHERE id = ?")
+                    .bind(id)
+                    .execute(pool)
+                    .await?;
+                batch.failed += 1;
+            }
+        }
+    }
+    
+    batch.remaining = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM media_metadata WHERE format IS NULL AND trashed_at IS NULL",
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(batch)
+}
+
+/// Tauri command to extract the metadata of the next batch of unopened files
+///
+/// Call it repeatedly until `remaining` is 0.
+///
+/// # Arguments
+///
+/// * `pool` - Database connection pool (injected by Tauri)
+/// * `limit` - Files per batch (defaults to 100)
+#[tauri::command]
+pub async fn extract_pending_metadata_command(
+    pool: State<'_, DbPool>,
+    limit: Option<i64>,
+) -> Result<MetadataBatch, String> {
+    extract_pending_metadata(&pool, limit.unwrap_or(100).max(1))
+        .await
+        .map_err(|e| e.to_string())
+}
+
 /// Convert database MediaMetadata to MediaInfo format
 fn convert_metadata_to_media_info(metadata: &database::MediaMetadata) -> Option<MediaInfo> {
     // Only return cached data if we have the essential video/audio codec information
     // This ensures we only use cache for complete metadata extractions
+    // An empty format marks files whose extraction failed
     let has_video_info = metadata.video_codec.is_some() && metadata.width.is_some();
-    let has_complete_info = has_video_info || metadata.format.is_some();
+    let has_complete_info = has_video_info || metadata.format.as_deref().is_some_and(|format| !format.is_empty());
     
     if !has_complete_info {
         return None;
@@ -646,6 +890,19 @@ fn convert_metadata_to_media_info(metadata: &database::MediaMetadata) -> Option<
         None
     };
     
+    let camera = CameraInfo {
+        make: metadata.camera_make.clone(),
+        model: metadata.camera_model.clone(),
+        lens: metadata.lens_model.clone(),
+        focal_length: metadata.focal_length,
+        aperture: metadata.aperture,
+        exposure_time: metadata.exposure_time,
+        iso: metadata.iso,
+        flash: metadata.flash,
+        date_taken: metadata.date_taken.clone(),
+        orientation: metadata.orientation,
+    };
+    
     let metadata_map = if let Some(metadata_json) = &metadata.metadata_json {
         serde_json::from_str(metadata_json).unwrap_or_default()
     } else {
@@ -664,6 +921,11 @@ fn convert_metadata_to_media_info(metadata: &database::MediaMetadata) -> Option<
             bitrate: metadata.bitrate,
             size: metadata.file_size,
         },
+        camera: Some(camera).filter(|camera| !camera.is_empty()),
+        location: metadata
+            .latitude
+            .zip(metadata.longitude)
+            .and_then(|(latitude, longitude)| GeoLocation::new(latitude, longitude, metadata.altitude)),
         metadata: metadata_map,
     })
 }
@@ -686,6 +948,26 @@ async fn store_media_info_in_database(
         return insert_metadata_without_folder(pool, file_path, media_info, file_modified).await;
     }
     
+    let params = media_info_to_params(folder_id.unwrap(), file_path, media_info, file_modified);
+    
+    database::insert_media_metadata(pool, params).await?;
+    debug!("Media info stored in database successfully");
+    
+    Ok(())
+}
+
+/// Builds insert parameters from freshly extracted media info
+///
+/// The media type comes from content detection, the audio tag columns are
+/// filled from the canonical metadata keys, the camera columns (and the
+/// capture date) from the EXIF camera settings and the GPS columns from the
+/// location.
+fn media_info_to_params(
+    folder_id: i64,
+    file_path: &str,
+    media_info: &MediaInfo,
+    file_modified: DateTime<Utc>,
+) -> InsertMediaParams {
     // Serialize additional metadata to JSON
     let metadata_json = if !media_info.metadata.is_empty() {
         Some(serde_json::to_string(&media_info.metadata).unwrap_or_default())
@@ -694,21 +976,26 @@ async fn store_media_info_in_database(
     };
     
     // Extract file name from path
-    let file_name = std::path::Path::new(file_path)
+    let file_name = Path::new(file_path)
         .file_name()
         .and_then(|n| n.to_str())
         .unwrap_or("unknown")
         .to_string();
     
     // Extract file extension
-    let file_type = std::path::Path::new(file_path)
+    let file_type = Path::new(file_path)
         .extension()
         .and_then(|e| e.to_str())
         .map(|s| s.to_lowercase())
         .unwrap_or_else(|| "unknown".to_string());
     
-    let params = InsertMediaParams {
-        folder_id: folder_id.unwrap(),
+    let media_type = media_detect::detect_file(Path::new(file_path))
+        .map(|detection| detection.format.media_type.as_str().to_string());
+    let tag = |key: &str| media_info.metadata.get(key).cloned();
+    let camera = media_info.camera.clone().unwrap_or_default();
+    
+    InsertMediaParams {
+        folder_id,
         file_path: file_path.to_string(),
         file_name,
         file_type,
@@ -716,7 +1003,7 @@ async fn store_media_info_in_database(
         width: media_info.video.as_ref().map(|v| v.width as i64),
         height: media_info.video.as_ref().map(|v| v.height as i64),
         duration: media_info.general.duration,
-        created_date: None,
+        created_date: camera.captured_at(),
         modified_date: file_modified,
         thumbnail_path: None,
         video_codec: media_info.video.as_ref().map(|v| v.codec.clone()),
@@ -729,12 +1016,25 @@ async fn store_media_info_in_database(
         audio_channels: media_info.audio.as_ref().map(|a| a.channels as i64),
         format: Some(media_info.general.format.clone()),
         metadata_json,
-    };
-    
-    database::insert_media_metadata(pool, params).await?;
-    debug!("Media info stored in database successfully");
-    
-    Ok(())
+        media_type,
+        artist: tag("artist"),
+        album: tag("album"),
+        title: tag("title"),
+        track_number: tag("track").as_deref().and_then(parse_track_number),
+        camera_make: camera.make,
+        camera_model: camera.model,
+        lens_model: camera.lens,
+        focal_length: camera.focal_length,
+        aperture: camera.aperture,
+        exposure_time: camera.exposure_time,
+        iso: camera.iso,
+        flash: camera.flash,
+        date_taken: camera.date_taken,
+        orientation: camera.orientation,
+        latitude: media_info.location.map(|location| location.latitude),
+        longitude: media_info.location.map(|location| location.longitude),
+        altitude: media_info.location.and_then(|location| location.altitude),
+    }
 }
 
 /// Find the folder_id for a given file path by checking scanned folders
@@ -757,24 +1057,7 @@ async fn insert_metadata_without_folder(
     media_info: &MediaInfo,
     file_modified: DateTime<Utc>,
 ) -> Result<(), database::DatabaseError> {
-    let metadata_json = if !media_info.metadata.is_empty() {
-        Some(serde_json::to_string(&media_info.metadata).unwrap_or_default())
-    } else {
-        None
-    };
-    
-    let file_name = std::path::Path::new(file_path)
-        .file_name()
-        .and_then(|n| n.to_str())
-        .unwrap_or("unknown")
-        .to_string();
-    
-    let file_type = std::path::Path::new(file_path)
-        .extension()
-        .and_then(|e| e.to_str())
-        .map(|s| s.to_lowercase())
-        .unwrap_or_else(|| "unknown".to_string());
-    
+    let params = media_info_to_params(0, file_path, media_info, file_modified);
     let now = Utc::now();
     
     // First, disable foreign key constraints for this connection
@@ -790,10 +1073,15 @@ async fn insert_metadata_without_folder(
             width, height, duration, created_date, modified_date,
             thumbnail_path, indexed_at,
             video_codec, video_codec_long, audio_codec, audio_codec_long,
-            bitrate, frame_rate, sample_rate, audio_channels, format, metadata_json
+            bitrate, frame_rate, sample_rate, audio_channels, format, metadata_json,
+            media_type, artist, album, title, track_number,
+            camera_make, camera_model, lens_model, focal_length, aperture,
+            exposure_time, iso, flash, date_taken, orientation,
+            latitude, longitude, altitude
         )
-        VALUES (0, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        VALUES (0, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
         ON CONFLICT(file_path) DO UPDATE SET
+            created_date = excluded.created_date,
             modified_date = excluded.modified_date,
             file_size = excluded.file_size,
             width = excluded.width,
@@ -808,30 +1096,66 @@ async fn insert_metadata_without_folder(
             sample_rate = excluded.sample_rate,
             audio_channels = excluded.audio_channels,
             format = excluded.format,
-            metadata_json = excluded.metadata_json
+            metadata_json = excluded.metadata_json,
+            media_type = excluded.media_type,
+            artist = excluded.artist,
+            album = excluded.album,
+            title = excluded.title,
+            track_number = excluded.track_number,
+            camera_make = excluded.camera_make,
+            camera_model = excluded.camera_model,
+            lens_model = excluded.lens_model,
+            focal_length = excluded.focal_length,
+            aperture = excluded.aperture,
+            exposure_time = excluded.exposure_time,
+            iso = excluded.iso,
+            flash = excluded.flash,
+            date_taken = excluded.date_taken,
+            orientation = excluded.orientation,
+            latitude = excluded.latitude,
+            longitude = excluded.longitude,
+            altitude = excluded.altitude
         "#,
     )
-    .bind(file_path)
-    .bind(&file_name)
-    .bind(&file_type)
-    .bind(media_info.general.size)
-    .bind(media_info.video.as_ref().map(|v| v.width as i64))
-    .bind(media_info.video.as_ref().map(|v| v.height as i64))
-    .bind(media_info.general.duration)
-    .bind::<Option<DateTime<Utc>>>(None)
-    .bind(file_modified)
-    .bind::<Option<&str>>(None)
+    .bind(&params.file_path)
+    .bind(&params.file_name)
+    .bind(&params.file_type)
+    .bind(params.file_size)
+    .bind(params.width)
+    .bind(params.height)
+    .bind(params.duration)
+    .bind(params.created_date)
+    .bind(params.modified_date)
+    .bind(params.thumbnail_path.as_deref())
     .bind(now)
-    .bind(media_info.video.as_ref().map(|v| v.codec.as_str()))
-    .bind(media_info.video.as_ref().map(|v| v.codec_long.as_str()))
-    .bind(media_info.audio.as_ref().map(|a| a.codec.as_str()))
-    .bind(media_info.audio.as_ref().map(|a| a.codec_long.as_str()))
-    .bind(media_info.general.bitrate)
-    .bind(media_info.video.as_ref().map(|v| v.fps))
-    .bind(media_info.audio.as_ref().map(|a| a.sample_rate as i64))
-    .bind(media_info.audio.as_ref().map(|a| a.channels as i64))
-    .bind(Some(media_info.general.format.as_str()))
-    .bind(metadata_json.as_deref())
+    .bind(params.video_codec.as_deref())
+    .bind(params.video_codec_long.as_deref())
+    .bind(params.audio_codec.as_deref())
+    .bind(params.audio_codec_long.as_deref())
+    .bind(params.bitrate)
+    .bind(params.frame_rate)
+    .bind(params.sample_rate)
+    .bind(params.audio_channels)
+    .bind(params.format.as_deref())
+    .bind(params.metadata_json.as_deref())
+    .bind(params.media_type.as_deref())
+    .bind(params.artist.as_deref())
+    .bind(params.album.as_deref())
+    .bind(params.title.as_deref())
+    .bind(params.track_number)
+    .bind(params.camera_make.as_deref())
+    .bind(params.camera_model.as_deref())
+    .bind(params.lens_model.as_deref())
+    .bind(params.focal_length)
+    .bind(params.aperture)
+    .bind(params.exposure_time)
+    .bind(params.iso)
+    .bind(params.flash)
+    .bind(params.date_taken.as_deref())
+    .bind(params.orientation)
+    .bind(params.latitude)
+    .bind(params.longitude)
+    .bind(params.altitude)
     .execute(pool)
     .await?;
     
@@ -843,3 +1167,45 @@ async fn insert_metadata_without_folder(
     debug!("Media info stored in database successfully (without folder)");
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[tokio::test]
+    async fn test_extract_pending_marks_and_skips_failed_files() {
+        let dir = std::env::temp_dir().join(format!("fmlm_media_info_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let broken = dir.join("broken.jpg");
+        std::fs::write(&broken, b"not a jpeg").unwrap();
+        
+        let pool = database::open_test_pool().await;
+        let folder_id = database::upsert_scanned_folder(&pool, &dir.to_string_lossy(), "media", 0).await.unwrap();
+        let rows: Vec<InsertMediaParams> = [broken.clone(), dir.join("missing.jpg")]
+            .iter()
+            .map(|path| InsertMediaParams {
+                folder_id,
+                file_path: path.to_string_lossy().into_owned(),
+                file_name: path.file_name().unwrap().to_string_lossy().into_owned(),
+                file_type: "jpg".to_string(),
+                media_type: Some("image".to_string()),
+                ..Default::default()
+            })
+            .collect();
+        database::upsert_media_batch(&pool, &rows).await.unwrap();
+        
+        let batch = extract_pending_metadata(&pool, 10).await.unwrap();
+        assert_eq!((batch.extracted, batch.failed, batch.remaining), (0, 2, 0));
+        let formats: Vec<Option<String>> = sqlx::query_scalar("SELECT format FROM media_metadata")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(formats, [Some(String::new()), Some(String::new())]);
+        
+        // The failed files are not read again
+        let batch = extract_pending_metadata(&pool, 10).await.unwrap();
+        assert_eq!((batch.extracted, batch.failed, batch.remaining), (0, 0, 0));
+        
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
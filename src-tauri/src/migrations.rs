@@ -0,0 +1,770 @@
+//! Versioned schema migrations
+//!
+//! Every schema change is an entry in [`MIGRATIONS`] with a strictly
+//! increasing version. Applied versions are recorded in the `schema_version`
+//! table, and each pending migration runs in its own transaction together
+//! with its version row, so a failing step rolls the whole migration back and
+//! is reported instead of leaving the schema half-updated.
+//!
+//! Databases created before versioning have no `schema_version` table and
+//! start at version 0. The first migrations are written to be safe on those
+//! databases: tables use `CREATE TABLE IF NOT EXISTS` and added columns are
+//! skipped when they already exist.
+
+use crate::database::{DatabaseError, DatabaseResult, DbPool};
+use chrono::Utc;
+use sqlx::{Row, SqliteConnection};
+use tracing::info;
+
+/// A single schema change
+pub enum Step {
+    /// Runs a SQL statement as-is
+    Sql(&'static str),
+    /// Adds a column unless the table already has it
+    AddColumn {
+        table: &'static str,
+        column: &'static str,
+        definition: &'static str,
+    },
+}
+
+/// An ordered, versioned set of schema changes
+pub struct Migration {
+    pub version: i64,
+    pub description: &'static str,
+    pub steps: &'static [Step],
+}
+
+/// Shorthand for an [`Step::AddColumn`] on `media_metadata`
+const fn media_column(column: &'static str, definition: &'static str) -> Step {
+    Step::AddColumn {
+        table: "media_metadata",
+        column,
+        definition,
+    }
+}
+
+/// All migrations, in application order
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "Initial schema",
+        steps: &[
+            Step::Sql(
+                r#"
+                CREATE TABLE IF NOT EXISTS scanned_folders (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    path TEXT NOT NULL UNIQUE,
+                    name TEXT NOT NULL,
+                    last_scanned DATETIME NOT NULL,
+                    file_count INTEGER NOT NULL DEFAULT 0,
+                    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+                )
+                "#,
+            ),
+            Step::Sql(
+                r#"
+                CREATE TABLE IF NOT EXISTS media_metadata (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    folder_id INTEGER NOT NULL,
+                    file_path TEXT NOT NULL UNIQUE,
+                    file_name TEXT NOT NULL,
+                    file_type TEXT NOT NULL,
+                    file_size INTEGER NOT NULL,
+                    width INTEGER,
+                    height INTEGER,
+                    duration REAL,
+                    created_date DATETIME,
+                    modified_date DATETIME NOT NULL,
+                    thumbnail_path TEXT,
+                    indexed_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                    FOREIGN KEY (folder_id) REFERENCES scanned_folders(id) ON DELETE CASCADE
+                )
+                "#,
+            ),
+            Step::Sql("CREATE INDEX IF NOT EXISTS idx_media_folder_id ON media_metadata(folder_id)"),
+            Step::Sql("CREATE INDEX IF NOT EXISTS idx_media_file_type ON media_metadata(file_type)"),
+            Step::Sql("CREATE INDEX IF NOT EXISTS idx_media_file_path ON media_metadata(file_path)"),
+            Step::Sql(
+                r#"
+                CREATE TABLE IF NOT EXISTS user_preferences (
+                    key TEXT PRIMARY KEY,
+                    value TEXT NOT NULL,
+                    updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+                )
+                "#,
+            ),
+        ],
+    },
+    Migration {
+        version: 2,
+        description: "Extended codec and format metadata",
+        steps: &[
+            media_column("video_codec", "TEXT"),
+            media_column("video_codec_long", "TEXT"),
+            media_column("audio_codec", "TEXT"),
+            media_column("audio_codec_long", "TEXT"),
+            media_column("bitrate", "INTEGER"),
+            media_column("frame_rate", "REAL"),
+            media_column("sample_rate", "INTEGER"),
+            media_column("audio_channels", "INTEGER"),
+            media_column("format", "TEXT"),
+            media_column("metadata_json", "TEXT"),
+        ],
+    },
+    Migration {
+        version: 3,
+        description: "Media type and audio tags",
+        steps: &[
+            media_column("media_type", "TEXT"),
+            media_column("artist", "TEXT"),
+            media_column("album", "TEXT"),
+            media_column("title", "TEXT"),
+            media_column("track_number", "INTEGER"),
+            Step::Sql("CREATE INDEX IF NOT EXISTS idx_media_media_type ON media_metadata(media_type)"),
+        ],
+    },
+    Migration {
+        version: 4,
+        description: "Full-text search index",
+        steps: &[
+            // Row IDs mirror media_metadata.id; metadata_text holds the values of metadata_json
+            Step::Sql(
+                r#"
+                CREATE VIRTUAL TABLE media_search USING fts5(
+                    file_name, file_path, metadata_text, tags,
+                    tokenize = 'unicode61 remove_diacritics 2'
+                )
+                "#,
+            ),
+            Step::Sql(
+                r#"
+                INSERT INTO media_search (rowid, file_name, file_path, metadata_text, tags)
+                SELECT id, file_name, file_path,
+                       CASE WHEN json_valid(metadata_json)
+                            THEN (SELECT group_concat(value, ' ') FROM json_each(metadata_json)) END,
+                       ''
+                FROM media_metadata
+                "#,
+            ),
+            Step::Sql(
+                r#"
+                CREATE TRIGGER media_search_insert AFTER INSERT ON media_metadata BEGIN
+                    INSERT INTO media_search (rowid, file_name, file_path, metadata_text, tags)
+                    VALUES (
+                        NEW.id, NEW.file_name, NEW.file_path,
+                        CASE WHEN json_valid(NEW.metadata_json)
+                             THEN (SELECT group_concat(value, ' ') FROM json_each(NEW.metadata_json)) END,
+                        ''
+                    );
+                END
+                "#,
+            ),
+            Step::Sql(
+                r#"
+                CREATE TRIGGER media_search_update
+                AFTER UPDATE OF file_name, file_path, metadata_json ON media_metadata BEGIN
+                    UPDATE media_search SET
+                        file_name = NEW.file_name,
+                        file_path = NEW.file_path,
+                        metadata_text = CASE WHEN json_valid(NEW.metadata_json)
+                            THEN (SELECT group_concat(value, ' ') FROM json_each(NEW.metadata_json)) END
+                    WHERE rowid = NEW.id;
+                END
+                "#,
+            ),
+            Step::Sql(
+                r#"
+                CREATE TRIGGER media_search_delete AFTER DELETE ON media_metadata BEGIN
+                    DELETE FROM media_search WHERE rowid = OLD.id;
+                END
+                "#,
+            ),
+        ],
+    },
+    Migration {
+        version: 5,
+        description: "Sort indexes",
+        steps: &[
+            Step::Sql(
+                "CREATE INDEX IF NOT EXISTS idx_media_capture_date \
+                 ON media_metadata(COALESCE(created_date, modified_date), id)",
+            ),
+            Step::Sql("CREATE INDEX IF NOT EXISTS idx_media_modified_date ON media_metadata(modified_date, id)"),
+            Step::Sql("CREATE INDEX IF NOT EXISTS idx_media_file_size ON media_metadata(file_size, id)"),
+        ],
+    },
+    Migration {
+        version: 6,
+        description: "Hierarchical tags",
+        steps: &[
+            media_column("quick_hash", "TEXT"),
+            Step::Sql(
+                r#"
+                CREATE TABLE tags (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    name TEXT NOT NULL,
+                    path TEXT NOT NULL UNIQUE COLLATE NOCASE,
+                    parent_id INTEGER REFERENCES tags(id) ON DELETE CASCADE,
+                    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+                )
+                "#,
+            ),
+            Step::Sql("CREATE INDEX idx_tags_parent_id ON tags(parent_id)"),
+            Step::Sql(
+                r#"
+                CREATE TABLE media_tags (
+                    media_id INTEGER NOT NULL REFERENCES media_metadata(id) ON DELETE CASCADE,
+                    tag_id INTEGER NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+                    added_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                    PRIMARY KEY (media_id, tag_id)
+                )
+                "#,
+            ),
+            Step::Sql("CREATE INDEX idx_media_tags_tag_id ON media_tags(tag_id)"),
+            // Tags of deleted rows, kept by content so a moved file gets them back
+            Step::Sql(
+                r#"
+                CREATE TABLE orphaned_tags (
+                    quick_hash TEXT NOT NULL,
+                    file_size INTEGER NOT NULL,
+                    tag_id INTEGER NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+                    orphaned_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                    PRIMARY KEY (quick_hash, tag_id)
+                )
+                "#,
+            ),
+            Step::Sql(
+                r#"
+                CREATE TRIGGER media_tags_orphan BEFORE DELETE ON media_metadata
+                WHEN OLD.quick_hash IS NOT NULL BEGIN
+                    INSERT OR REPLACE INTO orphaned_tags (quick_hash, file_size, tag_id)
+                    SELECT OLD.quick_hash, OLD.file_size, tag_id FROM media_tags WHERE media_id = OLD.id;
+                END
+                "#,
+            ),
+            // Keep the tags column of the search index in sync
+            Step::Sql(
+                r#"
+                CREATE TRIGGER media_tags_search_insert AFTER INSERT ON media_tags BEGIN
+                    UPDATE media_search SET tags = COALESCE((
+                        SELECT group_concat(t.path, ' ') FROM media_tags mt
+                        JOIN tags t ON t.id = mt.tag_id WHERE mt.media_id = NEW.media_id
+                    ), '')
+                    WHERE rowid = NEW.media_id;
+                END
+                "#,
+            ),
+            Step::Sql(
+                r#"
+                CREATE TRIGGER media_tags_search_delete AFTER DELETE ON media_tags BEGIN
+                    UPDATE media_search SET tags = COALESCE((
+                        SELECT group_concat(t.path, ' ') FROM media_tags mt
+                        JOIN tags t ON t.id = mt.tag_id WHERE mt.media_id = OLD.media_id
+                    ), '')
+                    WHERE rowid = OLD.media_id;
+                END
+                "#,
+            ),
+            Step::Sql(
+                r#"
+                CREATE TRIGGER tags_search_rename AFTER UPDATE OF path ON tags BEGIN
+                    UPDATE media_search SET tags = COALESCE((
+                        SELECT group_concat(t.path, ' ') FROM media_tags mt
+                        JOIN tags t ON t.id = mt.tag_id WHERE mt.media_id = media_search.rowid
+                    ), '')
+                    WHERE rowid IN (SELECT media_id FROM media_tags WHERE tag_id = NEW.id);
+                END
+                "#,
+            ),
+        ],
+    },
+    Migration {
+        version: 7,
+        description: "Albums and album folders",
+        steps: &[
+            Step::Sql(
+                r#"
+                CREATE TABLE album_folders (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    name TEXT NOT NULL,
+                    parent_id INTEGER REFERENCES album_folders(id) ON DELETE CASCADE,
+                    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+                )
+                "#,
+            ),
+            Step::Sql("CREATE INDEX idx_album_folders_parent_id ON album_folders(parent_id)"),
+            Step::Sql(
+                r#"
+                CREATE TABLE albums (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    name TEXT NOT NULL,
+                    folder_id INTEGER REFERENCES album_folders(id) ON DELETE CASCADE,
+                    cover_media_id INTEGER REFERENCES media_metadata(id) ON DELETE SET NULL,
+                    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                    updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+                )
+                "#,
+            ),
+            Step::Sql("CREATE INDEX idx_albums_folder_id ON albums(folder_id)"),
+            Step::Sql(
+                r#"
+                CREATE TABLE album_items (
+                    album_id INTEGER NOT NULL REFERENCES albums(id) ON DELETE CASCADE,
+                    media_id INTEGER NOT NULL REFERENCES media_metadata(id) ON DELETE CASCADE,
+                    position INTEGER NOT NULL,
+                    added_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                    PRIMARY KEY (album_id, media_id)
+                )
+                "#,
+            ),
+            Step::Sql("CREATE INDEX idx_album_items_position ON album_items(album_id, position)"),
+            Step::Sql("CREATE INDEX idx_album_items_media_id ON album_items(media_id)"),
+        ],
+    },
+    Migration {
+        version: 8,
+        description: "Smart albums",
+        steps: &[
+            // `definition` holds a serialized `albums::SmartAlbumDefinition`
+            Step::Sql(
+                r#"
+                CREATE TABLE smart_albums (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    name TEXT NOT NULL,
+                    folder_id INTEGER REFERENCES album_folders(id) ON DELETE CASCADE,
+                    definition TEXT NOT NULL,
+                    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                    updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+                )
+                "#,
+            ),
+            Step::Sql("CREATE INDEX idx_smart_albums_folder_id ON smart_albums(folder_id)"),
+        ],
+    },
+    Migration {
+        version: 9,
+        description: "Ratings, favorites, color labels and pick flags",
+        steps: &[
+            media_column("rating", "INTEGER NOT NULL DEFAULT 0 CHECK (rating BETWEEN 0 AND 5)"),
+            media_column("favorite", "INTEGER NOT NULL DEFAULT 0"),
+            media_column("color_label", "TEXT"),
+            media_column("pick_flag", "TEXT"),
+            Step::Sql("CREATE INDEX IF NOT EXISTS idx_media_rating ON media_metadata(rating, id)"),
+            Step::Sql("CREATE INDEX IF NOT EXISTS idx_media_pick_flag ON media_metadata(pick_flag)"),
+        ],
+    },
+    Migration {
+        version: 10,
+        description: "Trash",
+        steps: &[
+            media_column("trashed_at", "DATETIME"),
+            media_column("original_path", "TEXT"),
+            media_column("trash_info_path", "TEXT"),
+            Step::Sql("CREATE INDEX IF NOT EXISTS idx_media_trashed_at ON media_metadata(trashed_at)"),
+        ],
+    },
+    Migration {
+        version: 11,
+        description: "Content hashes",
+        steps: &[
+            media_column("content_hash", "TEXT"),
+            Step::Sql("CREATE INDEX IF NOT EXISTS idx_media_quick_hash ON media_metadata(quick_hash)"),
+            Step::Sql("CREATE INDEX IF NOT EXISTS idx_media_content_hash ON media_metadata(content_hash)"),
+        ],
+    },
+    Migration {
+        version: 12,
+        description: "Perceptual hashes",
+        steps: &[
+            media_column("dhash", "TEXT"),
+            media_column("phash", "TEXT"),
+        ],
+    },
+    Migration {
+        version: 13,
+        description: "Video fingerprints",
+        steps: &[
+            media_column("video_fingerprint", "TEXT"),
+        ],
+    },
+    Migration {
+        version: 14,
+        description: "Camera EXIF columns",
+        steps: &[
+            media_column("camera_make", "TEXT"),
+            media_column("camera_model", "TEXT"),
+            media_column("lens_model", "TEXT"),
+            media_column("focal_length", "REAL"),
+            media_column("aperture", "REAL"),
+            media_column("exposure_time", "REAL"),
+            media_column("iso", "INTEGER"),
+            media_column("flash", "INTEGER"),
+            media_column("date_taken", "TEXT"),
+            media_column("orientation", "INTEGER"),
+            Step::Sql("CREATE INDEX IF NOT EXISTS idx_media_camera ON media_metadata(camera_make, camera_model)"),
+            Step::Sql("CREATE INDEX IF NOT EXISTS idx_media_lens_model ON media_metadata(lens_model)"),
+        ],
+    },
+    Migration {
+        version: 15,
+        description: "GPS locations",
+        steps: &[
+            media_column("latitude", "REAL"),
+            media_column("longitude", "REAL"),
+            media_column("altitude", "REAL"),
+            // Row IDs mirror media_metadata.id; points are stored as zero-size boxes
+            Step::Sql("CREATE VIRTUAL TABLE media_locations USING rtree(id, min_lat, max_lat, min_lon, max_lon)"),
+            Step::Sql(
+                r#"
+                INSERT INTO media_locations (id, min_lat, max_lat, min_lon, max_lon)
+                SELECT id, latitude, latitude, longitude, longitude FROM media_metadata
+                WHERE latitude IS NOT NULL AND longitude IS NOT NULL
+                "#,
+            ),
+            Step::Sql(
+                r#"
+                CREATE TRIGGER media_locations_insert AFTER INSERT ON media_metadata
+                WHEN NEW.latitude IS NOT NULL AND NEW.longitude IS NOT NULL BEGIN
+                    INSERT INTO media_locations (id, min_lat, max_lat, min_lon, max_lon)
+                    VALUES (NEW.id, NEW.latitude, NEW.latitude, NEW.longitude, NEW.longitude);
+                END
+                "#,
+            ),
+            Step::Sql(
+                r#"
+                CREATE TRIGGER media_locations_update AFTER UPDATE OF latitude, longitude ON media_metadata BEGIN
+                    DELETE FROM media_locations WHERE id = OLD.id;
+                    INSERT INTO media_locations (id, min_lat, max_lat, min_lon, max_lon)
+                    SELECT NEW.id, NEW.latitude, NEW.latitude, NEW.longitude, NEW.longitude
+                    WHERE NEW.latitude IS NOT NULL AND NEW.longitude IS NOT NULL;
+                END
+                "#,
+            ),
+            Step::Sql(
+                r#"
+                CREATE TRIGGER media_locations_delete AFTER DELETE ON media_metadata BEGIN
+                    DELETE FROM media_locations WHERE id = OLD.id;
+                END
+                "#,
+            ),
+        ],
+    },
+    Migration {
+        version: 16,
+        description: "Place names",
+        steps: &[
+            // An empty country_code marks located rows with no place nearby
+            media_column("country_code", "TEXT"),
+            media_column("country", "TEXT"),
+            media_column("region", "TEXT"),
+            media_column("city", "TEXT"),
+            Step::Sql("CREATE INDEX IF NOT EXISTS idx_media_place ON media_metadata(country, region, city)"),
+            Step::Sql(
+                r#"
+                CREATE TRIGGER media_place_reset
+                AFTER UPDATE OF latitude, longitude ON media_metadata
+                WHEN OLD.latitude IS NOT NEW.latitude OR OLD.longitude IS NOT NEW.longitude BEGIN
+                    UPDATE media_metadata SET country_code = NULL, country = NULL, region = NULL, city = NULL
+                    WHERE id = NEW.id;
+                END
+                "#,
+            ),
+            // metadata_text now also holds the place names
+            Step::Sql("DROP TRIGGER media_search_insert"),
+            Step::Sql("DROP TRIGGER media_search_update"),
+            Step::Sql(
+                r#"
+                CREATE TRIGGER media_search_insert AFTER INSERT ON media_metadata BEGIN
+                    INSERT INTO media_search (rowid, file_name, file_path, metadata_text, tags)
+                    VALUES (
+                        NEW.id, NEW.file_name, NEW.file_path,
+                        concat_ws(' ',
+                            CASE WHEN json_valid(NEW.metadata_json)
+                                 THEN (SELECT group_concat(value, ' ') FROM json_each(NEW.metadata_json)) END,
+                            NEW.country, NEW.region, NEW.city),
+                        ''
+                    );
+                END
+                "#,
+            ),
+            Step::Sql(
+                r#"
+                CREATE TRIGGER media_search_update
+                AFTER UPDATE OF file_name, file_path, metadata_json, country, region, city ON media_metadata BEGIN
+                    UPDATE media_search SET
+                        file_name = NEW.file_name,
+                        file_path = NEW.file_path,
+                        metadata_text = concat_ws(' ',
+                            CASE WHEN json_valid(NEW.metadata_json)
+                                 THEN (SELECT group_concat(value, ' ') FROM json_each(NEW.metadata_json)) END,
+                            NEW.country, NEW.region, NEW.city)
+                    WHERE rowid = NEW.id;
+                END
+                "#,
+            ),
+        ],
+    },
+    Migration {
+        version: 17,
+        description: "Automatic place tags",
+        steps: &[
+            // The place tag attached by geocoding, removed with the place
+            media_column("place_tag_id", "INTEGER REFERENCES tags(id) ON DELETE SET NULL"),
+            Step::Sql("DROP TRIGGER media_place_reset"),
+            Step::Sql(
+                r#"
+                CREATE TRIGGER media_place_reset
+                AFTER UPDATE OF latitude, longitude ON media_metadata
+                WHEN OLD.latitude IS NOT NEW.latitude OR OLD.longitude IS NOT NEW.longitude BEGIN
+                    DELETE FROM media_tags WHERE media_id = NEW.id AND tag_id = OLD.place_tag_id;
+                    UPDATE media_metadata
+                    SET country_code = NULL, country = NULL, region = NULL, city = NULL, place_tag_id = NULL
+                    WHERE id = NEW.id;
+                END
+                "#,
+            ),
+        ],
+    },
+];
+
+/// Schema version this build of the app expects
+pub fn latest_version() -> i64 {
+    MIGRATIONS.last().map(|migration| migration.version).unwrap_or(0)
+}
+
+/// Returns the highest applied schema version (0 for unversioned databases)
+pub async fn current_version(pool: &DbPool) -> DatabaseResult<i64> {
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS schema_version (
+            version INTEGER PRIMARY KEY,
+            description TEXT NOT NULL,
+            applied_at DATETIME NOT NULL
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+    
+    let version: Option<i64> = sqlx::query("SELECT MAX(version) FROM schema_version")
+        .fetch_one(pool)
+        .await?
+        .get(0);
+    
+    Ok(version.unwrap_or(0))
+}
+
+/// Applies every pending migration in order
+///
+/// # Errors
+///
+/// Returns `DatabaseError::NewerSchema` if the database was written by a newer
+/// version of the app, or `DatabaseError::Migration` naming the migration that
+/// failed. A failed migration is rolled back entirely.
+pub async fn run_migrations(pool: &DbPool) -> DatabaseResult<()> {
+    let current = current_version(pool).await?;
+    let latest = latest_version();
+    
+    if current > latest {
+        return Err(DatabaseError::NewerSchema {
+            found: current,
+            supported: latest,
+        });
+    }
+    
+    for migration in MIGRATIONS.iter().filter(|migration| migration.version > current) {
+        info!("Applying migration {}: {}", migration.version, migration.description);
+        apply_migration(pool, migration).await.map_err(|e| {
+            DatabaseError::Migration(format!(
+                "Migration {} ({}) failed: {}",
+                migration.version, migration.description, e
+            ))
+        })?;
+    }
+    
+    Ok(())
+}
+
+/// Runs one migration and records its version in a single transaction
+async fn apply_migration(pool: &DbPool, migration: &Migration) -> DatabaseResult<()> {
+    let mut tx = pool.begin().await?;
+    
+    for step in migration.steps {
+        match step {
+            Step::Sql(sql) => {
+                sqlx::query(sql).execute(&mut *tx).await?;
+            }
+            Step::AddColumn { table, column, definition } => {
+                if !column_exists(&mut tx, table, column).await? {
+                    let sql = format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition);
+                    sqlx::query(&sql).execute(&mut *tx).await?;
+                }
+            }
+        }
+    }
+    
+    sqlx::query("INSERT INTO schema_version (version, description, applied_at) VALUES (?, ?, ?)")
+        .bind(migration.version)
+        .bind(migration.description)
+        .bind(Utc::now())
+        .execute(&mut *tx)
+        .await?;
+    
+    tx.commit().await?;
+    
+    Ok(())
+}
+
+/// Checks whether a table has a column
+async fn column_exists(conn: &mut SqliteConnection, table: &str, column: &str) -> DatabaseResult<bool> {
+    let count: i64 = sqlx::query("SELECT COUNT(*) FROM pragma_table_info(?) WHERE name = ?")
+        .bind(table)
+        .bind(column)
+        .fetch_one(conn)
+        .await?
+        .get(0);
+    
+    Ok(count > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{open_empty_test_pool, open_test_pool};
+    
+    /// Schema written by the app before versioned migrations existed, with
+    /// `frame_rate` missing to mimic an `ALTER TABLE` that failed silently
+    const LEGACY_FIXTURE: &str = r#"
+        CREATE TABLE scanned_folders (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            path TEXT NOT NULL UNIQUE,
+            name TEXT NOT NULL,
+            last_scanned DATETIME NOT NULL,
+            file_count INTEGER NOT NULL DEFAULT 0,
+            created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+        );
+        CREATE TABLE media_metadata (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            folder_id INTEGER NOT NULL,
+            file_path TEXT NOT NULL UNIQUE,
+            file_name TEXT NOT NULL,
+            file_type TEXT NOT NULL,
+            file_size INTEGER NOT NULL,
+            width INTEGER,
+            height INTEGER,
+            duration REAL,
+            created_date DATETIME,
+            modified_date DATETIME NOT NULL,
+            thumbnail_path TEXT,
+            indexed_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            video_codec TEXT,
+            video_codec_long TEXT,
+            audio_codec TEXT,
+            audio_codec_long TEXT,
+            bitrate INTEGER,
+            FOREIGN KEY (folder_id) REFERENCES scanned_folders(id) ON DELETE CASCADE
+        );
+        CREATE TABLE user_preferences (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL,
+            updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+        );
+        INSERT INTO scanned_folders (id, path, name, last_scanned, file_count)
+        VALUES (1, '/photos', 'photos', '2024-01-01T00:00:00Z', 1);
+        INSERT INTO media_metadata (folder_id, file_path, file_name, file_type, file_size, modified_date, video_codec)
+        VALUES (1, '/photos/clip.mp4', 'clip.mp4', 'mp4', 1024, '2024-01-01T00:00:00Z', 'h264');
+    "#;
+    
+    async fn columns(pool: &DbPool, table: &str) -> Vec<String> {
+        sqlx::query("SELECT name FROM pragma_table_info(?)")
+            .bind(table)
+            .fetch_all(pool)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|row| row.get(0))
+            .collect()
+    }
+    
+    #[test]
+    fn test_migration_versions_are_ordered() {
+        for pair in MIGRATIONS.windows(2) {
+            assert!(pair[0].version < pair[1].version);
+        }
+        assert_eq!(MIGRATIONS[0].version, 1);
+    }
+    
+    #[tokio::test]
+    async fn test_fresh_database_reaches_latest_version() {
+        let pool = open_test_pool().await;
+        
+        assert_eq!(current_version(&pool).await.unwrap(), latest_version());
+        let media_columns = columns(&pool, "media_metadata").await;
+        assert!(media_columns.contains(&"metadata_json".to_string()));
+        assert!(media_columns.contains(&"track_number".to_string()));
+        
+        // A second run has nothing left to apply
+        run_migrations(&pool).await.unwrap();
+        assert_eq!(current_version(&pool).await.unwrap(), latest_version());
+    }
+    
+    #[tokio::test]
+    async fn test_legacy_database_is_migrated_forward() {
+        let pool = open_empty_test_pool().await;
+        sqlx::raw_sql(LEGACY_FIXTURE).execute(&pool).await.unwrap();
+        
+        run_migrations(&pool).await.unwrap();
+        
+        assert_eq!(current_version(&pool).await.unwrap(), latest_version());
+        let media_columns = columns(&pool, "media_metadata").await;
+        for column in ["frame_rate", "format", "metadata_json", "media_type", "track_number"] {
+            assert!(media_columns.contains(&column.to_string()), "missing {}", column);
+        }
+        
+        let row = sqlx::query("SELECT file_path, video_codec, frame_rate FROM media_metadata")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(row.get::<String, _>(0), "/photos/clip.mp4");
+        assert_eq!(row.get::<String, _>(1), "h264");
+        assert_eq!(row.get::<Option<f64>, _>(2), None);
+    }
+    
+    #[tokio::test]
+    async fn test_newer_database_is_refused() {
+        let pool = open_test_pool().await;
+        sqlx::query("INSERT INTO schema_version (version, description, applied_at) VALUES (?, 'future', ?)")
+            .bind(latest_version() + 1)
+            .bind(Utc::now())
+            .execute(&pool)
+            .await
+            .unwrap();
+        
+        let result = run_migrations(&pool).await;
+        
+        assert!(matches!(
+            result,
+            Err(DatabaseError::NewerSchema { found, supported }) if found == latest_version() + 1 && supported == latest_version()
+        ));
+    }
+    
+    #[tokio::test]
+    async fn test_failed_migration_is_rolled_back() {
+        let pool = open_empty_test_pool().await;
+        let broken = Migration {
+            version: 1,
+            description: "Broken",
+            steps: &[
+                Step::Sql("CREATE TABLE partial (id INTEGER)"),
+                Step::Sql("THIS IS NOT SQL"),
+            ],
+        };
+        current_version(&pool).await.unwrap();
+        
+        assert!(apply_migration(&pool, &broken).await.is_err());
+        
+        assert!(columns(&pool, "partial").await.is_empty());
+        assert_eq!(current_version(&pool).await.unwrap(), 0);
+    }
+}
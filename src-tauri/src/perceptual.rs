@@ -0,0 +1,325 @@
+//! Perceptual image hashes
+//!
+//! Unlike content hashes, perceptual hashes of visually similar images are
+//! close to each other, so resized copies, re-encoded JPEGs and compressed
+//! versions (e.g. sent through a messenger) of the same photo can be found by
+//! comparing hashes bit by bit. Two 64-bit hashes are computed:
+//!
+//! * dHash compares the brightness of neighbouring pixels of a 9x8 grayscale
+//!   thumbnail. It is cheap and robust to scaling and compression.
+//! * pHash keeps the signs of the lowest frequencies of a 32x32 DCT relative
+//!   to their median, which also tolerates small brightness and color changes.
+//!
+//! The distance between two hashes is the number of differing bits (Hamming
+//! distance); [`BkTree`] finds all hashes within a distance without comparing
+//! every pair.
+
+use image::{imageops::FilterType, DynamicImage};
+use serde::{Deserialize, Serialize};
+
+/// Side of the grayscale image the pHash DCT runs on
+const PHASH_SIZE: usize = 32;
+
+/// Side of the low-frequency block kept from the DCT
+const PHASH_BLOCK: usize = 8;
+
+/// Perceptual hashes of one image
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImageHashes {
+    pub dhash: u64,
+    pub phash: u64,
+}
+
+impl ImageHashes {
+    /// Computes both hashes of a decoded image
+    pub fn compute(img: &DynamicImage) -> Self {
+        ImageHashes {
+            dhash: dhash(img),
+            phash: phash(img),
+        }
+    }
+}
+
+/// Hash used to compare images
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PerceptualAlgorithm {
+    DHash,
+    #[default]
+    PHash,
+}
+
+/// Formats a hash the way it is stored in the database
+pub fn to_hex(hash: u64) -> String {
+    format!("{:016x}", hash)
+}
+
+/// Parses a hash stored with [`to_hex`]
+pub fn from_hex(hex: &str) -> Option<u64> {
+    u64::from_str_radix(hex, 16).ok()
+}
+
+/// Number of differing bits between two hashes
+pub fn hamming_distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+/// Computes the difference hash of an image
+pub fn dhash(img: &DynamicImage) -> u64 {
+    let small = img.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            let left = small.get_pixel(x, y)[0];
+            let right = small.get_pixel(x + 1, y)[0];
+            hash = (hash << 1) | u64::from(left < right);
+        }
+    }
+    hash
+}
+
+/// Computes the DCT-based hash of an image
+pub fn phash(img: &DynamicImage) -> u64 {
+    let small = img
+        .resize_exact(PHASH_SIZE as u32, PHASH_SIZE as u32, FilterType::Triangle)
+        .to_luma8();
+    let pixels: Vec<f64> = small.pixels().map(|p| f64::from(p[0])).collect();
+    
+    // Separable 2D DCT-II, only for the low-frequency block that is kept
+    let n = PHASH_SIZE as f64;
+    let cosines: Vec<f64> = (0..PHASH_BLOCK)
+        .flat_map(|u| {
+            (0..PHASH_SIZE).map(move |x| {
+                (std::f64::consts::PI * (2.0 * x as f64 + 1.0) * u as f64 / (2.0 * n)).cos()
+            })
+        })
+        .collect();
+    let cosine = |u: usize, x: usize| cosines[u * PHASH_SIZE + x];
+    
+    let mut rows = vec![0.0; PHASH_SIZE * PHASH_BLOCK];
+    for y in 0..PHASH_SIZE {
+        for u in 0..PHASH_BLOCK {
+            rows[y * PHASH_BLOCK + u] = (0..PHASH_SIZE)
+                .map(|x| pixels[y * PHASH_SIZE + x] * cosine(u, x))
+                .sum();
+        }
+    }
+    let mut block = [0.0; PHASH_BLOCK * PHASH_BLOCK];
+    for v in 0..PHASH_BLOCK {
+        for u in 0..PHASH_BLOCK {
+            block[v * PHASH_BLOCK + u] = (0..PHASH_SIZE)
+                .map(|y| rows[y * PHASH_BLOCK + u] * cosine(v, y))
+                .sum();
+        }
+    }
+    
+    // The DC coefficient only reflects overall brightness
+    let mut sorted: Vec<f64> = block[1..].to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let median = sorted[sorted.len() / 2];
+    
+    block
+        .iter()
+        .fold(0u64, |hash, &coefficient| (hash << 1) | u64::from(coefficient > median))
+}
+
+/// Metric tree over 64-bit hashes with the Hamming distance
+///
+/// Each child edge is labelled with its distance to the parent, so a search
+/// within `max_distance` of a query only descends into children whose label
+/// is within `max_distance` of the query's distance to the parent.
+#[derive(Debug, Default)]
+pub struct BkTree<T> {
+    nodes: Vec<BkNode<T>>,
+}
+
+#[derive(Debug)]
+struct BkNode<T> {
+    hash: u64,
+    value: T,
+    children: Vec<(u32, usize)>,
+}
+
+impl<T> BkTree<T> {
+    pub fn new() -> Self {
+        BkTree { nodes: Vec::new() }
+    }
+    
+    /// Adds a hash with an associated value
+    pub fn insert(&mut self, hash: u64, value: T) {
+        let new_index = self.nodes.len();
+        self.nodes.push(BkNode {
+            hash,
+            value,
+            children: Vec::new(),
+        });
+        if new_index == 0 {
+            return;
+        }
+        
+        let mut current = 0;
+        loop {
+            let distance = hamming_distance(self.nodes[current].hash, hash);
+            let next = self.nodes[current]
+                .children
+                .iter()
+                .find(|(label, _)| *label == distance)
+                .map(|(_, child)| *child);
+            match next {
+                Some(child) => current = child,
+                None => {
+                    self.nodes[current].children.push((distance, new_index));
+                    return;
+                }
+            }
+        }
+    }
+    
+    /// Returns the values of all hashes within `max_distance` of `hash`
+    pub fn find(&self, hash: u64, max_distance: u32) -> Vec<(&T, u32)> {
+        let mut found = Vec::new();
+        if self.nodes.is_empty() {
+            return found;
+        }
+        
+        let mut stack = vec![0];
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            let distance = hamming_distance(node.hash, hash);
+            if distance <= max_distance {
+                found.push((&node.value, distance));
+            }
+            for &(label, child) in &node.children {
+                if label.abs_diff(distance) <= max_distance {
+                    stack.push(child);
+                }
+            }
+        }
+        found
+    }
+}
+
+/// Groups hashes whose chains of neighbours are within `max_distance`
+///
+/// Returns clusters of at least two indices into `hashes`.
+pub fn cluster(hashes: &[u64], max_distance: u32) -> Vec<Vec<usize>> {
+    let mut tree = BkTree::new();
+    for (index, &hash) in hashes.iter().enumerate() {
+        tree.insert(hash, index);
+    }
+    
+    let pairs = hashes.iter().enumerate().flat_map(|(index, &hash)| {
+        tree.find(hash, max_distance)
+            .into_iter()
+            .map(move |(&other, _)| (index, other))
+    });
+    group_pairs(hashes.len(), pairs)
+}
+
+/// Merges linked pairs of items into groups (connected components)
+///
+/// Returns groups of at least two of the `len` item indices, each sorted, in
+/// order of their first item.
+pub fn group_pairs(len: usize, pairs: impl IntoIterator<Item = (usize, usize)>) -> Vec<Vec<usize>> {
+    fn root(parent: &mut [usize], mut index: usize) -> usize {
+        while parent[index] != index {
+            parent[index] = parent[parent[index]];
+            index = parent[index];
+        }
+        index
+    }
+    
+    let mut parent: Vec<usize> = (0..len).collect();
+    for (a, b) in pairs {
+        let (a, b) = (root(&mut parent, a), root(&mut parent, b));
+        if a != b {
+            parent[a.max(b)] = a.min(b);
+        }
+    }
+    
+    let mut groups: std::collections::BTreeMap<usize, Vec<usize>> = Default::default();
+    for index in 0..len {
+        let root = root(&mut parent, index);
+        groups.entry(root).or_default().push(index);
+    }
+    groups.into_values().filter(|members| members.len() > 1).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageFormat, Rgb, RgbImage};
+    use std::io::Cursor;
+    
+    /// A synthetic "photo" with gradients and a few shapes
+    fn scene(width: u32, height: u32, shift: u32) -> DynamicImage {
+        let img = RgbImage::from_fn(width, height, |x, y| {
+            let (fx, fy) = (x * 256 / width, y * 256 / height);
+            let in_circle = (fx as i32 - 90).pow(2) + (fy as i32 - 120).pow(2) < 50 * 50;
+            let in_bar = (160..220).contains(&fx) && fy > 40;
+            if in_circle {
+                Rgb([230, 40, 40])
+            } else if in_bar {
+                Rgb([20, 20, 120])
+            } else {
+                Rgb([(fx + shift) as u8, fy as u8, 128])
+            }
+        });
+        DynamicImage::ImageRgb8(img)
+    }
+    
+    fn reencode(img: &DynamicImage, quality: u8) -> DynamicImage {
+        let mut jpeg = Vec::new();
+        let encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut jpeg, quality);
+        img.write_with_encoder(encoder).unwrap();
+        image::load(Cursor::new(jpeg), ImageFormat::Jpeg).unwrap()
+    }
+    
+    #[test]
+    fn test_hashes_survive_resizing_and_recompression() {
+        let original = scene(1024, 768, 0);
+        let variants = [
+            original.resize(320, 240, FilterType::Lanczos3),
+            reencode(&original, 40),
+            reencode(&original.resize(640, 480, FilterType::Triangle), 25),
+        ];
+        let different = DynamicImage::ImageRgb8(RgbImage::from_fn(1024, 768, |x, y| {
+            Rgb([((x * y) % 251) as u8, (y % 97) as u8, (x % 53) as u8])
+        }));
+        
+        let reference = ImageHashes::compute(&original);
+        for variant in &variants {
+            let hashes = ImageHashes::compute(variant);
+            assert!(hamming_distance(reference.dhash, hashes.dhash) <= 6);
+            assert!(hamming_distance(reference.phash, hashes.phash) <= 6);
+        }
+        let other = ImageHashes::compute(&different);
+        assert!(hamming_distance(reference.phash, other.phash) > 16);
+        
+        assert_eq!(from_hex(&to_hex(reference.phash)), Some(reference.phash));
+    }
+    
+    #[test]
+    fn test_cluster_links_neighbours() {
+        let hashes = [
+            0b0000_0000,
+            0b0000_0011,
+            0b0000_1111,
+            u64::MAX,
+            u64::MAX ^ 1,
+            0xF0F0_F0F0_0000_0000,
+        ];
+        
+        // 0-1 and 1-2 are two bits apart, so all three form one cluster
+        assert_eq!(cluster(&hashes, 2), [vec![0, 1, 2], vec![3, 4]]);
+        assert_eq!(cluster(&hashes, 1), [vec![3, 4]]);
+        
+        let mut tree = BkTree::new();
+        for (index, &hash) in hashes.iter().enumerate() {
+            tree.insert(hash, index);
+        }
+        let mut near: Vec<(usize, u32)> = tree.find(0, 4).into_iter().map(|(&i, d)| (i, d)).collect();
+        near.sort();
+        assert_eq!(near, [(0, 0), (1, 2), (2, 4)]);
+    }
+}
@@ -0,0 +1,276 @@
+//! Ratings, favorites, color labels and pick/reject flags
+//!
+//! User judgments live in `media_metadata` next to the indexed metadata, so
+//! they can be filtered and sorted on like any other column (see
+//! [`SearchFilters`](crate::search::SearchFilters) and
+//! [`SortKey::Rating`](crate::search::SortKey)). Rescans never overwrite them.
+//!
+//! Every setter works on a selection of rows at once so a culling pass can
+//! rate, label or pick/reject many items per keystroke.
+
+use crate::database::{DatabaseResult, DbPool};
+use serde::{Deserialize, Serialize};
+use sqlx::{QueryBuilder, Sqlite};
+use tauri::State;
+
+/// Highest star rating
+pub const MAX_RATING: u8 = 5;
+
+/// Media IDs bound per update, well below SQLite's variable limit
+const UPDATE_CHUNK_SIZE: usize = 500;
+
+/// Color label of a media item
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorLabel {
+    Red,
+    Orange,
+    Yellow,
+    Green,
+    Blue,
+    Purple,
+}
+
+impl ColorLabel {
+    /// Value stored in `media_metadata.color_label`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ColorLabel::Red => "red",
+            ColorLabel::Orange => "orange",
+            ColorLabel::Yellow => "yellow",
+            ColorLabel::Green => "green",
+            ColorLabel::Blue => "blue",
+            ColorLabel::Purple => "purple",
+        }
+    }
+}
+
+/// Culling decision for a media item
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PickFlag {
+    Pick,
+    Reject,
+}
+
+impl PickFlag {
+    /// Value stored in `media_metadata.pick_flag`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PickFlag::Pick => "pick",
+            PickFlag::Reject => "reject",
+        }
+    }
+}
+
+/// Sets one judgment column on a selection of rows
+///
+/// The IDs are bound [`UPDATE_CHUNK_SIZE`] at a time in one transaction, so
+/// selecting a whole shoot stays below SQLite's variable limit.
+///
+/// # Returns
+///
+/// Returns the number of rows updated
+async fn set_column<'a, T>(pool: &DbPool, column: &str, value: T, media_ids: &[i64]) -> DatabaseResult<u64>
+where
+    T: 'a + Clone + Send + sqlx::Encode<'a, Sqlite> + sqlx::Type<Sqlite>,
+{
+    if media_ids.is_empty() {
+        return Ok(0);
+    }
+    
+    let mut tx = pool.begin().await?;
+    let mut updated = 0;
+    for chunk in media_ids.chunks(UPDATE_CHUNK_SIZE) {
+        let mut builder = QueryBuilder::<Sqlite>::new(format!("UPDATE media_metadata SET {} = ", column));
+        builder.push_bind(value.clone()).push(" WHERE id IN (");
+        let mut separated = builder.separated(", ");
+        for id in chunk {
+            separated.push_bind(*id);
+        }
+        builder.push(")");
+        
+        updated += builder.build().execute(&mut *tx).await?.rows_affected();
+    }
+    tx.commit().await?;
+    
+    Ok(updated)
+}
+
+/// Sets the star rating (0 clears it) of several media items
+///
+/// # Errors
+///
+/// Returns an error if the rating is above [`MAX_RATING`].
+pub async fn set_rating(pool: &DbPool, media_ids: &[i64], rating: u8) -> Result<u64, String> {
+    if rating > MAX_RATING {
+        return Err(format!("Rating must be between 0 and {}", MAX_RATING));
+    }
+    set_column(pool, "rating", rating as i64, media_ids)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Marks or unmarks several media items as favorites
+pub async fn set_favorite(pool: &DbPool, media_ids: &[i64], favorite: bool) -> DatabaseResult<u64> {
+    set_column(pool, "favorite", favorite, media_ids).await
+}
+
+/// Sets or clears the color label of several media items
+pub async fn set_color_label(pool: &DbPool, media_ids: &[i64], label: Option<ColorLabel>) -> DatabaseResult<u64> {
+    set_column(pool, "color_label", label.map(ColorLabel::as_str), media_ids).await
+}
+
+/// Picks, rejects or unflags several media items
+pub async fn set_pick_flag(pool: &DbPool, media_ids: &[i64], flag: Option<PickFlag>) -> DatabaseResult<u64> {
+    set_column(pool, "pick_flag", flag.map(PickFlag::as_str), media_ids).await
+}
+
+/// Tauri command to set the star rating of several media items
+///
+/// # Arguments
+///
+/// * `pool` - Database connection pool (injected by Tauri)
+/// * `media_ids` - IDs of the media rows to rate
+/// * `rating` - Stars from 0 (unrated) to 5
+///
+/// # Returns
+///
+/// Returns the number of rows updated
+#[tauri::command]
+pub async fn set_media_rating(pool: State<'_, DbPool>, media_ids: Vec<i64>, rating: u8) -> Result<u64, String> {
+    set_rating(&pool, &media_ids, rating).await
+}
+
+/// Tauri command to mark or unmark several media items as favorites
+#[tauri::command]
+pub async fn set_media_favorite(
+    pool: State<'_, DbPool>,
+    media_ids: Vec<i64>,
+    favorite: bool,
+) -> Result<u64, String> {
+    set_favorite(&pool, &media_ids, favorite).await.map_err(|e| e.to_string())
+}
+
+/// Tauri command to set (or clear with `null`) the color label of several media items
+#[tauri::command]
+pub async fn set_media_color_label(
+    pool: State<'_, DbPool>,
+    media_ids: Vec<i64>,
+    label: Option<ColorLabel>,
+) -> Result<u64, String> {
+    set_color_label(&pool, &media_ids, label).await.map_err(|e| e.to_string())
+}
+
+/// Tauri command to pick, reject or (with `null`) unflag several media items
+#[tauri::command]
+pub async fn set_media_pick_flag(
+    pool: State<'_, DbPool>,
+    media_ids: Vec<i64>,
+    flag: Option<PickFlag>,
+) -> Result<u64, String> {
+    set_pick_flag(&pool, &media_ids, flag).await.map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{self, InsertMediaParams};
+    use crate::rescan::media_file_to_params;
+    use crate::scanner::{MediaFile, MediaType};
+    use crate::search::{self, MediaQuery, SearchFilters, SortDirection, SortKey};
+    
+    async fn seeded_pool(count: usize) -> DbPool {
+        let pool = database::open_test_pool().await;
+        let folder_id = database::upsert_scanned_folder(&pool, "/shoot", "shoot", count as i64).await.unwrap();
+        for i in 1..=count {
+            let params = InsertMediaParams {
+                folder_id,
+                file_path: format!("/shoot/{}.jpg", i),
+                file_name: format!("{}.jpg", i),
+                file_type: "jpg".to_string(),
+                file_size: 100,
+                media_type: Some("image".to_string()),
+                ..Default::default()
+            };
+            database::insert_media_metadata(&pool, params).await.unwrap();
+        }
+        pool
+    }
+    
+    async fn ids(pool: &DbPool, filters: SearchFilters, sort: SortKey) -> Vec<i64> {
+        let query = MediaQuery {
+            filters,
+            sort,
+            direction: SortDirection::Desc,
+            ..Default::default()
+        };
+        search::query_media(pool, &query)
+            .await
+            .unwrap()
+            .items
+            .into_iter()
+            .map(|m| m.id)
+            .collect()
+    }
+    
+    #[tokio::test]
+    async fn test_culling_workflow() {
+        let pool = seeded_pool(5).await;
+        
+        assert_eq!(set_rating(&pool, &[1, 2], 3).await.unwrap(), 2);
+        set_rating(&pool, &[4], 5).await.unwrap();
+        assert!(set_rating(&pool, &[1], 6).await.is_err());
+        // Selections larger than SQLite's variable limit are updated in chunks
+        let everything: Vec<i64> = (1..=40_000).collect();
+        assert_eq!(set_favorite(&pool, &everything, false).await.unwrap(), 5);
+        set_favorite(&pool, &[4], true).await.unwrap();
+        set_color_label(&pool, &[2, 3], Some(ColorLabel::Red)).await.unwrap();
+        set_color_label(&pool, &[3], None).await.unwrap();
+        set_pick_flag(&pool, &[1, 4], Some(PickFlag::Pick)).await.unwrap();
+        set_pick_flag(&pool, &[5], Some(PickFlag::Reject)).await.unwrap();
+        
+        let row = database::get_media_metadata_by_path(&pool, "/shoot/4.jpg").await.unwrap().unwrap();
+        assert_eq!((row.rating, row.favorite, row.pick_flag.as_deref()), (5, true, Some("pick")));
+        
+        let picks = SearchFilters {
+            pick_flags: vec!["pick".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(ids(&pool, picks, SortKey::Rating).await, [4, 1]);
+        
+        let not_rejected = SearchFilters {
+            pick_flags: vec!["pick".to_string(), "none".to_string()],
+            min_rating: Some(3),
+            ..Default::default()
+        };
+        assert_eq!(ids(&pool, not_rejected, SortKey::Rating).await, [4, 2, 1]);
+        
+        let red = SearchFilters {
+            color_labels: vec!["RED".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(ids(&pool, red, SortKey::Rating).await, [2]);
+        let favorites = SearchFilters {
+            favorite: Some(true),
+            ..Default::default()
+        };
+        assert_eq!(ids(&pool, favorites, SortKey::Rating).await, [4]);
+        
+        // Rescanning a changed file (as the rescan converts it) keeps its judgments
+        let file = MediaFile {
+            path: "/shoot/4.jpg".to_string(),
+            name: "4.jpg".to_string(),
+            size: 200,
+            modified: 10,
+            file_type: "jpg".to_string(),
+            media_type: MediaType::Image,
+            extension_mismatch: false,
+        };
+        database::upsert_media_batch(&pool, &[media_file_to_params(row.folder_id, &file)])
+            .await
+            .unwrap();
+        let row = database::get_media_metadata_by_path(&pool, "/shoot/4.jpg").await.unwrap().unwrap();
+        assert_eq!((row.file_size, row.rating, row.favorite), (200, 5, true));
+    }
+}
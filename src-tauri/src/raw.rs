@@ -0,0 +1,690 @@
+//! Camera RAW file support
+//!
+//! RAW files cannot be decoded by the `image` crate, but every supported
+//! format embeds a camera-rendered JPEG preview and describes the camera and
+//! sensor in TIFF-style tags. This module reads just those parts:
+//!
+//! - TIFF-based formats (CR2, NEF, ARW, DNG, ORF, RW2) are parsed by walking
+//!   their IFD chain and sub-IFDs
+//! - RAF files have a fixed header pointing at the preview and a metadata
+//!   directory holding the sensor size
+//! - CR3 files are ISO base media files; the camera tags live in a `CMT1` box
+//!
+//! Previews that are not referenced by any parsed tag (CR3, most ORF files)
+//! are found by scanning the start of the file for the largest complete JPEG.
+
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use thiserror::Error;
+use tracing::debug;
+
+/// Upper bound on the number of IFDs visited in one file
+const MAX_IFDS: usize = 32;
+
+/// Upper bound on the size of an embedded preview
+const MAX_PREVIEW_LEN: u64 = 64 * 1024 * 1024;
+
+/// Number of leading bytes searched when no tag points at a preview
+const PREVIEW_SCAN_LEN: u64 = 16 * 1024 * 1024;
+
+/// Canon's `uuid` box holding the CR3 metadata boxes
+const CANON_METADATA_UUID: [u8; 16] = [
+    0x85, 0xc0, 0xb6, 0x87, 0x82, 0x0f, 0x11, 0xe0, 0x81, 0x11, 0xf4, 0xce, 0x46, 0x2b, 0x6a, 0x48,
+];
+
+// TIFF tags used by this module
+const TAG_PANASONIC_SENSOR_WIDTH: u16 = 0x0002;
+const TAG_PANASONIC_SENSOR_HEIGHT: u16 = 0x0003;
+const TAG_PANASONIC_JPG_FROM_RAW: u16 = 0x002E;
+const TAG_NEW_SUBFILE_TYPE: u16 = 0x00FE;
+const TAG_IMAGE_WIDTH: u16 = 0x0100;
+const TAG_IMAGE_LENGTH: u16 = 0x0101;
+const TAG_COMPRESSION: u16 = 0x0103;
+const TAG_MAKE: u16 = 0x010F;
+const TAG_MODEL: u16 = 0x0110;
+const TAG_STRIP_OFFSETS: u16 = 0x0111;
+const TAG_ORIENTATION: u16 = 0x0112;
+const TAG_STRIP_BYTE_COUNTS: u16 = 0x0117;
+const TAG_SUB_IFDS: u16 = 0x014A;
+const TAG_JPEG_OFFSET: u16 = 0x0201;
+const TAG_JPEG_LENGTH: u16 = 0x0202;
+
+/// Error types for RAW file operations
+#[derive(Debug, Error)]
+pub enum RawError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    
+    #[error("Unsupported RAW file: {0}")]
+    Unsupported(String),
+    
+    #[error("No embedded preview found")]
+    NoPreview,
+}
+
+pub type RawResult<T> = Result<T, RawError>;
+
+/// Camera and sensor information read from a RAW file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RawInfo {
+    /// Camera manufacturer (e.g. "Canon")
+    pub make: Option<String>,
+    /// Camera model (e.g. "Canon EOS R5")
+    pub model: Option<String>,
+    /// Sensor width in pixels
+    pub width: Option<u32>,
+    /// Sensor height in pixels
+    pub height: Option<u32>,
+    /// EXIF orientation of the image (1 = upright)
+    pub orientation: u16,
+}
+
+/// Location of an embedded JPEG within the file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct PreviewLocation {
+    offset: u64,
+    len: u64,
+}
+
+/// Everything collected while parsing a file's structure
+#[derive(Debug, Default)]
+struct RawLayout {
+    info: RawInfo,
+    previews: Vec<PreviewLocation>,
+}
+
+/// Reads the camera and sensor information of a RAW file.
+///
+/// # Arguments
+///
+/// * `path` - Path to the RAW file
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or is not a supported RAW
+/// container.
+pub fn read_raw_info(path: &Path) -> RawResult<RawInfo> {
+    let mut reader = BufReader::new(File::open(path)?);
+    Ok(parse_layout(&mut reader)?.info)
+}
+
+/// Extracts the largest embedded JPEG preview of a RAW file.
+///
+/// # Arguments
+///
+/// * `path` - Path to the RAW file
+///
+/// # Returns
+///
+/// Returns the JPEG data and the orientation the image should be displayed in.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or contains no preview.
+pub fn extract_preview(path: &Path) -> RawResult<(Vec<u8>, u16)> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let file_len = reader.seek(SeekFrom::End(0))?;
+    
+    let layout = parse_layout(&mut reader).unwrap_or_else(|e| {
+        debug!("Cannot parse RAW structure, scanning for a preview: {}", e);
+        RawLayout::default()
+    });
+    
+    let best = layout
+        .previews
+        .iter()
+        .filter(|preview| preview.len <= MAX_PREVIEW_LEN && preview.offset + preview.len <= file_len)
+        .max_by_key(|preview| preview.len);
+    
+    if let Some(preview) = best {
+        let data = read_at(&mut reader, preview.offset, preview.len as usize)?;
+        if data.starts_with(&[0xFF, 0xD8]) {
+            return Ok((data, layout.info.orientation));
+        }
+    }
+    
+    // Fall back to searching the start of the file
+    let scan_len = file_len.min(PREVIEW_SCAN_LEN) as usize;
+    let head = read_at(&mut reader, 0, scan_len)?;
+    let (start, len) = find_largest_jpeg(&head).ok_or(RawError::NoPreview)?;
+    Ok((head[start..start + len].to_vec(), layout.info.orientation))
+}
+
+/// Parses the container of a RAW file, dispatching on its signature.
+fn parse_layout<R: Read + Seek>(reader: &mut R) -> RawResult<RawLayout> {
+    let header = read_at(reader, 0, 16)?;
+    
+    if header.starts_with(b"FUJIFILMCCD-RAW") {
+        return parse_raf(reader);
+    }
+    if header.len() >= 12 && &header[4..8] == b"ftyp" {
+        return parse_cr3(reader);
+    }
+    
+    let mut layout = RawLayout::default();
+    parse_tiff(reader, 0, &mut layout)?;
+    Ok(layout)
+}
+
+/// Byte order of a TIFF structure
+#[derive(Debug, Clone, Copy)]
+struct Tiff {
+    base: u64,
+    little_endian: bool,
+    /// Panasonic RW2 files use their own magic number and tags
+    panasonic: bool,
+}
+
+impl Tiff {
+    fn u16(&self, bytes: &[u8]) -> u16 {
+        let bytes = [bytes[0], bytes[1]];
+        if self.little_endian {
+            u16::from_le_bytes(bytes)
+        } else {
+            u16::from_be_bytes(bytes)
+        }
+    }
+    
+    fn u32(&self, bytes: &[u8]) -> u32 {
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        if self.little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        }
+    }
+}
+
+/// A single IFD entry
+#[derive(Debug, Clone, Copy)]
+struct IfdEntry {
+    tag: u16,
+    field_type: u16,
+    count: u32,
+    value: [u8; 4],
+}
+
+impl IfdEntry {
+    /// Size in bytes of one value of the entry's type
+    fn type_size(&self) -> u32 {
+        match self.field_type {
+            1 | 2 | 6 | 7 => 1,
+            3 | 8 => 2,
+            4 | 9 | 11 | 13 => 4,
+            5 | 10 | 12 => 8,
+            _ => 1,
+        }
+    }
+    
+    /// First numeric value of a SHORT or LONG entry
+    fn number(&self, tiff: &Tiff) -> Option<u32> {
+        match self.field_type {
+            3 => Some(tiff.u16(&self.value) as u32),
+            4 | 13 => Some(tiff.u32(&self.value)),
+            _ => None,
+        }
+    }
+    
+    /// Absolute offset of the entry's data when it doesn't fit inline
+    fn data_offset(&self, tiff: &Tiff) -> u64 {
+        tiff.base + tiff.u32(&self.value) as u64
+    }
+}
+
+/// Walks a TIFF structure starting at `base`, collecting info and previews.
+fn parse_tiff<R: Read + Seek>(reader: &mut R, base: u64, layout: &mut RawLayout) -> RawResult<()> {
+    let header = read_at(reader, base, 8)?;
+    if header.len() < 8 {
+        return Err(RawError::Unsupported("file too short".to_string()));
+    }
+    
+    let little_endian = match &header[0..2] {
+        b"II" => true,
+        b"MM" => false,
+        _ => return Err(RawError::Unsupported("not a TIFF container".to_string())),
+    };
+    let mut tiff = Tiff {
+        base,
+        little_endian,
+        panasonic: false,
+    };
+    // 42 for TIFF, 0x4F52/0x5352 for Olympus, 0x55 for Panasonic
+    let magic = tiff.u16(&header[2..4]);
+    tiff.panasonic = magic == 0x55;
+    if !matches!(magic, 42 | 0x55 | 0x4F52 | 0x5352) {
+        return Err(RawError::Unsupported(format!("unknown TIFF magic {:#x}", magic)));
+    }
+    
+    let mut pending = vec![(base + tiff.u32(&header[4..8]) as u64, true)];
+    let mut visited = HashSet::new();
+    let mut best_area = 0u64;
+    let mut first = true;
+    
+    while let Some((offset, follow_next)) = pending.pop() {
+        if offset == base || visited.len() >= MAX_IFDS || !visited.insert(offset) {
+            continue;
+        }
+        
+        let (entries, next) = read_ifd(reader, &tiff, offset)?;
+        let find = |tag: u16| entries.iter().find(|entry| entry.tag == tag);
+        let number = |tag: u16| find(tag).and_then(|entry| entry.number(&tiff));
+        
+        if first {
+            first = false;
+            layout.info.make = find(TAG_MAKE).and_then(|e| read_ascii(reader, &tiff, e));
+            layout.info.model = find(TAG_MODEL).and_then(|e| read_ascii(reader, &tiff, e));
+            layout.info.orientation = number(TAG_ORIENTATION).unwrap_or(1) as u16;
+        }
+        
+        // Sensor size: the largest image described by any IFD
+        let (width, height) = if tiff.panasonic && find(TAG_PANASONIC_SENSOR_WIDTH).is_some() {
+            (number(TAG_PANASONIC_SENSOR_WIDTH), number(TAG_PANASONIC_SENSOR_HEIGHT))
+        } else {
+            (number(TAG_IMAGE_WIDTH), number(TAG_IMAGE_LENGTH))
+        };
+        if let (Some(width), Some(height)) = (width, height) {
+            let area = width as u64 * height as u64;
+            if area > best_area {
+                best_area = area;
+                layout.info.width = Some(width);
+                layout.info.height = Some(height);
+            }
+        }
+        
+        // JPEG previews referenced by the IFD
+        if let (Some(offset), Some(len)) = (number(TAG_JPEG_OFFSET), number(TAG_JPEG_LENGTH)) {
+            layout.previews.push(PreviewLocation {
+                offset: base + offset as u64,
+                len: len as u64,
+            });
+        }
+        // Old-style JPEG strips (CR2 IFD0) and DNG previews (reduced resolution)
+        let compression = number(TAG_COMPRESSION).unwrap_or(1);
+        let is_preview = number(TAG_NEW_SUBFILE_TYPE).unwrap_or(0) & 1 == 1;
+        if compression == 6 || (compression == 7 && is_preview) {
+            let strips = find(TAG_STRIP_OFFSETS).filter(|e| e.count == 1);
+            let counts = find(TAG_STRIP_BYTE_COUNTS).filter(|e| e.count == 1);
+            if let (Some(strips), Some(counts)) = (strips, counts) {
+                if let (Some(offset), Some(len)) = (strips.number(&tiff), counts.number(&tiff)) {
+                    layout.previews.push(PreviewLocation {
+                        offset: base + offset as u64,
+                        len: len as u64,
+                    });
+                }
+            }
+        }
+        if let Some(entry) = find(TAG_PANASONIC_JPG_FROM_RAW).filter(|_| tiff.panasonic) {
+            layout.previews.push(PreviewLocation {
+                offset: entry.data_offset(&tiff),
+                len: entry.count as u64,
+            });
+        }
+        
+        if let Some(entry) = find(TAG_SUB_IFDS) {
+            for sub_ifd in read_longs(reader, &tiff, entry)? {
+                pending.push((base + sub_ifd as u64, false));
+            }
+        }
+        if follow_next && next != 0 {
+            pending.push((base + next as u64, true));
+        }
+    }
+    
+    Ok(())
+}
+
+/// Reads the entries of an IFD and the offset of the next one.
+fn read_ifd<R: Read + Seek>(
+    reader: &mut R,
+    tiff: &Tiff,
+    offset: u64,
+) -> RawResult<(Vec<IfdEntry>, u32)> {
+    let count_bytes = read_at(reader, offset, 2)?;
+    if count_bytes.len() < 2 {
+        return Ok((Vec::new(), 0));
+    }
+    let count = tiff.u16(&count_bytes) as usize;
+    
+    let data = read_at(reader, offset + 2, count * 12 + 4)?;
+    let entries = data
+        .chunks_exact(12)
+        .take(count)
+        .map(|chunk| IfdEntry {
+            tag: tiff.u16(&chunk[0..2]),
+            field_type: tiff.u16(&chunk[2..4]),
+            count: tiff.u32(&chunk[4..8]),
+            value: [chunk[8], chunk[9], chunk[10], chunk[11]],
+        })
+        .collect();
+    let next = if data.len() == count * 12 + 4 {
+        tiff.u32(&data[count * 12..])
+    } else {
+        0
+    };
+    
+    Ok((entries, next))
+}
+
+/// Reads an ASCII entry, trimming the NUL terminator and padding.
+fn read_ascii<R: Read + Seek>(reader: &mut R, tiff: &Tiff, entry: &IfdEntry) -> Option<String> {
+    if entry.field_type != 2 || entry.count == 0 || entry.count > 1024 {
+        return None;
+    }
+    let bytes = if entry.count <= 4 {
+        entry.value[..entry.count as usize].to_vec()
+    } else {
+        read_at(reader, entry.data_offset(tiff), entry.count as usize).ok()?
+    };
+    let text = String::from_utf8_lossy(&bytes)
+        .trim_end_matches('\0')
+        .trim()
+        .to_string();
+    (!text.is_empty()).then_some(text)
+}
+
+/// Reads all values of a LONG (or IFD) entry.
+fn read_longs<R: Read + Seek>(reader: &mut R, tiff: &Tiff, entry: &IfdEntry) -> RawResult<Vec<u32>> {
+    if !matches!(entry.field_type, 4 | 13) || entry.count > MAX_IFDS as u32 {
+        return Ok(Vec::new());
+    }
+    if entry.count * entry.type_size() <= 4 {
+        return Ok(vec![tiff.u32(&entry.value)]);
+    }
+    let data = read_at(reader, entry.data_offset(tiff), entry.count as usize * 4)?;
+    Ok(data.chunks_exact(4).map(|chunk| tiff.u32(chunk)).collect())
+}
+
+/// Parses a Fujifilm RAF file.
+///
+/// The big-endian header holds the camera model, the location of the
+/// embedded JPEG and the location of a tag directory with the sensor size.
+fn parse_raf<R: Read + Seek>(reader: &mut R) -> RawResult<RawLayout> {
+    let header = read_at(reader, 0, 0x60)?;
+    if header.len() < 0x60 {
+        return Err(RawError::Unsupported("RAF header too short".to_string()));
+    }
+    let be32 = |at: usize| u32::from_be_bytes([header[at], header[at + 1], header[at + 2], header[at + 3]]);
+    
+    let mut layout = RawLayout::default();
+    layout.info.make = Some("FUJIFILM".to_string());
+    let model = String::from_utf8_lossy(&header[0x1C..0x3C])
+        .trim_end_matches('\0')
+        .trim()
+        .to_string();
+    layout.info.model = (!model.is_empty()).then_some(model);
+    layout.info.orientation = 1;
+    
+    let jpeg_offset = be32(0x54) as u64;
+    layout.previews.push(PreviewLocation {
+        offset: jpeg_offset,
+        len: be32(0x58) as u64,
+    });
+    
+    // Tag directory: record count, then (tag, size, data) records
+    let directory = be32(0x5C) as u64;
+    let count_bytes = read_at(reader, directory, 4)?;
+    if count_bytes.len() == 4 {
+        let count = u32::from_be_bytes([count_bytes[0], count_bytes[1], count_bytes[2], count_bytes[3]]);
+        let mut offset = directory + 4;
+        for _ in 0..count.min(256) {
+            let record = read_at(reader, offset, 4)?;
+            if record.len() < 4 {
+                break;
+            }
+            let tag = u16::from_be_bytes([record[0], record[1]]);
+            let size = u16::from_be_bytes([record[2], record[3]]) as u64;
+            // Raw image full size: height then width
+            if tag == 0x0100 && size >= 4 {
+                let data = read_at(reader, offset + 4, 4)?;
+                if data.len() == 4 {
+                    layout.info.height = Some(u16::from_be_bytes([data[0], data[1]]) as u32);
+                    layout.info.width = Some(u16::from_be_bytes([data[2], data[3]]) as u32);
+                }
+                break;
+            }
+            offset += 4 + size;
+        }
+    }
+    
+    // The embedded JPEG carries the camera's EXIF orientation
+    if let Ok(Some(tiff_offset)) = find_exif_in_jpeg(reader, jpeg_offset) {
+        let mut exif = RawLayout::default();
+        if parse_tiff(reader, tiff_offset, &mut exif).is_ok() {
+            layout.info.orientation = exif.info.orientation;
+        }
+    }
+    
+    Ok(layout)
+}
+
+/// Finds the TIFF header of the EXIF segment of a JPEG at `offset`.
+fn find_exif_in_jpeg<R: Read + Seek>(reader: &mut R, offset: u64) -> RawResult<Option<u64>> {
+    let head = read_at(reader, offset, 12)?;
+    if head.len() == 12 && head.starts_with(&[0xFF, 0xD8, 0xFF, 0xE1]) && &head[6..12] == b"Exif\0\0" {
+        return Ok(Some(offset + 12));
+    }
+    Ok(None)
+}
+
+/// Parses a Canon CR3 file.
+///
+/// Walks `moov` to Canon's metadata `uuid` box and parses the TIFF IFD0
+/// stored in its `CMT1` box. The preview is left to the JPEG scan.
+fn parse_cr3<R: Read + Seek>(reader: &mut R) -> RawResult<RawLayout> {
+    let file_len = reader.seek(SeekFrom::End(0))?;
+    let mut layout = RawLayout::default();
+    layout.info.orientation = 1;
+    
+    let Some((moov_start, moov_end)) = find_box(reader, 0, file_len, b"moov")? else {
+        return Err(RawError::Unsupported("CR3 file without moov box".to_string()));
+    };
+    
+    let mut offset = moov_start;
+    while let Some((start, end)) = find_box(reader, offset, moov_end, b"uuid")? {
+        let uuid = read_at(reader, start, 16)?;
+        if uuid == CANON_METADATA_UUID {
+            if let Some((cmt1_start, _)) = find_box(reader, start + 16, end, b"CMT1")? {
+                parse_tiff(reader, cmt1_start, &mut layout)?;
+            }
+            break;
+        }
+        offset = end;
+    }
+    
+    Ok(layout)
+}
+
+/// Finds the first ISO BMFF box of a type between `start` and `end`.
+///
+/// # Returns
+///
+/// Returns the offsets of the box content and of the end of the box.
+fn find_box<R: Read + Seek>(
+    reader: &mut R,
+    start: u64,
+    end: u64,
+    box_type: &[u8; 4],
+) -> RawResult<Option<(u64, u64)>> {
+    let mut offset = start;
+    while offset + 8 <= end {
+        let header = read_at(reader, offset, 16)?;
+        if header.len() < 8 {
+            break;
+        }
+        let mut size = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as u64;
+        let mut header_len = 8;
+        if size == 1 && header.len() == 16 {
+            size = u64::from_be_bytes([
+                header[8], header[9], header[10], header[11], header[12], header[13], header[14], header[15],
+            ]);
+            header_len = 16;
+        } else if size == 0 {
+            size = end - offset;
+        }
+        if size < header_len {
+            break;
+        }
+        
+        if &header[4..8] == box_type {
+            return Ok(Some((offset + header_len, (offset + size).min(end))));
+        }
+        offset += size;
+    }
+    Ok(None)
+}
+
+/// Finds the largest complete JPEG stream in a buffer.
+///
+/// # Returns
+///
+/// Returns the start offset and length of the JPEG.
+fn find_largest_jpeg(data: &[u8]) -> Option<(usize, usize)> {
+    let mut best: Option<(usize, usize)> = None;
+    let mut search = 0;
+    
+    while let Some(position) = find_subslice(&data[search..], &[0xFF, 0xD8, 0xFF]) {
+        let start = search + position;
+        match jpeg_length(&data[start..]) {
+            Some(len) => {
+                if best.is_none_or(|(_, best_len)| len > best_len) {
+                    best = Some((start, len));
+                }
+                // Thumbnails embedded in this JPEG are smaller, skip over them
+                search = start + len;
+            }
+            None => search = start + 3,
+        }
+    }
+    
+    best
+}
+
+/// Measures a JPEG stream by walking its segments up to the EOI marker.
+fn jpeg_length(data: &[u8]) -> Option<usize> {
+    let mut pos = 2;
+    
+    loop {
+        if *data.get(pos)? != 0xFF {
+            return None;
+        }
+        let marker = *data.get(pos + 1)?;
+        match marker {
+            0xFF => pos += 1,
+            0xD9 => return Some(pos + 2),
+            0x01 | 0xD0..=0xD7 => pos += 2,
+            _ => {
+                let len = u16::from_be_bytes([*data.get(pos + 2)?, *data.get(pos + 3)?]) as usize;
+                pos += 2 + len;
+                
+                if marker == 0xDA {
+                    // Skip entropy-coded data up to the next real marker
+                    loop {
+                        let next = pos + find_subslice(data.get(pos..)?, &[0xFF])?;
+                        let following = *data.get(next + 1)?;
+                        if following == 0x00 || (0xD0..=0xD7).contains(&following) {
+                            pos = next + 2;
+                        } else {
+                            pos = next;
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn find_subslice(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+/// Reads up to `len` bytes at `offset`; shorter at the end of the file.
+fn read_at<R: Read + Seek>(reader: &mut R, offset: u64, len: usize) -> std::io::Result<Vec<u8>> {
+    reader.seek(SeekFrom::Start(offset))?;
+    let mut data = Vec::with_capacity(len);
+    reader.by_ref().take(len as u64).read_to_end(&mut data)?;
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    
+    /// A minimal JPEG stream: SOI, one APP0 segment, SOS with data, EOI
+    fn jpeg(payload: &[u8]) -> Vec<u8> {
+        let mut data = vec![0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x04, 0x00, 0x00];
+        data.extend_from_slice(&[0xFF, 0xDA, 0x00, 0x02]);
+        data.extend_from_slice(payload);
+        data.extend_from_slice(&[0xFF, 0xD9]);
+        data
+    }
+    
+    /// Builds a little-endian TIFF with Make, Model, size and a JPEG preview
+    fn tiff_with_preview(preview: &[u8]) -> Vec<u8> {
+        let mut data = b"II*\0".to_vec();
+        data.extend_from_slice(&8u32.to_le_bytes());
+        
+        let entries: u16 = 6;
+        let strings_offset = 8 + 2 + entries as u32 * 12 + 4;
+        let preview_offset = strings_offset + 16;
+        let entry = |tag: u16, field_type: u16, count: u32, value: u32| {
+            let mut bytes = tag.to_le_bytes().to_vec();
+            bytes.extend_from_slice(&field_type.to_le_bytes());
+            bytes.extend_from_slice(&count.to_le_bytes());
+            bytes.extend_from_slice(&value.to_le_bytes());
+            bytes
+        };
+        
+        data.extend_from_slice(&entries.to_le_bytes());
+        data.extend(entry(TAG_IMAGE_WIDTH, 4, 1, 6000));
+        data.extend(entry(TAG_IMAGE_LENGTH, 4, 1, 4000));
+        data.extend(entry(TAG_MAKE, 2, 6, strings_offset));
+        data.extend(entry(TAG_MODEL, 2, 10, strings_offset + 6));
+        data.extend(entry(TAG_JPEG_OFFSET, 4, 1, preview_offset));
+        data.extend(entry(TAG_JPEG_LENGTH, 4, 1, preview.len() as u32));
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data.extend_from_slice(b"NIKON\0NIKON Z6\0\0");
+        data.extend_from_slice(preview);
+        data
+    }
+    
+    #[test]
+    fn test_parse_tiff_layout() {
+        let preview = jpeg(b"preview");
+        let data = tiff_with_preview(&preview);
+        
+        let layout = parse_layout(&mut Cursor::new(data.clone())).unwrap();
+        
+        assert_eq!(layout.info.make.as_deref(), Some("NIKON"));
+        assert_eq!(layout.info.model.as_deref(), Some("NIKON Z6"));
+        assert_eq!((layout.info.width, layout.info.height), (Some(6000), Some(4000)));
+        assert_eq!(layout.previews.len(), 1);
+        
+        let location = layout.previews[0];
+        let start = location.offset as usize;
+        assert_eq!(&data[start..start + location.len as usize], preview.as_slice());
+    }
+    
+    #[test]
+    fn test_find_largest_jpeg() {
+        let small = jpeg(b"a");
+        let large = jpeg(b"larger preview with \xFF\x00 stuffed bytes");
+        let mut data = b"junk".to_vec();
+        data.extend_from_slice(&small);
+        data.extend_from_slice(b"more junk");
+        data.extend_from_slice(&large);
+        data.extend_from_slice(&[0xFF, 0xD8, 0xFF, 0xE0]);
+        
+        let (start, len) = find_largest_jpeg(&data).unwrap();
+        assert_eq!(&data[start..start + len], large.as_slice());
+        assert_eq!(find_largest_jpeg(b"no jpeg here"), None);
+    }
+    
+    #[test]
+    fn test_parse_layout_rejects_unknown_data() {
+        assert!(parse_layout(&mut Cursor::new(b"definitely not a raw file".to_vec())).is_err());
+    }
+}
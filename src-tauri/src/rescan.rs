@@ -0,0 +1,558 @@
+//! Incremental rescanning of registered folders
+//!
+//! Re-walks a folder that is already stored in `scanned_folders` and compares
+//! the files on disk with the rows `media_metadata` holds for it. Only the
+//! differences (added, modified, moved and removed files) are written back, so
+//! large libraries don't need a full re-index or frontend reconciliation.
+//!
+//! A file that disappeared from one path and showed up at another with the
+//! same size, modification time and content hash is treated as moved: its row
+//! is updated in place, keeping tags, ratings and album membership.
+
+use crate::database::{self, DbPool, IndexedFile, InsertMediaParams, MediaMove};
+use crate::hashing;
+use crate::ignore_rules;
+use crate::indexer::INDEX_BATCH_SIZE;
+use crate::scanner::{self, MediaFile, ScanOptions};
+use crate::tags;
+use crate::thumbnail;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use tauri::State;
+use tracing::{debug, info, warn};
+
+/// Outcome of an incremental rescan
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RescanSummary {
+    /// ID of the rescanned folder
+    pub folder_id: i64,
+    /// Files that were not in the index before
+    pub added: Vec<MediaFile>,
+    /// Files whose size or modification time changed
+    pub modified: Vec<MediaFile>,
+    /// Indexed files found at a new path
+    pub moved: Vec<MovedFile>,
+    /// Paths of indexed files that no longer exist
+    pub removed: Vec<String>,
+    /// Number of files that were already up to date
+    pub unchanged: usize,
+    /// Total number of media files now in the folder
+    pub file_count: i64,
+}
+
+/// An indexed file that was moved or renamed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MovedFile {
+    pub media_id: i64,
+    pub old_path: String,
+    pub new_path: String,
+}
+
+/// Differences between a folder on disk and its indexed rows
+#[derive(Debug, Default)]
+pub(crate) struct FolderDiff {
+    pub(crate) added: Vec<MediaFile>,
+    pub(crate) modified: Vec<MediaFile>,
+    pub(crate) moved: Vec<DetectedMove>,
+    pub(crate) removed: Vec<IndexedFile>,
+    pub(crate) unchanged: usize,
+}
+
+/// A missing indexed row paired with the file it moved to
+#[derive(Debug)]
+pub(crate) struct DetectedMove {
+    pub(crate) row: IndexedFile,
+    pub(crate) file: MediaFile,
+    /// Quick hash of the file, when one was computed to confirm the move
+    pub(crate) quick_hash: Option<String>,
+}
+
+impl DetectedMove {
+    /// Row update that points the media row at the new path
+    pub(crate) fn to_media_move(&self, folder_id: i64) -> MediaMove {
+        MediaMove {
+            id: self.row.id,
+            folder_id,
+            file_path: self.file.path.clone(),
+            file_name: self.file.name.clone(),
+            file_type: self.file.file_type.clone(),
+            quick_hash: self.quick_hash.clone(),
+        }
+    }
+    
+    pub(crate) fn to_moved_file(&self) -> MovedFile {
+        MovedFile {
+            media_id: self.row.id,
+            old_path: self.row.file_path.clone(),
+            new_path: self.file.path.clone(),
+        }
+    }
+}
+
+/// Pairing of a new file with a missing row, by index into the inputs
+#[derive(Debug, PartialEq)]
+pub(crate) struct MoveMatch {
+    pub(crate) added: usize,
+    pub(crate) removed: usize,
+    pub(crate) quick_hash: Option<String>,
+}
+
+/// Compares the files found on disk with the indexed rows of a folder.
+///
+/// A file counts as modified when either its size or its modification
+/// timestamp (at second precision, as stored by the scanner) differs from the
+/// indexed row. Indexed rows with no matching file on disk are reported as
+/// removed.
+pub(crate) fn diff_folder(files: Vec<MediaFile>, indexed: Vec<IndexedFile>) -> FolderDiff {
+    let mut indexed_by_path: HashMap<String, IndexedFile> = indexed
+        .into_iter()
+        .map(|row| (row.file_path.clone(), row))
+        .collect();
+    
+    let mut diff = FolderDiff::default();
+    
+    for file in files {
+        match indexed_by_path.remove(&file.path) {
+            None => diff.added.push(file),
+            Some(row) => {
+                let size_changed = row.file_size != file.size as i64;
+                let mtime_changed = row.modified_date.timestamp() != file.modified;
+                
+                if size_changed || mtime_changed {
+                    diff.modified.push(file);
+                } else {
+                    diff.unchanged += 1;
+                }
+            }
+        }
+    }
+    
+    // Whatever is left in the index was not found on disk
+    diff.removed = indexed_by_path.into_values().collect();
+    
+    diff
+}
+
+/// Pairs new files with missing rows that hold the same content.
+///
+/// Candidates must have the same size and modification time (second
+/// precision). When the missing row has a quick hash, the new file's quick
+/// hash must match it, and so must the full content hash when the row has one.
+/// Rows that were never hashed are only matched when the size and
+/// modification time are unambiguous (one missing row, one new file).
+///
+/// Hashes files, so call it off the async runtime.
+pub(crate) fn match_moves(added: &[MediaFile], removed: &[IndexedFile]) -> Vec<MoveMatch> {
+    let mut removed_by_key: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+    for (index, row) in removed.iter().enumerate() {
+        removed_by_key
+            .entry((row.file_size, row.modified_date.timestamp()))
+            .or_default()
+            .push(index);
+    }
+    let mut added_per_key: HashMap<(i64, i64), usize> = HashMap::new();
+    for file in added {
+        *added_per_key.entry((file.size as i64, file.modified)).or_default() += 1;
+    }
+    
+    let mut taken = vec![false; removed.len()];
+    let mut matches = Vec::new();
+    
+    for (added_index, file) in added.iter().enumerate() {
+        let key = (file.size as i64, file.modified);
+        let Some(candidates) = removed_by_key.get(&key) else {
+            continue;
+        };
+        let unambiguous = candidates.len() == 1 && added_per_key[&key] == 1;
+        let path = Path::new(&file.path);
+        
+        // Hashes of the new file, computed at most once
+        let mut quick: Option<Option<String>> = None;
+        let mut full: Option<Option<String>> = None;
+        let mut found = None;
+        
+        for &removed_index in candidates {
+            if taken[removed_index] {
+                continue;
+            }
+            let row = &removed[removed_index];
+            let Some(expected) = &row.quick_hash else {
+                if unambiguous {
+                    found = Some(removed_index);
+                    break;
+                }
+                continue;
+            };
+            
+            let quick = quick.get_or_insert_with(|| hashing::quick_hash(path).ok());
+            if quick.as_ref() != Some(expected) {
+                continue;
+            }
+            if let Some(expected) = &row.content_hash {
+                let full = full.get_or_insert_with(|| hashing::content_hash(path).ok());
+                if full.as_ref() != Some(expected) {
+                    continue;
+                }
+            }
+            found = Some(removed_index);
+            break;
+        }
+        
+        if let Some(removed_index) = found {
+            taken[removed_index] = true;
+            matches.push(MoveMatch {
+                added: added_index,
+                removed: removed_index,
+                quick_hash: quick.flatten(),
+            });
+        }
+    }
+    
+    matches
+}
+
+/// Turns added/removed pairs of a diff that hold the same content into moves.
+///
+/// Hashes files, so call it off the async runtime.
+pub(crate) fn detect_moves(diff: &mut FolderDiff) {
+    let matches = match_moves(&diff.added, &diff.removed);
+    if matches.is_empty() {
+        return;
+    }
+    
+    let mut added: Vec<Option<MediaFile>> = diff.added.drain(..).map(Some).collect();
+    let mut removed: Vec<Option<IndexedFile>> = diff.removed.drain(..).map(Some).collect();
+    for m in matches {
+        if let (Some(file), Some(row)) = (added[m.added].take(), removed[m.removed].take()) {
+            diff.moved.push(DetectedMove {
+                row,
+                file,
+                quick_hash: m.quick_hash,
+            });
+        }
+    }
+    diff.added = added.into_iter().flatten().collect();
+    diff.removed = removed.into_iter().flatten().collect();
+}
+
+/// Builds insert parameters for a freshly scanned file.
+///
+/// Extended metadata is left empty so that `media_info` re-extracts it the next
+/// time the file is viewed instead of serving stale cached values.
+pub(crate) fn media_file_to_params(folder_id: i64, file: &MediaFile) -> InsertMediaParams {
+    InsertMediaParams {
+        folder_id,
+        file_path: file.path.clone(),
+        file_name: file.name.clone(),
+        file_type: file.file_type.clone(),
+        file_size: file.size as i64,
+        modified_date: DateTime::from_timestamp(file.modified, 0).unwrap_or_else(Utc::now),
+        media_type: Some(file.media_type.as_str().to_string()),
+        ..Default::default()
+    }
+}
+
+/// Drops cached thumbnails for files that changed or disappeared.
+///
+/// Failures are logged and otherwise ignored; a stale thumbnail is not worth
+/// failing the rescan for.
+pub(crate) fn invalidate_thumbnails<'a>(paths: impl IntoIterator<Item = &'a str>) {
+    for path in paths {
+        if let Err(e) = thumbnail::invalidate_thumbnail(path) {
+            warn!("Failed to invalidate thumbnail for {}: {}", path, e);
+        }
+    }
+}
+
+/// What [`sync_folder_index`] wrote for a folder
+pub(crate) struct FolderSync {
+    pub(crate) diff: FolderDiff,
+    /// Number of transactions committed
+    pub(crate) batches: usize,
+}
+
+/// Brings the indexed rows of a folder in line with the files found on disk.
+///
+/// Shared by the scan-and-index pipeline and incremental rescans. Moved files
+/// keep their rows, new and changed files are upserted and rows of vanished
+/// files deleted, `batch_size` rows per transaction. The folder's
+/// `file_count` and `last_scanned` are only refreshed once every batch has
+/// been committed, so an interrupted run does not look like a finished one;
+/// the next run diffs against the rows already written and resumes. Cached
+/// thumbnails of changed files are dropped and orphaned tags restored.
+///
+/// # Arguments
+///
+/// * `pool` - Database connection pool
+/// * `folder_id` - ID of the folder in `scanned_folders`
+/// * `files` - Every media file currently in the folder
+/// * `batch_size` - Rows written per transaction
+///
+/// # Errors
+///
+/// Returns an error if move detection or a database write fails. Batches
+/// committed before the failure are kept.
+pub(crate) async fn sync_folder_index(
+    pool: &DbPool,
+    folder_id: i64,
+    files: Vec<MediaFile>,
+    batch_size: usize,
+) -> Result<FolderSync, String> {
+    let file_count = files.len() as i64;
+    let indexed = database::get_indexed_files(pool, folder_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    
+    let diff = tokio::task::spawn_blocking(move || {
+        let mut diff = diff_folder(files, indexed);
+        detect_moves(&mut diff);
+        diff
+    })
+    .await
+    .map_err(|e| format!("Move detection failed: {}", e))?;
+    debug!(
+        "Folder {} diff: {} added, {} modified, {} moved, {} removed, {} unchanged",
+        folder_id,
+        diff.added.len(),
+        diff.modified.len(),
+        diff.moved.len(),
+        diff.removed.len(),
+        diff.unchanged
+    );
+    
+    let upserts: Vec<InsertMediaParams> = diff
+        .added
+        .iter()
+        .chain(diff.modified.iter())
+        .map(|file| media_file_to_params(folder_id, file))
+        .collect();
+    let moves: Vec<MediaMove> = diff.moved.iter().map(|m| m.to_media_move(folder_id)).collect();
+    let removed_ids: Vec<i64> = diff.removed.iter().map(|row| row.id).collect();
+    
+    let mut batches = 0;
+    for chunk in moves.chunks(batch_size) {
+        database::move_media_batch(pool, chunk)
+            .await
+            .map_err(|e| e.to_string())?;
+        batches += 1;
+    }
+    for chunk in upserts.chunks(batch_size) {
+        database::upsert_media_batch(pool, chunk)
+            .await
+            .map_err(|e| e.to_string())?;
+        batches += 1;
+    }
+    for chunk in removed_ids.chunks(batch_size) {
+        database::delete_media_batch(pool, chunk)
+            .await
+            .map_err(|e| e.to_string())?;
+        batches += 1;
+    }
+    
+    database::update_folder_stats(pool, folder_id, file_count)
+        .await
+        .map_err(|e| e.to_string())?;
+    
+    invalidate_thumbnails(
+        diff.modified
+            .iter()
+            .map(|file| file.path.as_str())
+            .chain(diff.moved.iter().map(|m| m.row.file_path.as_str()))
+            .chain(diff.removed.iter().map(|row| row.file_path.as_str())),
+    );
+    
+    let added_paths: Vec<String> = diff.added.iter().map(|file| file.path.clone()).collect();
+    if let Err(e) = tags::restore_moved_tags(pool, &added_paths).await {
+        warn!("Cannot restore tags of moved files: {}", e);
+    }
+    
+    Ok(FolderSync { diff, batches })
+}
+
+/// Rescans a registered folder and updates its index incrementally.
+///
+/// # Arguments
+///
+/// * `pool` - Database connection pool
+/// * `folder_id` - ID of the folder in `scanned_folders`
+/// * `options` - Recursion, threading and ignore options for the walk
+///
+/// # Errors
+///
+/// Returns an error if the folder is unknown, its path is no longer a
+/// directory, or the database update fails.
+pub async fn rescan_folder_incremental(
+    pool: &DbPool,
+    folder_id: i64,
+    options: ScanOptions,
+) -> Result<RescanSummary, String> {
+    let folder = database::get_scanned_folder(pool, folder_id)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Scanned folder not found: {}", folder_id))?;
+    
+    info!("Incremental rescan of folder {} ({})", folder.id, folder.path);
+    
+    // Walking the tree is blocking IO, keep it off the async runtime
+    let folder_path = folder.path.clone();
+    let files = tokio::task::spawn_blocking(move || {
+        let path = Path::new(&folder_path);
+        scanner::validate_directory(path)?;
+        Ok::<_, String>(scanner::collect_media_files(path, &options))
+    })
+    .await
+    .map_err(|e| format!("Scan task failed: {}", e))??;
+    
+    let file_count = files.len() as i64;
+    let FolderSync { diff, .. } = sync_folder_index(pool, folder_id, files, INDEX_BATCH_SIZE).await?;
+    
+    Ok(RescanSummary {
+        folder_id,
+        added: diff.added,
+        modified: diff.modified,
+        moved: diff.moved.iter().map(DetectedMove::to_moved_file).collect(),
+        removed: diff.removed.into_iter().map(|row| row.file_path).collect(),
+        unchanged: diff.unchanged,
+        file_count,
+    })
+}
+
+/// Tauri command to incrementally rescan a registered folder
+///
+/// # Arguments
+///
+/// * `pool` - Database connection pool (injected by Tauri)
+/// * `folder_id` - ID of the folder in `scanned_folders`
+/// * `recursive` - Whether to scan subdirectories recursively
+/// * `threads` - Optional worker thread count for the walk
+///
+/// # Returns
+///
+/// Returns a summary of added, modified and removed files
+#[tauri::command]
+pub async fn rescan_folder(
+    pool: State<'_, DbPool>,
+    folder_id: i64,
+    recursive: bool,
+    threads: Option<usize>,
+) -> Result<RescanSummary, String> {
+    let options = ScanOptions::new(recursive)
+        .with_threads(threads)
+        .with_ignore(ignore_rules::load_ignore_settings_or_default(&pool).await);
+    rescan_folder_incremental(&pool, folder_id, options).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::MediaType;
+    
+    fn media_file(path: &str, size: u64, modified: i64) -> MediaFile {
+        MediaFile {
+            path: path.to_string(),
+            name: path.rsplit('/').next().unwrap_or(path).to_string(),
+            size,
+            modified,
+            file_type: "jpg".to_string(),
+            media_type: MediaType::Image,
+            extension_mismatch: false,
+        }
+    }
+    
+    fn indexed_file(id: i64, path: &str, size: i64, modified: i64) -> IndexedFile {
+        IndexedFile {
+            id,
+            file_path: path.to_string(),
+            file_size: size,
+            modified_date: DateTime::from_timestamp(modified, 0).unwrap(),
+            quick_hash: None,
+            content_hash: None,
+        }
+    }
+    
+    #[test]
+    fn test_diff_folder_classifies_changes() {
+        let files = vec![
+            media_file("/lib/new.jpg", 10, 100),
+            media_file("/lib/same.jpg", 20, 200),
+            media_file("/lib/resized.jpg", 31, 300),
+            media_file("/lib/touched.jpg", 40, 401),
+        ];
+        let indexed = vec![
+            indexed_file(1, "/lib/same.jpg", 20, 200),
+            indexed_file(2, "/lib/resized.jpg", 30, 300),
+            indexed_file(3, "/lib/touched.jpg", 40, 400),
+            indexed_file(4, "/lib/gone.jpg", 50, 500),
+        ];
+        
+        let diff = diff_folder(files, indexed);
+        
+        assert_eq!(diff.added.len(), 1);
+        assert_eq!(diff.added[0].path, "/lib/new.jpg");
+        assert_eq!(diff.modified.len(), 2);
+        assert_eq!(diff.unchanged, 1);
+        assert_eq!(diff.removed.len(), 1);
+        assert_eq!(diff.removed[0].id, 4);
+    }
+    
+    #[test]
+    fn test_diff_folder_empty_index() {
+        let files = vec![media_file("/lib/a.jpg", 1, 1), media_file("/lib/b.jpg", 2, 2)];
+        
+        let diff = diff_folder(files, Vec::new());
+        
+        assert_eq!(diff.added.len(), 2);
+        assert!(diff.modified.is_empty());
+        assert!(diff.removed.is_empty());
+        assert_eq!(diff.unchanged, 0);
+    }
+    
+    #[test]
+    fn test_detect_moves_requires_matching_content() {
+        let dir = std::env::temp_dir().join(format!("fmlm_rescan_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = |name: &str| dir.join(name).to_string_lossy().to_string();
+        std::fs::write(path("renamed.jpg"), b"moved content").unwrap();
+        std::fs::write(path("other.jpg"), b"other content").unwrap();
+        
+        let mut hashed = indexed_file(1, "/lib/old.jpg", 13, 100);
+        hashed.quick_hash = Some(hashing::quick_hash(Path::new(&path("renamed.jpg"))).unwrap());
+        let mut hash_mismatch = indexed_file(2, "/lib/gone.jpg", 13, 100);
+        hash_mismatch.quick_hash = Some("deadbeef".to_string());
+        
+        let files = vec![
+            media_file(&path("renamed.jpg"), 13, 100),
+            media_file(&path("other.jpg"), 13, 100),
+            media_file("/lib/2024/plain.jpg", 50, 200),
+            media_file("/lib/twin1.jpg", 60, 300),
+            media_file("/lib/twin2.jpg", 60, 300),
+        ];
+        let indexed = vec![
+            hashed,
+            hash_mismatch,
+            indexed_file(3, "/lib/plain.jpg", 50, 200),
+            indexed_file(4, "/lib/twin.jpg", 60, 300),
+        ];
+        
+        let mut diff = diff_folder(files, indexed);
+        detect_moves(&mut diff);
+        
+        let mut moved: Vec<(i64, String)> = diff
+            .moved
+            .iter()
+            .map(|m| (m.row.id, m.file.name.clone()))
+            .collect();
+        moved.sort();
+        // Unhashed rows only move when the size and mtime are unambiguous
+        assert_eq!(moved, [(1, "renamed.jpg".to_string()), (3, "plain.jpg".to_string())]);
+        assert!(diff.moved[0].quick_hash.is_some());
+        assert_eq!(diff.added.len(), 3);
+        let mut removed: Vec<i64> = diff.removed.iter().map(|row| row.id).collect();
+        removed.sort();
+        assert_eq!(removed, [2, 4]);
+        
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,429 @@
+//! Background scan jobs
+//!
+//! Runs directory scans on a blocking worker instead of inside the IPC call,
+//! so the frontend gets a job ID immediately and can follow the scan through
+//! periodic `scan-progress` events, query its status, or cancel it mid-walk.
+//! A final `scan-finished` event is emitted when the job ends.
+
+use crate::database::DbPool;
+use crate::ignore_rules;
+use crate::scanner::{self, MediaFile, ScanObserver, ScanOptions};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, State};
+use tracing::{debug, error, info};
+
+/// Event emitted periodically while a scan is running
+pub const SCAN_PROGRESS_EVENT: &str = "scan-progress";
+
+/// Event emitted once when a scan completes, fails or is cancelled
+pub const SCAN_FINISHED_EVENT: &str = "scan-finished";
+
+/// Minimum time between two progress events for the same job
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Maximum number of error messages kept per job (the count is always exact)
+const MAX_REPORTED_ERRORS: usize = 100;
+
+/// How long a finished job whose results were never taken stays registered
+const FINISHED_JOB_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Lifecycle state of a scan job
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "state", content = "message", rename_all = "lowercase")]
+pub enum ScanJobStatus {
+    /// The walk is in progress
+    Running,
+    /// The walk finished and results are ready
+    Completed,
+    /// The job was cancelled before finishing
+    Cancelled,
+    /// The job could not run (e.g. the path is not a directory)
+    Failed(String),
+}
+
+/// Progress counters of a scan job
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScanProgress {
+    /// Number of files visited so far
+    pub files_seen: u64,
+    /// Number of supported media files found so far
+    pub media_found: u64,
+    /// Directory currently being walked
+    pub current_directory: Option<String>,
+    /// Total number of entries that could not be read
+    pub error_count: u64,
+    /// First error messages encountered (capped)
+    pub errors: Vec<String>,
+}
+
+/// Snapshot of a scan job sent to the frontend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanJobInfo {
+    pub job_id: u64,
+    pub path: String,
+    pub recursive: bool,
+    pub status: ScanJobStatus,
+    pub progress: ScanProgress,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+/// Shared state of a single scan job
+struct ScanJob {
+    info: Mutex<ScanJobInfo>,
+    cancelled: AtomicBool,
+    results: Mutex<Option<Vec<MediaFile>>>,
+}
+
+impl ScanJob {
+    fn snapshot(&self) -> Option<ScanJobInfo> {
+        self.info.lock().ok().map(|info| info.clone())
+    }
+}
+
+/// Keeps track of all background scan jobs.
+///
+/// Managed as Tauri state. Jobs stay registered after finishing until their
+/// results are taken with [`take_scan_results`], or until the next scan starts
+/// more than [`FINISHED_JOB_TTL`] after they finished.
+#[derive(Default)]
+pub struct ScanJobManager {
+    next_id: AtomicU64,
+    jobs: Mutex<HashMap<u64, Arc<ScanJob>>>,
+}
+
+impl ScanJobManager {
+    fn get(&self, job_id: u64) -> Result<Arc<ScanJob>, String> {
+        self.jobs
+            .lock()
+            .map_err(|_| "Scan job registry is poisoned".to_string())?
+            .get(&job_id)
+            .cloned()
+            .ok_or_else(|| format!("Scan job not found: {}", job_id))
+    }
+    
+    /// Forgets jobs that finished more than [`FINISHED_JOB_TTL`] before `now`
+    fn evict_finished(&self, now: DateTime<Utc>) {
+        let Ok(mut jobs) = self.jobs.lock() else {
+            return;
+        };
+        let ttl = chrono::Duration::from_std(FINISHED_JOB_TTL).unwrap_or(chrono::Duration::MAX);
+        jobs.retain(|job_id, job| {
+            let expired = job
+                .snapshot()
+                .and_then(|info| info.finished_at)
+                .is_some_and(|finished_at| now - finished_at > ttl);
+            if expired {
+                debug!("Evicting unclaimed scan job {}", job_id);
+            }
+            !expired
+        });
+    }
+}
+
+/// Progress accumulated by the observer between two publications
+struct ObserverState {
+    progress: ScanProgress,
+    last_emit: Instant,
+}
+
+/// Observer that records progress on the job and emits throttled events
+struct JobObserver {
+    app_handle: AppHandle,
+    job: Arc<ScanJob>,
+    state: Mutex<ObserverState>,
+}
+
+impl JobObserver {
+    /// Applies an update to the local counters and publishes them if due.
+    ///
+    /// Counters are kept on the observer and only copied into the shared job
+    /// state at most every [`PROGRESS_INTERVAL`] unless `force` is set, so
+    /// status queries never wait on the walk's hot loop.
+    fn update(&self, force: bool, apply: impl FnOnce(&mut ScanProgress)) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        apply(&mut state.progress);
+        
+        if !force && state.last_emit.elapsed() < PROGRESS_INTERVAL {
+            return;
+        }
+        state.last_emit = Instant::now();
+        
+        let snapshot = match self.job.info.lock() {
+            Ok(mut info) => {
+                info.progress = state.progress.clone();
+                info.clone()
+            }
+            Err(_) => return,
+        };
+        drop(state);
+        
+        if let Err(e) = self.app_handle.emit(SCAN_PROGRESS_EVENT, &snapshot) {
+            error!("Failed to emit scan progress: {}", e);
+        }
+    }
+}
+
+impl ScanObserver for JobObserver {
+    fn on_directory(&self, path: &Path) {
+        self.update(false, |progress| {
+            progress.current_directory = Some(path.to_string_lossy().to_string());
+        });
+    }
+    
+    fn on_file(&self, _path: &Path, media_file: Option<&MediaFile>) {
+        self.update(false, |progress| {
+            progress.files_seen += 1;
+            if media_file.is_some() {
+                progress.media_found += 1;
+            }
+        });
+    }
+    
+    fn on_error(&self, path: Option<&Path>, error: &dyn std::error::Error) {
+        self.update(false, |progress| {
+            progress.error_count += 1;
+            if progress.errors.len() < MAX_REPORTED_ERRORS {
+                let message = match path {
+                    Some(path) => format!("{}: {}", path.display(), error),
+                    None => error.to_string(),
+                };
+                progress.errors.push(message);
+            }
+        });
+    }
+    
+    fn is_cancelled(&self) -> bool {
+        self.job.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// Runs a scan job to completion on the current (blocking) thread
+fn run_job(app_handle: AppHandle, job: Arc<ScanJob>, path: PathBuf, options: ScanOptions) {
+    let observer = JobObserver {
+        app_handle: app_handle.clone(),
+        job: job.clone(),
+        state: Mutex::new(ObserverState {
+            progress: ScanProgress::default(),
+            last_emit: Instant::now(),
+        }),
+    };
+    
+    let outcome = scanner::validate_directory(&path).map(|()| {
+        let mut media_files = scanner::walk_media_files(&path, &options, &observer);
+        observer.update(true, |_| {});
+        scanner::sort_newest_first(&mut media_files);
+        media_files
+    });
+    
+    // The cancellation flag is read under the info lock, so a cancel request
+    // either lands before the job is marked finished or not at all
+    let snapshot = match job.info.lock() {
+        Ok(mut info) => {
+            info.status = match outcome {
+                Err(e) => ScanJobStatus::Failed(e),
+                Ok(_) if observer.is_cancelled() => ScanJobStatus::Cancelled,
+                Ok(media_files) => {
+                    if let Ok(mut results) = job.results.lock() {
+                        *results = Some(media_files);
+                    }
+                    ScanJobStatus::Completed
+                }
+            };
+            info.finished_at = Some(Utc::now());
+            info.clone()
+        }
+        Err(_) => return,
+    };
+    
+    info!(
+        "Scan job {} finished: {:?} ({} media files)",
+        snapshot.job_id, snapshot.status, snapshot.progress.media_found
+    );
+    if let Err(e) = app_handle.emit(SCAN_FINISHED_EVENT, &snapshot) {
+        error!("Failed to emit scan finished event: {}", e);
+    }
+}
+
+/// Starts a background scan of a directory
+///
+/// # Arguments
+///
+/// * `app` - Application handle (injected by Tauri)
+/// * `manager` - Scan job registry (injected by Tauri)
+/// * `pool` - Database connection pool used to load the ignore settings (injected by Tauri)
+/// * `path` - The directory path to scan
+/// * `recursive` - Whether to scan subdirectories recursively
+/// * `threads` - Optional worker thread count for the walk
+///
+/// # Returns
+///
+/// Returns the ID of the new job
+#[tauri::command]
+pub async fn start_scan(
+    app: AppHandle,
+    manager: State<'_, ScanJobManager>,
+    pool: State<'_, DbPool>,
+    path: String,
+    recursive: bool,
+    threads: Option<usize>,
+) -> Result<u64, String> {
+    manager.evict_finished(Utc::now());
+    
+    let job_id = manager.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+    let job = Arc::new(ScanJob {
+        info: Mutex::new(ScanJobInfo {
+            job_id,
+            path: path.clone(),
+            recursive,
+            status: ScanJobStatus::Running,
+            progress: ScanProgress::default(),
+            started_at: Utc::now(),
+            finished_at: None,
+        }),
+        cancelled: AtomicBool::new(false),
+        results: Mutex::new(None),
+    });
+    
+    manager
+        .jobs
+        .lock()
+        .map_err(|_| "Scan job registry is poisoned".to_string())?
+        .insert(job_id, job.clone());
+    
+    debug!("Starting scan job {} for {}", job_id, path);
+    let options = ScanOptions::new(recursive)
+        .with_threads(threads)
+        .with_ignore(ignore_rules::load_ignore_settings_or_default(&pool).await);
+    tauri::async_runtime::spawn_blocking(move || {
+        run_job(app, job, PathBuf::from(path), options);
+    });
+    
+    Ok(job_id)
+}
+
+/// Gets the current status and progress of a scan job
+///
+/// # Arguments
+///
+/// * `manager` - Scan job registry (injected by Tauri)
+/// * `job_id` - ID returned by `start_scan`
+#[tauri::command]
+pub fn get_scan_status(manager: State<'_, ScanJobManager>, job_id: u64) -> Result<ScanJobInfo, String> {
+    manager
+        .get(job_id)?
+        .snapshot()
+        .ok_or_else(|| "Scan job state is poisoned".to_string())
+}
+
+/// Requests cancellation of a running scan job
+///
+/// The walk stops at the next entry; a `scan-finished` event with the
+/// `cancelled` state follows. Jobs that already finished are left alone.
+///
+/// # Arguments
+///
+/// * `manager` - Scan job registry (injected by Tauri)
+/// * `job_id` - ID returned by `start_scan`
+#[tauri::command]
+pub fn cancel_scan(manager: State<'_, ScanJobManager>, job_id: u64) -> Result<(), String> {
+    let job = manager.get(job_id)?;
+    let info = job.info.lock().map_err(|_| "Scan job state is poisoned".to_string())?;
+    if info.status == ScanJobStatus::Running {
+        job.cancelled.store(true, Ordering::Relaxed);
+        debug!("Cancellation requested for scan job {}", job_id);
+    }
+    Ok(())
+}
+
+/// Takes the results of a finished scan job and forgets the job
+///
+/// # Arguments
+///
+/// * `manager` - Scan job registry (injected by Tauri)
+/// * `job_id` - ID returned by `start_scan`
+///
+/// # Returns
+///
+/// Returns the discovered media files sorted newest first. Cancelled or failed
+/// jobs return an error describing why no results exist.
+///
+/// # Errors
+///
+/// Returns an error if the job is unknown or still running.
+#[tauri::command]
+pub fn take_scan_results(
+    manager: State<'_, ScanJobManager>,
+    job_id: u64,
+) -> Result<Vec<MediaFile>, String> {
+    let job = manager.get(job_id)?;
+    let status = job
+        .snapshot()
+        .map(|info| info.status)
+        .ok_or_else(|| "Scan job state is poisoned".to_string())?;
+    
+    let results = match status {
+        ScanJobStatus::Running => return Err(format!("Scan job {} is still running", job_id)),
+        ScanJobStatus::Completed => job
+            .results
+            .lock()
+            .map_err(|_| "Scan job state is poisoned".to_string())?
+            .take()
+            .ok_or_else(|| format!("Results of scan job {} were already taken", job_id)),
+        ScanJobStatus::Cancelled => Err(format!("Scan job {} was cancelled", job_id)),
+        ScanJobStatus::Failed(message) => Err(message),
+    };
+    
+    if let Ok(mut jobs) = manager.jobs.lock() {
+        jobs.remove(&job_id);
+    }
+    
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn job(job_id: u64, status: ScanJobStatus, finished_at: Option<DateTime<Utc>>) -> Arc<ScanJob> {
+        Arc::new(ScanJob {
+            info: Mutex::new(ScanJobInfo {
+                job_id,
+                path: "/photos".to_string(),
+                recursive: true,
+                status,
+                progress: ScanProgress::default(),
+                started_at: Utc::now(),
+                finished_at,
+            }),
+            cancelled: AtomicBool::new(false),
+            results: Mutex::new(None),
+        })
+    }
+    
+    #[test]
+    fn test_evict_finished_keeps_running_and_recent_jobs() {
+        let now = Utc::now();
+        let manager = ScanJobManager::default();
+        {
+            let mut jobs = manager.jobs.lock().unwrap();
+            jobs.insert(1, job(1, ScanJobStatus::Running, None));
+            jobs.insert(2, job(2, ScanJobStatus::Completed, Some(now - chrono::Duration::minutes(1))));
+            jobs.insert(3, job(3, ScanJobStatus::Cancelled, Some(now - chrono::Duration::hours(1))));
+            jobs.insert(4, job(4, ScanJobStatus::Failed("gone".to_string()), Some(now - chrono::Duration::hours(1))));
+        }
+        
+        manager.evict_finished(now);
+        let mut remaining: Vec<u64> = manager.jobs.lock().unwrap().keys().copied().collect();
+        remaining.sort();
+        assert_eq!(remaining, [1, 2]);
+    }
+}
@@ -1,5 +1,12 @@
+use crate::database::DbPool;
+use crate::ignore_rules::{self, IgnoreChain, IgnoreRules, IgnoreSettings};
+use crate::media_detect::{self, Detection};
+use rayon::Scope;
 use serde::{Deserialize, Serialize};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tauri::State;
+use tracing::warn;
 use walkdir::WalkDir;
 
 /// Represents a media file with its metadata.
@@ -18,8 +25,11 @@ pub struct MediaFile {
     pub modified: i64,
     /// File type/extension (e.g., "jpg", "mp4")
     pub file_type: String,
-    /// Whether it's an image or video
+    /// Whether it's an image, video or audio file
     pub media_type: MediaType,
+    /// Whether the extension does not match the detected content
+    #[serde(default)]
+    pub extension_mismatch: bool,
 }
 
 /// Classification of media file types.
@@ -33,6 +43,8 @@ pub enum MediaType {
     Image,
     /// Video file (mp4, mov, etc.)
     Video,
+    /// Audio file (mp3, flac, etc.)
+    Audio,
     /// Unsupported or unknown file type
     Unknown,
 }
@@ -43,17 +55,22 @@ impl Default for MediaType {
     }
 }
 
-/// Supported image extensions
-const IMAGE_EXTENSIONS: &[&str] = &[
-    "jpg", "jpeg", "png", "gif", "bmp", "webp", "heic", "heif", "tiff", "tif", "svg",
-];
-
-/// Supported video extensions
-const VIDEO_EXTENSIONS: &[&str] = &[
-    "mp4", "mov", "avi", "mkv", "webm", "flv", "wmv", "m4v", "mpg", "mpeg",
-];
+impl MediaType {
+    /// Returns the lowercase name used in serialized data and the database.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Image => "image",
+            Self::Video => "video",
+            Self::Audio => "audio",
+            Self::Unknown => "unknown",
+        }
+    }
+}
 
-/// Determines the media type based on file extension.
+/// Determines the media type based on file extension alone.
+///
+/// Files on disk are classified by content first (see [`media_detect`]); this
+/// is the fallback used when the content is not recognized.
 ///
 /// # Arguments
 ///
@@ -62,25 +79,82 @@ const VIDEO_EXTENSIONS: &[&str] = &[
 /// # Returns
 ///
 /// Returns the corresponding `MediaType` for the extension.
-fn determine_media_type(extension: &str) -> MediaType {
-    if IMAGE_EXTENSIONS.contains(&extension) {
-        MediaType::Image
-    } else if VIDEO_EXTENSIONS.contains(&extension) {
-        MediaType::Video
-    } else {
-        MediaType::Unknown
+pub fn determine_media_type(extension: &str) -> MediaType {
+    media_detect::format_for_extension(extension)
+        .map(|format| format.media_type)
+        .unwrap_or_default()
+}
+
+/// Options controlling how a directory is walked.
+///
+/// The default is a recursive, single-threaded walk with the default ignore
+/// settings. Commands replace the latter with the user's saved settings.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScanOptions {
+    /// Whether to scan subdirectories recursively
+    pub recursive: bool,
+    /// Number of worker threads for the walk: `1` walks sequentially, `0`
+    /// uses one thread per CPU, any other value uses that many threads
+    pub threads: usize,
+    /// Exclude globs and hidden-file handling (see [`crate::ignore_rules`])
+    pub ignore: IgnoreSettings,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        Self {
+            recursive: true,
+            threads: 1,
+            ignore: IgnoreSettings::default(),
+        }
+    }
+}
+
+impl ScanOptions {
+    /// Creates sequential scan options.
+    pub fn new(recursive: bool) -> Self {
+        Self {
+            recursive,
+            ..Self::default()
+        }
+    }
+    
+    /// Sets the worker thread count, keeping the current value for `None`.
+    pub fn with_threads(mut self, threads: Option<usize>) -> Self {
+        if let Some(threads) = threads {
+            self.threads = threads;
+        }
+        self
+    }
+    
+    /// Sets the ignore settings.
+    pub fn with_ignore(mut self, ignore: IgnoreSettings) -> Self {
+        self.ignore = ignore;
+        self
+    }
+    
+    /// Whether the walk should use the parallel traversal.
+    ///
+    /// Non-recursive scans only list a single directory, so they never gain
+    /// anything from extra threads.
+    fn is_parallel(&self) -> bool {
+        self.recursive && self.threads != 1
     }
 }
 
 /// Scans a directory for media files.
 ///
 /// Recursively or non-recursively scans the specified directory path for
-/// supported image and video files, collecting metadata for each discovered file.
+/// supported image, video and audio files, collecting metadata for each discovered file.
+/// Paths matched by `.fmlmignore` files or the saved exclude globs are skipped.
 ///
 /// # Arguments
 ///
+/// * `pool` - Database connection pool used to load the ignore settings (injected by Tauri)
 /// * `path` - The directory path to scan (as a String for Tauri command compatibility)
 /// * `recursive` - Whether to scan subdirectories recursively
+/// * `threads` - Optional worker thread count (see [`ScanOptions::threads`]);
+///   defaults to a sequential walk
 ///
 /// # Returns
 ///
@@ -93,17 +167,81 @@ fn determine_media_type(extension: &str) -> MediaType {
 /// * The path does not exist
 /// * The path is not a directory
 ///
+#[tauri::command]
+pub async fn scan_directory(
+    pool: State<'_, DbPool>,
+    path: String,
+    recursive: bool,
+    threads: Option<usize>,
+) -> Result<Vec<MediaFile>, String> {
+    let options = ScanOptions::new(recursive)
+        .with_threads(threads)
+        .with_ignore(ignore_rules::load_ignore_settings_or_default(&pool).await);
+    
+    // Walking the tree is blocking IO, keep it off the async runtime
+    tokio::task::spawn_blocking(move || scan_directory_with_options(Path::new(&path), &options))
+        .await
+        .map_err(|e| format!("Scan task failed: {}", e))?
+}
+
+/// Scans a directory for media files with explicit options.
+///
+/// # Arguments
+///
+/// * `path` - The directory path to scan
+/// * `options` - Recursion, threading and ignore options
+///
+/// # Returns
+///
+/// Returns all discovered media files sorted by modification date (newest first).
+///
+/// # Errors
+///
+/// Returns an error if the path does not exist or is not a directory.
+///
 /// # Examples
 ///
 /// ```no_run
-/// # use fmlm_lib::scan_directory;
-/// let files = scan_directory("/path/to/photos".to_string(), true)?;
+/// # use fmlm_lib::scanner::{scan_directory_with_options, ScanOptions};
+/// # use std::path::Path;
+/// let options = ScanOptions::new(true).with_threads(Some(8));
+/// let files = scan_directory_with_options(Path::new("/path/to/photos"), &options)?;
 /// # Ok::<(), String>(())
 /// ```
-#[tauri::command]
-pub fn scan_directory(path: String, recursive: bool) -> Result<Vec<MediaFile>, String> {
-    let path = Path::new(&path);
+pub fn scan_directory_with_options(
+    path: &Path,
+    options: &ScanOptions,
+) -> Result<Vec<MediaFile>, String> {
+    validate_directory(path)?;
     
+    let mut media_files = collect_media_files(path, options);
+    sort_newest_first(&mut media_files);
+    
+    Ok(media_files)
+}
+
+/// Sorts media files by modification date (newest first).
+///
+/// Ties are broken by path so sequential and parallel walks, which visit
+/// files in different orders, produce identical results.
+pub fn sort_newest_first(media_files: &mut [MediaFile]) {
+    media_files.sort_by(|a, b| {
+        b.modified
+            .cmp(&a.modified)
+            .then_with(|| a.path.cmp(&b.path))
+    });
+}
+
+/// Checks that a path exists and is a directory.
+///
+/// # Arguments
+///
+/// * `path` - The path to validate
+///
+/// # Errors
+///
+/// Returns an error message if the path does not exist or is not a directory.
+pub fn validate_directory(path: &Path) -> Result<(), String> {
     // Validate path exists
     if !path.exists() {
         return Err(format!("Path does not exist: {}", path.display()));
@@ -114,6 +252,94 @@ pub fn scan_directory(path: String, recursive: bool) -> Result<Vec<MediaFile>, S
         return Err(format!("Path is not a directory: {}", path.display()));
     }
     
+    Ok(())
+}
+
+/// Receives notifications while a directory is being walked.
+///
+/// Lets long-running callers such as background scan jobs report progress and
+/// stop the walk early. All methods have no-op defaults. Parallel walks call
+/// the observer from several threads at once, hence the `Sync` bound.
+pub trait ScanObserver: Sync {
+    /// Called when the walk enters a directory
+    fn on_directory(&self, _path: &Path) {}
+    
+    /// Called for every file visited, with the media file if it is supported
+    fn on_file(&self, _path: &Path, _media_file: Option<&MediaFile>) {}
+    
+    /// Called when an entry cannot be read
+    fn on_error(&self, _path: Option<&Path>, _error: &dyn std::error::Error) {}
+    
+    /// Checked before each entry; returning `true` stops the walk
+    fn is_cancelled(&self) -> bool {
+        false
+    }
+}
+
+/// Observer that ignores every notification
+struct NoopObserver;
+
+impl ScanObserver for NoopObserver {}
+
+/// Walks a directory and collects every supported media file.
+///
+/// Unlike [`scan_directory`], the result is returned in walk order and the
+/// path is not validated, so callers that only need the set of files (such as
+/// the incremental rescan) can skip the sort.
+///
+/// # Arguments
+///
+/// * `path` - The directory to walk
+/// * `options` - Recursion, threading and ignore options
+///
+/// # Returns
+///
+/// Returns all discovered media files. Entries that cannot be read or are
+/// ignored are skipped.
+pub fn collect_media_files(path: &Path, options: &ScanOptions) -> Vec<MediaFile> {
+    walk_media_files(path, options, &NoopObserver)
+}
+
+/// Walks a directory like [`collect_media_files`], reporting to an observer.
+///
+/// # Arguments
+///
+/// * `path` - The directory to walk
+/// * `options` - Recursion, threading and ignore options
+/// * `observer` - Receives progress notifications and can cancel the walk
+///
+/// # Returns
+///
+/// Returns the media files discovered so far. If the observer cancels the
+/// walk, the result is partial.
+pub fn walk_media_files(
+    path: &Path,
+    options: &ScanOptions,
+    observer: &impl ScanObserver,
+) -> Vec<MediaFile> {
+    let rules = IgnoreRules::new(path, &options.ignore);
+    
+    if options.is_parallel() {
+        match rayon::ThreadPoolBuilder::new().num_threads(options.threads).build() {
+            Ok(pool) => return walk_parallel(&pool, path, &rules, observer),
+            Err(e) => warn!("Cannot create scan thread pool, walking sequentially: {}", e),
+        }
+    }
+    
+    walk_sequential(path, options.recursive, &rules, observer)
+}
+
+/// Single-threaded walk based on `walkdir`.
+///
+/// Ignored entries are filtered before `walkdir` descends into them. Since the
+/// walk is depth-first, the `.fmlmignore` rules of the current branch are kept
+/// in a stack indexed by directory depth.
+fn walk_sequential(
+    path: &Path,
+    recursive: bool,
+    rules: &IgnoreRules,
+    observer: &impl ScanObserver,
+) -> Vec<MediaFile> {
     let mut media_files = Vec::new();
     
     let walker = if recursive {
@@ -122,49 +348,301 @@ pub fn scan_directory(path: String, recursive: bool) -> Result<Vec<MediaFile>, S
         WalkDir::new(path).max_depth(1).follow_links(true)
     };
     
-    for entry in walker.into_iter().filter_map(Result::ok) {
+    let mut chains: Vec<IgnoreChain> = Vec::new();
+    let walker = walker.into_iter().filter_entry(|entry| {
+        let depth = entry.depth();
+        chains.truncate(depth);
+        
+        let parent = depth
+            .checked_sub(1)
+            .and_then(|index| chains.get(index))
+            .cloned()
+            .flatten();
+        let is_dir = entry.file_type().is_dir();
+        
+        // The scan root itself is never ignored
+        if depth > 0 && rules.is_ignored(&parent, entry.path(), is_dir) {
+            return false;
+        }
+        if is_dir {
+            chains.push(rules.enter_directory(entry.path(), &parent));
+        }
+        true
+    });
+    
+    for entry in walker {
+        if observer.is_cancelled() {
+            break;
+        }
+        
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                observer.on_error(e.path(), &e);
+                continue;
+            }
+        };
+        
         // Skip directories, only process files
         if entry.file_type().is_dir() {
+            observer.on_directory(entry.path());
             continue;
         }
         
         let file_path = entry.path();
         
-        // Extract and normalize file extension
-        let extension = file_path
-            .extension()
-            .and_then(|ext| ext.to_str())
-            .map(str::to_lowercase)
-            .unwrap_or_default();
+        // Skip unsupported file types
+        let Some(detection) = media_detect::detect_file(file_path) else {
+            observer.on_file(file_path, None);
+            continue;
+        };
+        
+        // Extract file metadata
+        match entry.metadata() {
+            Ok(metadata) => {
+                let media_file = build_media_file(file_path, &metadata, detection);
+                observer.on_file(file_path, Some(&media_file));
+                media_files.push(media_file);
+            }
+            Err(e) => observer.on_error(Some(file_path), &e),
+        }
+    }
+    
+    media_files
+}
+
+/// Multi-threaded recursive walk.
+///
+/// Every subdirectory becomes its own task on the rayon pool, so idle threads
+/// steal pending directories from busy ones. This keeps all threads waiting
+/// on IO at once, which is what makes the difference on spinning disks and
+/// network mounts where each `read_dir`/`stat` has high latency. Each task
+/// collects its files locally and merges them once per directory.
+fn walk_parallel<O: ScanObserver>(
+    pool: &rayon::ThreadPool,
+    root: &Path,
+    rules: &IgnoreRules,
+    observer: &O,
+) -> Vec<MediaFile> {
+    let walk = ParallelWalk {
+        rules,
+        observer,
+        results: Mutex::new(Vec::new()),
+    };
+    
+    let ancestors = Arc::new(Ancestors {
+        path: std::fs::canonicalize(root).unwrap_or_else(|_| root.to_path_buf()),
+        parent: None,
+    });
+    pool.scope(|scope| visit_directory(scope, root.to_path_buf(), None, ancestors, &walk));
+    
+    walk.results.into_inner().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// State shared by all tasks of a parallel walk
+struct ParallelWalk<'a, O> {
+    rules: &'a IgnoreRules,
+    observer: &'a O,
+    results: Mutex<Vec<MediaFile>>,
+}
+
+/// Canonical path of a directory of the parallel walk, linked to its ancestors
+struct Ancestors {
+    path: PathBuf,
+    parent: Option<Arc<Ancestors>>,
+}
+
+impl Ancestors {
+    /// Checks whether `path` is this directory or one of its ancestors
+    fn contains(&self, path: &Path) -> bool {
+        let mut current = Some(self);
+        while let Some(ancestors) = current {
+            if ancestors.path == path {
+                return true;
+            }
+            current = ancestors.parent.as_deref();
+        }
+        false
+    }
+}
+
+/// Lists one directory for the parallel walk, spawning tasks for subdirectories.
+///
+/// Symbolic links are followed like in the sequential walk; a directory that
+/// resolves to one of the directories above it (directly or through several
+/// links) is reported as an error instead of being followed forever, as
+/// `walkdir` does.
+///
+/// `parent` holds the `.fmlmignore` rules in effect in the parent directory,
+/// and `ancestors` the canonical paths of `dir` and the directories above it.
+fn visit_directory<'s, O: ScanObserver>(
+    scope: &Scope<'s>,
+    dir: PathBuf,
+    parent: IgnoreChain,
+    ancestors: Arc<Ancestors>,
+    walk: &'s ParallelWalk<'s, O>,
+) {
+    let observer = walk.observer;
+    if observer.is_cancelled() {
+        return;
+    }
+    observer.on_directory(&dir);
+    
+    let chain = walk.rules.enter_directory(&dir, &parent);
+    
+    let entries = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            observer.on_error(Some(&dir), &e);
+            return;
+        }
+    };
+    
+    let mut media_files = Vec::new();
+    
+    for entry in entries {
+        if observer.is_cancelled() {
+            break;
+        }
         
-        // Determine media type based on extension
-        let media_type = determine_media_type(&extension);
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                observer.on_error(Some(&dir), &e);
+                continue;
+            }
+        };
+        let file_path = entry.path();
         
-        // Skip unsupported file types
-        if media_type == MediaType::Unknown {
+        // The directory entry type is free on most platforms; only symlinks
+        // need an extra stat to find out what they point to
+        let file_type = match entry.file_type() {
+            Ok(file_type) => file_type,
+            Err(e) => {
+                observer.on_error(Some(&file_path), &e);
+                continue;
+            }
+        };
+        let is_dir = if file_type.is_symlink() {
+            match std::fs::metadata(&file_path) {
+                Ok(metadata) => metadata.is_dir(),
+                Err(e) => {
+                    observer.on_error(Some(&file_path), &e);
+                    continue;
+                }
+            }
+        } else {
+            file_type.is_dir()
+        };
+        
+        if walk.rules.is_ignored(&chain, &file_path, is_dir) {
             continue;
         }
         
-        // Extract file metadata
-        if let Ok(metadata) = entry.metadata() {
-            let modified = extract_modified_timestamp(&metadata);
-            let file_name = extract_file_name(file_path);
+        if is_dir {
+            // Only links need resolving; a plain subdirectory extends its parent's path
+            let canonical = if file_type.is_symlink() {
+                match std::fs::canonicalize(&file_path) {
+                    Ok(canonical) => canonical,
+                    Err(e) => {
+                        observer.on_error(Some(&file_path), &e);
+                        continue;
+                    }
+                }
+            } else {
+                ancestors.path.join(entry.file_name())
+            };
+            if ancestors.contains(&canonical) {
+                let error = std::io::Error::other("symbolic link loop detected");
+                observer.on_error(Some(&file_path), &error);
+                continue;
+            }
             
-            media_files.push(MediaFile {
-                path: file_path.to_string_lossy().to_string(),
-                name: file_name,
-                size: metadata.len(),
-                modified,
-                file_type: extension,
-                media_type,
+            let chain = chain.clone();
+            let ancestors = Arc::new(Ancestors {
+                path: canonical,
+                parent: Some(ancestors.clone()),
             });
+            scope.spawn(move |scope| visit_directory(scope, file_path, chain, ancestors, walk));
+            continue;
+        }
+        
+        // Skip unsupported file types
+        let Some(detection) = media_detect::detect_file(&file_path) else {
+            observer.on_file(&file_path, None);
+            continue;
+        };
+        
+        // Follows symlinks, matching `walkdir` with `follow_links(true)`
+        match std::fs::metadata(&file_path) {
+            Ok(metadata) => {
+                let media_file = build_media_file(&file_path, &metadata, detection);
+                observer.on_file(&file_path, Some(&media_file));
+                media_files.push(media_file);
+            }
+            Err(e) => observer.on_error(Some(&file_path), &e),
         }
     }
     
-    // Sort by modification date (newest first)
-    media_files.sort_by(|a, b| b.modified.cmp(&a.modified));
+    if !media_files.is_empty() {
+        if let Ok(mut results) = walk.results.lock() {
+            results.append(&mut media_files);
+        }
+    }
+}
+
+/// Builds a `MediaFile` for a single path.
+///
+/// Used when individual files need to be (re)indexed outside of a directory
+/// walk, e.g. in response to filesystem events.
+///
+/// # Arguments
+///
+/// * `path` - Path to the file
+///
+/// # Returns
+///
+/// Returns `None` if the path is not a supported media file or its metadata
+/// cannot be read.
+pub fn media_file_from_path(path: &Path) -> Option<MediaFile> {
+    let metadata = std::fs::metadata(path).ok()?;
+    if !metadata.is_file() {
+        return None;
+    }
     
-    Ok(media_files)
+    let detection = media_detect::detect_file(path)?;
+    Some(build_media_file(path, &metadata, detection))
+}
+
+/// Assembles a `MediaFile` from a path, its filesystem metadata and its
+/// detected format.
+///
+/// The file type is the lowercase extension, unless the extension does not
+/// match the content, in which case the detected format's name is used.
+fn build_media_file(
+    file_path: &Path,
+    metadata: &std::fs::Metadata,
+    detection: Detection,
+) -> MediaFile {
+    let file_type = if detection.extension_mismatch {
+        detection.format.name.to_string()
+    } else {
+        file_path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_lowercase)
+            .unwrap_or_default()
+    };
+    
+    MediaFile {
+        path: file_path.to_string_lossy().to_string(),
+        name: extract_file_name(file_path),
+        size: metadata.len(),
+        modified: extract_modified_timestamp(metadata),
+        file_type,
+        media_type: detection.format.media_type,
+        extension_mismatch: detection.extension_mismatch,
+    }
 }
 
 /// Extracts the Unix timestamp from file metadata.
@@ -224,6 +702,13 @@ mod tests {
         assert_eq!(determine_media_type("mkv"), MediaType::Video);
     }
     
+    #[test]
+    fn test_determine_media_type_audio() {
+        assert_eq!(determine_media_type("mp3"), MediaType::Audio);
+        assert_eq!(determine_media_type("flac"), MediaType::Audio);
+        assert_eq!(determine_media_type("opus"), MediaType::Audio);
+    }
+    
     #[test]
     fn test_determine_media_type_unknown() {
         assert_eq!(determine_media_type("txt"), MediaType::Unknown);
@@ -245,15 +730,119 @@ mod tests {
             modified: 1234567890,
             file_type: "jpg".to_string(),
             media_type: MediaType::Image,
+            extension_mismatch: false,
         };
         
         let file2 = file1.clone();
         assert_eq!(file1, file2);
     }
     
+    /// Creates a small directory tree with media and non-media files
+    fn create_test_tree(name: &str) -> std::path::PathBuf {
+        let root = std::env::temp_dir().join(format!("fmlm_scanner_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        
+        for dir in ["a", "a/b", "a/b/c", "d"] {
+            std::fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        for file in ["top.jpg", "a/clip.mp4", "a/b/photo.png", "a/b/c/deep.heic", "d/notes.txt", "d/x.gif"] {
+            std::fs::write(root.join(file), file.as_bytes()).unwrap();
+        }
+        
+        root
+    }
+    
+    #[test]
+    fn test_parallel_walk_matches_sequential() {
+        let root = create_test_tree("parallel");
+        
+        let mut sequential = collect_media_files(&root, &ScanOptions::new(true));
+        let mut parallel = collect_media_files(&root, &ScanOptions::new(true).with_threads(Some(4)));
+        sort_newest_first(&mut sequential);
+        sort_newest_first(&mut parallel);
+        
+        assert_eq!(sequential.len(), 5);
+        assert_eq!(sequential, parallel);
+        
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+    
+    #[test]
+    fn test_non_recursive_walk_ignores_threads() {
+        let root = create_test_tree("non_recursive");
+        
+        let files = collect_media_files(&root, &ScanOptions::new(false).with_threads(Some(4)));
+        
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].name, "top.jpg");
+        
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+    
+    #[test]
+    fn test_media_file_from_path_sniffs_content() {
+        let root = create_test_tree("sniff");
+        std::fs::write(root.join("recovered.bin"), [0xFF, 0xD8, 0xFF, 0xE0, 0, 0x10]).unwrap();
+        
+        let file = media_file_from_path(&root.join("recovered.bin")).unwrap();
+        assert_eq!(file.media_type, MediaType::Image);
+        assert_eq!(file.file_type, "jpg");
+        assert!(file.extension_mismatch);
+        
+        // Unrecognized content falls back to the extension
+        let file = media_file_from_path(&root.join("top.jpg")).unwrap();
+        assert_eq!(file.file_type, "jpg");
+        assert!(!file.extension_mismatch);
+        assert!(media_file_from_path(&root.join("d/notes.txt")).is_none());
+        
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+    
+    #[test]
+    fn test_walks_skip_ignored_paths() {
+        let root = create_test_tree("ignored");
+        std::fs::create_dir_all(root.join("a/@eaDir")).unwrap();
+        std::fs::write(root.join("a/@eaDir/preview.jpg"), b"preview").unwrap();
+        std::fs::write(root.join(".hidden.jpg"), b"hidden").unwrap();
+        std::fs::write(root.join("a").join(ignore_rules::IGNORE_FILE_NAME), b"b/\n").unwrap();
+        
+        for threads in [1, 4] {
+            let options = ScanOptions::new(true).with_threads(Some(threads));
+            let mut names: Vec<String> = collect_media_files(&root, &options)
+                .into_iter()
+                .map(|file| file.name)
+                .collect();
+            names.sort();
+            
+            assert_eq!(names, ["clip.mp4", "top.jpg", "x.gif"]);
+        }
+        
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+    
+    #[cfg(unix)]
+    #[test]
+    fn test_walks_stop_at_symlink_cycles() {
+        let root = create_test_tree("symlink_cycle");
+        // a/to_d -> d and d/to_a -> a: neither link points at its own parent
+        std::os::unix::fs::symlink(root.join("d"), root.join("a/to_d")).unwrap();
+        std::os::unix::fs::symlink(root.join("a"), root.join("d/to_a")).unwrap();
+        
+        let mut sequential = collect_media_files(&root, &ScanOptions::new(true));
+        let mut parallel = collect_media_files(&root, &ScanOptions::new(true).with_threads(Some(4)));
+        sort_newest_first(&mut sequential);
+        sort_newest_first(&mut parallel);
+        
+        // Both directories are walked once directly and once through the other's link
+        assert_eq!(sequential.len(), 9);
+        assert_eq!(sequential, parallel);
+        
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+    
     #[test]
     fn test_scan_directory_invalid_path() {
-        let result = scan_directory("/nonexistent/path".to_string(), false);
+        let result = scan_directory_with_options(Path::new("/nonexistent/path"), &ScanOptions::new(false));
         assert!(result.is_err());
         assert!(result.unwrap_err().contains("does not exist"));
     }
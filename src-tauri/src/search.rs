@@ -0,0 +1,746 @@
+//! Full-text and structured media search
+//!
+//! Free text is matched through the `media_search` FTS5 table (file name,
+//! path, `metadata_json` values and user tags), which triggers keep in sync
+//! with `media_metadata`. Structured filters are translated into SQL
+//! conditions so filtering happens in SQLite instead of in the frontend.
+//!
+//! [`query_media`] adds sorting and keyset pagination on top of the same
+//! filters, so the grid can load one page at a time.
+
+use crate::database::{DatabaseResult, DbPool, MediaMetadata};
+use crate::location::{self, BoundingBox};
+use crate::tags::{self, TagExpr};
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{QueryBuilder, Row, Sqlite};
+use tauri::State;
+
+/// Number of results returned when the caller gives no limit
+pub const DEFAULT_SEARCH_LIMIT: i64 = 500;
+
+/// Largest page size accepted by [`query_media`]
+pub const MAX_PAGE_SIZE: i64 = 1000;
+
+/// Search criteria; every field is optional and set fields are combined with AND
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchFilters {
+    /// Free text matched against names, paths, metadata values and tags
+    pub text: Option<String>,
+    /// Restrict to one scanned folder
+    pub folder_id: Option<i64>,
+    /// Allowed media types (`image`, `video`, `audio`); empty means any
+    pub media_types: Vec<String>,
+    /// Allowed file extensions (e.g. `jpg`); empty means any
+    pub file_types: Vec<String>,
+    /// Earliest capture date (falls back to the modification date)
+    pub date_from: Option<DateTime<Utc>>,
+    /// Latest capture date (falls back to the modification date)
+    pub date_to: Option<DateTime<Utc>>,
+    /// Minimum file size in bytes
+    pub min_size: Option<i64>,
+    /// Maximum file size in bytes
+    pub max_size: Option<i64>,
+    /// Minimum width in pixels
+    pub min_width: Option<i64>,
+    /// Maximum width in pixels
+    pub max_width: Option<i64>,
+    /// Minimum height in pixels
+    pub min_height: Option<i64>,
+    /// Maximum height in pixels
+    pub max_height: Option<i64>,
+    /// Minimum duration in seconds
+    pub min_duration: Option<f64>,
+    /// Maximum duration in seconds
+    pub max_duration: Option<f64>,
+    /// Video codec name (e.g. `h264`), case-insensitive
+    pub video_codec: Option<String>,
+    /// Audio codec name (e.g. `aac`), case-insensitive
+    pub audio_codec: Option<String>,
+    /// Camera manufacturer (e.g. `Canon`), case-insensitive
+    pub camera_make: Option<String>,
+    /// Camera model (e.g. `Canon EOS R5`), case-insensitive
+    pub camera_model: Option<String>,
+    /// Lens model, case-insensitive
+    pub lens_model: Option<String>,
+    /// Minimum ISO sensitivity
+    pub min_iso: Option<i64>,
+    /// Maximum ISO sensitivity
+    pub max_iso: Option<i64>,
+    /// Minimum focal length in millimeters
+    pub min_focal_length: Option<f64>,
+    /// Maximum focal length in millimeters
+    pub max_focal_length: Option<f64>,
+    /// Minimum f-number (widest aperture)
+    pub min_aperture: Option<f64>,
+    /// Maximum f-number (narrowest aperture)
+    pub max_aperture: Option<f64>,
+    /// `true` for photos taken with flash, `false` for photos taken without
+    pub flash: Option<bool>,
+    /// `true` for media with GPS coordinates, `false` for media without
+    pub has_location: Option<bool>,
+    /// Area the media must have been taken in
+    pub bounds: Option<BoundingBox>,
+    /// Country name (e.g. `Portugal`), case-insensitive
+    pub country: Option<String>,
+    /// Region name (e.g. `Lisbon`), case-insensitive
+    pub region: Option<String>,
+    /// City name, case-insensitive
+    pub city: Option<String>,
+    /// Tag expression such as `Places/Portugal AND NOT Work`; tags include their descendants
+    pub tags: Option<TagExpr>,
+    /// `true` for media with at least one tag, `false` for untagged media
+    pub has_tags: Option<bool>,
+    /// Minimum star rating (0-5)
+    pub min_rating: Option<i64>,
+    /// Maximum star rating (0-5)
+    pub max_rating: Option<i64>,
+    /// `true` for favorites only, `false` to exclude them
+    pub favorite: Option<bool>,
+    /// Allowed color labels (e.g. `red`); empty means any
+    pub color_labels: Vec<String>,
+    /// Allowed pick flags (`pick`, `reject`, or `none` for unflagged); empty means any
+    pub pick_flags: Vec<String>,
+}
+
+/// Field a media query is ordered by
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortKey {
+    /// Capture date, falling back to the modification date
+    #[default]
+    CaptureDate,
+    ModifiedDate,
+    /// File name, case-insensitive
+    Name,
+    Size,
+    /// Duration (files without one sort as 0)
+    Duration,
+    /// Pixel count (width × height)
+    Resolution,
+    /// Star rating
+    Rating,
+}
+
+impl SortKey {
+    /// SQL expression the key orders by; never NULL so keyset comparisons hold
+    fn expression(self) -> &'static str {
+        match self {
+            SortKey::CaptureDate => "COALESCE(media_metadata.created_date, media_metadata.modified_date)",
+            SortKey::ModifiedDate => "media_metadata.modified_date",
+            SortKey::Name => "media_metadata.file_name COLLATE NOCASE",
+            SortKey::Size => "media_metadata.file_size",
+            SortKey::Duration => "COALESCE(media_metadata.duration, 0.0)",
+            SortKey::Resolution => "COALESCE(media_metadata.width, 0) * COALESCE(media_metadata.height, 0)",
+            SortKey::Rating => "media_metadata.rating",
+        }
+    }
+    
+    /// Value of the sort expression for a row
+    fn value_of(self, media: &MediaMetadata) -> CursorValue {
+        match self {
+            SortKey::CaptureDate => CursorValue::Date(media.created_date.unwrap_or(media.modified_date)),
+            SortKey::ModifiedDate => CursorValue::Date(media.modified_date),
+            SortKey::Name => CursorValue::Text(media.file_name.clone()),
+            SortKey::Size => CursorValue::Int(media.file_size),
+            SortKey::Duration => CursorValue::Real(media.duration.unwrap_or(0.0)),
+            SortKey::Resolution => CursorValue::Int(media.width.unwrap_or(0) * media.height.unwrap_or(0)),
+            SortKey::Rating => CursorValue::Int(media.rating),
+        }
+    }
+}
+
+/// Sort direction
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortDirection {
+    Asc,
+    #[default]
+    Desc,
+}
+
+/// One page request of a media query
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MediaQuery {
+    /// Filters applied before sorting
+    pub filters: SearchFilters,
+    pub sort: SortKey,
+    pub direction: SortDirection,
+    /// `next_cursor` of the previous page; omitted for the first page
+    pub cursor: Option<String>,
+    /// Page size (defaults to 500, capped at 1000)
+    pub limit: Option<i64>,
+}
+
+/// One page of media rows
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MediaPage {
+    pub items: Vec<MediaMetadata>,
+    /// Cursor for the following page, `None` on the last page
+    pub next_cursor: Option<String>,
+    /// Number of rows matching the filters across all pages
+    pub total_count: i64,
+}
+
+/// Sort value stored in a cursor
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+enum CursorValue {
+    Date(DateTime<Utc>),
+    Text(String),
+    Int(i64),
+    Real(f64),
+}
+
+/// Position after the last row of a page (sort value plus row ID as tie-breaker)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Cursor {
+    sort: SortKey,
+    value: CursorValue,
+    id: i64,
+}
+
+impl Cursor {
+    fn encode(&self) -> String {
+        let json = serde_json::to_vec(self).unwrap_or_default();
+        general_purpose::URL_SAFE_NO_PAD.encode(json)
+    }
+    
+    fn decode(cursor: &str) -> Result<Self, String> {
+        general_purpose::URL_SAFE_NO_PAD
+            .decode(cursor)
+            .ok()
+            .and_then(|json| serde_json::from_slice(&json).ok())
+            .ok_or_else(|| "Invalid cursor".to_string())
+    }
+}
+
+/// Turns user input into an FTS5 query.
+///
+/// Each word becomes a quoted prefix term, so punctuation in the input can't
+/// produce FTS syntax errors and partial words still match. Terms are
+/// implicitly combined with AND.
+///
+/// # Returns
+///
+/// Returns `None` if the input contains no words.
+pub fn fts_query(text: &str) -> Option<String> {
+    let terms: Vec<String> = text
+        .split_whitespace()
+        .map(|word| format!("\"{}\"*", word.replace('"', "\"\"")))
+        .collect();
+    
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(" "))
+    }
+}
+
+/// Appends the `FROM` clause and the filter conditions for `filters`.
+///
+/// The builder must hold a `SELECT` over `media_metadata` columns up to the
+/// `FROM`. The appended clause ends with a `WHERE`, so callers can chain
+/// further `AND` conditions, ordering and limits.
+///
+/// # Returns
+///
+/// Returns `true` if the full-text table was joined (and `media_search.rank`
+/// is available for ordering).
+pub(crate) fn push_search_clause(builder: &mut QueryBuilder<'_, Sqlite>, filters: &SearchFilters) -> bool {
+    let text_query = filters.text.as_deref().and_then(fts_query);
+    
+    builder.push(" FROM media_metadata");
+    if text_query.is_some() {
+        builder.push(" JOIN media_search ON media_search.rowid = media_metadata.id");
+    }
+    // Trashed rows only show up in the trash view
+    builder.push(" WHERE media_metadata.trashed_at IS NULL");
+    
+    if let Some(query) = &text_query {
+        builder.push(" AND media_search MATCH ").push_bind(query.clone());
+    }
+    
+    if let Some(folder_id) = filters.folder_id {
+        builder.push(" AND media_metadata.folder_id = ").push_bind(folder_id);
+    }
+    
+    push_in_list(builder, "media_metadata.media_type", &filters.media_types);
+    push_in_list(builder, "media_metadata.file_type", &filters.file_types);
+    
+    let capture_date = " AND COALESCE(media_metadata.created_date, media_metadata.modified_date)";
+    if let Some(from) = filters.date_from {
+        builder.push(capture_date).push(" >= ").push_bind(from);
+    }
+    if let Some(to) = filters.date_to {
+        builder.push(capture_date).push(" <= ").push_bind(to);
+    }
+    
+    push_range(builder, "media_metadata.file_size", filters.min_size, filters.max_size);
+    push_range(builder, "media_metadata.width", filters.min_width, filters.max_width);
+    push_range(builder, "media_metadata.height", filters.min_height, filters.max_height);
+    push_range(builder, "media_metadata.duration", filters.min_duration, filters.max_duration);
+    
+    push_equal_nocase(builder, "media_metadata.video_codec", &filters.video_codec);
+    push_equal_nocase(builder, "media_metadata.audio_codec", &filters.audio_codec);
+    
+    push_equal_nocase(builder, "media_metadata.camera_make", &filters.camera_make);
+    push_equal_nocase(builder, "media_metadata.camera_model", &filters.camera_model);
+    push_equal_nocase(builder, "media_metadata.lens_model", &filters.lens_model);
+    push_range(builder, "media_metadata.iso", filters.min_iso, filters.max_iso);
+    push_range(builder, "media_metadata.focal_length", filters.min_focal_length, filters.max_focal_length);
+    push_range(builder, "media_metadata.aperture", filters.min_aperture, filters.max_aperture);
+    if let Some(flash) = filters.flash {
+        builder.push(" AND media_metadata.flash = ").push_bind(flash);
+    }
+    if let Some(has_location) = filters.has_location {
+        builder.push(if has_location {
+            " AND media_metadata.latitude IS NOT NULL"
+        } else {
+            " AND media_metadata.latitude IS NULL"
+        });
+    }
+    if let Some(bounds) = &filters.bounds {
+        location::push_bounds_condition(builder, bounds);
+    }
+    push_equal_nocase(builder, "media_metadata.country", &filters.country);
+    push_equal_nocase(builder, "media_metadata.region", &filters.region);
+    push_equal_nocase(builder, "media_metadata.city", &filters.city);
+    
+    if let Some(expr) = &filters.tags {
+        builder.push(" AND ");
+        tags::push_tag_condition(builder, expr);
+    }
+    if let Some(has_tags) = filters.has_tags {
+        builder.push(if has_tags { " AND " } else { " AND NOT " });
+        builder.push("EXISTS (SELECT 1 FROM media_tags mt WHERE mt.media_id = media_metadata.id)");
+    }
+    
+    push_range(builder, "media_metadata.rating", filters.min_rating, filters.max_rating);
+    if let Some(favorite) = filters.favorite {
+        builder.push(" AND media_metadata.favorite = ").push_bind(favorite);
+    }
+    push_in_list(builder, "media_metadata.color_label", &filters.color_labels);
+    if !filters.pick_flags.is_empty() {
+        let (unflagged, flags): (Vec<String>, Vec<String>) = filters
+            .pick_flags
+            .iter()
+            .map(|flag| flag.to_lowercase())
+            .partition(|flag| flag == "none");
+        builder.push(" AND (");
+        if unflagged.is_empty() {
+            builder.push("0");
+        } else {
+            builder.push("media_metadata.pick_flag IS NULL");
+        }
+        if !flags.is_empty() {
+            builder.push(" OR media_metadata.pick_flag IN (");
+            let mut separated = builder.separated(", ");
+            for flag in flags {
+                separated.push_bind(flag);
+            }
+            builder.push(")");
+        }
+        builder.push(")");
+    }
+    
+    text_query.is_some()
+}
+
+/// Appends `AND column IN (...)` (case-insensitive) unless `values` is empty
+fn push_in_list(builder: &mut QueryBuilder<'_, Sqlite>, column: &str, values: &[String]) {
+    if values.is_empty() {
+        return;
+    }
+    
+    builder.push(format!(" AND lower({}) IN (", column));
+    let mut separated = builder.separated(", ");
+    for value in values {
+        separated.push_bind(value.to_lowercase());
+    }
+    builder.push(")");
+}
+
+/// Appends a case-insensitive `AND column = value` unless `value` is `None`
+fn push_equal_nocase(builder: &mut QueryBuilder<'_, Sqlite>, column: &str, value: &Option<String>) {
+    if let Some(value) = value {
+        builder
+            .push(format!(" AND {} = ", column))
+            .push_bind(value.clone())
+            .push(" COLLATE NOCASE");
+    }
+}
+
+/// Appends inclusive lower and upper bounds on a column
+fn push_range<'a, T>(builder: &mut QueryBuilder<'a, Sqlite>, column: &str, min: Option<T>, max: Option<T>)
+where
+    T: 'a + Send + sqlx::Encode<'a, Sqlite> + sqlx::Type<Sqlite>,
+{
+    if let Some(min) = min {
+        builder.push(format!(" AND {} >= ", column)).push_bind(min);
+    }
+    if let Some(max) = max {
+        builder.push(format!(" AND {} <= ", column)).push_bind(max);
+    }
+}
+
+/// Finds media rows matching the filters
+///
+/// Results are ordered by relevance when free text is given, otherwise
+/// newest first.
+///
+/// # Arguments
+///
+/// * `pool` - Database connection pool
+/// * `filters` - Search criteria
+/// * `limit` - Maximum number of rows to return
+pub async fn find_media(pool: &DbPool, filters: &SearchFilters, limit: i64) -> DatabaseResult<Vec<MediaMetadata>> {
+    let mut builder = QueryBuilder::<Sqlite>::new("SELECT media_metadata.*");
+    let ranked = push_search_clause(&mut builder, filters);
+    
+    builder.push(" ORDER BY ");
+    if ranked {
+        builder.push("media_search.rank, ");
+    }
+    builder
+        .push("media_metadata.modified_date DESC, media_metadata.id DESC LIMIT ")
+        .push_bind(limit);
+    
+    let media = builder
+        .build_query_as::<MediaMetadata>()
+        .fetch_all(pool)
+        .await?;
+    
+    Ok(media)
+}
+
+/// Counts the media rows matching the filters
+pub async fn count_media(pool: &DbPool, filters: &SearchFilters) -> DatabaseResult<i64> {
+    let mut builder = QueryBuilder::<Sqlite>::new("SELECT COUNT(*)");
+    push_search_clause(&mut builder, filters);
+    let count: i64 = builder.build().fetch_one(pool).await?.get(0);
+    Ok(count)
+}
+
+/// Fetches one page of media rows
+///
+/// Pages are addressed by keyset (the sort value and ID of the last row seen)
+/// rather than by offset, so deep pages are as cheap as the first and rows
+/// inserted meanwhile don't shift later pages.
+///
+/// # Arguments
+///
+/// * `pool` - Database connection pool
+/// * `query` - Filters, sort order, cursor and page size
+///
+/// # Errors
+///
+/// Returns an error if the cursor is malformed or was issued for another sort key.
+pub async fn query_media(pool: &DbPool, query: &MediaQuery) -> Result<MediaPage, String> {
+    let limit = query.limit.unwrap_or(DEFAULT_SEARCH_LIMIT).clamp(1, MAX_PAGE_SIZE);
+    let cursor = query.cursor.as_deref().map(Cursor::decode).transpose()?;
+    if cursor.as_ref().is_some_and(|cursor| cursor.sort != query.sort) {
+        return Err("Cursor was issued for a different sort order".to_string());
+    }
+    
+    let total_count = count_media(pool, &query.filters)
+        .await
+        .map_err(|e| e.to_string())?;
+    
+    let expression = query.sort.expression();
+    let (comparison, order) = match query.direction {
+        SortDirection::Asc => (">", "ASC"),
+        SortDirection::Desc => ("<", "DESC"),
+    };
+    
+    let mut builder = QueryBuilder::<Sqlite>::new("SELECT media_metadata.*");
+    push_search_clause(&mut builder, &query.filters);
+    if let Some(cursor) = cursor {
+        builder.push(format!(" AND ({}, media_metadata.id) {} (", expression, comparison));
+        match cursor.value {
+            CursorValue::Date(value) => builder.push_bind(value),
+            CursorValue::Text(value) => builder.push_bind(value),
+            CursorValue::Int(value) => builder.push_bind(value),
+            CursorValue::Real(value) => builder.push_bind(value),
+        };
+        builder.push(", ").push_bind(cursor.id).push(")");
+    }
+    builder
+        .push(format!(" ORDER BY {} {}, media_metadata.id {} LIMIT ", expression, order, order))
+        // One extra row tells whether another page follows
+        .push_bind(limit + 1);
+    
+    let mut items = builder
+        .build_query_as::<MediaMetadata>()
+        .fetch_all(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    
+    let next_cursor = if items.len() as i64 > limit {
+        items.truncate(limit as usize);
+        items.last().map(|last| {
+            Cursor {
+                sort: query.sort,
+                value: query.sort.value_of(last),
+                id: last.id,
+            }
+            .encode()
+        })
+    } else {
+        None
+    };
+    
+    Ok(MediaPage {
+        items,
+        next_cursor,
+        total_count,
+    })
+}
+
+/// Tauri command to fetch one sorted page of media
+///
+/// # Arguments
+///
+/// * `pool` - Database connection pool (injected by Tauri)
+/// * `query` - Filters, sort key and direction, cursor and page size
+///
+/// # Returns
+///
+/// Returns the page, the cursor of the next page and the total match count
+#[tauri::command]
+pub async fn get_media_page(pool: State<'_, DbPool>, query: MediaQuery) -> Result<MediaPage, String> {
+    query_media(&pool, &query).await
+}
+
+/// Tauri command to search indexed media
+///
+/// # Arguments
+///
+/// * `pool` - Database connection pool (injected by Tauri)
+/// * `filters` - Search criteria (text and structured filters)
+/// * `limit` - Maximum number of results (defaults to 500)
+///
+/// # Returns
+///
+/// Returns the matching media rows, best matches first
+#[tauri::command]
+pub async fn search_media(
+    pool: State<'_, DbPool>,
+    filters: SearchFilters,
+    limit: Option<i64>,
+) -> Result<Vec<MediaMetadata>, String> {
+    find_media(&pool, &filters, limit.unwrap_or(DEFAULT_SEARCH_LIMIT))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{self, InsertMediaParams};
+    
+    fn params(folder_id: i64, path: &str, media_type: &str, size: i64, metadata_json: Option<&str>) -> InsertMediaParams {
+        InsertMediaParams {
+            folder_id,
+            file_path: path.to_string(),
+            file_name: path.rsplit('/').next().unwrap().to_string(),
+            file_type: path.rsplit('.').next().unwrap().to_string(),
+            file_size: size,
+            modified_date: Utc::now(),
+            metadata_json: metadata_json.map(str::to_string),
+            media_type: Some(media_type.to_string()),
+            ..Default::default()
+        }
+    }
+    
+    async fn seeded_pool() -> DbPool {
+        let pool = database::open_test_pool().await;
+        let folder_id = database::upsert_scanned_folder(&pool, "/lib", "lib", 3).await.unwrap();
+        let rows = [
+            params(folder_id, "/lib/beach/sunset.jpg", "image", 2_000, Some(r#"{"Model":"Canon EOS R5"}"#)),
+            params(folder_id, "/lib/beach/waves.mp4", "video", 50_000, Some(r#"{"title":"Surf session"}"#)),
+            params(folder_id, "/lib/music/song.flac", "audio", 9_000, None),
+        ];
+        database::upsert_media_batch(&pool, &rows).await.unwrap();
+        pool
+    }
+    
+    fn names(media: &[MediaMetadata]) -> Vec<&str> {
+        let mut names: Vec<&str> = media.iter().map(|m| m.file_name.as_str()).collect();
+        names.sort();
+        names
+    }
+    
+    #[test]
+    fn test_fts_query_quotes_terms() {
+        assert_eq!(fts_query("  sun \"set "), Some("\"sun\"* \"\"\"set\"*".to_string()));
+        assert_eq!(fts_query("   "), None);
+    }
+    
+    #[tokio::test]
+    async fn test_text_search_covers_paths_and_metadata() {
+        let pool = seeded_pool().await;
+        let search = |text: &str| SearchFilters {
+            text: Some(text.to_string()),
+            ..Default::default()
+        };
+        
+        assert_eq!(names(&find_media(&pool, &search("beach"), 10).await.unwrap()), ["sunset.jpg", "waves.mp4"]);
+        assert_eq!(names(&find_media(&pool, &search("canon"), 10).await.unwrap()), ["sunset.jpg"]);
+        assert_eq!(names(&find_media(&pool, &search("surf sess"), 10).await.unwrap()), ["waves.mp4"]);
+        assert!(find_media(&pool, &search("mountain"), 10).await.unwrap().is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_structured_filters() {
+        let pool = seeded_pool().await;
+        
+        let filters = SearchFilters {
+            media_types: vec!["image".to_string(), "AUDIO".to_string()],
+            min_size: Some(5_000),
+            ..Default::default()
+        };
+        assert_eq!(names(&find_media(&pool, &filters, 10).await.unwrap()), ["song.flac"]);
+        
+        let filters = SearchFilters {
+            text: Some("beach".to_string()),
+            max_size: Some(10_000),
+            ..Default::default()
+        };
+        assert_eq!(names(&find_media(&pool, &filters, 10).await.unwrap()), ["sunset.jpg"]);
+    }
+    
+    #[tokio::test]
+    async fn test_camera_filters() {
+        let pool = database::open_test_pool().await;
+        let folder_id = database::upsert_scanned_folder(&pool, "/lib", "lib", 2).await.unwrap();
+        let photo = |path: &str, model: &str, iso: i64, flash: bool| InsertMediaParams {
+            camera_make: Some("Canon".to_string()),
+            camera_model: Some(model.to_string()),
+            iso: Some(iso),
+            flash: Some(flash),
+            ..params(folder_id, path, "image", 1_000, None)
+        };
+        let rows = [
+            photo("/lib/day.jpg", "Canon EOS R5", 100, false),
+            photo("/lib/night.jpg", "Canon EOS R6", 3200, true),
+        ];
+        database::upsert_media_batch(&pool, &rows).await.unwrap();
+        
+        let filters = SearchFilters {
+            camera_model: Some("canon eos r6".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(names(&find_media(&pool, &filters, 10).await.unwrap()), ["night.jpg"]);
+        
+        let filters = SearchFilters {
+            camera_make: Some("CANON".to_string()),
+            max_iso: Some(800),
+            ..Default::default()
+        };
+        assert_eq!(names(&find_media(&pool, &filters, 10).await.unwrap()), ["day.jpg"]);
+        
+        let filters = SearchFilters {
+            flash: Some(true),
+            ..Default::default()
+        };
+        assert_eq!(names(&find_media(&pool, &filters, 10).await.unwrap()), ["night.jpg"]);
+    }
+    
+    /// Walks every page of a query and returns the file names in order
+    async fn all_pages(pool: &DbPool, mut query: MediaQuery) -> Vec<String> {
+        let mut names = Vec::new();
+        loop {
+            let page = query_media(pool, &query).await.unwrap();
+            assert_eq!(page.total_count, 3);
+            names.extend(page.items.into_iter().map(|m| m.file_name));
+            match page.next_cursor {
+                Some(cursor) => query.cursor = Some(cursor),
+                None => return names,
+            }
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_query_media_pages_in_sort_order() {
+        let pool = seeded_pool().await;
+        
+        let by_size = MediaQuery {
+            sort: SortKey::Size,
+            direction: SortDirection::Desc,
+            limit: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(all_pages(&pool, by_size).await, ["waves.mp4", "song.flac", "sunset.jpg"]);
+        
+        let by_name = MediaQuery {
+            sort: SortKey::Name,
+            direction: SortDirection::Asc,
+            limit: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(all_pages(&pool, by_name).await, ["song.flac", "sunset.jpg", "waves.mp4"]);
+    }
+    
+    #[tokio::test]
+    async fn test_query_media_rejects_foreign_cursor() {
+        let pool = seeded_pool().await;
+        let first = query_media(&pool, &MediaQuery { limit: Some(1), ..Default::default() })
+            .await
+            .unwrap();
+        
+        let query = MediaQuery {
+            sort: SortKey::Name,
+            cursor: first.next_cursor,
+            ..Default::default()
+        };
+        assert!(query_media(&pool, &query).await.is_err());
+        
+        let query = MediaQuery {
+            cursor: Some("not a cursor".to_string()),
+            ..Default::default()
+        };
+        assert!(query_media(&pool, &query).await.is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_search_index_follows_updates_and_deletes() {
+        let pool = seeded_pool().await;
+        let search = |text: &str| {
+            let pool = pool.clone();
+            let filters = SearchFilters {
+                text: Some(text.to_string()),
+                ..Default::default()
+            };
+            async move {
+                find_media(&pool, &filters, 10)
+                    .await
+                    .unwrap()
+                    .into_iter()
+                    .map(|media| media.file_path)
+                    .collect::<Vec<_>>()
+            }
+        };
+        
+        // New metadata replaces the old values in the index
+        let folder_id = database::upsert_scanned_folder(&pool, "/lib", "lib", 3).await.unwrap();
+        let retitled = params(folder_id, "/lib/beach/waves.mp4", "video", 50_000, Some(r#"{"title":"Dawn patrol"}"#));
+        database::upsert_media_batch(&pool, &[retitled]).await.unwrap();
+        assert!(search("surf").await.is_empty());
+        assert_eq!(search("dawn").await, ["/lib/beach/waves.mp4"]);
+        
+        // So does a new path
+        sqlx::query("UPDATE media_metadata SET file_path = '/lib/coast/waves.mp4' WHERE file_path = '/lib/beach/waves.mp4'")
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert_eq!(search("coast").await, ["/lib/coast/waves.mp4"]);
+        assert_eq!(search("beach").await, ["/lib/beach/sunset.jpg"]);
+        
+        database::delete_media_metadata(&pool, "/lib/beach/sunset.jpg").await.unwrap();
+        
+        assert!(search("sunset").await.is_empty());
+    }
+}
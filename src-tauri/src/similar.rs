@@ -0,0 +1,504 @@
+//! Similar-photo (FR-2.3) and near-duplicate video (FR-2.4) grouping
+//!
+//! Images carry perceptual hashes in `media_metadata.dhash` and `phash` (hex
+//! encoded, see [`perceptual`]). They are filled in when a thumbnail is
+//! generated from the full image and by the perceptual phase of the hashing
+//! job (see [`duplicates`](crate::duplicates)). An empty string marks an image
+//! that could not be decoded, so it isn't retried until the file changes.
+//!
+//! Images whose hashes are within a Hamming distance of each other are
+//! grouped; chains of close images end up in the same group.
+//!
+//! Videos carry a frame fingerprint in `media_metadata.video_fingerprint`: the
+//! pHashes of [`VIDEO_SAMPLE_COUNT`] frames spread evenly over the duration.
+//! Two videos are grouped when enough frames of one have a close frame in the
+//! other, which holds for re-encodes and resolution variants, and for trims
+//! as long as the sampled scenes last a few seconds. Each group is ranked so
+//! the copy worth keeping (highest resolution, then bitrate) comes first.
+
+use crate::database::{DatabaseResult, DbPool, MediaMetadata};
+use crate::perceptual::{self, BkTree, ImageHashes, PerceptualAlgorithm};
+use crate::thumbnail::{self, ThumbnailResult};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use std::collections::HashSet;
+use tauri::State;
+
+/// Default maximum Hamming distance between similar images
+pub const DEFAULT_MAX_DISTANCE: u32 = 10;
+
+/// Largest accepted distance; beyond it unrelated images start to match
+pub const MAX_DISTANCE_LIMIT: u32 = 24;
+
+/// How images are compared
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SimilarityOptions {
+    #[serde(default)]
+    pub algorithm: PerceptualAlgorithm,
+    /// Maximum number of differing hash bits (out of 64)
+    #[serde(default = "default_max_distance")]
+    pub max_distance: u32,
+}
+
+fn default_max_distance() -> u32 {
+    DEFAULT_MAX_DISTANCE
+}
+
+impl Default for SimilarityOptions {
+    fn default() -> Self {
+        SimilarityOptions {
+            algorithm: PerceptualAlgorithm::default(),
+            max_distance: DEFAULT_MAX_DISTANCE,
+        }
+    }
+}
+
+/// An image of a similarity group
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimilarMedia {
+    #[serde(flatten)]
+    pub media: MediaMetadata,
+    /// Hamming distance to the first image of the group
+    pub distance: u32,
+}
+
+/// Visually similar images, largest resolution first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimilarGroup {
+    pub media: Vec<SimilarMedia>,
+}
+
+#[derive(FromRow)]
+struct HashedImage {
+    #[sqlx(flatten)]
+    media: MediaMetadata,
+    dhash: String,
+    phash: String,
+}
+
+/// Stores the perceptual hashes of the image at a path
+pub async fn store_image_hashes(pool: &DbPool, file_path: &str, hashes: &ImageHashes) -> DatabaseResult<()> {
+    sqlx::query("UPDATE media_metadata SET dhash = ?, phash = ? WHERE file_path = ?")
+        .bind(perceptual::to_hex(hashes.dhash))
+        .bind(perceptual::to_hex(hashes.phash))
+        .bind(file_path)
+        .execute(pool)
+        .await?;
+    
+    Ok(())
+}
+
+/// Groups visually similar images of the library
+///
+/// Only images hashed so far are considered. Groups are sorted by size, and
+/// each group by resolution then file size, so the likely original comes
+/// first.
+///
+/// # Errors
+///
+/// Returns an error if `max_distance` is above [`MAX_DISTANCE_LIMIT`].
+pub async fn find_similar_images(pool: &DbPool, options: SimilarityOptions) -> Result<Vec<SimilarGroup>, String> {
+    if options.max_distance > MAX_DISTANCE_LIMIT {
+        return Err(format!("Distance must be at most {}", MAX_DISTANCE_LIMIT));
+    }
+    
+    let rows = sqlx::query_as::<_, HashedImage>(
+        r#"
+        SELECT * FROM media_metadata
+        WHERE trashed_at IS NULL AND media_type = 'image'
+          AND dhash IS NOT NULL AND dhash != '' AND phash IS NOT NULL AND phash != ''
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    
+    let (media, hashes): (Vec<MediaMetadata>, Vec<u64>) = rows
+        .into_iter()
+        .filter_map(|row| {
+            let hash = match options.algorithm {
+                PerceptualAlgorithm::DHash => perceptual::from_hex(&row.dhash),
+                PerceptualAlgorithm::PHash => perceptual::from_hex(&row.phash),
+            };
+            hash.map(|hash| (row.media, hash))
+        })
+        .unzip();
+    
+    let mut media: Vec<Option<MediaMetadata>> = media.into_iter().map(Some).collect();
+    let mut groups: Vec<SimilarGroup> = perceptual::cluster(&hashes, options.max_distance)
+        .into_iter()
+        .map(|mut members| {
+            members.sort_by_key(|&index| {
+                let item = media[index].as_ref();
+                let pixels = item.map_or(0, |m| m.width.unwrap_or(0) * m.height.unwrap_or(0));
+                std::cmp::Reverse((pixels, item.map_or(0, |m| m.file_size)))
+            });
+            let first = hashes[members[0]];
+            SimilarGroup {
+                media: members
+                    .into_iter()
+                    .filter_map(|index| {
+                        media[index].take().map(|media| SimilarMedia {
+                            media,
+                            distance: perceptual::hamming_distance(first, hashes[index]),
+                        })
+                    })
+                    .collect(),
+            }
+        })
+        .collect();
+    groups.sort_by_key(|group| std::cmp::Reverse(group.media.len()));
+    
+    Ok(groups)
+}
+
+/// Tauri command to group visually similar images
+///
+/// # Arguments
+///
+/// * `pool` - Database connection pool (injected by Tauri)
+/// * `options` - Hash and distance threshold; defaults to pHash within 10 bits
+#[tauri::command]
+pub async fn get_similar_images(
+    pool: State<'_, DbPool>,
+    options: Option<SimilarityOptions>,
+) -> Result<Vec<SimilarGroup>, String> {
+    find_similar_images(&pool, options.unwrap_or_default()).await
+}
+
+// ============================================================================
+// Videos
+// ============================================================================
+
+/// Number of frames sampled for a video fingerprint
+pub const VIDEO_SAMPLE_COUNT: usize = 12;
+
+/// Fewest frames a fingerprint needs before it can match another video
+///
+/// Videos that fail to decode past their first frames would otherwise match
+/// anything sharing that frame, such as a black intro.
+pub const MIN_FINGERPRINT_FRAMES: usize = VIDEO_SAMPLE_COUNT / 2;
+
+/// Default share of frames that must have a close match
+pub const DEFAULT_MIN_FRAME_MATCH: f64 = 0.6;
+
+/// How videos are compared
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct VideoSimilarityOptions {
+    /// Maximum pHash distance between two matching frames
+    #[serde(default = "default_max_distance")]
+    pub max_distance: u32,
+    /// Share of frames (0.0 to 1.0) of one video that must match the other
+    #[serde(default = "default_min_frame_match")]
+    pub min_frame_match: f64,
+}
+
+fn default_min_frame_match() -> f64 {
+    DEFAULT_MIN_FRAME_MATCH
+}
+
+impl Default for VideoSimilarityOptions {
+    fn default() -> Self {
+        VideoSimilarityOptions {
+            max_distance: DEFAULT_MAX_DISTANCE,
+            min_frame_match: DEFAULT_MIN_FRAME_MATCH,
+        }
+    }
+}
+
+/// A video of a near-duplicate group
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RankedVideo {
+    #[serde(flatten)]
+    pub media: MediaMetadata,
+    /// Whether this is the copy to keep (the first of the group)
+    pub keep: bool,
+    /// Share of matching frames with the kept copy
+    pub similarity: f64,
+}
+
+/// Videos that are variants of each other, best copy first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VideoGroup {
+    pub media: Vec<RankedVideo>,
+}
+
+#[derive(FromRow)]
+struct FingerprintedVideo {
+    #[sqlx(flatten)]
+    media: MediaMetadata,
+    video_fingerprint: String,
+}
+
+/// Computes the frame fingerprint of a video
+///
+/// Decodes frames at evenly spaced fractions of the duration and returns
+/// their pHashes. Frames that fail to decode are left out.
+pub fn video_fingerprint(file_path: &str) -> ThumbnailResult<Vec<u64>> {
+    let fractions: Vec<f64> = (0..VIDEO_SAMPLE_COUNT)
+        .map(|i| (i as f64 + 0.5) / VIDEO_SAMPLE_COUNT as f64)
+        .collect();
+    let frames = thumbnail::sample_video_frames(file_path, &fractions)?;
+    Ok(frames.iter().map(perceptual::phash).collect())
+}
+
+/// Formats a fingerprint the way it is stored in the database
+pub fn fingerprint_to_string(fingerprint: &[u64]) -> String {
+    fingerprint
+        .iter()
+        .map(|&hash| perceptual::to_hex(hash))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Parses a fingerprint stored with [`fingerprint_to_string`]
+pub fn parse_fingerprint(value: &str) -> Vec<u64> {
+    value.split(',').filter_map(perceptual::from_hex).collect()
+}
+
+/// Share of frames of `a` with a frame of `b` within `max_distance`
+fn frame_match(a: &[u64], b: &[u64], max_distance: u32) -> f64 {
+    if a.is_empty() {
+        return 0.0;
+    }
+    let matched = a
+        .iter()
+        .filter(|&&frame| b.iter().any(|&other| perceptual::hamming_distance(frame, other) <= max_distance))
+        .count();
+    matched as f64 / a.len() as f64
+}
+
+/// Similarity of two fingerprints from 0.0 to 1.0
+///
+/// Taken in the direction that favors the shorter video, whose frames are
+/// all expected to appear in the longer one when it is a trim. Fingerprints
+/// shorter than [`MIN_FINGERPRINT_FRAMES`] never match.
+pub fn frame_similarity(a: &[u64], b: &[u64], max_distance: u32) -> f64 {
+    if a.len().min(b.len()) < MIN_FINGERPRINT_FRAMES {
+        return 0.0;
+    }
+    frame_match(a, b, max_distance).max(frame_match(b, a, max_distance))
+}
+
+/// Stores the frame fingerprint of the video at a path
+pub async fn store_video_fingerprint(pool: &DbPool, file_path: &str, fingerprint: &[u64]) -> DatabaseResult<()> {
+    sqlx::query("UPDATE media_metadata SET video_fingerprint = ? WHERE file_path = ?")
+        .bind(fingerprint_to_string(fingerprint))
+        .bind(file_path)
+        .execute(pool)
+        .await?;
+    
+    Ok(())
+}
+
+/// Groups videos that are re-encodes, trims or resolution variants of each other
+///
+/// Only fingerprinted videos are considered. Each group is ranked by
+/// resolution, then bitrate, then file size; the first video is the one to
+/// keep.
+///
+/// # Errors
+///
+/// Returns an error if `max_distance` is above [`MAX_DISTANCE_LIMIT`] or
+/// `min_frame_match` is outside 0.0 to 1.0.
+pub async fn find_similar_videos(pool: &DbPool, options: VideoSimilarityOptions) -> Result<Vec<VideoGroup>, String> {
+    if options.max_distance > MAX_DISTANCE_LIMIT {
+        return Err(format!("Distance must be at most {}", MAX_DISTANCE_LIMIT));
+    }
+    if !(0.0..=1.0).contains(&options.min_frame_match) {
+        return Err("Frame match must be between 0 and 1".to_string());
+    }
+    
+    let rows = sqlx::query_as::<_, FingerprintedVideo>(
+        r#"
+        SELECT * FROM media_metadata
+        WHERE trashed_at IS NULL AND media_type = 'video'
+          AND video_fingerprint IS NOT NULL AND video_fingerprint != ''
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    
+    let (media, fingerprints): (Vec<MediaMetadata>, Vec<Vec<u64>>) = rows
+        .into_iter()
+        .map(|row| (row.media, parse_fingerprint(&row.video_fingerprint)))
+        .unzip();
+    
+    // Only videos sharing at least one close frame are compared in full
+    let mut tree = BkTree::new();
+    for (index, fingerprint) in fingerprints.iter().enumerate() {
+        for &hash in fingerprint {
+            tree.insert(hash, index);
+        }
+    }
+    let mut candidates = HashSet::new();
+    for (index, fingerprint) in fingerprints.iter().enumerate() {
+        for &hash in fingerprint {
+            for (&other, _) in tree.find(hash, options.max_distance) {
+                if other > index {
+                    candidates.insert((index, other));
+                }
+            }
+        }
+    }
+    let pairs = candidates.into_iter().filter(|&(a, b)| {
+        frame_similarity(&fingerprints[a], &fingerprints[b], options.max_distance) >= options.min_frame_match
+    });
+    
+    let mut media: Vec<Option<MediaMetadata>> = media.into_iter().map(Some).collect();
+    let mut groups: Vec<VideoGroup> = perceptual::group_pairs(fingerprints.len(), pairs)
+        .into_iter()
+        .map(|mut members| {
+            members.sort_by_key(|&index| {
+                let item = media[index].as_ref();
+                let pixels = item.map_or(0, |m| m.width.unwrap_or(0) * m.height.unwrap_or(0));
+                let bitrate = item.and_then(|m| m.bitrate).unwrap_or(0);
+                std::cmp::Reverse((pixels, bitrate, item.map_or(0, |m| m.file_size)))
+            });
+            let kept = &fingerprints[members[0]];
+            VideoGroup {
+                media: members
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(rank, &index)| {
+                        media[index].take().map(|media| RankedVideo {
+                            media,
+                            keep: rank == 0,
+                            similarity: frame_similarity(kept, &fingerprints[index], options.max_distance),
+                        })
+                    })
+                    .collect(),
+            }
+        })
+        .collect();
+    groups.sort_by_key(|group| std::cmp::Reverse(group.media.len()));
+    
+    Ok(groups)
+}
+
+/// Tauri command to group near-duplicate videos, best copy first
+///
+/// # Arguments
+///
+/// * `pool` - Database connection pool (injected by Tauri)
+/// * `options` - Frame distance and match share; defaults to 10 bits and 60%
+#[tauri::command]
+pub async fn get_similar_videos(
+    pool: State<'_, DbPool>,
+    options: Option<VideoSimilarityOptions>,
+) -> Result<Vec<VideoGroup>, String> {
+    find_similar_videos(&pool, options.unwrap_or_default()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{self, InsertMediaParams};
+    
+    #[tokio::test]
+    async fn test_find_similar_images_groups_close_hashes() {
+        let pool = database::open_test_pool().await;
+        let folder_id = database::upsert_scanned_folder(&pool, "/photos", "photos", 4).await.unwrap();
+        let images = [
+            ("/photos/original.jpg", 4000, 0x0F0F_0F0F_0F0F_0F0F),
+            ("/photos/whatsapp.jpg", 1000, 0x0F0F_0F0F_0F0F_0F0C),
+            ("/photos/resized.jpg", 2000, 0x0F0F_0F0F_0F0F_0F1F),
+            ("/photos/unrelated.jpg", 3000, 0xF0F0_F0F0_F0F0_F0F0),
+        ];
+        for (path, size, hash) in images {
+            let params = InsertMediaParams {
+                folder_id,
+                file_path: path.to_string(),
+                file_name: path.rsplit('/').next().unwrap().to_string(),
+                file_type: "jpg".to_string(),
+                file_size: size,
+                media_type: Some("image".to_string()),
+                ..Default::default()
+            };
+            database::insert_media_metadata(&pool, params).await.unwrap();
+            let hashes = ImageHashes { dhash: hash, phash: hash };
+            store_image_hashes(&pool, path, &hashes).await.unwrap();
+        }
+        
+        let groups = find_similar_images(&pool, SimilarityOptions::default()).await.unwrap();
+        assert_eq!(groups.len(), 1);
+        let members: Vec<(&str, u32)> = groups[0]
+            .media
+            .iter()
+            .map(|m| (m.media.file_name.as_str(), m.distance))
+            .collect();
+        assert_eq!(members, [("original.jpg", 0), ("resized.jpg", 1), ("whatsapp.jpg", 2)]);
+        
+        let strict = SimilarityOptions {
+            algorithm: PerceptualAlgorithm::DHash,
+            max_distance: 1,
+        };
+        let groups = find_similar_images(&pool, strict).await.unwrap();
+        assert_eq!(groups[0].media.len(), 2);
+        
+        let too_loose = SimilarityOptions {
+            max_distance: 40,
+            ..Default::default()
+        };
+        assert!(find_similar_images(&pool, too_loose).await.is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_find_similar_videos_ranks_best_copy_first() {
+        let pool = database::open_test_pool().await;
+        let folder_id = database::upsert_scanned_folder(&pool, "/videos", "videos", 4).await.unwrap();
+        
+        let original: Vec<u64> = (0..12u64).map(|i| i.wrapping_mul(0x9E37_79B9_7F4A_7C15)).collect();
+        // Re-encoded export: every frame a few bits off
+        let export: Vec<u64> = original.iter().map(|hash| hash ^ 0b101).collect();
+        // Trimmed clip: the middle of the video, sampled at other positions
+        let trim: Vec<u64> = original[3..9].iter().flat_map(|&hash| [hash ^ 1, hash ^ 2]).collect();
+        let unrelated: Vec<u64> = (100..112u64).map(|i| i.wrapping_mul(0xC2B2_AE3D_27D4_EB4F)).collect();
+        
+        let videos = [
+            ("/videos/export.mp4", 1920, 5_000_000, &export),
+            ("/videos/original.mov", 3840, 50_000_000, &original),
+            ("/videos/trim.mp4", 1280, 2_000_000, &trim),
+            ("/videos/other.mov", 3840, 40_000_000, &unrelated),
+        ];
+        for (path, width, bitrate, fingerprint) in videos {
+            let params = InsertMediaParams {
+                folder_id,
+                file_path: path.to_string(),
+                file_name: path.rsplit('/').next().unwrap().to_string(),
+                file_type: "mp4".to_string(),
+                file_size: 1000,
+                media_type: Some("video".to_string()),
+                width: Some(width),
+                height: Some(width * 9 / 16),
+                bitrate: Some(bitrate),
+                ..Default::default()
+            };
+            database::insert_media_metadata(&pool, params).await.unwrap();
+            store_video_fingerprint(&pool, path, fingerprint).await.unwrap();
+        }
+        
+        let groups = find_similar_videos(&pool, VideoSimilarityOptions::default()).await.unwrap();
+        assert_eq!(groups.len(), 1);
+        let ranked: Vec<(&str, bool)> = groups[0]
+            .media
+            .iter()
+            .map(|video| (video.media.file_name.as_str(), video.keep))
+            .collect();
+        assert_eq!(ranked, [("original.mov", true), ("export.mp4", false), ("trim.mp4", false)]);
+        assert_eq!(groups[0].media[2].similarity, 1.0);
+        
+        assert_eq!(parse_fingerprint(&fingerprint_to_string(&original)), original);
+    }
+    
+    #[test]
+    fn test_short_fingerprints_never_match() {
+        let original: Vec<u64> = (0..12u64).map(|i| i.wrapping_mul(0x9E37_79B9_7F4A_7C15)).collect();
+        
+        assert_eq!(frame_similarity(&original[..1], &original, DEFAULT_MAX_DISTANCE), 0.0);
+        assert_eq!(frame_similarity(&original, &original[..1], DEFAULT_MAX_DISTANCE), 0.0);
+        let half = &original[..MIN_FINGERPRINT_FRAMES];
+        assert_eq!(frame_similarity(half, &original, DEFAULT_MAX_DISTANCE), 1.0);
+    }
+}
@@ -0,0 +1,938 @@
+//! Hierarchical tags
+//!
+//! Tags are stored by their full path (e.g. `Places/Portugal/Lisbon`), with
+//! every ancestor existing as a tag of its own. Paths compare
+//! case-insensitively. Filtering by a tag also matches media tagged with any
+//! of its descendants, and tag expressions combine tags with AND, OR and NOT.
+//!
+//! Tags follow a file's content rather than its path: rows carry a quick
+//! content hash once tagged, the tags of deleted rows are parked in
+//! `orphaned_tags`, and [`restore_moved_tags`] re-attaches them when a file
+//! with the same size and hash shows up elsewhere.
+
+use crate::database::{DatabaseError, DbPool};
+use crate::hashing;
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, QueryBuilder, Row, Sqlite, SqliteConnection};
+use std::collections::HashSet;
+use std::fmt;
+use std::path::Path;
+use tauri::State;
+use thiserror::Error;
+use tracing::{debug, info, warn};
+
+/// Separator between the levels of a tag path
+pub const TAG_SEPARATOR: char = '/';
+
+/// Days an orphaned tag waits for its file to reappear before it is dropped
+pub const ORPHAN_RETENTION_DAYS: i64 = 30;
+
+/// Error types for tag operations
+#[derive(Debug, Error)]
+pub enum TagError {
+    #[error("Database error: {0}")]
+    Database(#[from] DatabaseError),
+    
+    #[error("Invalid tag: {0}")]
+    InvalidTag(String),
+    
+    #[error("Tag not found: {0}")]
+    NotFound(String),
+    
+    #[error("Invalid tag expression: {0}")]
+    InvalidExpression(String),
+    
+    #[error("Hashing task failed: {0}")]
+    Hashing(#[from] tokio::task::JoinError),
+}
+
+impl From<sqlx::Error> for TagError {
+    fn from(err: sqlx::Error) -> Self {
+        TagError::Database(err.into())
+    }
+}
+
+pub type TagResult<T> = Result<T, TagError>;
+
+/// A tag with its usage counts
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct TagCount {
+    pub id: i64,
+    /// Last level of the path (e.g. `Lisbon`)
+    pub name: String,
+    /// Full path (e.g. `Places/Portugal/Lisbon`)
+    pub path: String,
+    pub parent_id: Option<i64>,
+    /// Media tagged with exactly this tag
+    pub media_count: i64,
+    /// Media tagged with this tag or any descendant
+    pub total_count: i64,
+}
+
+/// Normalizes a tag path: trims every level and drops empty ones.
+///
+/// # Errors
+///
+/// Returns an error if no level is left or the path contains a double quote.
+pub fn normalize_tag_path(path: &str) -> TagResult<String> {
+    if path.contains('"') {
+        return Err(TagError::InvalidTag(format!("'{}' contains a double quote", path)));
+    }
+    
+    let levels: Vec<&str> = path
+        .split(TAG_SEPARATOR)
+        .map(str::trim)
+        .filter(|level| !level.is_empty())
+        .collect();
+    
+    if levels.is_empty() {
+        return Err(TagError::InvalidTag(format!("'{}' is empty", path)));
+    }
+    
+    Ok(levels.join(&TAG_SEPARATOR.to_string()))
+}
+
+/// Escapes `%`, `_` and `\` for a `LIKE ... ESCAPE '\'` pattern
+fn escape_like(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+/// Pattern matching every descendant of a tag path
+fn descendants_pattern(path: &str) -> String {
+    format!("{}{}%", escape_like(path), TAG_SEPARATOR)
+}
+
+// ============================================================================
+// Tag Expressions
+// ============================================================================
+
+/// Boolean expression over tags, e.g. `Places/Portugal AND (Family OR Friends) AND NOT Work`
+///
+/// Keywords are case-insensitive, adjacent terms are implicitly combined with
+/// AND, and tags containing spaces or parentheses are written in double
+/// quotes. Serialized as its text form.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum TagExpr {
+    Tag(String),
+    And(Box<TagExpr>, Box<TagExpr>),
+    Or(Box<TagExpr>, Box<TagExpr>),
+    Not(Box<TagExpr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    And,
+    Or,
+    Not,
+    Tag(String),
+}
+
+fn tokenize(input: &str) -> TagResult<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+    
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::Open);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::Close);
+            }
+            '"' => {
+                chars.next();
+                let mut tag = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(c) => tag.push(c),
+                        None => {
+                            return Err(TagError::InvalidExpression(format!("unclosed quote in {}", input)));
+                        }
+                    }
+                }
+                tokens.push(Token::Tag(tag));
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == '(' || c == ')' || c == '"' {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                tokens.push(match word.to_ascii_uppercase().as_str() {
+                    "AND" => Token::And,
+                    "OR" => Token::Or,
+                    "NOT" => Token::Not,
+                    _ => Token::Tag(word),
+                });
+            }
+        }
+    }
+    
+    Ok(tokens)
+}
+
+/// Recursive-descent parser; OR binds loosest, then AND, then NOT
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+    
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+    
+    fn parse_or(&mut self) -> TagResult<TagExpr> {
+        let mut expr = self.parse_and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            expr = TagExpr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+    
+    fn parse_and(&mut self) -> TagResult<TagExpr> {
+        let mut expr = self.parse_unary()?;
+        loop {
+            match self.peek() {
+                Some(Token::And) => {
+                    self.next();
+                }
+                Some(Token::Tag(_)) | Some(Token::Not) | Some(Token::Open) => {}
+                _ => return Ok(expr),
+            }
+            expr = TagExpr::And(Box::new(expr), Box::new(self.parse_unary()?));
+        }
+    }
+    
+    fn parse_unary(&mut self) -> TagResult<TagExpr> {
+        match self.next() {
+            Some(Token::Not) => Ok(TagExpr::Not(Box::new(self.parse_unary()?))),
+            Some(Token::Open) => {
+                let expr = self.parse_or()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(expr),
+                    _ => Err(TagError::InvalidExpression("missing ')'".to_string())),
+                }
+            }
+            Some(Token::Tag(tag)) => Ok(TagExpr::Tag(normalize_tag_path(&tag)?)),
+            Some(token) => Err(TagError::InvalidExpression(format!("unexpected {:?}", token))),
+            None => Err(TagError::InvalidExpression("unexpected end of expression".to_string())),
+        }
+    }
+}
+
+impl TagExpr {
+    /// Parses the text form of an expression
+    pub fn parse(input: &str) -> TagResult<Self> {
+        let mut parser = Parser {
+            tokens: tokenize(input)?,
+            position: 0,
+        };
+        let expr = parser.parse_or()?;
+        
+        match parser.peek() {
+            None => Ok(expr),
+            Some(token) => Err(TagError::InvalidExpression(format!("unexpected {:?}", token))),
+        }
+    }
+}
+
+impl fmt::Display for TagExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TagExpr::Tag(tag) => {
+                let needs_quotes = tag.contains(|c: char| c.is_whitespace() || c == '(' || c == ')')
+                    || ["AND", "OR", "NOT"].contains(&tag.to_ascii_uppercase().as_str());
+                if needs_quotes {
+                    write!(f, "\"{}\"", tag)
+                } else {
+                    write!(f, "{}", tag)
+                }
+            }
+            TagExpr::And(left, right) => write!(f, "({} AND {})", left, right),
+            TagExpr::Or(left, right) => write!(f, "({} OR {})", left, right),
+            TagExpr::Not(inner) => write!(f, "NOT {}", inner),
+        }
+    }
+}
+
+impl TryFrom<String> for TagExpr {
+    type Error = String;
+    
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        TagExpr::parse(&value).map_err(|e| e.to_string())
+    }
+}
+
+impl From<TagExpr> for String {
+    fn from(expr: TagExpr) -> Self {
+        expr.to_string()
+    }
+}
+
+/// Appends the SQL condition for a tag expression on `media_metadata` rows
+pub(crate) fn push_tag_condition(builder: &mut QueryBuilder<'_, Sqlite>, expr: &TagExpr) {
+    match expr {
+        TagExpr::Tag(path) => {
+            builder
+                .push(
+                    "EXISTS (SELECT 1 FROM media_tags mt JOIN tags t ON t.id = mt.tag_id \
+                     WHERE mt.media_id = media_metadata.id AND (t.path = ",
+                )
+                .push_bind(path.clone())
+                .push(" OR t.path LIKE ")
+                .push_bind(descendants_pattern(path))
+                .push(" ESCAPE '\\'))");
+        }
+        TagExpr::And(left, right) | TagExpr::Or(left, right) => {
+            let operator = if matches!(expr, TagExpr::And(..)) { " AND " } else { " OR " };
+            builder.push("(");
+            push_tag_condition(builder, left);
+            builder.push(operator);
+            push_tag_condition(builder, right);
+            builder.push(")");
+        }
+        TagExpr::Not(inner) => {
+            builder.push("NOT ");
+            push_tag_condition(builder, inner);
+        }
+    }
+}
+
+// ============================================================================
+// Tag Operations
+// ============================================================================
+
+/// Returns the ID of a tag, creating it and any missing ancestors
+async fn ensure_tag(conn: &mut SqliteConnection, path: &str) -> TagResult<i64> {
+    let mut parent_id: Option<i64> = None;
+    let mut current = String::new();
+    
+    for level in path.split(TAG_SEPARATOR) {
+        if !current.is_empty() {
+            current.push(TAG_SEPARATOR);
+        }
+        current.push_str(level);
+        
+        sqlx::query("INSERT INTO tags (name, path, parent_id) VALUES (?, ?, ?) ON CONFLICT(path) DO NOTHING")
+            .bind(level)
+            .bind(&current)
+            .bind(parent_id)
+            .execute(&mut *conn)
+            .await?;
+        
+        let id: i64 = sqlx::query("SELECT id FROM tags WHERE path = ?")
+            .bind(&current)
+            .fetch_one(&mut *conn)
+            .await?
+            .get(0);
+        parent_id = Some(id);
+    }
+    
+    parent_id.ok_or_else(|| TagError::InvalidTag(path.to_string()))
+}
+
+/// Lists every tag with its direct and inclusive media counts, sorted by path
+pub async fn list_tags(pool: &DbPool) -> TagResult<Vec<TagCount>> {
+    let tags = sqlx::query_as::<_, TagCount>(
+        r#"
+        SELECT t.id, t.name, t.path, t.parent_id,
+               (SELECT COUNT(*) FROM media_tags mt WHERE mt.tag_id = t.id) AS media_count,
+               (SELECT COUNT(DISTINCT mt.media_id) FROM media_tags mt
+                JOIN tags d ON d.id = mt.tag_id
+                WHERE d.path = t.path
+                   OR d.path LIKE replace(replace(replace(t.path, '\', '\\'), '%', '\%'), '_', '\_') || '/%' ESCAPE '\'
+               ) AS total_count
+        FROM tags t
+        ORDER BY t.path
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+    
+    Ok(tags)
+}
+
+/// Returns the tag paths of a media row
+pub async fn get_media_tags(pool: &DbPool, media_id: i64) -> TagResult<Vec<String>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT t.path FROM media_tags mt
+        JOIN tags t ON t.id = mt.tag_id
+        WHERE mt.media_id = ?
+        ORDER BY t.path
+        "#,
+    )
+    .bind(media_id)
+    .fetch_all(pool)
+    .await?;
+    
+    Ok(rows.into_iter().map(|row| row.get(0)).collect())
+}
+
+/// Adds tags to media rows, creating the tags as needed
+///
+/// The tagged rows get a quick content hash so their tags can follow the
+/// file if it is moved.
+///
+/// # Returns
+///
+/// Returns the number of tag assignments that were not already present
+pub async fn add_tags(pool: &DbPool, media_ids: &[i64], tags: &[String]) -> TagResult<u64> {
+    let paths = tags
+        .iter()
+        .map(|tag| normalize_tag_path(tag))
+        .collect::<TagResult<Vec<_>>>()?;
+    
+    let mut tx = pool.begin().await?;
+    let mut added = 0;
+    for path in &paths {
+        let tag_id = ensure_tag(&mut tx, path).await?;
+        for media_id in media_ids {
+            added += sqlx::query("INSERT OR IGNORE INTO media_tags (media_id, tag_id) VALUES (?, ?)")
+                .bind(media_id)
+                .bind(tag_id)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+        }
+    }
+    tx.commit().await?;
+    
+    hash_tagged_media(pool).await?;
+    
+    Ok(added)
+}
+
+/// Removes tags from media rows (descendant tags are left alone)
+///
+/// # Returns
+///
+/// Returns the number of tag assignments removed
+pub async fn remove_tags(pool: &DbPool, media_ids: &[i64], tags: &[String]) -> TagResult<u64> {
+    let paths = tags
+        .iter()
+        .map(|tag| normalize_tag_path(tag))
+        .collect::<TagResult<Vec<_>>>()?;
+    
+    let mut tx = pool.begin().await?;
+    let mut removed = 0;
+    for path in &paths {
+        for media_id in media_ids {
+            removed += sqlx::query(
+                "DELETE FROM media_tags WHERE media_id = ? AND tag_id = (SELECT id FROM tags WHERE path = ?)",
+            )
+            .bind(media_id)
+            .bind(path)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        }
+    }
+    tx.commit().await?;
+    
+    Ok(removed)
+}
+
+/// Moves a tag and its descendants to a new path inside a transaction.
+///
+/// Levels whose new path already exists are merged into the existing tag
+/// (assignments are moved over and the source tag is deleted).
+async fn move_tag_tree(conn: &mut SqliteConnection, from: &str, to: &str) -> TagResult<()> {
+    let lower_from = from.to_lowercase();
+    let lower_to = to.to_lowercase();
+    if lower_to.starts_with(&format!("{}{}", lower_from, TAG_SEPARATOR)) {
+        return Err(TagError::InvalidTag(format!("cannot move '{}' below itself", from)));
+    }
+    
+    // Parents come before their children
+    let subtree: Vec<(i64, String)> = sqlx::query(
+        "SELECT id, path FROM tags WHERE path = ? OR path LIKE ? ESCAPE '\\' ORDER BY length(path)",
+    )
+    .bind(from)
+    .bind(descendants_pattern(from))
+    .fetch_all(&mut *conn)
+    .await?
+    .into_iter()
+    .map(|row| (row.get(0), row.get(1)))
+    .collect();
+    
+    if subtree.is_empty() {
+        return Err(TagError::NotFound(from.to_string()));
+    }
+    
+    let mut merged = Vec::new();
+    for (id, path) in subtree {
+        // The subtree only holds `from` and paths below it, which share its prefix
+        let new_path = format!("{}{}", to, &path[from.len()..]);
+        let existing: Option<i64> = sqlx::query("SELECT id FROM tags WHERE path = ?")
+            .bind(&new_path)
+            .fetch_optional(&mut *conn)
+            .await?
+            .map(|row| row.get(0));
+        
+        match existing {
+            Some(target_id) if target_id != id => {
+                sqlx::query(
+                    "INSERT OR IGNORE INTO media_tags (media_id, tag_id, added_at) \
+                     SELECT media_id, ?, added_at FROM media_tags WHERE tag_id = ?",
+                )
+                .bind(target_id)
+                .bind(id)
+                .execute(&mut *conn)
+                .await?;
+                sqlx::query("UPDATE OR IGNORE orphaned_tags SET tag_id = ? WHERE tag_id = ?")
+                    .bind(target_id)
+                    .bind(id)
+                    .execute(&mut *conn)
+                    .await?;
+                sqlx::query("UPDATE tags SET parent_id = ? WHERE parent_id = ?")
+                    .bind(target_id)
+                    .bind(id)
+                    .execute(&mut *conn)
+                    .await?;
+                merged.push(id);
+            }
+            _ => {
+                let (parent_id, name) = match new_path.rsplit_once(TAG_SEPARATOR) {
+                    Some((parent, name)) => (Some(ensure_tag(conn, parent).await?), name.to_string()),
+                    None => (None, new_path.clone()),
+                };
+                sqlx::query("UPDATE tags SET path = ?, name = ?, parent_id = ? WHERE id = ?")
+                    .bind(&new_path)
+                    .bind(name)
+                    .bind(parent_id)
+                    .bind(id)
+                    .execute(&mut *conn)
+                    .await?;
+            }
+        }
+    }
+    
+    for id in merged {
+        sqlx::query("DELETE FROM tags WHERE id = ?")
+            .bind(id)
+            .execute(&mut *conn)
+            .await?;
+    }
+    
+    Ok(())
+}
+
+/// Renames a tag (and its descendants); merges into the target if it exists
+pub async fn rename_tag(pool: &DbPool, from: &str, to: &str) -> TagResult<()> {
+    let from = normalize_tag_path(from)?;
+    let to = normalize_tag_path(to)?;
+    
+    let mut tx = pool.begin().await?;
+    move_tag_tree(&mut tx, &from, &to).await?;
+    tx.commit().await?;
+    
+    info!("Renamed tag {} to {}", from, to);
+    Ok(())
+}
+
+/// Merges several tags (and their descendants) into one target tag
+pub async fn merge_tags(pool: &DbPool, sources: &[String], target: &str) -> TagResult<()> {
+    let target = normalize_tag_path(target)?;
+    
+    let mut tx = pool.begin().await?;
+    ensure_tag(&mut tx, &target).await?;
+    for source in sources {
+        let source = normalize_tag_path(source)?;
+        if !source.eq_ignore_ascii_case(&target) {
+            move_tag_tree(&mut tx, &source, &target).await?;
+        }
+    }
+    tx.commit().await?;
+    
+    info!("Merged {} tags into {}", sources.len(), target);
+    Ok(())
+}
+
+/// Deletes a tag, its descendants and all their assignments
+pub async fn delete_tag(pool: &DbPool, path: &str) -> TagResult<()> {
+    let path = normalize_tag_path(path)?;
+    let deleted = sqlx::query("DELETE FROM tags WHERE path = ?")
+        .bind(&path)
+        .execute(pool)
+        .await?
+        .rows_affected();
+    
+    if deleted == 0 {
+        return Err(TagError::NotFound(path));
+    }
+    Ok(())
+}
+
+// ============================================================================
+// Moved Files
+// ============================================================================
+
+/// Computes the quick hash of tagged rows that don't have one yet
+///
+/// Covers newly tagged files and tagged files whose content changed (the
+/// upsert clears the hash when size or modification time differ).
+async fn hash_tagged_media(pool: &DbPool) -> TagResult<()> {
+    let rows: Vec<(i64, String)> = sqlx::query(
+        r#"
+        SELECT DISTINCT m.id, m.file_path FROM media_metadata m
+        JOIN media_tags mt ON mt.media_id = m.id
+        WHERE m.quick_hash IS NULL
+        "#,
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|row| (row.get(0), row.get(1)))
+    .collect();
+    
+    if rows.is_empty() {
+        return Ok(());
+    }
+    
+    let hashes = tokio::task::spawn_blocking(move || hash_files(rows)).await?;
+    
+    let mut tx = pool.begin().await?;
+    for (id, hash) in hashes {
+        sqlx::query("UPDATE media_metadata SET quick_hash = ? WHERE id = ?")
+            .bind(hash)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+    
+    Ok(())
+}
+
+/// Hashes files, skipping (and logging) the ones that can't be read
+fn hash_files(files: Vec<(i64, String)>) -> Vec<(i64, String)> {
+    files
+        .into_iter()
+        .filter_map(|(id, path)| match hashing::quick_hash(Path::new(&path)) {
+            Ok(hash) => Some((id, hash)),
+            Err(e) => {
+                warn!("Cannot hash {}: {}", path, e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Re-attaches orphaned tags to newly indexed files with the same content
+///
+/// Call after new rows were written (scan, rescan or watcher). Only files
+/// whose size matches an orphan are hashed. Orphans older than
+/// [`ORPHAN_RETENTION_DAYS`] are dropped first, and tagged rows still missing
+/// a hash are hashed afterwards.
+///
+/// # Arguments
+///
+/// * `pool` - Database connection pool
+/// * `paths` - Paths of the newly indexed files
+///
+/// # Returns
+///
+/// Returns the number of files that got tags back
+pub async fn restore_moved_tags(pool: &DbPool, paths: &[String]) -> TagResult<usize> {
+    sqlx::query("DELETE FROM orphaned_tags WHERE orphaned_at < datetime('now', ?)")
+        .bind(format!("-{} days", ORPHAN_RETENTION_DAYS))
+        .execute(pool)
+        .await?;
+    
+    let orphan_sizes: HashSet<i64> = sqlx::query("SELECT DISTINCT file_size FROM orphaned_tags")
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|row| row.get(0))
+        .collect();
+    
+    let mut restored = 0;
+    if !orphan_sizes.is_empty() && !paths.is_empty() {
+        let mut candidates = Vec::new();
+        for path in paths {
+            let row = sqlx::query("SELECT id, file_size FROM media_metadata WHERE file_path = ?")
+                .bind(path)
+                .fetch_optional(pool)
+                .await?;
+            if let Some(row) = row {
+                if orphan_sizes.contains(&row.get::<i64, _>(1)) {
+                    candidates.push((row.get::<i64, _>(0), path.clone()));
+                }
+            }
+        }
+        
+        let hashes = tokio::task::spawn_blocking(move || hash_files(candidates)).await?;
+        
+        for (id, hash) in hashes {
+            let mut tx = pool.begin().await?;
+            let attached = sqlx::query(
+                "INSERT OR IGNORE INTO media_tags (media_id, tag_id) \
+                 SELECT ?, tag_id FROM orphaned_tags WHERE quick_hash = ?",
+            )
+            .bind(id)
+            .bind(&hash)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+            
+            if attached > 0 {
+                sqlx::query("UPDATE media_metadata SET quick_hash = ? WHERE id = ?")
+                    .bind(&hash)
+                    .bind(id)
+                    .execute(&mut *tx)
+                    .await?;
+                sqlx::query("DELETE FROM orphaned_tags WHERE quick_hash = ?")
+                    .bind(&hash)
+                    .execute(&mut *tx)
+                    .await?;
+                restored += 1;
+            }
+            tx.commit().await?;
+        }
+    }
+    
+    hash_tagged_media(pool).await?;
+    
+    if restored > 0 {
+        debug!("Restored tags of {} moved files", restored);
+    }
+    Ok(restored)
+}
+
+// ============================================================================
+// Commands
+// ============================================================================
+
+/// Tauri command to list all tags with their media counts
+#[tauri::command]
+pub async fn get_tags(pool: State<'_, DbPool>) -> Result<Vec<TagCount>, String> {
+    list_tags(&pool).await.map_err(|e| e.to_string())
+}
+
+/// Tauri command to get the tags of one media item
+#[tauri::command]
+pub async fn get_tags_for_media(pool: State<'_, DbPool>, media_id: i64) -> Result<Vec<String>, String> {
+    get_media_tags(&pool, media_id).await.map_err(|e| e.to_string())
+}
+
+/// Tauri command to add tags to several media items at once
+///
+/// # Arguments
+///
+/// * `pool` - Database connection pool (injected by Tauri)
+/// * `media_ids` - IDs of the media rows to tag
+/// * `tags` - Tag paths such as `Places/Portugal/Lisbon`
+///
+/// # Returns
+///
+/// Returns the number of new tag assignments
+#[tauri::command]
+pub async fn add_tags_to_media(
+    pool: State<'_, DbPool>,
+    media_ids: Vec<i64>,
+    tags: Vec<String>,
+) -> Result<u64, String> {
+    add_tags(&pool, &media_ids, &tags).await.map_err(|e| e.to_string())
+}
+
+/// Tauri command to remove tags from several media items at once
+///
+/// # Returns
+///
+/// Returns the number of removed tag assignments
+#[tauri::command]
+pub async fn remove_tags_from_media(
+    pool: State<'_, DbPool>,
+    media_ids: Vec<i64>,
+    tags: Vec<String>,
+) -> Result<u64, String> {
+    remove_tags(&pool, &media_ids, &tags).await.map_err(|e| e.to_string())
+}
+
+/// Tauri command to rename a tag; merges into `to` if that tag exists
+#[tauri::command]
+pub async fn rename_tag_command(pool: State<'_, DbPool>, from: String, to: String) -> Result<(), String> {
+    rename_tag(&pool, &from, &to).await.map_err(|e| e.to_string())
+}
+
+/// Tauri command to merge several tags into one
+#[tauri::command]
+pub async fn merge_tags_command(
+    pool: State<'_, DbPool>,
+    sources: Vec<String>,
+    target: String,
+) -> Result<(), String> {
+    merge_tags(&pool, &sources, &target).await.map_err(|e| e.to_string())
+}
+
+/// Tauri command to delete a tag and its descendants
+#[tauri::command]
+pub async fn delete_tag_command(pool: State<'_, DbPool>, path: String) -> Result<(), String> {
+    delete_tag(&pool, &path).await.map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{self, InsertMediaParams};
+    use crate::search::{self, SearchFilters};
+    use chrono::Utc;
+    
+    fn params(folder_id: i64, path: &str, size: i64) -> InsertMediaParams {
+        InsertMediaParams {
+            folder_id,
+            file_path: path.to_string(),
+            file_name: path.rsplit('/').next().unwrap().to_string(),
+            file_type: "jpg".to_string(),
+            file_size: size,
+            modified_date: Utc::now(),
+            media_type: Some("image".to_string()),
+            ..Default::default()
+        }
+    }
+    
+    /// Pool with three rows (IDs 1-3) in one folder
+    async fn seeded_pool() -> DbPool {
+        let pool = database::open_test_pool().await;
+        let folder_id = database::upsert_scanned_folder(&pool, "/lib", "lib", 3).await.unwrap();
+        for (i, name) in ["a.jpg", "b.jpg", "c.jpg"].iter().enumerate() {
+            database::insert_media_metadata(&pool, params(folder_id, &format!("/lib/{}", name), i as i64))
+                .await
+                .unwrap();
+        }
+        pool
+    }
+    
+    async fn matching(pool: &DbPool, expression: &str) -> Vec<i64> {
+        let filters = SearchFilters {
+            tags: Some(TagExpr::parse(expression).unwrap()),
+            ..Default::default()
+        };
+        let mut ids: Vec<i64> = search::find_media(pool, &filters, 10)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|m| m.id)
+            .collect();
+        ids.sort();
+        ids
+    }
+    
+    fn tags(paths: &[&str]) -> Vec<String> {
+        paths.iter().map(|path| path.to_string()).collect()
+    }
+    
+    #[test]
+    fn test_normalize_tag_path() {
+        assert_eq!(normalize_tag_path(" Places / Portugal//Lisbon/ ").unwrap(), "Places/Portugal/Lisbon");
+        assert!(normalize_tag_path(" / ").is_err());
+        assert!(normalize_tag_path("a\"b").is_err());
+    }
+    
+    #[test]
+    fn test_parse_tag_expression() {
+        let expr = TagExpr::parse("places/portugal and (Family or \"Best Friends\") not Work").unwrap();
+        assert_eq!(
+            expr.to_string(),
+            "((places/portugal AND (Family OR \"Best Friends\")) AND NOT Work)"
+        );
+        assert_eq!(TagExpr::parse(&expr.to_string()).unwrap(), expr);
+        
+        assert!(TagExpr::parse("a AND").is_err());
+        assert!(TagExpr::parse("(a OR b").is_err());
+        assert!(TagExpr::parse("a )").is_err());
+        assert!(matches!(
+            TagExpr::parse("Family AND \"Best Friends"),
+            Err(TagError::InvalidExpression(_))
+        ));
+    }
+    
+    #[tokio::test]
+    async fn test_hierarchical_tags_and_counts() {
+        let pool = seeded_pool().await;
+        add_tags(&pool, &[1, 2], &tags(&["Places/Portugal/Lisbon"])).await.unwrap();
+        add_tags(&pool, &[3], &tags(&["places/portugal/Porto", "Family"])).await.unwrap();
+        
+        let counts = list_tags(&pool).await.unwrap();
+        let count = |path: &str| {
+            let tag = counts.iter().find(|tag| tag.path == path).unwrap();
+            (tag.media_count, tag.total_count)
+        };
+        assert_eq!(count("Places"), (0, 3));
+        assert_eq!(count("Places/Portugal/Lisbon"), (2, 2));
+        assert_eq!(count("Family"), (1, 1));
+        
+        assert_eq!(matching(&pool, "places/portugal").await, [1, 2, 3]);
+        assert_eq!(matching(&pool, "Places/Portugal AND NOT Family").await, [1, 2]);
+        assert_eq!(matching(&pool, "Places/Portugal/Porto OR Lisbon").await, [3]);
+        
+        assert_eq!(remove_tags(&pool, &[1], &tags(&["Places/Portugal/Lisbon"])).await.unwrap(), 1);
+        assert_eq!(get_media_tags(&pool, 1).await.unwrap(), Vec::<String>::new());
+        assert_eq!(get_media_tags(&pool, 3).await.unwrap(), ["Family", "places/portugal/Porto"]);
+    }
+    
+    #[tokio::test]
+    async fn test_rename_and_merge_tags() {
+        let pool = seeded_pool().await;
+        add_tags(&pool, &[1], &tags(&["Trips/Lisbon"])).await.unwrap();
+        add_tags(&pool, &[2], &tags(&["Places/Portugal/Lisbon"])).await.unwrap();
+        add_tags(&pool, &[3], &tags(&["Holiday"])).await.unwrap();
+        
+        // Lisbon exists below the target, so it is merged
+        rename_tag(&pool, "Trips", "Places/Portugal").await.unwrap();
+        assert_eq!(get_media_tags(&pool, 1).await.unwrap(), ["Places/Portugal/Lisbon"]);
+        
+        merge_tags(&pool, &tags(&["Holiday"]), "Places/Portugal/Lisbon").await.unwrap();
+        let paths: Vec<String> = list_tags(&pool).await.unwrap().into_iter().map(|tag| tag.path).collect();
+        assert_eq!(paths, ["Places", "Places/Portugal", "Places/Portugal/Lisbon"]);
+        assert_eq!(matching(&pool, "Lisbon").await, Vec::<i64>::new());
+        assert_eq!(matching(&pool, "Places/Portugal/Lisbon").await, [1, 2, 3]);
+        
+        assert!(rename_tag(&pool, "Places", "Places/Sub").await.is_err());
+        assert!(matches!(rename_tag(&pool, "Nope", "Other").await, Err(TagError::NotFound(_))));
+    }
+    
+    #[tokio::test]
+    async fn test_tags_survive_a_move() {
+        let dir = std::env::temp_dir().join(format!("fmlm_tags_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let old_path = dir.join("old.jpg").to_string_lossy().to_string();
+        let new_path = dir.join("new.jpg").to_string_lossy().to_string();
+        std::fs::write(&old_path, b"same content").unwrap();
+        
+        let pool = database::open_test_pool().await;
+        let folder_id = database::upsert_scanned_folder(&pool, "/lib", "lib", 1).await.unwrap();
+        let old_id = database::insert_media_metadata(&pool, params(folder_id, &old_path, 12)).await.unwrap();
+        add_tags(&pool, &[old_id], &tags(&["Family"])).await.unwrap();
+        
+        std::fs::rename(&old_path, &new_path).unwrap();
+        database::delete_media_metadata(&pool, &old_path).await.unwrap();
+        let new_id = database::insert_media_metadata(&pool, params(folder_id, &new_path, 12)).await.unwrap();
+        
+        assert_eq!(restore_moved_tags(&pool, &[new_path.clone()]).await.unwrap(), 1);
+        assert_eq!(get_media_tags(&pool, new_id).await.unwrap(), ["Family"]);
+        
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
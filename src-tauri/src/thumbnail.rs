@@ -2,14 +2,20 @@
 //!
 //! Handles thumbnail generation for images and videos with persistent caching.
 //! Uses SHA-256 hashes of file paths as cache keys to avoid regenerating thumbnails.
+//! Images decoded for a new thumbnail also get their perceptual hashes
+//! computed, so similar-photo grouping doesn't have to decode them again.
 
-use image::{imageops::FilterType, DynamicImage, ImageFormat};
+use image::{imageops::FilterType, DynamicImage, ImageFormat, ImageReader};
 use sha2::{Digest, Sha256};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::fs;
 use std::ffi::CString;
 use thiserror::Error;
 use tracing::{info, warn, error, debug, instrument};
+use crate::media_detect;
+use crate::perceptual::ImageHashes;
+use crate::raw;
+use crate::scanner::MediaType;
 
 /// Thumbnail dimensions (width x height)
 const THUMBNAIL_SIZE: u32 = 256;
@@ -31,6 +37,9 @@ pub enum ThumbnailError {
     
     #[error("FFmpeg error: {0}")]
     Ffmpeg(String),
+    
+    #[error("RAW error: {0}")]
+    Raw(#[from] raw::RawError),
 }
 
 pub type ThumbnailResult<T> = Result<T, ThumbnailError>;
@@ -72,28 +81,76 @@ pub fn thumbnail_exists(file_path: &str) -> ThumbnailResult<bool> {
     Ok(thumbnail_path.exists())
 }
 
+/// Remove the cached thumbnail for a file, if any
+///
+/// Returns `true` when a cached thumbnail was deleted.
+pub fn invalidate_thumbnail(file_path: &str) -> ThumbnailResult<bool> {
+    let thumbnail_path = get_thumbnail_path(file_path)?;
+    if thumbnail_path.exists() {
+        fs::remove_file(&thumbnail_path)?;
+        debug!("Invalidated cached thumbnail for {}", file_path);
+        return Ok(true);
+    }
+    Ok(false)
+}
+
 /// Resize an image to thumbnail size maintaining aspect ratio
 fn resize_to_thumbnail(img: DynamicImage) -> DynamicImage {
     img.resize(THUMBNAIL_SIZE, THUMBNAIL_SIZE, FilterType::Lanczos3)
 }
 
+/// A generated (or cached) thumbnail
+#[derive(Debug, Clone)]
+pub struct GeneratedThumbnail {
+    /// Path of the cached thumbnail
+    pub path: String,
+    /// Perceptual hashes, when the source image was decoded for this thumbnail
+    pub image_hashes: Option<ImageHashes>,
+}
+
+impl From<String> for GeneratedThumbnail {
+    fn from(path: String) -> Self {
+        GeneratedThumbnail {
+            path,
+            image_hashes: None,
+        }
+    }
+}
+
+/// Decode an image file, using the embedded preview of camera RAW files
+pub fn load_image(file_path: &str) -> ThumbnailResult<DynamicImage> {
+    let is_raw = media_detect::detect_file(Path::new(file_path))
+        .is_some_and(|detection| detection.format.is_raw);
+    
+    if is_raw {
+        load_raw_preview(file_path)
+    } else {
+        // Decode by content so misnamed and extensionless images still open
+        let img = ImageReader::open(file_path)?
+            .with_guessed_format()?
+            .decode()
+            .map_err(|e| {
+                error!("Failed to open image: {}", e);
+                e
+            })?;
+        Ok(img)
+    }
+}
+
 /// Generate a thumbnail for an image file
 #[instrument(skip_all, fields(file_path = %file_path))]
-pub fn generate_image_thumbnail(file_path: &str) -> ThumbnailResult<String> {
+pub fn generate_image_thumbnail(file_path: &str) -> ThumbnailResult<GeneratedThumbnail> {
     debug!("Starting image thumbnail generation");
     let thumbnail_path = get_thumbnail_path(file_path)?;
     
     if thumbnail_path.exists() {
         debug!("Thumbnail already exists in cache");
-        return Ok(thumbnail_path.to_string_lossy().to_string());
+        return Ok(thumbnail_path.to_string_lossy().to_string().into());
     }
     
     info!("Generating new image thumbnail");
-    let img = image::open(file_path)
-        .map_err(|e| {
-            error!("Failed to open image: {}", e);
-            e
-        })?;
+    let img = load_image(file_path)?;
+    let image_hashes = ImageHashes::compute(&img);
     
     let thumbnail = resize_to_thumbnail(img);
     thumbnail.save_with_format(&thumbnail_path, ImageFormat::Jpeg)
@@ -103,229 +160,384 @@ pub fn generate_image_thumbnail(file_path: &str) -> ThumbnailResult<String> {
         })?;
     
     info!("Image thumbnail generated successfully");
-    Ok(thumbnail_path.to_string_lossy().to_string())
+    Ok(GeneratedThumbnail {
+        path: thumbnail_path.to_string_lossy().to_string(),
+        image_hashes: Some(image_hashes),
+    })
 }
 
-/// Generate a thumbnail for a video file by extracting a frame
-#[instrument(skip_all, fields(file_path = %file_path))]
-pub fn generate_video_thumbnail(file_path: &str) -> ThumbnailResult<String> {
-    use rsmpeg::avcodec::{AVCodec, AVCodecContext};
-    use rsmpeg::avformat::AVFormatContextInput;
-    use rsmpeg::avutil::AVFrame;
-    use rsmpeg::ffi;
-    use rsmpeg::swscale::SwsContext;
-    
-    debug!("Starting video thumbnail generation");
-    let thumbnail_path = get_thumbnail_path(file_path)?;
-    
-    if thumbnail_path.exists() {
-        debug!("Video thumbnail already exists in cache");
-        return Ok(thumbnail_path.to_string_lossy().to_string());
-    }
-    
-    info!("Generating new video thumbnail");
-    let c_file_path = CString::new(file_path)
-        .map_err(|_e| {
-            error!("Invalid file path (contains null bytes)");
-            ThumbnailError::VideoDecoding("Invalid file path".to_string())
-        })?;
-    
-    debug!("Opening video file");
-    let mut input_context = AVFormatContextInput::open(&c_file_path)
-        .map_err(|e| {
-            error!("Failed to open video file: {:?}", e);
-            ThumbnailError::VideoDecoding(format!("Failed to open video: {:?}", e))
-        })?;
-    
-    debug!("Reading stream info");
-    input_context.dump(0, &c_file_path)
+/// Decode the embedded JPEG preview of a camera RAW file
+///
+/// The preview is rotated according to the file's orientation tag, since
+/// cameras store it in sensor orientation.
+fn load_raw_preview(file_path: &str) -> ThumbnailResult<DynamicImage> {
+    let (jpeg, orientation) = raw::extract_preview(Path::new(file_path))
         .map_err(|e| {
-            error!("Failed to read stream info: {:?}", e);
-            ThumbnailError::VideoDecoding(format!("Failed to read stream info: {:?}", e))
+            error!("Failed to extract RAW preview: {}", e);
+            e
         })?;
+    let img = image::load_from_memory_with_format(&jpeg, ImageFormat::Jpeg)?;
     
-    debug!("Finding video stream and setting up decoder");
-    let (stream_index, mut decoder) = {
-        let (stream_index, stream) = input_context
-            .streams()
-            .into_iter()
-            .enumerate()
-            .find(|(_, stream)| {
-                stream.codecpar().codec_type == ffi::AVMEDIA_TYPE_VIDEO
-            })
-            .ok_or_else(|| {
-                error!("No video stream found in file");
-                ThumbnailError::VideoDecoding("No video stream found".to_string())
-            })?;
+    Ok(match orientation {
+        3 => img.rotate180(),
+        6 => img.rotate90(),
+        8 => img.rotate270(),
+        _ => img,
+    })
+}
+
+/// Maximum number of packets read after a seek before giving up on a frame
+const MAX_PACKETS: usize = 100;
+
+/// An open video stream that decodes RGB frames at given positions
+///
+/// Built on FFmpeg through rsmpeg. Used for thumbnails and for the frame
+/// fingerprints of near-duplicate video detection.
+pub struct VideoFrameReader {
+    input_context: rsmpeg::avformat::AVFormatContextInput,
+    decoder: rsmpeg::avcodec::AVCodecContext,
+    scaler: rsmpeg::swscale::SwsContext,
+    stream_index: usize,
+    time_base: rsmpeg::ffi::AVRational,
+    width: i32,
+    height: i32,
+}
+
+impl VideoFrameReader {
+    /// Open the first real video stream of a file (cover art is skipped)
+    pub fn open(file_path: &str) -> ThumbnailResult<Self> {
+        use rsmpeg::avcodec::{AVCodec, AVCodecContext};
+        use rsmpeg::avformat::AVFormatContextInput;
+        use rsmpeg::ffi;
+        use rsmpeg::swscale::SwsContext;
         
-        debug!("Found video stream at index {}", stream_index);
-        let codec_id = stream.codecpar().codec_id;
-        let decoder = AVCodec::find_decoder(codec_id)
-            .ok_or_else(|| {
-                error!("Failed to find decoder for codec");
-                ThumbnailError::VideoDecoding("Failed to find decoder".to_string())
+        let c_file_path = CString::new(file_path)
+            .map_err(|_e| {
+                error!("Invalid file path (contains null bytes)");
+                ThumbnailError::VideoDecoding("Invalid file path".to_string())
             })?;
         
-        let mut decoder_context = AVCodecContext::new(&decoder);
-        decoder_context
-            .apply_codecpar(&stream.codecpar())
+        debug!("Opening video file");
+        let mut input_context = AVFormatContextInput::open(&c_file_path)
             .map_err(|e| {
-                error!("Failed to copy codec parameters: {:?}", e);
-                ThumbnailError::VideoDecoding(format!("Failed to copy codec parameters: {:?}", e))
+                error!("Failed to open video file: {:?}", e);
+                ThumbnailError::VideoDecoding(format!("Failed to open video: {:?}", e))
             })?;
         
-        decoder_context
-            .open(None)
+        debug!("Reading stream info");
+        input_context.dump(0, &c_file_path)
             .map_err(|e| {
-                error!("Failed to open decoder: {:?}", e);
-                ThumbnailError::VideoDecoding(format!("Failed to open decoder: {:?}", e))
+                error!("Failed to read stream info: {:?}", e);
+                ThumbnailError::VideoDecoding(format!("Failed to read stream info: {:?}", e))
             })?;
         
-        debug!("Decoder opened successfully");
-        (stream_index, decoder_context)
-    };
-    
-    let duration = input_context.duration;
-    if duration > 0 {
-        let seek_target = (duration as f64 * 0.1) as i64;
-        let _ = unsafe {
-            ffi::avformat_seek_file(
-                input_context.as_mut_ptr(),
-                stream_index as i32,
-                i64::MIN,
-                seek_target,
-                seek_target,
-                0,
-            )
+        debug!("Finding video stream and setting up decoder");
+        let (stream_index, time_base, decoder) = {
+            let (stream_index, stream) = input_context
+                .streams()
+                .into_iter()
+                .enumerate()
+                .find(|(_, stream)| {
+                    stream.codecpar().codec_type == ffi::AVMEDIA_TYPE_VIDEO
+                        && stream.disposition & ffi::AV_DISPOSITION_ATTACHED_PIC as i32 == 0
+                })
+                .ok_or_else(|| {
+                    error!("No video stream found in file");
+                    ThumbnailError::VideoDecoding("No video stream found".to_string())
+                })?;
+            
+            debug!("Found video stream at index {}", stream_index);
+            let codec_id = stream.codecpar().codec_id;
+            let decoder = AVCodec::find_decoder(codec_id)
+                .ok_or_else(|| {
+                    error!("Failed to find decoder for codec");
+                    ThumbnailError::VideoDecoding("Failed to find decoder".to_string())
+                })?;
+            
+            let mut decoder_context = AVCodecContext::new(&decoder);
+            decoder_context
+                .apply_codecpar(&stream.codecpar())
+                .map_err(|e| {
+                    error!("Failed to copy codec parameters: {:?}", e);
+                    ThumbnailError::VideoDecoding(format!("Failed to copy codec parameters: {:?}", e))
+                })?;
+            
+            decoder_context
+                .open(None)
+                .map_err(|e| {
+                    error!("Failed to open decoder: {:?}", e);
+                    ThumbnailError::VideoDecoding(format!("Failed to open decoder: {:?}", e))
+                })?;
+            
+            debug!("Decoder opened successfully");
+            (stream_index, stream.time_base, decoder_context)
         };
+        
+        let width = decoder.width;
+        let height = decoder.height;
+        let scaler = SwsContext::get_context(
+            width,
+            height,
+            decoder.pix_fmt,
+            width,
+            height,
+            ffi::AV_PIX_FMT_RGB24,
+            ffi::SWS_BILINEAR,
+            None,
+            None,
+            None,
+        ).ok_or_else(|| ThumbnailError::VideoDecoding("Failed to create scaler".to_string()))?;
+        
+        Ok(VideoFrameReader {
+            input_context,
+            decoder,
+            scaler,
+            stream_index,
+            time_base,
+            width,
+            height,
+        })
     }
     
-    let src_width = decoder.width as i32;
-    let src_height = decoder.height as i32;
-    let src_format = decoder.pix_fmt;
-    
-    let mut scaler = SwsContext::get_context(
-        src_width,
-        src_height,
-        src_format,
-        src_width,
-        src_height,
-        ffi::AV_PIX_FMT_RGB24,
-        ffi::SWS_BILINEAR,
-        None,
-        None,
-        None,
-    ).ok_or_else(|| ThumbnailError::VideoDecoding("Failed to create scaler".to_string()))?;
-    
-    let mut frame_found = false;
-    let mut rgb_data: Option<Vec<u8>> = None;
-    let mut packets_checked = 0;
-    const MAX_PACKETS: usize = 100;
+    /// Duration of the file in `AV_TIME_BASE` units (0 when unknown)
+    pub fn duration(&self) -> i64 {
+        self.input_context.duration.max(0)
+    }
     
-    while let Ok(packet) = input_context.read_packet() {
-        if let Some(pkt) = packet {
+    /// Decode a frame at a fraction (0.0 to 1.0) of the duration
+    ///
+    /// Seeking lands on the keyframe before the position. With `accurate`
+    /// set, frames are decoded up to the position itself (or the last frame
+    /// read within the packet budget); otherwise the keyframe is returned.
+    pub fn frame_at(&mut self, fraction: f64, accurate: bool) -> ThumbnailResult<DynamicImage> {
+        use rsmpeg::ffi;
+        
+        let mut target = None;
+        let duration = self.duration();
+        if duration > 0 {
+            // Convert from AV_TIME_BASE units to the stream's time base
+            let seconds = duration as f64 * fraction / ffi::AV_TIME_BASE as f64;
+            let time_base = self.time_base.num as f64 / self.time_base.den.max(1) as f64;
+            let seek_target = if time_base > 0.0 { (seconds / time_base) as i64 } else { 0 };
+            let _ = unsafe {
+                ffi::avformat_seek_file(
+                    self.input_context.as_mut_ptr(),
+                    self.stream_index as i32,
+                    i64::MIN,
+                    seek_target,
+                    seek_target,
+                    0,
+                )
+            };
+            // Drop frames buffered before the seek
+            unsafe { ffi::avcodec_flush_buffers(self.decoder.as_mut_ptr()) };
+            target = Some(seek_target);
+        }
+        
+        let mut rgb_data: Option<Vec<u8>> = None;
+        let mut packets_checked = 0;
+        
+        while let Ok(Some(pkt)) = self.input_context.read_packet() {
             if packets_checked >= MAX_PACKETS {
                 break;
             }
             packets_checked += 1;
             
-            if pkt.stream_index != stream_index as i32 {
+            if pkt.stream_index != self.stream_index as i32 {
                 continue;
             }
             
-            decoder
+            self.decoder
                 .send_packet(Some(&pkt))
                 .map_err(|e| ThumbnailError::VideoDecoding(format!("Failed to send packet: {:?}", e)))?;
             
-            loop {
-                match decoder.receive_frame() {
-                    Ok(decoded_frame) => {
-                        let mut rgb_frame = AVFrame::new();
-                        rgb_frame.set_width(src_width);
-                        rgb_frame.set_height(src_height);
-                        rgb_frame.set_format(ffi::AV_PIX_FMT_RGB24);
-                        
-                        rgb_frame
-                            .alloc_buffer()
-                            .map_err(|e| ThumbnailError::VideoDecoding(format!("Failed to allocate frame: {:?}", e)))?;
-                        
-                        scaler
-                            .scale_frame(&decoded_frame, 0, src_height, &mut rgb_frame)
-                            .map_err(|e| ThumbnailError::VideoDecoding(format!("Failed to scale frame: {:?}", e)))?;
-                        
-                        let buffer_size = unsafe {
-                            ffi::av_image_get_buffer_size(
-                                ffi::AV_PIX_FMT_RGB24,
-                                src_width,
-                                src_height,
-                                1,
-                            )
-                        };
-                        
-                        if buffer_size > 0 {
-                            let mut buffer = vec![0u8; buffer_size as usize];
-                            unsafe {
-                                ffi::av_image_copy_to_buffer(
-                                    buffer.as_mut_ptr(),
-                                    buffer_size,
-                                    rgb_frame.data.as_ptr() as *const *const u8,
-                                    rgb_frame.linesize.as_ptr(),
-                                    ffi::AV_PIX_FMT_RGB24,
-                                    src_width,
-                                    src_height,
-                                    1,
-                                );
-                            }
-                            rgb_data = Some(buffer);
-                            frame_found = true;
-                        }
-                        break;
-                    }
-                    Err(rsmpeg::error::RsmpegError::DecoderDrainError)
-                    | Err(rsmpeg::error::RsmpegError::DecoderFlushedError) => break,
-                    Err(_) => break,
+            let mut reached = false;
+            while let Ok(decoded_frame) = self.decoder.receive_frame() {
+                rgb_data = Some(self.frame_to_rgb(&decoded_frame)?);
+                reached = !accurate
+                    || decoded_frame.pts == ffi::AV_NOPTS_VALUE
+                    || target.is_none_or(|target| decoded_frame.pts >= target);
+                if reached {
+                    break;
                 }
             }
             
-            if frame_found {
+            if reached {
                 break;
             }
-        } else {
-            break;
         }
-    }
-    
-    debug!("Flushing decoder");
-    let _ = decoder.send_packet(None);
-    
-    if let Some(buffer) = rgb_data {
-        info!("Successfully decoded video frame, creating thumbnail");
-        let img = image::RgbImage::from_raw(src_width as u32, src_height as u32, buffer)
+        
+        let buffer = rgb_data.ok_or_else(|| {
+            error!("No frames could be decoded from video after checking {} packets", packets_checked);
+            ThumbnailError::VideoDecoding("No frames could be decoded".to_string())
+        })?;
+        let img = image::RgbImage::from_raw(self.width as u32, self.height as u32, buffer)
             .ok_or_else(|| {
                 error!("Failed to create image from decoded frame data");
                 ThumbnailError::VideoDecoding("Failed to create image from frame".to_string())
             })?;
         
-        let dynamic_img = DynamicImage::ImageRgb8(img);
-        let thumbnail = resize_to_thumbnail(dynamic_img);
-        thumbnail.save_with_format(&thumbnail_path, ImageFormat::Jpeg)
-            .map_err(|e| {
-                error!("Failed to save video thumbnail: {}", e);
-                e
-            })?;
+        Ok(DynamicImage::ImageRgb8(img))
+    }
+    
+    /// Convert a decoded frame to packed RGB24 bytes
+    fn frame_to_rgb(&mut self, decoded_frame: &rsmpeg::avutil::AVFrame) -> ThumbnailResult<Vec<u8>> {
+        use rsmpeg::avutil::AVFrame;
+        use rsmpeg::ffi;
         
-        info!("Video thumbnail generated successfully");
-        Ok(thumbnail_path.to_string_lossy().to_string())
-    } else {
-        error!("No frames could be decoded from video after checking {} packets", packets_checked);
-        Err(ThumbnailError::VideoDecoding("No frames could be decoded".to_string()))
+        let mut rgb_frame = AVFrame::new();
+        rgb_frame.set_width(self.width);
+        rgb_frame.set_height(self.height);
+        rgb_frame.set_format(ffi::AV_PIX_FMT_RGB24);
+        
+        rgb_frame
+            .alloc_buffer()
+            .map_err(|e| ThumbnailError::VideoDecoding(format!("Failed to allocate frame: {:?}", e)))?;
+        
+        self.scaler
+            .scale_frame(decoded_frame, 0, self.height, &mut rgb_frame)
+            .map_err(|e| ThumbnailError::VideoDecoding(format!("Failed to scale frame: {:?}", e)))?;
+        
+        let buffer_size = unsafe {
+            ffi::av_image_get_buffer_size(
+                ffi::AV_PIX_FMT_RGB24,
+                self.width,
+                self.height,
+                1,
+            )
+        };
+        if buffer_size <= 0 {
+            return Err(ThumbnailError::VideoDecoding("Invalid frame size".to_string()));
+        }
+        
+        let mut buffer = vec![0u8; buffer_size as usize];
+        unsafe {
+            ffi::av_image_copy_to_buffer(
+                buffer.as_mut_ptr(),
+                buffer_size,
+                rgb_frame.data.as_ptr() as *const *const u8,
+                rgb_frame.linesize.as_ptr(),
+                ffi::AV_PIX_FMT_RGB24,
+                self.width,
+                self.height,
+                1,
+            );
+        }
+        Ok(buffer)
     }
 }
 
+/// Generate a thumbnail for a video file by extracting a frame
+#[instrument(skip_all, fields(file_path = %file_path))]
+pub fn generate_video_thumbnail(file_path: &str) -> ThumbnailResult<String> {
+    debug!("Starting video thumbnail generation");
+    let thumbnail_path = get_thumbnail_path(file_path)?;
+    
+    if thumbnail_path.exists() {
+        debug!("Video thumbnail already exists in cache");
+        return Ok(thumbnail_path.to_string_lossy().to_string());
+    }
+    
+    info!("Generating new video thumbnail");
+    let mut reader = VideoFrameReader::open(file_path)?;
+    let frame = reader.frame_at(0.1, false)?;
+    
+    info!("Successfully decoded video frame, creating thumbnail");
+    let thumbnail = resize_to_thumbnail(frame);
+    thumbnail.save_with_format(&thumbnail_path, ImageFormat::Jpeg)
+        .map_err(|e| {
+            error!("Failed to save video thumbnail: {}", e);
+            e
+        })?;
+    
+    info!("Video thumbnail generated successfully");
+    Ok(thumbnail_path.to_string_lossy().to_string())
+}
+
+/// Decode frames at several fractions of a video's duration
+///
+/// Frames that can't be decoded are skipped.
+pub fn sample_video_frames(file_path: &str, fractions: &[f64]) -> ThumbnailResult<Vec<DynamicImage>> {
+    let mut reader = VideoFrameReader::open(file_path)?;
+    let mut frames = Vec::with_capacity(fractions.len());
+    for &fraction in fractions {
+        match reader.frame_at(fraction, true) {
+            Ok(frame) => frames.push(frame),
+            Err(e) => warn!("Cannot decode frame at {:.0}% of {}: {}", fraction * 100.0, file_path, e),
+        }
+    }
+    Ok(frames)
+}
+
+/// Generate a thumbnail for an audio file from its embedded cover art
+///
+/// FFmpeg exposes cover art (ID3 `APIC`, FLAC pictures, MP4 `covr`) as a video
+/// stream with the attached-picture disposition whose packet holds the encoded
+/// image, so it is decoded directly instead of through a video decoder.
+#[instrument(skip_all, fields(file_path = %file_path))]
+pub fn generate_audio_thumbnail(file_path: &str) -> ThumbnailResult<String> {
+    use rsmpeg::avformat::AVFormatContextInput;
+    use rsmpeg::ffi;
+    
+    debug!("Starting audio thumbnail generation");
+    let thumbnail_path = get_thumbnail_path(file_path)?;
+    
+    if thumbnail_path.exists() {
+        debug!("Audio thumbnail already exists in cache");
+        return Ok(thumbnail_path.to_string_lossy().to_string());
+    }
+    
+    let c_file_path = CString::new(file_path)
+        .map_err(|_e| ThumbnailError::VideoDecoding("Invalid file path".to_string()))?;
+    
+    let input_context = AVFormatContextInput::open(&c_file_path)
+        .map_err(|e| {
+            error!("Failed to open audio file: {:?}", e);
+            ThumbnailError::Ffmpeg(format!("Failed to open audio: {:?}", e))
+        })?;
+    
+    let cover = input_context
+        .streams()
+        .into_iter()
+        .find(|stream| stream.disposition & ffi::AV_DISPOSITION_ATTACHED_PIC as i32 != 0)
+        .and_then(|stream| {
+            let packet = &stream.attached_pic;
+            if packet.data.is_null() || packet.size <= 0 {
+                return None;
+            }
+            // SAFETY: the packet is owned by the stream, which outlives this borrow
+            let data = unsafe { std::slice::from_raw_parts(packet.data, packet.size as usize) };
+            Some(data.to_vec())
+        })
+        .ok_or_else(|| {
+            debug!("Audio file has no cover art");
+            ThumbnailError::UnsupportedFormat
+        })?;
+    
+    info!("Generating new audio thumbnail from cover art");
+    let img = image::load_from_memory(&cover)?;
+    let thumbnail = resize_to_thumbnail(img);
+    thumbnail.save_with_format(&thumbnail_path, ImageFormat::Jpeg)
+        .map_err(|e| {
+            error!("Failed to save audio thumbnail: {}", e);
+            e
+        })?;
+    
+    info!("Audio thumbnail generated successfully");
+    Ok(thumbnail_path.to_string_lossy().to_string())
+}
+
 /// Generate a thumbnail for any supported media file
-pub fn generate_thumbnail(file_path: &str, is_video: bool) -> ThumbnailResult<String> {
-    if is_video {
-        generate_video_thumbnail(file_path)
+///
+/// Audio files are recognized by content and use their cover art.
+pub fn generate_thumbnail(file_path: &str, is_video: bool) -> ThumbnailResult<GeneratedThumbnail> {
+    let is_audio = media_detect::detect_file(Path::new(file_path))
+        .is_some_and(|detection| detection.format.media_type == MediaType::Audio);
+    
+    if is_audio {
+        generate_audio_thumbnail(file_path).map(Into::into)
+    } else if is_video {
+        generate_video_thumbnail(file_path).map(Into::into)
     } else {
         generate_image_thumbnail(file_path)
     }
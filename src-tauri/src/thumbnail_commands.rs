@@ -1,8 +1,11 @@
 //! Tauri commands for thumbnail operations
 
-use crate::thumbnail;
+use crate::database::DbPool;
+use crate::similar;
+use crate::thumbnail::{self, GeneratedThumbnail};
 use serde::{Deserialize, Serialize};
-use tracing::{info, error, debug};
+use tracing::{info, error, debug, warn};
+use tauri::State;
 use tokio::sync::Semaphore;
 use std::sync::Arc;
 use once_cell::sync::Lazy;
@@ -21,6 +24,15 @@ fn thumbnail_to_data_url(thumbnail_path: &str) -> Result<String, String> {
     Ok(format!("data:image/jpeg;base64,{}", base64_data))
 }
 
+/// Store the perceptual hashes computed while generating an image thumbnail
+async fn store_image_hashes(pool: &DbPool, file_path: &str, thumbnail: &GeneratedThumbnail) {
+    if let Some(hashes) = &thumbnail.image_hashes {
+        if let Err(e) = similar::store_image_hashes(pool, file_path, hashes).await {
+            warn!("Failed to store perceptual hashes for {}: {}", file_path, e);
+        }
+    }
+}
+
 /// Response for thumbnail generation request
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ThumbnailResponse {
@@ -34,6 +46,7 @@ pub struct ThumbnailResponse {
 ///
 /// # Arguments
 ///
+/// * `pool` - Database connection pool (injected by Tauri)
 /// * `file_path` - Path to the media file
 /// * `is_video` - Whether the file is a video
 ///
@@ -41,7 +54,7 @@ pub struct ThumbnailResponse {
 ///
 /// ThumbnailResponse with the path to the thumbnail or error
 #[tauri::command]
-pub async fn generate_thumbnail(file_path: String, is_video: bool) -> ThumbnailResponse {
+pub async fn generate_thumbnail(pool: State<'_, DbPool>, file_path: String, is_video: bool) -> ThumbnailResponse {
     info!("Thumbnail generation requested for: {}", file_path);
     
     // Acquire semaphore permit to limit concurrency
@@ -49,7 +62,9 @@ pub async fn generate_thumbnail(file_path: String, is_video: bool) -> ThumbnailR
     debug!("Acquired semaphore permit for thumbnail generation");
     
     match thumbnail::generate_thumbnail(&file_path, is_video) {
-        Ok(thumbnail_path) => {
+        Ok(thumbnail) => {
+            store_image_hashes(&pool, &file_path, &thumbnail).await;
+            let thumbnail_path = thumbnail.path;
             info!("Thumbnail generated successfully: {}", thumbnail_path);
             
             // Convert to data URL for browser compatibility
@@ -136,17 +151,22 @@ pub fn get_cache_size() -> Result<u64, String> {
 ///
 /// # Arguments
 ///
+/// * `pool` - Database connection pool (injected by Tauri)
 /// * `files` - Vector of (file_path, is_video) tuples
 ///
 /// # Returns
 ///
 /// Vector of ThumbnailResponse for each file
 #[tauri::command]
-pub async fn generate_thumbnails_batch(files: Vec<(String, bool)>) -> Vec<ThumbnailResponse> {
+pub async fn generate_thumbnails_batch(
+    pool: State<'_, DbPool>,
+    files: Vec<(String, bool)>,
+) -> Vec<ThumbnailResponse> {
     info!("Batch thumbnail generation requested for {} files", files.len());
     use futures::stream::{self, StreamExt};
     
     // Process files in parallel with concurrency limit
+    let pool: &DbPool = &pool;
     let results: Vec<ThumbnailResponse> = stream::iter(files)
         .map(|(file_path, is_video)| async move {
             // Acquire semaphore permit
@@ -154,7 +174,9 @@ pub async fn generate_thumbnails_batch(files: Vec<(String, bool)>) -> Vec<Thumbn
             debug!("Processing thumbnail for: {}", file_path);
             
             match thumbnail::generate_thumbnail(&file_path, is_video) {
-                Ok(thumbnail_path) => {
+                Ok(thumbnail) => {
+                    store_image_hashes(pool, &file_path, &thumbnail).await;
+                    let thumbnail_path = thumbnail.path;
                     debug!("Batch thumbnail generated: {}", thumbnail_path);
                     
                     // Convert to data URL for browser compatibility
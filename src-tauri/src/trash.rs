@@ -0,0 +1,738 @@
+//! Trash with restore and retention
+//!
+//! Deleting media from the library moves the file out of the way instead of
+//! removing it: either into an app-managed trash folder in the app data
+//! directory, or into the desktop trash following the freedesktop.org trash
+//! specification (Linux only). The row stays in `media_metadata` with
+//! `trashed_at` set, `file_path` pointing at the trashed file and
+//! `original_path` remembering where it came from, so tags, ratings and album
+//! membership survive a restore.
+//!
+//! Trashed rows are hidden from searches, folder listings and rescans.
+//! Items older than the configured retention period are purged on startup.
+
+use crate::database::{self, DatabaseError, DbPool, MediaMetadata};
+use crate::rescan;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, Row};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager, State};
+use thiserror::Error;
+use tracing::{debug, info, warn};
+
+/// Preference key holding where deleted files go ("app" or "system")
+pub const TRASH_LOCATION_KEY: &str = "trash_location";
+
+/// Preference key holding the retention period in days ("0" keeps items forever)
+pub const TRASH_RETENTION_KEY: &str = "trash_retention_days";
+
+/// Retention period used until the user picks one
+pub const DEFAULT_RETENTION_DAYS: u32 = 30;
+
+/// Name of the app-managed trash folder inside the app data directory
+pub const APP_TRASH_DIR: &str = "trash";
+
+/// Error types for trash operations
+#[derive(Debug, Error)]
+pub enum TrashError {
+    #[error("Database error: {0}")]
+    Database(#[from] DatabaseError),
+    
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+    
+    #[error("Media not found: {0}")]
+    NotFound(i64),
+    
+    #[error("Media {0} is already in the trash")]
+    AlreadyTrashed(i64),
+    
+    #[error("Media {0} is not in the trash")]
+    NotTrashed(i64),
+    
+    #[error("Cannot restore, a file already exists at {0}")]
+    RestoreConflict(String),
+}
+
+impl From<sqlx::Error> for TrashError {
+    fn from(err: sqlx::Error) -> Self {
+        TrashError::Database(err.into())
+    }
+}
+
+pub type TrashResult<T> = Result<T, TrashError>;
+
+/// Where deleted files are moved
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TrashLocation {
+    /// The app's own trash folder
+    #[default]
+    App,
+    /// The desktop trash (freedesktop.org specification); falls back to the
+    /// app trash on other platforms
+    System,
+}
+
+/// User-configurable trash behavior
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrashSettings {
+    pub location: TrashLocation,
+    /// Days an item stays in the trash before it is purged (0 = forever)
+    pub retention_days: u32,
+}
+
+impl Default for TrashSettings {
+    fn default() -> Self {
+        Self {
+            location: TrashLocation::App,
+            retention_days: DEFAULT_RETENTION_DAYS,
+        }
+    }
+}
+
+/// A trashed media row
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct TrashedMedia {
+    /// The row itself; `file_path` points into the trash
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub media: MediaMetadata,
+    /// Path the file is restored to
+    pub original_path: String,
+    pub trashed_at: DateTime<Utc>,
+}
+
+/// Result of a bulk trash or restore operation
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TrashOutcome {
+    /// IDs of the rows that were moved
+    pub succeeded: Vec<i64>,
+    /// Rows that could not be moved, with the reason
+    pub failed: Vec<TrashFailure>,
+}
+
+/// A row a bulk operation could not move
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashFailure {
+    pub media_id: i64,
+    pub error: String,
+}
+
+/// Loads the trash settings from `user_preferences`
+pub async fn load_trash_settings(pool: &DbPool) -> database::DatabaseResult<TrashSettings> {
+    let mut settings = TrashSettings::default();
+    
+    if let Some(value) = database::get_preference(pool, TRASH_LOCATION_KEY).await? {
+        settings.location = if value == "system" { TrashLocation::System } else { TrashLocation::App };
+    }
+    
+    if let Some(value) = database::get_preference(pool, TRASH_RETENTION_KEY).await? {
+        match value.parse() {
+            Ok(days) => settings.retention_days = days,
+            Err(e) => warn!("Invalid {} preference, using default: {}", TRASH_RETENTION_KEY, e),
+        }
+    }
+    
+    Ok(settings)
+}
+
+/// Returns the app-managed trash folder
+pub fn app_trash_dir(app: &AppHandle) -> TrashResult<PathBuf> {
+    let app_data_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| io::Error::new(io::ErrorKind::NotFound, e.to_string()))?;
+    Ok(app_data_dir.join(APP_TRASH_DIR))
+}
+
+/// Moves a file, copying it when source and target are on different filesystems
+///
+/// A copy that fails midway is removed so no partial file is left behind.
+fn move_file(from: &Path, to: &Path) -> io::Result<()> {
+    match fs::rename(from, to) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            if let Err(e) = fs::copy(from, to) {
+                let _ = fs::remove_file(to);
+                return Err(e);
+            }
+            fs::remove_file(from)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Home trash as defined by the freedesktop.org trash specification
+mod freedesktop {
+    use super::move_file;
+    use chrono::Local;
+    use std::fs::{self, OpenOptions};
+    use std::io::{self, Write};
+    use std::path::{Path, PathBuf};
+    
+    /// `$XDG_DATA_HOME/Trash`, usually `~/.local/share/Trash`
+    pub fn home_trash() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("Trash"))
+    }
+    
+    /// Percent-encodes a path for the `Path=` key of a `.trashinfo` file
+    fn encode_path(path: &Path) -> String {
+        let mut encoded = String::new();
+        for byte in path.to_string_lossy().bytes() {
+            match byte {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                    encoded.push(byte as char)
+                }
+                _ => encoded.push_str(&format!("%{:02X}", byte)),
+            }
+        }
+        encoded
+    }
+    
+    /// Name to try for the `attempt`-th collision (`photo.jpg`, `photo (2).jpg`, ...)
+    fn candidate_name(path: &Path, attempt: u32) -> String {
+        let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        if attempt == 1 {
+            return name;
+        }
+        match (path.file_stem(), path.extension()) {
+            (Some(stem), Some(ext)) => {
+                format!("{} ({}).{}", stem.to_string_lossy(), attempt, ext.to_string_lossy())
+            }
+            _ => format!("{} ({})", name, attempt),
+        }
+    }
+    
+    /// Moves a file into a trash directory
+    ///
+    /// The `.trashinfo` file is created first, exclusively, which reserves
+    /// the name in `files/` as the specification requires.
+    ///
+    /// # Returns
+    ///
+    /// Returns the trashed file's path and its `.trashinfo` path
+    pub fn move_to_trash(path: &Path, trash_root: &Path) -> io::Result<(PathBuf, PathBuf)> {
+        let files_dir = trash_root.join("files");
+        let info_dir = trash_root.join("info");
+        fs::create_dir_all(&files_dir)?;
+        fs::create_dir_all(&info_dir)?;
+        
+        for attempt in 1.. {
+            let name = candidate_name(path, attempt);
+            let info_path = info_dir.join(format!("{}.trashinfo", name));
+            let target = files_dir.join(&name);
+            if target.exists() {
+                continue;
+            }
+            
+            let mut info = match OpenOptions::new().write(true).create_new(true).open(&info_path) {
+                Ok(file) => file,
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            };
+            write!(
+                info,
+                "[Trash Info]\nPath={}\nDeletionDate={}\n",
+                encode_path(path),
+                Local::now().format("%Y-%m-%dT%H:%M:%S")
+            )?;
+            
+            if let Err(e) = move_file(path, &target) {
+                let _ = fs::remove_file(&info_path);
+                return Err(e);
+            }
+            return Ok((target, info_path));
+        }
+        unreachable!("the candidate loop only ends by returning")
+    }
+    
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        
+        #[test]
+        fn test_move_to_trash_writes_info_and_avoids_collisions() {
+            let dir = std::env::temp_dir().join(format!("fmlm_freedesktop_{}", std::process::id()));
+            let trash = dir.join("Trash");
+            fs::create_dir_all(dir.join("My Photos")).unwrap();
+            
+            let mut trashed = Vec::new();
+            for _ in 0..2 {
+                let file = dir.join("My Photos").join("a%b.jpg");
+                fs::write(&file, b"x").unwrap();
+                trashed.push(move_to_trash(&file, &trash).unwrap());
+                assert!(!file.exists());
+            }
+            
+            assert_eq!(trashed[0].0, trash.join("files").join("a%b.jpg"));
+            assert_eq!(trashed[1].0, trash.join("files").join("a%b (2).jpg"));
+            let info = fs::read_to_string(&trashed[1].1).unwrap();
+            assert!(info.starts_with("[Trash Info]\nPath="));
+            assert!(info.contains("/My%20Photos/a%25b.jpg\nDeletionDate="));
+            
+            fs::remove_dir_all(&dir).unwrap();
+        }
+    }
+}
+
+/// Moves one media file to the trash and marks its row as trashed
+async fn trash_one(
+    pool: &DbPool,
+    media_id: i64,
+    location: TrashLocation,
+    app_trash: &Path,
+) -> TrashResult<()> {
+    let row = sqlx::query("SELECT file_path, file_name, trashed_at IS NOT NULL FROM media_metadata WHERE id = ?")
+        .bind(media_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or(TrashError::NotFound(media_id))?;
+    let file_path: String = row.get(0);
+    let file_name: String = row.get(1);
+    if row.get::<bool, _>(2) {
+        return Err(TrashError::AlreadyTrashed(media_id));
+    }
+    
+    let source = PathBuf::from(&file_path);
+    let system_trash = match location {
+        TrashLocation::System if cfg!(target_os = "linux") => freedesktop::home_trash(),
+        TrashLocation::System => {
+            warn!("The system trash is only supported on Linux, using the app trash");
+            None
+        }
+        TrashLocation::App => None,
+    };
+    
+    let (target, info_path) = match system_trash {
+        Some(trash_root) => {
+            let (target, info_path) = freedesktop::move_to_trash(&source, &trash_root)?;
+            (target, Some(info_path))
+        }
+        None => {
+            fs::create_dir_all(app_trash)?;
+            // Prefixing the row ID keeps names unique inside the trash folder
+            let target = app_trash.join(format!("{}_{}", media_id, file_name));
+            move_file(&source, &target)?;
+            (target, None)
+        }
+    };
+    
+    let result = sqlx::query(
+        r#"
+        UPDATE media_metadata
+        SET file_path = ?, original_path = file_path, trashed_at = ?, trash_info_path = ?
+        WHERE id = ?
+        "#,
+    )
+    .bind(target.to_string_lossy().to_string())
+    .bind(Utc::now())
+    .bind(info_path.as_ref().map(|path| path.to_string_lossy().to_string()))
+    .bind(media_id)
+    .execute(pool)
+    .await;
+    
+    if let Err(e) = result {
+        // Put the file back so the index still matches the disk
+        if move_file(&target, &source).is_ok() {
+            if let Some(info_path) = info_path {
+                let _ = fs::remove_file(info_path);
+            }
+        }
+        return Err(e.into());
+    }
+    
+    rescan::invalidate_thumbnails([file_path.as_str()]);
+    Ok(())
+}
+
+/// Moves media files to the trash
+///
+/// # Arguments
+///
+/// * `pool` - Database connection pool
+/// * `media_ids` - IDs of the rows whose files should be trashed
+/// * `location` - App trash or system trash
+/// * `app_trash` - The app-managed trash folder
+///
+/// # Returns
+///
+/// Returns the rows that were trashed and the ones that failed
+pub async fn trash_media(
+    pool: &DbPool,
+    media_ids: &[i64],
+    location: TrashLocation,
+    app_trash: &Path,
+) -> TrashOutcome {
+    let mut outcome = TrashOutcome::default();
+    for &media_id in media_ids {
+        match trash_one(pool, media_id, location, app_trash).await {
+            Ok(()) => outcome.succeeded.push(media_id),
+            Err(e) => outcome.failed.push(TrashFailure {
+                media_id,
+                error: e.to_string(),
+            }),
+        }
+    }
+    info!("Moved {} files to the trash ({} failed)", outcome.succeeded.len(), outcome.failed.len());
+    outcome
+}
+
+/// Moves one trashed file back to its original path
+async fn restore_one(pool: &DbPool, media_id: i64) -> TrashResult<()> {
+    let row = sqlx::query("SELECT file_path, original_path, trash_info_path FROM media_metadata WHERE id = ?")
+        .bind(media_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or(TrashError::NotFound(media_id))?;
+    let trashed_path: String = row.get(0);
+    let original_path: Option<String> = row.get(1);
+    let info_path: Option<String> = row.get(2);
+    let original_path = original_path.ok_or(TrashError::NotTrashed(media_id))?;
+    
+    let taken = sqlx::query("SELECT 1 FROM media_metadata WHERE file_path = ?")
+        .bind(&original_path)
+        .fetch_optional(pool)
+        .await?
+        .is_some();
+    let target = PathBuf::from(&original_path);
+    if taken || target.exists() {
+        return Err(TrashError::RestoreConflict(original_path));
+    }
+    
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    move_file(Path::new(&trashed_path), &target)?;
+    
+    let result = sqlx::query(
+        r#"
+        UPDATE media_metadata
+        SET file_path = original_path, original_path = NULL, trashed_at = NULL, trash_info_path = NULL
+        WHERE id = ?
+        "#,
+    )
+    .bind(media_id)
+    .execute(pool)
+    .await;
+    
+    if let Err(e) = result {
+        let _ = move_file(&target, Path::new(&trashed_path));
+        return Err(e.into());
+    }
+    
+    if let Some(info_path) = info_path {
+        if let Err(e) = fs::remove_file(&info_path) {
+            warn!("Cannot remove trash info {}: {}", info_path, e);
+        }
+    }
+    rescan::invalidate_thumbnails([trashed_path.as_str()]);
+    Ok(())
+}
+
+/// Restores trashed media to their original paths
+///
+/// # Returns
+///
+/// Returns the rows that were restored and the ones that failed (for
+/// instance because another file now occupies the original path)
+pub async fn restore_media(pool: &DbPool, media_ids: &[i64]) -> TrashOutcome {
+    let mut outcome = TrashOutcome::default();
+    for &media_id in media_ids {
+        match restore_one(pool, media_id).await {
+            Ok(()) => outcome.succeeded.push(media_id),
+            Err(e) => outcome.failed.push(TrashFailure {
+                media_id,
+                error: e.to_string(),
+            }),
+        }
+    }
+    info!("Restored {} files from the trash ({} failed)", outcome.succeeded.len(), outcome.failed.len());
+    outcome
+}
+
+/// Lists the trashed media, most recently trashed first
+pub async fn list_trash(pool: &DbPool) -> TrashResult<Vec<TrashedMedia>> {
+    let items = sqlx::query_as::<_, TrashedMedia>(
+        "SELECT * FROM media_metadata WHERE trashed_at IS NOT NULL ORDER BY trashed_at DESC, id DESC",
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(items)
+}
+
+/// Permanently deletes trashed items
+///
+/// Also removes files left in the app trash folder without a row (for
+/// instance after their scanned folder was removed from the library).
+///
+/// # Arguments
+///
+/// * `pool` - Database connection pool
+/// * `app_trash` - The app-managed trash folder
+/// * `trashed_before` - Only purge items trashed before this time; `None` empties the trash
+///
+/// # Returns
+///
+/// Returns the number of rows purged
+pub async fn purge_trash(
+    pool: &DbPool,
+    app_trash: &Path,
+    trashed_before: Option<DateTime<Utc>>,
+) -> TrashResult<usize> {
+    let rows = sqlx::query(
+        r#"
+        SELECT id, file_path, trash_info_path FROM media_metadata
+        WHERE trashed_at IS NOT NULL AND (?1 IS NULL OR trashed_at < ?1)
+        "#,
+    )
+    .bind(trashed_before)
+    .fetch_all(pool)
+    .await?;
+    
+    let mut purged = 0;
+    for row in rows {
+        let id: i64 = row.get(0);
+        let file_path: String = row.get(1);
+        let info_path: Option<String> = row.get(2);
+        
+        match fs::remove_file(&file_path) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => {
+                warn!("Cannot purge {}: {}", file_path, e);
+                continue;
+            }
+        }
+        if let Some(info_path) = info_path {
+            let _ = fs::remove_file(info_path);
+        }
+        rescan::invalidate_thumbnails([file_path.as_str()]);
+        
+        sqlx::query("DELETE FROM media_metadata WHERE id = ?")
+            .bind(id)
+            .execute(pool)
+            .await?;
+        purged += 1;
+    }
+    
+    if let Ok(entries) = fs::read_dir(app_trash) {
+        for entry in entries.flatten() {
+            let path = entry.path().to_string_lossy().to_string();
+            let referenced = sqlx::query("SELECT 1 FROM media_metadata WHERE file_path = ?")
+                .bind(&path)
+                .fetch_optional(pool)
+                .await?
+                .is_some();
+            if !referenced {
+                debug!("Removing unreferenced trash file {}", path);
+                let _ = fs::remove_file(&path);
+            }
+        }
+    }
+    
+    if purged > 0 {
+        info!("Purged {} items from the trash", purged);
+    }
+    Ok(purged)
+}
+
+/// Purges items older than the configured retention period
+///
+/// Called on startup; does nothing when the retention period is 0.
+pub async fn purge_expired(pool: &DbPool, app_trash: &Path) -> TrashResult<usize> {
+    let settings = load_trash_settings(pool).await?;
+    if settings.retention_days == 0 {
+        return Ok(0);
+    }
+    let cutoff = Utc::now() - Duration::days(settings.retention_days as i64);
+    purge_trash(pool, app_trash, Some(cutoff)).await
+}
+
+/// Tauri command to move media files to the trash
+///
+/// The location (app or system trash) comes from the trash settings.
+///
+/// # Arguments
+///
+/// * `app` - Application handle (injected by Tauri)
+/// * `pool` - Database connection pool (injected by Tauri)
+/// * `media_ids` - IDs of the rows whose files should be trashed
+///
+/// # Returns
+///
+/// Returns the rows that were trashed and the ones that failed
+#[tauri::command]
+pub async fn move_to_trash(
+    app: AppHandle,
+    pool: State<'_, DbPool>,
+    media_ids: Vec<i64>,
+) -> Result<TrashOutcome, String> {
+    let settings = load_trash_settings(&pool).await.map_err(|e| e.to_string())?;
+    let app_trash = app_trash_dir(&app).map_err(|e| e.to_string())?;
+    Ok(trash_media(&pool, &media_ids, settings.location, &app_trash).await)
+}
+
+/// Tauri command to restore trashed media to their original paths
+#[tauri::command]
+pub async fn restore_from_trash(pool: State<'_, DbPool>, media_ids: Vec<i64>) -> Result<TrashOutcome, String> {
+    Ok(restore_media(&pool, &media_ids).await)
+}
+
+/// Tauri command to list the trashed media
+#[tauri::command]
+pub async fn get_trash(pool: State<'_, DbPool>) -> Result<Vec<TrashedMedia>, String> {
+    list_trash(&pool).await.map_err(|e| e.to_string())
+}
+
+/// Tauri command to permanently delete everything in the trash
+///
+/// # Returns
+///
+/// Returns the number of items deleted
+#[tauri::command]
+pub async fn empty_trash(app: AppHandle, pool: State<'_, DbPool>) -> Result<usize, String> {
+    let app_trash = app_trash_dir(&app).map_err(|e| e.to_string())?;
+    purge_trash(&pool, &app_trash, None).await.map_err(|e| e.to_string())
+}
+
+/// Tauri command to get the trash settings
+#[tauri::command]
+pub async fn get_trash_settings(pool: State<'_, DbPool>) -> Result<TrashSettings, String> {
+    load_trash_settings(&pool).await.map_err(|e| e.to_string())
+}
+
+/// Tauri command to save the trash settings
+#[tauri::command]
+pub async fn set_trash_settings(pool: State<'_, DbPool>, settings: TrashSettings) -> Result<(), String> {
+    let location = match settings.location {
+        TrashLocation::App => "app",
+        TrashLocation::System => "system",
+    };
+    database::set_preference(&pool, TRASH_LOCATION_KEY, location)
+        .await
+        .map_err(|e| e.to_string())?;
+    database::set_preference(&pool, TRASH_RETENTION_KEY, &settings.retention_days.to_string())
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::InsertMediaParams;
+    use crate::search::{self, SearchFilters};
+    
+    struct Fixture {
+        pool: DbPool,
+        dir: PathBuf,
+        trash: PathBuf,
+        folder_id: i64,
+    }
+    
+    impl Fixture {
+        async fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("fmlm_trash_{}_{}", name, std::process::id()));
+            fs::create_dir_all(dir.join("lib")).unwrap();
+            let pool = database::open_test_pool().await;
+            let folder_id = database::upsert_scanned_folder(&pool, &dir.join("lib").to_string_lossy(), "lib", 0)
+                .await
+                .unwrap();
+            Fixture {
+                pool,
+                trash: dir.join("trash"),
+                dir,
+                folder_id,
+            }
+        }
+        
+        async fn add_file(&self, name: &str) -> (i64, PathBuf) {
+            let path = self.dir.join("lib").join(name);
+            fs::write(&path, name.as_bytes()).unwrap();
+            let params = InsertMediaParams {
+                folder_id: self.folder_id,
+                file_path: path.to_string_lossy().to_string(),
+                file_name: name.to_string(),
+                file_type: "jpg".to_string(),
+                file_size: name.len() as i64,
+                media_type: Some("image".to_string()),
+                ..Default::default()
+            };
+            let id = database::insert_media_metadata(&self.pool, params).await.unwrap();
+            (id, path)
+        }
+    }
+    
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_trash_and_restore_keep_the_row() {
+        let f = Fixture::new("restore").await;
+        let (id, path) = f.add_file("a.jpg").await;
+        let (other, _) = f.add_file("b.jpg").await;
+        crate::ratings::set_rating(&f.pool, &[id], 4).await.unwrap();
+        crate::tags::add_tags(&f.pool, &[id], &["Family".to_string()]).await.unwrap();
+        
+        let outcome = trash_media(&f.pool, &[id, 999], TrashLocation::App, &f.trash).await;
+        assert_eq!(outcome.succeeded, [id]);
+        assert_eq!(outcome.failed[0].media_id, 999);
+        assert!(!path.exists());
+        
+        let trashed = list_trash(&f.pool).await.unwrap();
+        assert_eq!(trashed.len(), 1);
+        assert_eq!(trashed[0].original_path, path.to_string_lossy());
+        assert!(Path::new(&trashed[0].media.file_path).exists());
+        
+        // Hidden from searches and from rescans, which would otherwise delete it
+        let visible = search::find_media(&f.pool, &SearchFilters::default(), 10).await.unwrap();
+        assert_eq!(visible.iter().map(|m| m.id).collect::<Vec<_>>(), [other]);
+        assert_eq!(database::get_indexed_files(&f.pool, f.folder_id).await.unwrap().len(), 1);
+        assert_eq!(trash_media(&f.pool, &[id], TrashLocation::App, &f.trash).await.failed.len(), 1);
+        
+        let outcome = restore_media(&f.pool, &[id]).await;
+        assert_eq!(outcome.succeeded, [id]);
+        assert_eq!(fs::read(&path).unwrap(), b"a.jpg");
+        let row = database::get_media_metadata_by_path(&f.pool, &path.to_string_lossy()).await.unwrap().unwrap();
+        assert_eq!((row.id, row.rating), (id, 4));
+        assert_eq!(crate::tags::get_media_tags(&f.pool, id).await.unwrap(), ["Family"]);
+        assert!(list_trash(&f.pool).await.unwrap().is_empty());
+        
+        // Restoring over a file that appeared meanwhile is refused
+        trash_media(&f.pool, &[id], TrashLocation::App, &f.trash).await;
+        fs::write(&path, b"new").unwrap();
+        assert!(restore_media(&f.pool, &[id]).await.failed[0].error.contains("already exists"));
+    }
+    
+    #[tokio::test]
+    async fn test_purge_respects_retention() {
+        let f = Fixture::new("purge").await;
+        let (old, _) = f.add_file("old.jpg").await;
+        let (recent, _) = f.add_file("recent.jpg").await;
+        trash_media(&f.pool, &[old, recent], TrashLocation::App, &f.trash).await;
+        sqlx::query("UPDATE media_metadata SET trashed_at = ? WHERE id = ?")
+            .bind(Utc::now() - Duration::days(40))
+            .bind(old)
+            .execute(&f.pool)
+            .await
+            .unwrap();
+        let stray = f.trash.join("stray.jpg");
+        fs::write(&stray, b"x").unwrap();
+        
+        assert_eq!(purge_expired(&f.pool, &f.trash).await.unwrap(), 1);
+        let left = list_trash(&f.pool).await.unwrap();
+        assert_eq!(left.iter().map(|t| t.media.id).collect::<Vec<_>>(), [recent]);
+        assert!(!stray.exists());
+        assert_eq!(fs::read_dir(&f.trash).unwrap().count(), 1);
+        
+        assert_eq!(purge_trash(&f.pool, &f.trash, None).await.unwrap(), 1);
+        assert_eq!(fs::read_dir(&f.trash).unwrap().count(), 0);
+    }
+}
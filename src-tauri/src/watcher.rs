@@ -0,0 +1,556 @@
+//! Live filesystem watching for registered library folders
+//!
+//! Subscribes to every folder in `scanned_folders` and keeps `media_metadata`
+//! in sync with changes made outside the app (sync tools, file managers, etc.).
+//! Raw events from `notify` are debounced, then each touched path is
+//! reconciled against the disk: new or changed media files are upserted,
+//! missing ones are removed, and cached thumbnails are invalidated. A file
+//! that disappears and reappears elsewhere within one batch (a move or rename)
+//! keeps its row, which is pointed at the new path. Paths excluded by the
+//! scan ignore rules are left alone. The resulting changes are emitted to the
+//! frontend as a `library-changed` event.
+
+use crate::database::{self, DbPool, IndexedFile, MediaMove};
+use crate::ignore_rules::{self, IgnoreRules, IgnoreSettings};
+use crate::rescan::{self, media_file_to_params};
+use crate::scanner::{self, MediaFile, ScanOptions};
+use crate::tags;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+use thiserror::Error;
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+use tracing::{debug, error, info, warn};
+
+/// Name of the event emitted to the frontend when the library changes
+pub const LIBRARY_CHANGED_EVENT: &str = "library-changed";
+
+/// Quiet period after the last filesystem event before a batch is processed
+const DEBOUNCE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Upper bound on how long events are batched while a folder keeps changing
+const MAX_BATCH_DELAY: Duration = Duration::from_secs(5);
+
+/// Error types for watcher operations
+#[derive(Debug, Error)]
+pub enum WatcherError {
+    #[error("Watcher error: {0}")]
+    Notify(#[from] notify::Error),
+    
+    #[error("Database error: {0}")]
+    Database(#[from] database::DatabaseError),
+    
+    #[error("Background task failed: {0}")]
+    Task(#[from] tokio::task::JoinError),
+    
+    #[error("Watcher state is poisoned")]
+    Poisoned,
+}
+
+pub type WatcherResult<T> = Result<T, WatcherError>;
+
+/// Kind of change applied to the library
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    /// A media file was indexed for the first time
+    Added,
+    /// An indexed media file changed size or modification time
+    Modified,
+    /// An indexed media file was moved or renamed
+    Moved,
+    /// An indexed media file no longer exists
+    Removed,
+}
+
+/// A single library change reported to the frontend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LibraryChange {
+    /// ID of the scanned folder the file belongs to
+    pub folder_id: i64,
+    /// Absolute path of the affected file
+    pub path: String,
+    /// What happened to the file
+    pub change: ChangeKind,
+    /// Previous path of a moved file
+    pub old_path: Option<String>,
+}
+
+/// What reconciling the paths of one batch found
+#[derive(Default)]
+struct BatchChanges {
+    /// Changes already written to the index
+    changes: Vec<LibraryChange>,
+    /// Media files without a row yet, with the folder they belong to
+    new_files: Vec<(i64, MediaFile)>,
+    /// Rows whose files are gone, with the folder they were found under
+    missing: Vec<(i64, IndexedFile)>,
+}
+
+/// Registered folders keyed by path
+type FolderMap = Arc<RwLock<HashMap<PathBuf, i64>>>;
+
+/// Watches registered library folders and keeps the index up to date.
+///
+/// Managed as Tauri state so folder commands can subscribe and unsubscribe
+/// paths as folders are added or forgotten.
+pub struct LibraryWatcher {
+    watcher: Mutex<RecommendedWatcher>,
+    folders: FolderMap,
+}
+
+impl LibraryWatcher {
+    /// Starts watching a folder recursively.
+    ///
+    /// # Arguments
+    ///
+    /// * `folder_id` - ID of the folder in `scanned_folders`
+    /// * `path` - Path of the folder on disk
+    pub fn watch_folder(&self, folder_id: i64, path: &Path) -> WatcherResult<()> {
+        self.watcher
+            .lock()
+            .map_err(|_| WatcherError::Poisoned)?
+            .watch(path, RecursiveMode::Recursive)?;
+        
+        self.folders
+            .write()
+            .map_err(|_| WatcherError::Poisoned)?
+            .insert(path.to_path_buf(), folder_id);
+        
+        info!("Watching folder {} ({})", folder_id, path.display());
+        Ok(())
+    }
+    
+    /// Stops watching a folder.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the folder on disk
+    pub fn unwatch_folder(&self, path: &Path) -> WatcherResult<()> {
+        self.folders
+            .write()
+            .map_err(|_| WatcherError::Poisoned)?
+            .remove(path);
+        
+        self.watcher
+            .lock()
+            .map_err(|_| WatcherError::Poisoned)?
+            .unwatch(path)?;
+        
+        info!("Stopped watching folder {}", path.display());
+        Ok(())
+    }
+}
+
+/// Creates the library watcher and subscribes to every scanned folder.
+///
+/// Folders that cannot be watched (e.g. an unplugged drive) are logged and
+/// skipped so the rest of the library is still kept in sync.
+///
+/// # Arguments
+///
+/// * `app_handle` - Tauri application handle used to emit change events
+/// * `pool` - Database connection pool
+///
+/// # Errors
+///
+/// Returns an error if the platform watcher cannot be created or the scanned
+/// folders cannot be loaded.
+pub async fn start(app_handle: AppHandle, pool: DbPool) -> WatcherResult<LibraryWatcher> {
+    let (tx, rx) = mpsc::unbounded_channel::<(PathBuf, bool)>();
+    
+    let watcher = notify::recommended_watcher(move |result: notify::Result<Event>| {
+        match result {
+            Ok(event) => {
+                if matches!(event.kind, EventKind::Access(_)) {
+                    return;
+                }
+                // Only creations and renames can bring a whole directory into view;
+                // other events on directories are ignored to avoid rewalking them.
+                let may_add_directory = matches!(
+                    event.kind,
+                    EventKind::Create(_) | EventKind::Modify(notify::event::ModifyKind::Name(_))
+                );
+                for path in event.paths {
+                    // The receiver only goes away when the app shuts down
+                    let _ = tx.send((path, may_add_directory));
+                }
+            }
+            Err(e) => warn!("Filesystem watch error: {}", e),
+        }
+    })?;
+    
+    let library_watcher = LibraryWatcher {
+        watcher: Mutex::new(watcher),
+        folders: Arc::new(RwLock::new(HashMap::new())),
+    };
+    
+    for folder in database::get_all_scanned_folders(&pool).await? {
+        if let Err(e) = library_watcher.watch_folder(folder.id, Path::new(&folder.path)) {
+            warn!("Cannot watch folder {}: {}", folder.path, e);
+        }
+    }
+    
+    tauri::async_runtime::spawn(process_events(
+        rx,
+        pool,
+        app_handle,
+        library_watcher.folders.clone(),
+    ));
+    
+    Ok(library_watcher)
+}
+
+/// Receives raw events, debounces them and applies the resulting changes.
+///
+/// Sync tools tend to write a file in several chunks, producing a burst of
+/// events for the same path. Events are therefore collected until the
+/// filesystem has been quiet for [`DEBOUNCE_INTERVAL`] (or [`MAX_BATCH_DELAY`]
+/// has passed) and each distinct path is reconciled once.
+async fn process_events(
+    mut rx: UnboundedReceiver<(PathBuf, bool)>,
+    pool: DbPool,
+    app_handle: AppHandle,
+    folders: FolderMap,
+) {
+    while let Some((path, may_add_directory)) = rx.recv().await {
+        let mut pending: HashMap<PathBuf, bool> = HashMap::new();
+        pending.insert(path, may_add_directory);
+        
+        let batch_started = Instant::now();
+        while batch_started.elapsed() < MAX_BATCH_DELAY {
+            match tokio::time::timeout(DEBOUNCE_INTERVAL, rx.recv()).await {
+                Ok(Some((path, may_add_directory))) => {
+                    *pending.entry(path).or_insert(false) |= may_add_directory;
+                }
+                // Channel closed or quiet period elapsed
+                Ok(None) | Err(_) => break,
+            }
+        }
+        
+        debug!("Processing {} changed paths", pending.len());
+        let settings = ignore_rules::load_ignore_settings_or_default(&pool).await;
+        let mut batch = BatchChanges::default();
+        
+        for (path, may_add_directory) in pending {
+            let Some((folder_path, folder_id)) = owning_folder(&folders, &path) else {
+                continue;
+            };
+            let result = reconcile_path(
+                &pool,
+                folder_id,
+                &folder_path,
+                &path,
+                may_add_directory,
+                &settings,
+                &mut batch,
+            )
+            .await;
+            
+            if let Err(e) = result {
+                error!("Failed to update index for {}: {}", path.display(), e);
+            }
+        }
+        
+        let changes = match apply_batch(&pool, batch).await {
+            Ok(changes) => changes,
+            Err(e) => {
+                error!("Failed to update index: {}", e);
+                continue;
+            }
+        };
+        if changes.is_empty() {
+            continue;
+        }
+        
+        // Files moved while the app was closed can still carry orphaned tags
+        let added_paths: Vec<String> = changes
+            .iter()
+            .filter(|change| change.change == ChangeKind::Added)
+            .map(|change| change.path.clone())
+            .collect();
+        if let Err(e) = tags::restore_moved_tags(&pool, &added_paths).await {
+            warn!("Cannot restore tags of moved files: {}", e);
+        }
+        
+        info!("Library changed: {} files updated", changes.len());
+        if let Err(e) = app_handle.emit(LIBRARY_CHANGED_EVENT, &changes) {
+            error!("Failed to emit library change event: {}", e);
+        }
+    }
+    
+    debug!("Watcher event channel closed");
+}
+
+/// Finds the registered folder that contains a path.
+///
+/// When folders are nested, the deepest one wins.
+///
+/// # Returns
+///
+/// Returns the folder's path and ID.
+fn owning_folder(folders: &FolderMap, path: &Path) -> Option<(PathBuf, i64)> {
+    let folders = folders.read().ok()?;
+    folders
+        .iter()
+        .filter(|(folder_path, _)| path.starts_with(folder_path))
+        .max_by_key(|(folder_path, _)| folder_path.components().count())
+        .map(|(folder_path, folder_id)| (folder_path.clone(), *folder_id))
+}
+
+/// Brings the index in line with the current state of a path on disk.
+///
+/// * Missing paths mark the matching row and every row below it as missing.
+/// * Existing media files are upserted when changed, or recorded as new.
+/// * Existing directories are walked only when they may have just appeared
+///   (created or renamed into place).
+/// * Existing paths matched by the ignore rules are skipped.
+///
+/// New files and missing rows are only written by [`apply_batch`], once the
+/// whole batch is known, so that moves can be told apart from deletions.
+async fn reconcile_path(
+    pool: &DbPool,
+    folder_id: i64,
+    folder_path: &Path,
+    path: &Path,
+    may_add_directory: bool,
+    settings: &IgnoreSettings,
+    batch: &mut BatchChanges,
+) -> WatcherResult<()> {
+    let rules = IgnoreRules::new(folder_path, settings);
+    
+    match std::fs::metadata(path) {
+        Err(_) => {
+            let path_str = path.to_string_lossy();
+            for row in database::get_indexed_files_at_path(pool, &path_str).await? {
+                batch.missing.push((folder_id, row));
+            }
+        }
+        Ok(metadata) if metadata.is_dir() => {
+            if !may_add_directory || rules.is_path_ignored(folder_path, path, true) {
+                return Ok(());
+            }
+            
+            let dir = path.to_path_buf();
+            let options = ScanOptions::default().with_ignore(settings.clone());
+            let files = tokio::task::spawn_blocking(move || {
+                scanner::collect_media_files(&dir, &options)
+            })
+            .await?;
+            
+            // The walk only sees `.fmlmignore` files from the new directory
+            // down, so check the rules of the folders above it as well
+            let files = files
+                .into_iter()
+                .filter(|file| !rules.is_path_ignored(folder_path, Path::new(&file.path), false));
+            
+            for file in files {
+                upsert_file(pool, folder_id, file, batch).await?;
+            }
+        }
+        Ok(_) => {
+            if rules.is_path_ignored(folder_path, path, false) {
+                return Ok(());
+            }
+            if let Some(file) = scanner::media_file_from_path(path) {
+                upsert_file(pool, folder_id, file, batch).await?;
+            }
+        }
+    }
+    
+    Ok(())
+}
+
+/// Updates a single file if it differs from its stored row, or records it as
+/// new when it has none.
+async fn upsert_file(
+    pool: &DbPool,
+    folder_id: i64,
+    file: MediaFile,
+    batch: &mut BatchChanges,
+) -> WatcherResult<()> {
+    match database::get_media_metadata_by_path(pool, &file.path).await? {
+        None => batch.new_files.push((folder_id, file)),
+        Some(row)
+            if row.file_size == file.size as i64
+                && row.modified_date.timestamp() == file.modified => {}
+        Some(_) => {
+            database::insert_media_metadata(pool, media_file_to_params(folder_id, &file)).await?;
+            rescan::invalidate_thumbnails([file.path.as_str()]);
+            batch.changes.push(LibraryChange {
+                folder_id,
+                path: file.path,
+                change: ChangeKind::Modified,
+                old_path: None,
+            });
+        }
+    }
+    
+    Ok(())
+}
+
+/// Writes the new files and missing rows of a batch.
+///
+/// New files that hold the content of a missing row (see
+/// [`rescan::match_moves`]) take over that row; the remaining new files are
+/// inserted and the remaining missing rows deleted.
+///
+/// # Returns
+///
+/// Returns every change of the batch
+async fn apply_batch(pool: &DbPool, batch: BatchChanges) -> WatcherResult<Vec<LibraryChange>> {
+    let BatchChanges {
+        mut changes,
+        new_files,
+        missing,
+    } = batch;
+    
+    // A file inside a new directory can be reported both by the directory
+    // walk and by its own event, and a row below a deleted directory likewise
+    let mut seen_paths = HashSet::new();
+    let new_files: Vec<(i64, MediaFile)> = new_files
+        .into_iter()
+        .filter(|(_, file)| seen_paths.insert(file.path.clone()))
+        .collect();
+    let mut seen_ids = HashSet::new();
+    let (missing_folders, missing): (Vec<i64>, Vec<IndexedFile>) = missing
+        .into_iter()
+        .filter(|(_, row)| seen_ids.insert(row.id))
+        .unzip();
+    
+    let files: Vec<MediaFile> = new_files.iter().map(|(_, file)| file.clone()).collect();
+    // Failing here leaves the batch to the next rescan rather than turning
+    // moves into a delete and an insert
+    let (matches, missing) = tokio::task::spawn_blocking(move || {
+        let matches = rescan::match_moves(&files, &missing);
+        (matches, missing)
+    })
+    .await?;
+    
+    let mut moved_files = HashSet::new();
+    let mut moved_rows = HashSet::new();
+    let mut moves = Vec::new();
+    for m in matches {
+        let (folder_id, file) = &new_files[m.added];
+        let row = &missing[m.removed];
+        moves.push(MediaMove {
+            id: row.id,
+            folder_id: *folder_id,
+            file_path: file.path.clone(),
+            file_name: file.name.clone(),
+            file_type: file.file_type.clone(),
+            quick_hash: m.quick_hash,
+        });
+        rescan::invalidate_thumbnails([row.file_path.as_str()]);
+        changes.push(LibraryChange {
+            folder_id: *folder_id,
+            path: file.path.clone(),
+            change: ChangeKind::Moved,
+            old_path: Some(row.file_path.clone()),
+        });
+        moved_files.insert(m.added);
+        moved_rows.insert(m.removed);
+    }
+    database::move_media_batch(pool, &moves).await?;
+    
+    let added = new_files
+        .into_iter()
+        .enumerate()
+        .filter(|(index, _)| !moved_files.contains(index))
+        .map(|(_, added)| added);
+    for (folder_id, file) in added {
+        database::insert_media_metadata(pool, media_file_to_params(folder_id, &file)).await?;
+        changes.push(LibraryChange {
+            folder_id,
+            path: file.path,
+            change: ChangeKind::Added,
+            old_path: None,
+        });
+    }
+    
+    let removed: Vec<(i64, IndexedFile)> = missing_folders
+        .into_iter()
+        .zip(missing)
+        .enumerate()
+        .filter(|(index, _)| !moved_rows.contains(index))
+        .map(|(_, removed)| removed)
+        .collect();
+    let removed_ids: Vec<i64> = removed.iter().map(|(_, row)| row.id).collect();
+    database::delete_media_batch(pool, &removed_ids).await?;
+    rescan::invalidate_thumbnails(removed.iter().map(|(_, row)| row.file_path.as_str()));
+    for (folder_id, row) in removed {
+        changes.push(LibraryChange {
+            folder_id,
+            path: row.file_path,
+            change: ChangeKind::Removed,
+            old_path: None,
+        });
+    }
+    
+    Ok(changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_owning_folder_prefers_deepest_match() {
+        let folders: FolderMap = Arc::new(RwLock::new(HashMap::from([
+            (PathBuf::from("/photos"), 1),
+            (PathBuf::from("/photos/trips"), 2),
+        ])));
+        
+        let folder_id = |path: &str| owning_folder(&folders, Path::new(path)).map(|(_, id)| id);
+        
+        assert_eq!(folder_id("/photos/a.jpg"), Some(1));
+        assert_eq!(folder_id("/photos/trips/b.jpg"), Some(2));
+        assert_eq!(folder_id("/photoshoot/c.jpg"), None);
+    }
+    
+    #[tokio::test]
+    async fn test_apply_batch_keeps_row_of_moved_file() {
+        let dir = std::env::temp_dir().join(format!("fmlm_watcher_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let old_path = dir.join("a.jpg");
+        let new_path = dir.join("b.jpg");
+        std::fs::write(&old_path, b"holiday photo").unwrap();
+        
+        let pool = database::open_test_pool().await;
+        let folder_id = database::upsert_scanned_folder(&pool, &dir.to_string_lossy(), "watched", 1).await.unwrap();
+        let file = scanner::media_file_from_path(&old_path).unwrap();
+        database::insert_media_metadata(&pool, media_file_to_params(folder_id, &file)).await.unwrap();
+        let old_path = old_path.to_string_lossy().into_owned();
+        let id = database::get_media_metadata_by_path(&pool, &old_path).await.unwrap().unwrap().id;
+        tags::add_tags(&pool, &[id], &["Trips/Alps".to_string()]).await.unwrap();
+        
+        // The watcher sees the removal and the creation in the same batch
+        std::fs::rename(&old_path, &new_path).unwrap();
+        let batch = BatchChanges {
+            changes: Vec::new(),
+            new_files: vec![(folder_id, scanner::media_file_from_path(&new_path).unwrap())],
+            missing: database::get_indexed_files_at_path(&pool, &old_path)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|row| (folder_id, row))
+                .collect(),
+        };
+        let changes = apply_batch(&pool, batch).await.unwrap();
+        
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].change, ChangeKind::Moved);
+        assert_eq!(changes[0].old_path.as_deref(), Some(old_path.as_str()));
+        let new_path = new_path.to_string_lossy();
+        let row = database::get_media_metadata_by_path(&pool, &new_path).await.unwrap().unwrap();
+        assert_eq!(row.id, id);
+        assert_eq!(tags::get_media_tags(&pool, id).await.unwrap(), ["Trips/Alps"]);
+        assert!(database::get_media_metadata_by_path(&pool, &old_path).await.unwrap().is_none());
+        
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
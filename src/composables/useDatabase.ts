@@ -34,8 +34,16 @@ export interface MediaMetadata {
   modified_date: string;
   thumbnail_path: string | null;
   indexed_at: string;
+  rating: number;
+  favorite: boolean;
+  color_label: ColorLabel | null;
+  pick_flag: PickFlag | null;
 }
 
+export type ColorLabel = 'red' | 'orange' | 'yellow' | 'green' | 'blue' | 'purple';
+
+export type PickFlag = 'pick' | 'reject';
+
 export interface InsertMediaParams {
   folder_id: number;
   file_path: string;
@@ -50,6 +58,271 @@ export interface InsertMediaParams {
   thumbnail_path?: string;
 }
 
+export interface SearchFilters {
+  text?: string;
+  folder_id?: number;
+  media_types?: string[];
+  file_types?: string[];
+  date_from?: string;
+  date_to?: string;
+  min_size?: number;
+  max_size?: number;
+  min_width?: number;
+  max_width?: number;
+  min_height?: number;
+  max_height?: number;
+  min_duration?: number;
+  max_duration?: number;
+  video_codec?: string;
+  audio_codec?: string;
+  camera_make?: string;
+  camera_model?: string;
+  lens_model?: string;
+  min_iso?: number;
+  max_iso?: number;
+  /** Focal length in millimeters */
+  min_focal_length?: number;
+  max_focal_length?: number;
+  /** Aperture as an f-number */
+  min_aperture?: number;
+  max_aperture?: number;
+  /** true for photos taken with flash */
+  flash?: boolean;
+  /** true for media with GPS coordinates */
+  has_location?: boolean;
+  bounds?: BoundingBox;
+  /** Case-insensitive place names resolved from GPS coordinates */
+  country?: string;
+  region?: string;
+  city?: string;
+  /** Tag expression, e.g. `Places/Portugal AND (Family OR Friends) AND NOT Work` */
+  tags?: string;
+  /** true for tagged media, false for untagged media */
+  has_tags?: boolean;
+  min_rating?: number;
+  max_rating?: number;
+  favorite?: boolean;
+  color_labels?: ColorLabel[];
+  /** 'none' matches unflagged media */
+  pick_flags?: (PickFlag | 'none')[];
+}
+
+export type SortKey = 'capture_date' | 'modified_date' | 'name' | 'size' | 'duration' | 'resolution' | 'rating';
+
+export interface MediaQuery {
+  filters?: SearchFilters;
+  sort?: SortKey;
+  direction?: 'asc' | 'desc';
+  cursor?: string | null;
+  limit?: number;
+}
+
+export interface MediaPage {
+  items: MediaMetadata[];
+  next_cursor: string | null;
+  total_count: number;
+}
+
+export interface TagCount {
+  id: number;
+  name: string;
+  path: string;
+  parent_id: number | null;
+  media_count: number;
+  total_count: number;
+}
+
+export interface Album {
+  id: number;
+  name: string;
+  folder_id: number | null;
+  /** Chosen cover, or the first item when none was chosen */
+  cover_media_id: number | null;
+  cover_file_path: string | null;
+  item_count: number;
+  created_at: string;
+  updated_at: string;
+}
+
+export interface AlbumFolder {
+  id: number;
+  name: string;
+  parent_id: number | null;
+  created_at: string;
+}
+
+export interface SmartAlbumDefinition {
+  filters?: SearchFilters;
+  sort?: SortKey;
+  direction?: 'asc' | 'desc';
+}
+
+export interface SmartAlbum {
+  id: number;
+  name: string;
+  folder_id: number | null;
+  definition: SmartAlbumDefinition;
+  /** Number of media currently matching the definition */
+  item_count: number;
+  created_at: string;
+  updated_at: string;
+}
+
+export interface TrashedMedia extends MediaMetadata {
+  /** Path the file is restored to; file_path points into the trash */
+  original_path: string;
+  trashed_at: string;
+}
+
+export interface TrashOutcome {
+  succeeded: number[];
+  failed: { media_id: number; error: string }[];
+}
+
+export interface TrashSettings {
+  /** 'system' uses the desktop trash (Linux only) */
+  location: 'app' | 'system';
+  /** Days before trashed items are purged; 0 keeps them forever */
+  retention_days: number;
+}
+
+export interface HashJobInfo {
+  status:
+    | { state: 'running' }
+    | { state: 'completed' }
+    | { state: 'cancelled' }
+    | { state: 'failed'; message: string };
+  progress: {
+    /** 'quick' hashes same-size files, 'full' confirms same quick hashes, 'perceptual' hashes images, 'video' fingerprints videos */
+    phase: 'quick' | 'full' | 'perceptual' | 'video';
+    total: number;
+    hashed: number;
+    error_count: number;
+  };
+  started_at: string;
+  finished_at: string | null;
+}
+
+export interface DuplicateGroup {
+  content_hash: string;
+  file_size: number;
+  media: MediaMetadata[];
+  /** Bytes freed by keeping a single copy */
+  reclaimable_bytes: number;
+}
+
+export interface DuplicateReport {
+  groups: DuplicateGroup[];
+  total_reclaimable_bytes: number;
+}
+
+export interface SimilarityOptions {
+  algorithm?: 'dhash' | 'phash';
+  /** Maximum differing bits out of 64 (default 10, at most 24) */
+  max_distance?: number;
+}
+
+export interface SimilarMedia extends MediaMetadata {
+  /** Hamming distance to the first image of the group */
+  distance: number;
+}
+
+export interface SimilarGroup {
+  /** Largest resolution first */
+  media: SimilarMedia[];
+}
+
+export interface VideoSimilarityOptions {
+  /** Maximum differing bits between matching frames (default 10) */
+  max_distance?: number;
+  /** Share of frames that must match, 0 to 1 (default 0.6) */
+  min_frame_match?: number;
+}
+
+export interface RankedVideo extends MediaMetadata {
+  /** The copy to keep (highest resolution, then bitrate) */
+  keep: boolean;
+  /** Share of matching frames with the kept copy */
+  similarity: number;
+}
+
+export interface VideoGroup {
+  media: RankedVideo[];
+}
+
+export type KeepRule =
+  | { rule: 'largest' }
+  | { rule: 'oldest' }
+  | { rule: 'preferred_root'; path: string }
+  | { rule: 'most_metadata' };
+
+export type ResolveAction = 'trash' | 'hardlink' | 'symlink';
+
+export interface ResolvePolicy {
+  rules: KeepRule[];
+  action: ResolveAction;
+  groups?: number[][];
+  dry_run?: boolean;
+}
+
+export interface ResolvedFile {
+  media_id: number;
+  file_path: string;
+  file_size: number;
+  error: string | null;
+}
+
+export interface GroupResolution {
+  keep: ResolvedFile;
+  losers: ResolvedFile[];
+}
+
+export interface ResolveReport {
+  dry_run: boolean;
+  action: ResolveAction;
+  groups: GroupResolution[];
+  reclaimable_bytes: number;
+  failed: number;
+}
+
+/** Map area; west > east when it crosses the antimeridian */
+export interface BoundingBox {
+  south: number;
+  west: number;
+  north: number;
+  east: number;
+}
+
+export interface LocationCluster {
+  latitude: number;
+  longitude: number;
+  count: number;
+  /** Most recently captured member */
+  media_id: number;
+  bounds: BoundingBox;
+}
+
+export interface MetadataBatch {
+  extracted: number;
+  failed: number;
+  remaining: number;
+}
+
+export interface Place {
+  country_code: string;
+  country: string;
+  region: string | null;
+  /** Nearest city, when close enough */
+  city: string | null;
+}
+
+export interface GeocodeBatch {
+  geocoded: number;
+  /** Located media with no place nearby (e.g. at sea) */
+  unresolved: number;
+  remaining: number;
+}
+
 export interface UserPreference {
   key: string;
   value: string;
@@ -126,6 +399,412 @@ export function useDatabase() {
     return invoke<void>('delete_media_metadata', { filePath });
   };
 
+  /**
+   * Search media by text (name, path, metadata, tags) and structured filters
+   */
+  const searchMedia = async (filters: SearchFilters, limit?: number): Promise<MediaMetadata[]> => {
+    return invoke<MediaMetadata[]>('search_media', { filters, limit });
+  };
+
+  /**
+   * Fetch one sorted page of media; pass the previous page's next_cursor to continue
+   */
+  const getMediaPage = async (query: MediaQuery): Promise<MediaPage> => {
+    return invoke<MediaPage>('get_media_page', { query });
+  };
+
+  /**
+   * Extract codec, EXIF and GPS metadata of the next batch of never-opened files;
+   * call again until `remaining` is 0
+   */
+  const extractPendingMetadata = async (limit?: number): Promise<MetadataBatch> => {
+    return invoke<MetadataBatch>('extract_pending_metadata_command', { limit });
+  };
+
+  /**
+   * Cluster the located media in the visible map area at the given zoom level
+   */
+  const getLocationClusters = async (zoom: number, bounds?: BoundingBox): Promise<LocationCluster[]> => {
+    return invoke<LocationCluster[]>('get_location_clusters', { bounds, zoom });
+  };
+
+  /**
+   * Resolve the country, region and city of the next batch of located media offline
+   * and tag them under `Places/`; call again until `remaining` is 0
+   */
+  const geocodePending = async (limit?: number): Promise<GeocodeBatch> => {
+    return invoke<GeocodeBatch>('geocode_pending_command', { limit });
+  };
+
+  /**
+   * Find the place of a point from the bundled dataset
+   */
+  const reverseGeocode = async (latitude: number, longitude: number): Promise<Place | null> => {
+    return invoke<Place | null>('reverse_geocode', { latitude, longitude });
+  };
+
+  // ------------------------------------------------------------------------
+  // Tags
+  // ------------------------------------------------------------------------
+
+  /**
+   * Get all tags with direct and inclusive (descendant) media counts
+   */
+  const getTags = async (): Promise<TagCount[]> => {
+    return invoke<TagCount[]>('get_tags');
+  };
+
+  /**
+   * Get the tag paths of one media item
+   */
+  const getTagsForMedia = async (mediaId: number): Promise<string[]> => {
+    return invoke<string[]>('get_tags_for_media', { mediaId });
+  };
+
+  /**
+   * Add tags (paths like `Places/Portugal/Lisbon`) to several media items
+   */
+  const addTags = async (mediaIds: number[], tags: string[]): Promise<number> => {
+    return invoke<number>('add_tags_to_media', { mediaIds, tags });
+  };
+
+  /**
+   * Remove tags from several media items
+   */
+  const removeTags = async (mediaIds: number[], tags: string[]): Promise<number> => {
+    return invoke<number>('remove_tags_from_media', { mediaIds, tags });
+  };
+
+  /**
+   * Rename a tag and its descendants; merges into `to` if it already exists
+   */
+  const renameTag = async (from: string, to: string): Promise<void> => {
+    return invoke<void>('rename_tag_command', { from, to });
+  };
+
+  /**
+   * Merge several tags into one
+   */
+  const mergeTags = async (sources: string[], target: string): Promise<void> => {
+    return invoke<void>('merge_tags_command', { sources, target });
+  };
+
+  /**
+   * Delete a tag and its descendants
+   */
+  const deleteTag = async (path: string): Promise<void> => {
+    return invoke<void>('delete_tag_command', { path });
+  };
+
+  // ------------------------------------------------------------------------
+  // Albums
+  // ------------------------------------------------------------------------
+
+  /**
+   * Get all albums with item counts and covers
+   */
+  const getAlbums = async (): Promise<Album[]> => {
+    return invoke<Album[]>('get_albums');
+  };
+
+  /**
+   * Get all album folders
+   */
+  const getAlbumFolders = async (): Promise<AlbumFolder[]> => {
+    return invoke<AlbumFolder[]>('get_album_folders');
+  };
+
+  /**
+   * Get the media of an album in album order
+   */
+  const getAlbumItems = async (albumId: number): Promise<MediaMetadata[]> => {
+    return invoke<MediaMetadata[]>('get_album_items', { albumId });
+  };
+
+  /**
+   * Get the IDs of the albums containing a media item
+   */
+  const getAlbumsForMedia = async (mediaId: number): Promise<number[]> => {
+    return invoke<number[]>('get_albums_for_media', { mediaId });
+  };
+
+  /**
+   * Create an album, optionally inside an album folder
+   */
+  const createAlbum = async (name: string, folderId?: number | null): Promise<number> => {
+    return invoke<number>('create_album_command', { name, folderId });
+  };
+
+  /**
+   * Rename an album
+   */
+  const renameAlbum = async (albumId: number, name: string): Promise<void> => {
+    return invoke<void>('rename_album_command', { albumId, name });
+  };
+
+  /**
+   * Move an album into an album folder (null for the top level)
+   */
+  const moveAlbum = async (albumId: number, folderId: number | null): Promise<void> => {
+    return invoke<void>('move_album_command', { albumId, folderId });
+  };
+
+  /**
+   * Delete an album (the media is kept)
+   */
+  const deleteAlbum = async (albumId: number): Promise<void> => {
+    return invoke<void>('delete_album_command', { albumId });
+  };
+
+  /**
+   * Append media items to an album
+   */
+  const addToAlbum = async (albumId: number, mediaIds: number[]): Promise<number> => {
+    return invoke<number>('add_to_album_command', { albumId, mediaIds });
+  };
+
+  /**
+   * Remove media items from an album
+   */
+  const removeFromAlbum = async (albumId: number, mediaIds: number[]): Promise<number> => {
+    return invoke<number>('remove_from_album_command', { albumId, mediaIds });
+  };
+
+  /**
+   * Reorder an album; unlisted items keep their relative order after the listed ones
+   */
+  const reorderAlbum = async (albumId: number, mediaIds: number[]): Promise<void> => {
+    return invoke<void>('reorder_album_command', { albumId, mediaIds });
+  };
+
+  /**
+   * Choose the cover item of an album (null to use the first item)
+   */
+  const setAlbumCover = async (albumId: number, mediaId: number | null): Promise<void> => {
+    return invoke<void>('set_album_cover_command', { albumId, mediaId });
+  };
+
+  /**
+   * Create an album folder, optionally inside another one
+   */
+  const createAlbumFolder = async (name: string, parentId?: number | null): Promise<number> => {
+    return invoke<number>('create_album_folder_command', { name, parentId });
+  };
+
+  /**
+   * Rename an album folder
+   */
+  const renameAlbumFolder = async (folderId: number, name: string): Promise<void> => {
+    return invoke<void>('rename_album_folder_command', { folderId, name });
+  };
+
+  /**
+   * Move an album folder below another one (null for the top level)
+   */
+  const moveAlbumFolder = async (folderId: number, parentId: number | null): Promise<void> => {
+    return invoke<void>('move_album_folder_command', { folderId, parentId });
+  };
+
+  /**
+   * Delete an album folder with its subfolders and albums
+   */
+  const deleteAlbumFolder = async (folderId: number): Promise<void> => {
+    return invoke<void>('delete_album_folder_command', { folderId });
+  };
+
+  /**
+   * Get all smart albums with their current item counts
+   */
+  const getSmartAlbums = async (): Promise<SmartAlbum[]> => {
+    return invoke<SmartAlbum[]>('get_smart_albums');
+  };
+
+  /**
+   * Fetch one page of a smart album's live contents
+   */
+  const getSmartAlbumPage = async (
+    smartAlbumId: number,
+    cursor?: string | null,
+    limit?: number
+  ): Promise<MediaPage> => {
+    return invoke<MediaPage>('get_smart_album_page', { smartAlbumId, cursor, limit });
+  };
+
+  /**
+   * Save a query as a smart album
+   */
+  const createSmartAlbum = async (
+    name: string,
+    definition: SmartAlbumDefinition,
+    folderId?: number | null
+  ): Promise<number> => {
+    return invoke<number>('create_smart_album_command', { name, folderId, definition });
+  };
+
+  /**
+   * Change the name and saved query of a smart album
+   */
+  const updateSmartAlbum = async (
+    smartAlbumId: number,
+    name: string,
+    definition: SmartAlbumDefinition
+  ): Promise<void> => {
+    return invoke<void>('update_smart_album_command', { smartAlbumId, name, definition });
+  };
+
+  /**
+   * Move a smart album into an album folder (null for the top level)
+   */
+  const moveSmartAlbum = async (smartAlbumId: number, folderId: number | null): Promise<void> => {
+    return invoke<void>('move_smart_album_command', { smartAlbumId, folderId });
+  };
+
+  /**
+   * Delete a smart album
+   */
+  const deleteSmartAlbum = async (smartAlbumId: number): Promise<void> => {
+    return invoke<void>('delete_smart_album_command', { smartAlbumId });
+  };
+
+  // ------------------------------------------------------------------------
+  // Ratings and Flags
+  // ------------------------------------------------------------------------
+
+  /**
+   * Set the star rating (0-5, 0 = unrated) of several media items
+   */
+  const setRating = async (mediaIds: number[], rating: number): Promise<number> => {
+    return invoke<number>('set_media_rating', { mediaIds, rating });
+  };
+
+  /**
+   * Mark or unmark several media items as favorites
+   */
+  const setFavorite = async (mediaIds: number[], favorite: boolean): Promise<number> => {
+    return invoke<number>('set_media_favorite', { mediaIds, favorite });
+  };
+
+  /**
+   * Set or clear (null) the color label of several media items
+   */
+  const setColorLabel = async (mediaIds: number[], label: ColorLabel | null): Promise<number> => {
+    return invoke<number>('set_media_color_label', { mediaIds, label });
+  };
+
+  /**
+   * Pick, reject or unflag (null) several media items
+   */
+  const setPickFlag = async (mediaIds: number[], flag: PickFlag | null): Promise<number> => {
+    return invoke<number>('set_media_pick_flag', { mediaIds, flag });
+  };
+
+  // ------------------------------------------------------------------------
+  // Trash
+  // ------------------------------------------------------------------------
+
+  /**
+   * Move media files to the trash (rows are kept and hidden)
+   */
+  const moveToTrash = async (mediaIds: number[]): Promise<TrashOutcome> => {
+    return invoke<TrashOutcome>('move_to_trash', { mediaIds });
+  };
+
+  /**
+   * Restore trashed media to their original paths
+   */
+  const restoreFromTrash = async (mediaIds: number[]): Promise<TrashOutcome> => {
+    return invoke<TrashOutcome>('restore_from_trash', { mediaIds });
+  };
+
+  /**
+   * Get the trashed media, most recently trashed first
+   */
+  const getTrash = async (): Promise<TrashedMedia[]> => {
+    return invoke<TrashedMedia[]>('get_trash');
+  };
+
+  /**
+   * Permanently delete everything in the trash
+   */
+  const emptyTrash = async (): Promise<number> => {
+    return invoke<number>('empty_trash');
+  };
+
+  /**
+   * Get the trash location and retention period
+   */
+  const getTrashSettings = async (): Promise<TrashSettings> => {
+    return invoke<TrashSettings>('get_trash_settings');
+  };
+
+  /**
+   * Save the trash location and retention period
+   */
+  const setTrashSettings = async (settings: TrashSettings): Promise<void> => {
+    return invoke<void>('set_trash_settings', { settings });
+  };
+
+  // ------------------------------------------------------------------------
+  // Content Hashes and Duplicates
+  // ------------------------------------------------------------------------
+
+  /**
+   * Start hashing the library in the background (progress via 'hash-progress')
+   */
+  const startHashing = async (): Promise<HashJobInfo> => {
+    return invoke<HashJobInfo>('start_hashing');
+  };
+
+  /**
+   * Get the state of the current or last hashing job
+   */
+  const getHashingStatus = async (): Promise<HashJobInfo | null> => {
+    return invoke<HashJobInfo | null>('get_hashing_status');
+  };
+
+  /**
+   * Stop the running hashing job
+   */
+  const cancelHashing = async (): Promise<void> => {
+    return invoke<void>('cancel_hashing');
+  };
+
+  /**
+   * Compute full content hashes for specific media right away
+   */
+  const hashMedia = async (mediaIds: number[]): Promise<number> => {
+    return invoke<number>('hash_media', { mediaIds });
+  };
+
+  /**
+   * Get the exact-duplicate groups found so far
+   */
+  const getDuplicates = async (): Promise<DuplicateReport> => {
+    return invoke<DuplicateReport>('get_duplicates');
+  };
+
+  /**
+   * Group visually similar images by perceptual hash distance
+   */
+  const getSimilarImages = async (options?: SimilarityOptions): Promise<SimilarGroup[]> => {
+    return invoke<SimilarGroup[]>('get_similar_images', { options });
+  };
+
+  /**
+   * Group re-encodes, trims and resolution variants of videos, best copy first
+   */
+  const getSimilarVideos = async (options?: VideoSimilarityOptions): Promise<VideoGroup[]> => {
+    return invoke<VideoGroup[]>('get_similar_videos', { options });
+  };
+
+  /**
+   * Keep one copy per duplicate group and trash or link the others
+   * (use `dry_run` to preview the decisions)
+   */
+  const resolveDuplicates = async (policy: ResolvePolicy): Promise<ResolveReport> => {
+    return invoke<ResolveReport>('resolve_duplicates_command', { policy });
+  };
+
   // ------------------------------------------------------------------------
   // User Preferences
   // ------------------------------------------------------------------------
@@ -168,6 +847,64 @@ export function useDatabase() {
     getMediaByFolder,
     getAllMedia,
     deleteMediaMetadata,
+    searchMedia,
+    getMediaPage,
+    extractPendingMetadata,
+    getLocationClusters,
+    geocodePending,
+    reverseGeocode,
+    // Tags
+    getTags,
+    getTagsForMedia,
+    addTags,
+    removeTags,
+    renameTag,
+    mergeTags,
+    deleteTag,
+    // Albums
+    getAlbums,
+    getAlbumFolders,
+    getAlbumItems,
+    getAlbumsForMedia,
+    createAlbum,
+    renameAlbum,
+    moveAlbum,
+    deleteAlbum,
+    addToAlbum,
+    removeFromAlbum,
+    reorderAlbum,
+    setAlbumCover,
+    createAlbumFolder,
+    renameAlbumFolder,
+    moveAlbumFolder,
+    deleteAlbumFolder,
+    getSmartAlbums,
+    getSmartAlbumPage,
+    createSmartAlbum,
+    updateSmartAlbum,
+    moveSmartAlbum,
+    deleteSmartAlbum,
+    // Ratings and flags
+    setRating,
+    setFavorite,
+    setColorLabel,
+    setPickFlag,
+    // Trash
+    moveToTrash,
+    restoreFromTrash,
+    getTrash,
+    emptyTrash,
+    getTrashSettings,
+    setTrashSettings,
+    // Duplicates
+    startHashing,
+    getHashingStatus,
+    cancelHashing,
+    hashMedia,
+    getDuplicates,
+    getSimilarImages,
+    getSimilarVideos,
+    resolveDuplicates,
     // Preferences
     setPreference,
     getPreference,
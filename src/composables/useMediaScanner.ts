@@ -1,8 +1,7 @@
 import { ref, Ref } from 'vue'
 import { invoke } from '@tauri-apps/api/core'
-import { useDatabase } from './useDatabase'
 
-export type MediaType = 'image' | 'video' | 'unknown'
+export type MediaType = 'image' | 'video' | 'audio' | 'unknown'
 
 export interface MediaFile {
   path: string
@@ -13,6 +12,18 @@ export interface MediaFile {
   media_type: MediaType
 }
 
+export interface IndexSummary {
+  folder_id: number
+  files: MediaFile[]
+  added: number
+  updated: number
+  moved: number
+  removed: number
+  unchanged: number
+  batches: number
+  duration_ms: number
+}
+
 export interface MediaScannerState {
   mediaFiles: Ref<MediaFile[]>
   isLoading: Ref<boolean>
@@ -31,8 +42,6 @@ export function useMediaScanner(): MediaScannerState & {
   const selectedPath = ref<string | null>(null)
   const selectedFolderId = ref<number | null>(null)
 
-  const db = useDatabase()
-
   /**
    * Scan a directory and save it to the database
    * Returns the folder ID from the database
@@ -43,27 +52,16 @@ export function useMediaScanner(): MediaScannerState & {
       error.value = null
       selectedPath.value = path
 
-      // Scan the directory for media files
-      const result = await invoke<MediaFile[]>('scan_directory', {
+      // Scan the directory and index its media files in the database
+      const summary = await invoke<IndexSummary>('scan_and_index', {
         path,
         recursive,
       })
 
-      mediaFiles.value = result
-
-      // Extract folder name from path
-      const folderName = path.split(/[\\/]/).filter(Boolean).pop() || path
-
-      // Save or update the folder in the database
-      const folderId = await db.addScannedFolder(
-        path,
-        folderName,
-        result.length
-      )
-
-      selectedFolderId.value = folderId
+      mediaFiles.value = summary.files
+      selectedFolderId.value = summary.folder_id
 
-      return folderId
+      return summary.folder_id
     } catch (err) {
       error.value = err instanceof Error ? err.message : String(err)
       mediaFiles.value = []